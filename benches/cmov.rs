@@ -0,0 +1,85 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_assembly;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Running time of the same min and max computation compiled at -O0, where
+// each ternary is a branch, and at -O1, where it picks with cmov. A harness
+// written in C calls the compiled code a few million times, with values
+// that either always take the same side of the comparison or take a random
+// one. A branch on random values is mispredicted about half the time, so
+// -O0 slows down on them while -O1 runs as fast either way.
+//
+// Linux only; the harness and the assembly are built with the system's cc
+// and objcopy:
+//
+//     cargo bench --bench cmov
+
+// The compiled code, whose main objcopy renames so the harness can call it
+const KERNEL: &str = "int main(void) {
+    extern int x;
+    extern int y;
+    int a = x;
+    int b = y;
+    int low = a < b ? a : b;
+    int high = a < b ? b : a;
+    return high - low;
+}
+";
+
+// With an argument x is random, and without one it is always below y
+const HARNESS: &str = "int kernel(void);
+int x, y = 512;
+int main(int argc, char **argv) {
+    unsigned state = 1;
+    int sum = 0;
+    for (int i = 0; i < 5000000; i++) {
+        state = state * 1103515245u + 12345u;
+        x = argc > 1 ? (int)(state >> 16) & 1023 : 0;
+        sum += kernel();
+    }
+    return sum & 1;
+}
+";
+
+fn run(command: &mut Command) {
+    let status = command.status().unwrap_or_else(|err| panic!("{:?}: {}", command, err));
+    assert!(status.success(), "{:?} failed", command);
+}
+
+// The harness linked with KERNEL compiled at opt_level
+fn build(dir: &Path, opt_level: u8) -> PathBuf {
+    let options = CodegenOptions { opt_level, ..CodegenOptions::new(Target::Linux) };
+    let assembly = dir.join(format!("kernel-O{}.s", opt_level));
+    let object = assembly.with_extension("o");
+    let executable = dir.join(format!("cmov-O{}", opt_level));
+    fs::write(&assembly, compile_to_assembly(KERNEL, &Dialect::default(), &options).unwrap()).unwrap();
+    run(Command::new("cc").arg("-c").arg(&assembly).arg("-o").arg(&object));
+    run(Command::new("objcopy").args(["--redefine-sym", "main=kernel"]).arg(&object));
+    run(Command::new("cc").arg("-O2").arg(dir.join("harness.c")).arg(&object).arg("-o").arg(&executable));
+    executable
+}
+
+fn cmov(c: &mut Criterion) {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cmov");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("harness.c"), HARNESS).unwrap();
+    let executables = [(0, build(&dir, 0)), (1, build(&dir, 1))];
+
+    let mut group = c.benchmark_group("min and max");
+    group.sample_size(10);
+    for (values, args) in [("predictable", &[][..]), ("random", &["random"][..])] {
+        for (opt_level, executable) in &executables {
+            group.bench_with_input(BenchmarkId::new(format!("-O{}", opt_level), values), executable, |b, executable| {
+                b.iter(|| Command::new(executable).args(args).status().unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cmov);
+criterion_main!(benches);
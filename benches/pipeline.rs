@@ -0,0 +1,79 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::lex::Lex;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::{assembly, optimize, parser, tac, typecheck};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+// Throughput of the lexer in tokens per second, and of the parser and of
+// codegen (TAC, the optimization passes, assembly and writing it out) in
+// source lines per second, over synthetic programs of a few sizes. Growth
+// that isn't linear in the size shows up as falling throughput.
+//
+//     cargo bench --bench pipeline
+
+const SIZES: [usize; 3] = [100, 400, 1600];
+
+// main with one declaration per line, each an expression over the variable
+// declared on the line before
+fn program(lines: usize) -> String {
+    let mut source = String::from("int main(void) {\n    int x0 = 1;\n");
+    for n in 1..lines {
+        source.push_str(&format!("    int x{} = x{} * 3 + ({} << 2) - x{} / 7 > 5 ? x{} : -{};\n", n, n - 1, n, n - 1, n - 1, n));
+    }
+    source.push_str(&format!("    return x{} & 255;\n}}\n", lines - 1));
+    source
+}
+
+fn lex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex (tokens)");
+    for lines in SIZES {
+        let source = program(lines);
+        let tokens = Lex::new(&source).get_tokens().unwrap().len();
+        group.throughput(Throughput::Elements(tokens as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &source, |b, source| {
+            b.iter(|| Lex::new(black_box(source)).get_tokens().unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse (lines)");
+    group.sample_size(10);
+    for lines in SIZES {
+        let tokens = Lex::new(&program(lines)).get_tokens().unwrap();
+        group.throughput(Throughput::Elements(lines as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &tokens, |b, tokens| {
+            b.iter_batched(|| tokens.clone(), |mut tokens| parser::parse_program(&mut tokens, &Dialect::default()).unwrap(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn codegen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("codegen (lines)");
+    group.sample_size(10);
+    let options = CodegenOptions::new(Target::Linux);
+    for lines in SIZES {
+        let mut tokens = Lex::new(&program(lines)).get_tokens().unwrap();
+        let program = parser::parse_program(&mut tokens, &Dialect::default()).unwrap();
+        let program = typecheck::typecheck_program(parser::resolve_program(program).unwrap()).unwrap();
+        group.throughput(Throughput::Elements(lines as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &program, |b, program| {
+            b.iter_batched(|| program.clone(), |program| {
+                let mut tac = tac::generate_tac(program).unwrap();
+                for (_, pass) in optimize::PASSES {
+                    pass(&mut tac.function);
+                }
+                let mut assembly = assembly::generate_assembly_ast(tac);
+                assembly.apply_fixes();
+                assembly.to_assembly_file(&options)
+            }, BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lex, parse, codegen);
+criterion_main!(benches);
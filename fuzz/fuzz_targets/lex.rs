@@ -0,0 +1,10 @@
+#![no_main]
+
+use c_compiler_lib::Lex;
+use libfuzzer_sys::fuzz_target;
+
+// Any input must either lex or produce a LexError, never panic. Run from the
+// repository root with `cargo fuzz run lex` (or `parse` for the parser).
+fuzz_target!(|source: &str| {
+    let _ = Lex::new(source).get_tokens();
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::{parse_and_resolve_program, Lex};
+use libfuzzer_sys::fuzz_target;
+
+// The parser and resolver recurse over the expression tree, so nesting
+// thousands of levels deep can still overflow the stack. Keep inputs below
+// that; everything shorter must parse or produce an error, never panic.
+const MAX_LEN: usize = 2048;
+
+fuzz_target!(|source: &str| {
+    if source.len() > MAX_LEN {
+        return;
+    }
+    let Ok(mut tokens) = Lex::new(source).get_tokens() else {
+        return;
+    };
+    let _ = parse_and_resolve_program(&mut tokens, &Dialect::default());
+});
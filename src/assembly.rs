@@ -1,5 +1,13 @@
-use crate::tac::{Program as TacProgram, Function as TacFunction, Instruction as TacInstruction, Val, UnaryOperator as TacUnaryOperator, BinaryOperator as TacBinaryOperator};
-use std::collections::HashMap;
+use crate::tac::{narrow, Program as TacProgram, Instruction as TacInstruction, Val, UnaryOperator as TacUnaryOperator, BinaryOperator as TacBinaryOperator};
+use crate::driver::Diagnostic;
+use crate::intern::{Interner, Symbol};
+use crate::stack_slots::assign_stack_slots;
+use crate::target::{CodegenOptions, Target};
+use crate::{typecheck, verify};
+use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Write};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CodeGen {
@@ -8,24 +16,58 @@ pub enum CodeGen {
     G,
     GE,
     L,
-    LE
+    LE,
+    O, // Overflow, only tested with -ftrapv
 }
 
 
-#[derive(Debug, Clone)]
+// Width of an operation: b, w, l and q in AT&T instruction suffixes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    Byte,
+    Word,
+    Longword,
+    Quadword,
+}
+
+impl Size {
+    fn suffix(&self) -> char {
+        match self {
+            Size::Byte => 'b',
+            Size::Word => 'w',
+            Size::Longword => 'l',
+            Size::Quadword => 'q',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Reg {
     AX,
+    CX,
     DX,
+    DI,
+    SI,
+    R8,
+    R9,
     R10,
     R11,
+    SP,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operand {
-    Imm(i32),
+    Imm(i64),
     Register(Reg),
-    Pseudo(String),
+    Pseudo(Symbol),
     Stack(i32),
+    // A displacement from a register holding an address
+    Memory(Reg, i32),
+    // An object with static storage, addressed relative to %rip
+    Data(Symbol),
+    // A static local, which only this object sees, so its label is a local
+    // one the assembler keeps out of the symbol table
+    Static(Symbol),
 }
 
 #[derive(Debug, Clone)]
@@ -49,29 +91,159 @@ pub enum BinaryOperator {
 
 #[derive(Debug, Clone)]
 pub enum Instruction {
-    Mov(Operand, Operand),
-    Unary(UnaryOperator, Operand),
-    Binary(BinaryOperator, Operand, Operand),
-    Cmp(Operand, Operand),
-    Idiv(Operand),
-    Cdq, //sign extension
-    Jmp(String),
-    JmpCC(CodeGen, String),
+    Mov(Size, Operand, Operand),
+    // Widening moves from the first size to the second (movsbl, movzbl, ...)
+    MovSignExtend(Size, Size, Operand, Operand),
+    MovZeroExtend(Size, Size, Operand, Operand),
+    Unary(UnaryOperator, Size, Operand),
+    Binary(BinaryOperator, Size, Operand, Operand),
+    Cmp(Size, Operand, Operand),
+    Idiv(Size, Operand),
+    // Unsigned division of DX:AX
+    Div(Size, Operand),
+    Cdq(Size), //sign extension of AX into DX: cwd, cdq or cqo
+    Jmp(Symbol),
+    JmpCC(CodeGen, Symbol),
     SetCC(CodeGen, Operand),
-    Label(String),
+    // Conditional move into a register, only selected with -O1
+    Cmov(CodeGen, Size, Operand, Operand),
+    Label(Symbol),
     AllocateStack(i32),
     Ret,
+    // Basic inline assembly. Every value lives in its stack slot between TAC
+    // instructions, so it may use any register except %rsp and %rbp.
+    InlineAsm(String),
+    // Annotation naming the TAC instruction the following code came from;
+    // only written out with --asm-comments
+    Comment(String),
+    // Reports the message on stderr and aborts. The function writes it as a
+    // jump to a handler after its code.
+    Abort(String),
+    Ud2, // Raises an invalid opcode exception, which stops the program
+    // Calls the function with the arguments and leaves its result in AX.
+    // Where the arguments go depends on the target's calling convention, so
+    // the function writes it out.
+    Call(Symbol, Vec<Operand>),
+}
+
+// How an instruction accesses an operand. Instructions that only write part
+// of their destination (setcc writes one byte) also read it, since the rest
+// of the old value survives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    pub fn reads(&self) -> bool {
+        matches!(self, Access::Read | Access::ReadWrite)
+    }
+
+    pub fn writes(&self) -> bool {
+        matches!(self, Access::Write | Access::ReadWrite)
+    }
+}
+
+impl Instruction {
+    // The explicit operands in the order they are written (source before
+    // destination), each with the width it is accessed at and how
+    pub fn operands(&self) -> Vec<(&Operand, Size, Access)> {
+        match self {
+            Instruction::Mov(size, src, dst) => vec![(src, *size, Access::Read), (dst, *size, Access::Write)],
+            Instruction::MovSignExtend(src_size, dst_size, src, dst) | Instruction::MovZeroExtend(src_size, dst_size, src, dst) => {
+                vec![(src, *src_size, Access::Read), (dst, *dst_size, Access::Write)]
+            }
+            Instruction::Unary(_, size, dst) => vec![(dst, *size, Access::ReadWrite)],
+            // The shift count is a byte, %cl or an immediate
+            Instruction::Binary(BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight, size, src, dst) => {
+                vec![(src, Size::Byte, Access::Read), (dst, *size, Access::ReadWrite)]
+            }
+            Instruction::Binary(_, size, src, dst) => vec![(src, *size, Access::Read), (dst, *size, Access::ReadWrite)],
+            Instruction::Cmp(size, src, dst) => vec![(src, *size, Access::Read), (dst, *size, Access::Read)],
+            Instruction::Idiv(size, op) | Instruction::Div(size, op) => vec![(op, *size, Access::Read)],
+            Instruction::SetCC(_, dst) => vec![(dst, Size::Byte, Access::ReadWrite)],
+            // The destination keeps its value when the condition fails
+            Instruction::Cmov(_, size, src, dst) => vec![(src, *size, Access::Read), (dst, *size, Access::ReadWrite)],
+            Instruction::Cdq(_) | Instruction::Jmp(_) | Instruction::JmpCC(..) | Instruction::Label(_)
+            | Instruction::AllocateStack(_) | Instruction::Ret | Instruction::InlineAsm(_) | Instruction::Comment(_)
+            | Instruction::Abort(_) | Instruction::Ud2 => vec![],
+            Instruction::Call(_, args) => args.iter().map(|arg| (arg, Size::Longword, Access::Read)).collect(),
+        }
+    }
+
+    // Registers the instruction reads and writes without naming them
+    fn implicit_registers(&self) -> (Vec<Reg>, Vec<Reg>) {
+        match self {
+            Instruction::Cdq(_) => (vec![Reg::AX], vec![Reg::DX]),
+            Instruction::Idiv(..) | Instruction::Div(..) => (vec![Reg::AX, Reg::DX], vec![Reg::AX, Reg::DX]),
+            // The return value
+            Instruction::Ret => (vec![Reg::AX], vec![]),
+            // The result, and every register the callee may overwrite
+            Instruction::Call(..) => (vec![], vec![Reg::AX, Reg::CX, Reg::DX, Reg::DI, Reg::SI, Reg::R8, Reg::R9, Reg::R10, Reg::R11]),
+            _ => (vec![], vec![]),
+        }
+    }
+
+    // Everything the instruction reads, explicit operands first
+    pub fn uses(&self) -> Vec<Operand> {
+        let explicit = self.operands().into_iter().filter(|(_, _, access)| access.reads()).map(|(op, _, _)| op.clone());
+        explicit.chain(self.implicit_registers().0.into_iter().map(Operand::Register)).collect()
+    }
+
+    // Everything the instruction writes, explicit operands first
+    pub fn defs(&self) -> Vec<Operand> {
+        let explicit = self.operands().into_iter().filter(|(_, _, access)| access.writes()).map(|(op, _, _)| op.clone());
+        explicit.chain(self.implicit_registers().1.into_iter().map(Operand::Register)).collect()
+    }
+
+    // The same instruction with each explicit operand replaced by f(index,
+    // operand), where index is its position in operands()
+    pub fn map_operands(&self, mut f: impl FnMut(usize, &Operand) -> Operand) -> Instruction {
+        match self {
+            Instruction::Mov(size, src, dst) => Instruction::Mov(*size, f(0, src), f(1, dst)),
+            Instruction::MovSignExtend(src_size, dst_size, src, dst) => Instruction::MovSignExtend(*src_size, *dst_size, f(0, src), f(1, dst)),
+            Instruction::MovZeroExtend(src_size, dst_size, src, dst) => Instruction::MovZeroExtend(*src_size, *dst_size, f(0, src), f(1, dst)),
+            Instruction::Unary(op, size, dst) => Instruction::Unary(op.clone(), *size, f(0, dst)),
+            Instruction::Binary(op, size, src, dst) => Instruction::Binary(op.clone(), *size, f(0, src), f(1, dst)),
+            Instruction::Cmp(size, src, dst) => Instruction::Cmp(*size, f(0, src), f(1, dst)),
+            Instruction::Idiv(size, op) => Instruction::Idiv(*size, f(0, op)),
+            Instruction::Div(size, op) => Instruction::Div(*size, f(0, op)),
+            Instruction::SetCC(code, dst) => Instruction::SetCC(code.clone(), f(0, dst)),
+            Instruction::Cmov(code, size, src, dst) => Instruction::Cmov(code.clone(), *size, f(0, src), f(1, dst)),
+            Instruction::Call(name, args) => Instruction::Call(*name, args.iter().enumerate().map(|(index, arg)| f(index, arg)).collect()),
+            _ => self.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
-    name: String,
+    name: Symbol,
     instructions: Vec<Instruction>,
 }
 
+// A static variable in the data section: its label and its initial value
 #[derive(Debug, Clone)]
+pub struct StaticVariable {
+    label: Symbol,
+    init: i32,
+}
+
+#[derive(Clone)]
 pub struct Program {
     function: Function,
+    statics: Vec<StaticVariable>,
+    // The interner of the compilation that made the program, which its
+    // names are read back through
+    interner: Interner,
+}
+
+impl fmt::Debug for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.interner.enter(|| f.debug_struct("Program").field("function", &self.function).field("statics", &self.statics).finish())
+    }
 }
 
 impl From<TacUnaryOperator> for UnaryOperator {
@@ -87,493 +259,969 @@ impl From<TacUnaryOperator> for UnaryOperator {
 impl From<Val> for Operand {
     fn from(val: Val) -> Self {
         match val {
-            Val::Constant(int) => Operand::Imm(int),
+            Val::Constant(int) => Operand::Imm(int as i64),
             Val::Identifier(id) => Operand::Pseudo(id),
         }
     }
 }
 
+// Division divides the double-width value in DX:AX. Signed division
+// sign-extends the dividend into DX, unsigned division zeroes DX. The quotient
+// ends up in AX and the remainder in DX; result picks which one is kept.
+fn division(size: Size, signed: bool, dividend: Operand, divisor: Operand, result: Reg, dst: Operand, out: &mut Vec<Instruction>) {
+    out.push(Instruction::Mov(size, dividend, Operand::Register(Reg::AX)));
+    if signed {
+        out.push(Instruction::Cdq(size));
+        out.push(Instruction::Idiv(size, divisor));
+    } else {
+        out.push(Instruction::Binary(BinaryOperator::Caret, size, Operand::Register(Reg::DX), Operand::Register(Reg::DX)));
+        out.push(Instruction::Div(size, divisor));
+    }
+    out.push(Instruction::Mov(size, Operand::Register(result), dst));
+}
+
+// The condition a comparison operator sets its result on, if it is one
+fn comparison(operator: &TacBinaryOperator) -> Option<CodeGen> {
+    match operator {
+        TacBinaryOperator::GreaterThan => Some(CodeGen::G),
+        TacBinaryOperator::GreaterThanOrEqual => Some(CodeGen::GE),
+        TacBinaryOperator::LessThan => Some(CodeGen::L),
+        TacBinaryOperator::LessThanOrEqual => Some(CodeGen::LE),
+        TacBinaryOperator::Equal => Some(CodeGen::E),
+        TacBinaryOperator::NotEqual => Some(CodeGen::NE),
+        _ => None,
+    }
+}
+
 impl TacInstruction {
-    fn to_assembly_instructions(&self) -> Vec<Instruction> {
-        println!("Converting TAC instruction to assembly: {:?}", self);
+    // Appends the instructions this one becomes to out, consuming it.
+    // operand turns each value into the operand it lives in.
+    fn lower(self, operand: &impl Fn(Val) -> Operand, out: &mut Vec<Instruction>) {
         match self {
             TacInstruction::Return(val) => {
-                println!("Generating RETURN for value: {:?}", val);
-                vec![
-                Instruction::Mov(Operand::from(val.clone()), Operand::Register(Reg::AX)),
-                Instruction::Ret,
-            ] },
+                out.push(Instruction::Mov(Size::Longword, operand(val), Operand::Register(Reg::AX)));
+                out.push(Instruction::Ret);
+            },
+            TacInstruction::Unary { operator: TacUnaryOperator::LogicalNot, src, dst } => {
+                let dst = operand(dst);
+                out.push(Instruction::Cmp(Size::Longword, Operand::Imm(0), operand(src)));
+                out.push(Instruction::Mov(Size::Longword, Operand::Imm(0), dst.clone()));
+                out.push(Instruction::SetCC(CodeGen::E, dst));
+            },
             TacInstruction::Unary { operator, src, dst } => {
-            println!("Generating UNARY op: {:?}, src: {:?}, dst: {:?}", operator, src, dst);
-            match operator{
-                TacUnaryOperator::LogicalNot => vec![
-                    Instruction::Cmp(Operand::Imm(0), Operand::from(src.clone())),
-                    Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                    Instruction::SetCC(CodeGen::E, Operand::from(dst.clone())),
-                ],
-                _ => vec![
-                    Instruction::Mov(Operand::from(src.clone()), Operand::from(dst.clone())),
-                    Instruction::Unary(UnaryOperator::from(operator.clone()), Operand::from(dst.clone())),
-                ]
-            }
-        },
+                let dst = operand(dst);
+                out.push(Instruction::Mov(Size::Longword, operand(src), dst.clone()));
+                out.push(Instruction::Unary(UnaryOperator::from(operator), Size::Longword, dst));
+            },
             TacInstruction::Binary { operator, src1, src2, dst } => {
-                println!("Generating BINARY op: {:?}, src1: {:?}, src2: {:?}, dst: {:?}", operator, src1, src2, dst);
-                match operator {
-                    // Handling the division operator
-                    TacBinaryOperator::Divide => vec![
-                        Instruction::Mov(Operand::from(src1.clone()), Operand::Register(Reg::AX)),
-                        Instruction::Cdq,
-                        Instruction::Idiv(Operand::from(src2.clone())),
-                        Instruction::Mov(Operand::Register(Reg::AX), Operand::from(dst.clone())),
-                    ],
-
-                    // Handling the modulo operator
-                    TacBinaryOperator::Modulo => vec![
-                        Instruction::Mov(Operand::from(src1.clone()), Operand::Register(Reg::AX)),
-                        Instruction::Cdq,
-                        Instruction::Idiv(Operand::from(src2.clone())),
-                        Instruction::Mov(Operand::Register(Reg::DX), Operand::from(dst.clone())),
-                    ],
-
-                    TacBinaryOperator::GreaterThan => vec![
-                        Instruction::Cmp(Operand::from(src2.clone()), Operand::from(src1.clone())),
-                        Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                        Instruction::SetCC(CodeGen::G, Operand::from(dst.clone())),
-                    ],
-                    TacBinaryOperator::GreaterThanOrEqual => vec![
-                        Instruction::Cmp(Operand::from(src2.clone()), Operand::from(src1.clone())),
-                        Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                        Instruction::SetCC(CodeGen::GE, Operand::from(dst.clone())),
-                    ],
-                    TacBinaryOperator::LessThan => vec![
-                        Instruction::Cmp(Operand::from(src2.clone()), Operand::from(src1.clone())),
-                        Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                        Instruction::SetCC(CodeGen::L, Operand::from(dst.clone())),
-                    ],
-                    TacBinaryOperator::LessThanOrEqual => vec![
-                        Instruction::Cmp(Operand::from(src2.clone()), Operand::from(src1.clone())),
-                        Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                        Instruction::SetCC(CodeGen::LE, Operand::from(dst.clone())),
-                    ],
-                    TacBinaryOperator::Equal => vec![
-                        Instruction::Cmp(Operand::from(src2.clone()), Operand::from(src1.clone())),
-                        Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                        Instruction::SetCC(CodeGen::E, Operand::from(dst.clone())),
-                    ],
-                    TacBinaryOperator::NotEqual => vec![
-                        Instruction::Cmp(Operand::from(src2.clone()), Operand::from(src1.clone())),
-                        Instruction::Mov(Operand::Imm(0), Operand::from(dst.clone())),
-                        Instruction::SetCC(CodeGen::NE, Operand::from(dst.clone())),
-                    ],
-
-                    // Handling other binary operators
-                    _ => vec![
-                        Instruction::Mov(Operand::from(src1.clone()), Operand::from(dst.clone())),
-                        Instruction::Binary(
-                            match operator {
-                                TacBinaryOperator::Add => BinaryOperator::Add,
-                                TacBinaryOperator::Subtract => BinaryOperator::Sub,
-                                TacBinaryOperator::Multiply => BinaryOperator::Mul,
-                                TacBinaryOperator::Ampersand => BinaryOperator::Ampersand,
-                                TacBinaryOperator::Pipe => BinaryOperator::Pipe,
-                                TacBinaryOperator::Caret => BinaryOperator::Caret,
-                                TacBinaryOperator::ShiftLeft => BinaryOperator::ShiftLeft,
-                                TacBinaryOperator::ShiftRight => BinaryOperator::ShiftRight,
-                                _ => panic!("Invalid operator"),
-                            },
-                            Operand::from(src2.clone()),
-                            Operand::from(dst.clone()),
-                        ),
-                    ],
+                let (src1, src2, dst) = (operand(src1), operand(src2), operand(dst));
+                if let Some(condition) = comparison(&operator) {
+                    out.push(Instruction::Cmp(Size::Longword, src2, src1));
+                    out.push(Instruction::Mov(Size::Longword, Operand::Imm(0), dst.clone()));
+                    out.push(Instruction::SetCC(condition, dst));
+                    return;
                 }
+                let operator = match operator {
+                    TacBinaryOperator::Divide => return division(Size::Longword, true, src1, src2, Reg::AX, dst, out),
+                    // The remainder is left in DX
+                    TacBinaryOperator::Modulo => return division(Size::Longword, true, src1, src2, Reg::DX, dst, out),
+                    TacBinaryOperator::Add => BinaryOperator::Add,
+                    TacBinaryOperator::Subtract => BinaryOperator::Sub,
+                    TacBinaryOperator::Multiply => BinaryOperator::Mul,
+                    TacBinaryOperator::Ampersand => BinaryOperator::Ampersand,
+                    TacBinaryOperator::Pipe => BinaryOperator::Pipe,
+                    TacBinaryOperator::Caret => BinaryOperator::Caret,
+                    TacBinaryOperator::ShiftLeft => BinaryOperator::ShiftLeft,
+                    TacBinaryOperator::ShiftRight => BinaryOperator::ShiftRight,
+                    _ => panic!("Invalid operator"),
+                };
+                out.push(Instruction::Mov(Size::Longword, src1, dst.clone()));
+                out.push(Instruction::Binary(operator, Size::Longword, src2, dst));
             },
-            TacInstruction::JumpIfZero { src, label } => {
-                println!("Generating JUMP IF ZERO for src: {:?}, target: {:?}", src, label);
-                vec![
-                    Instruction::Cmp(Operand::from(src.clone()), Operand::Imm(0)),
-                    Instruction::JmpCC(CodeGen::E, label.to_string()),
-                ]
+            TacInstruction::JumpIfZero { src, label, .. } => {
+                out.push(Instruction::Cmp(Size::Longword, operand(src), Operand::Imm(0)));
+                out.push(Instruction::JmpCC(CodeGen::E, label));
             },
-            TacInstruction::JumpIfNotZero { src, label } => {
-                println!("Generating JUMP IF NOT ZERO for src: {:?}, target: {:?}", src, label);
-                vec![
-                    Instruction::Cmp(Operand::from(src.clone()), Operand::Imm(0)),
-                    Instruction::JmpCC(CodeGen::NE, label.to_string()),
-                ]
+            TacInstruction::JumpIfNotZero { src, label, .. } => {
+                out.push(Instruction::Cmp(Size::Longword, operand(src), Operand::Imm(0)));
+                out.push(Instruction::JmpCC(CodeGen::NE, label));
             },
-            TacInstruction::Jump { label } => {
-                println!("Generating JUMP for target: {:?}", label);
-                vec![
-                    Instruction::Jmp(label.to_string()),
-                ]
+            TacInstruction::Jump { label } => out.push(Instruction::Jmp(label)),
+            TacInstruction::Label { label } => out.push(Instruction::Label(label)),
+            TacInstruction::FunCall { name, args, dst } => {
+                out.push(Instruction::Call(name, args.into_iter().map(operand).collect()));
+                out.push(Instruction::Mov(Size::Longword, Operand::Register(Reg::AX), operand(dst)));
             },
-            TacInstruction::Label{label}  => {
-                println!("Generating LABEL for target: {:?}", label);
-                vec![
-                    Instruction::Label(label.to_string()),
-                ]
+            TacInstruction::InlineAsm(template) => out.push(Instruction::InlineAsm(template)),
+            TacInstruction::Abort(message) => out.push(Instruction::Abort(message)),
+            TacInstruction::Trap => out.push(Instruction::Ud2),
+            TacInstruction::Unreachable => {}
+            TacInstruction::Copy { src, dst } => out.push(Instruction::Mov(Size::Longword, operand(src), operand(dst))),
+            // A constant is converted here, as movs and movz can't take an immediate
+            TacInstruction::Narrow { target, src: Val::Constant(value), dst } => {
+                out.push(Instruction::Mov(Size::Longword, operand(Val::Constant(narrow(&target, value))), operand(dst)));
             },
-            TacInstruction::Copy { src, dst } => {
-                println!("Generating COPY for src: {:?}, dst: {:?}", src, dst);
-                vec![
-                    Instruction::Mov(Operand::from(src.clone()), Operand::from(dst.clone())),
-                ]
+            TacInstruction::Narrow { target, src, dst } => {
+                let size = if typecheck::size(&target) == 1 { Size::Byte } else { Size::Word };
+                out.push(if typecheck::is_signed(&target) {
+                    Instruction::MovSignExtend(size, Size::Longword, operand(src), operand(dst))
+                } else {
+                    Instruction::MovZeroExtend(size, Size::Longword, operand(src), operand(dst))
+                });
             },
         }
     }
 }
 
-impl TacFunction {
-    fn to_assembly_function(&self) -> Function {
-        let instructions: Vec<Instruction> = self.body.iter()
-            .flat_map(|instr| instr.to_assembly_instructions())
+impl TacProgram {
+    // Lowers the program to assembly, consuming it so that values and inline
+    // assembly move into the instructions that use them
+    pub fn into_assembly_program(self) -> Program {
+        self.interner.clone().enter(|| self.into_assembly_program_in())
+    }
+
+    fn into_assembly_program_in(self) -> Program {
+        // Externs have no pseudo-register; the linker finds them by name
+        let externs: HashSet<Symbol> = self.externs.into_iter().collect();
+        // A static gets a label of its own, written with the target's local
+        // label prefix so it can't clash with any symbol, such as a function
+        // named main.static in another object
+        let statics: Vec<StaticVariable> = self.statics.iter()
+            .map(|(name, init)| StaticVariable { label: Symbol::intern(&format!("{}.static", name)), init: *init })
             .collect();
-        
-        Function {
-            name: self.identifier.clone(),
-            instructions,
+        let static_labels: HashMap<Symbol, Symbol> = self.statics.iter().zip(&statics)
+            .map(|((name, _), variable)| (*name, variable.label))
+            .collect();
+        let operand = |val: Val| match val {
+            Val::Identifier(name) if externs.contains(&name) => Operand::Data(name),
+            Val::Identifier(name) if static_labels.contains_key(&name) => Operand::Static(static_labels[&name]),
+            val => Operand::from(val),
+        };
+        // Most TAC instructions become a comment and two or three instructions
+        let mut instructions = Vec::with_capacity(self.function.body.len() * 4);
+        for instr in self.function.body {
+            if !matches!(instr, TacInstruction::Label { .. }) {
+                instructions.push(Instruction::Comment(instr.to_string()));
+            }
+            instr.lower(&operand, &mut instructions);
         }
+        Program { function: Function { name: self.function.identifier, instructions }, statics, interner: self.interner }
     }
 }
 
-impl TacProgram {
-    pub fn to_assembly_program(&self) -> Program {
-        let function = self.function.to_assembly_function();
-        Program { function }
+impl Reg {
+    fn name(&self, size: Size) -> &'static str {
+        match (self, size) {
+            (Reg::AX, Size::Byte) => "%al",
+            (Reg::AX, Size::Word) => "%ax",
+            (Reg::AX, Size::Longword) => "%eax",
+            (Reg::AX, Size::Quadword) => "%rax",
+            (Reg::CX, Size::Byte) => "%cl",
+            (Reg::CX, Size::Word) => "%cx",
+            (Reg::CX, Size::Longword) => "%ecx",
+            (Reg::CX, Size::Quadword) => "%rcx",
+            (Reg::DX, Size::Byte) => "%dl",
+            (Reg::DX, Size::Word) => "%dx",
+            (Reg::DX, Size::Longword) => "%edx",
+            (Reg::DX, Size::Quadword) => "%rdx",
+            (Reg::DI, Size::Byte) => "%dil",
+            (Reg::DI, Size::Word) => "%di",
+            (Reg::DI, Size::Longword) => "%edi",
+            (Reg::DI, Size::Quadword) => "%rdi",
+            (Reg::SI, Size::Byte) => "%sil",
+            (Reg::SI, Size::Word) => "%si",
+            (Reg::SI, Size::Longword) => "%esi",
+            (Reg::SI, Size::Quadword) => "%rsi",
+            (Reg::R8, Size::Byte) => "%r8b",
+            (Reg::R8, Size::Word) => "%r8w",
+            (Reg::R8, Size::Longword) => "%r8d",
+            (Reg::R8, Size::Quadword) => "%r8",
+            (Reg::R9, Size::Byte) => "%r9b",
+            (Reg::R9, Size::Word) => "%r9w",
+            (Reg::R9, Size::Longword) => "%r9d",
+            (Reg::R9, Size::Quadword) => "%r9",
+            (Reg::R10, Size::Byte) => "%r10b",
+            (Reg::R10, Size::Word) => "%r10w",
+            (Reg::R10, Size::Longword) => "%r10d",
+            (Reg::R10, Size::Quadword) => "%r10",
+            (Reg::R11, Size::Byte) => "%r11b",
+            (Reg::R11, Size::Word) => "%r11w",
+            (Reg::R11, Size::Longword) => "%r11d",
+            (Reg::R11, Size::Quadword) => "%r11",
+            (Reg::SP, Size::Byte) => "%spl",
+            (Reg::SP, Size::Word) => "%sp",
+            (Reg::SP, Size::Longword) => "%esp",
+            (Reg::SP, Size::Quadword) => "%rsp",
+        }
     }
 }
 
+impl fmt::Display for CodeGen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CodeGen::E => "e",
+            CodeGen::NE => "ne",
+            CodeGen::G => "g",
+            CodeGen::GE => "ge",
+            CodeGen::L => "l",
+            CodeGen::LE => "le",
+            CodeGen::O => "o",
+        })
+    }
+}
+
+// Operands display as their 32-bit form
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sized(Size::Longword))
+    }
+}
+
+// An operand displayed at a given width; only registers are spelled
+// differently
+pub struct SizedOperand<'a>(&'a Operand, Size);
+
 impl Operand {
-    pub fn to_assembly_file(&self) -> String {
-        match self {
-            Operand::Imm(int) => format!("${}", int),
-            Operand::Register(reg) => match reg {
-                Reg::AX => "%eax".to_string(),
-                Reg::R10 => "%r10d".to_string(),
-                Reg::R11 => "%r11d".to_string(),
-                Reg::DX => "%edx".to_string(),
-            },
-            Operand::Pseudo(id) => id.clone(),
-            Operand::Stack(offset) => format!("{}(%rbp)", offset),
+    pub fn sized(&self, size: Size) -> SizedOperand<'_> {
+        SizedOperand(self, size)
+    }
+
+    pub fn is_memory(&self) -> bool {
+        matches!(self, Operand::Stack(_) | Operand::Memory(..) | Operand::Data(_) | Operand::Static(_))
+    }
+}
+
+impl fmt::Display for SizedOperand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Operand::Imm(int) => write!(f, "${}", int),
+            Operand::Register(reg) => f.write_str(reg.name(self.1)),
+            Operand::Pseudo(id) => write!(f, "{}", id),
+            Operand::Stack(offset) => write!(f, "{}(%rbp)", offset),
+            Operand::Memory(base, offset) => write!(f, "{}({})", offset, base.name(Size::Quadword)),
+            Operand::Data(name) | Operand::Static(name) => write!(f, "{}(%rip)", name),
         }
     }
-    pub fn to_assembly_file_byte(&self) -> String {
-        match self {
-            Operand::Imm(int) => format!("${}", int),
-            Operand::Register(reg) => match reg {
-                Reg::AX => "%al".to_string(),
-                Reg::R10 => "%r10b".to_string(),
-                Reg::R11 => "%r11b".to_string(),
-                Reg::DX => "%dl".to_string(),
-            },
-            Operand::Pseudo(id) => id.clone(),
-            Operand::Stack(offset) => format!("{}(%rbp)", offset),
+}
+
+// Labels are spelled differently per target, so an instruction needs one to
+// be displayed. Instructions that expand to several lines separate them with
+// newlines.
+pub struct InstructionDisplay<'a> {
+    instruction: &'a Instruction,
+    target: Target,
+}
+
+impl Instruction {
+    pub fn display(&self, target: Target) -> InstructionDisplay<'_> {
+        InstructionDisplay { instruction: self, target }
+    }
+}
+
+impl fmt::Display for InstructionDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label_prefix = self.target.local_label_prefix();
+        match self.instruction {
+            Instruction::Mov(size, src, dst) => {
+                write!(f, "mov{} {}, {}", size.suffix(), src.sized(*size), dst.sized(*size))
+            }
+            Instruction::MovSignExtend(src_size, dst_size, src, dst) => {
+                write!(f, "movs{}{} {}, {}", src_size.suffix(), dst_size.suffix(), src.sized(*src_size), dst.sized(*dst_size))
+            }
+            Instruction::MovZeroExtend(src_size, dst_size, src, dst) => {
+                write!(f, "movz{}{} {}, {}", src_size.suffix(), dst_size.suffix(), src.sized(*src_size), dst.sized(*dst_size))
+            }
+            Instruction::Unary(op, size, dst) => {
+                let mnemonic = match op {
+                    UnaryOperator::Neg => "neg",
+                    UnaryOperator::Not => "not",
+                    UnaryOperator::LogicalNot => unreachable!("! is lowered to cmp and sete"),
+                };
+                write!(f, "{}{} {}", mnemonic, size.suffix(), dst.sized(*size))
+            }
+            Instruction::Binary(op, size, src, dst) => {
+                let (mnemonic, src_size) = match op {
+                    BinaryOperator::Add => ("add", *size),
+                    BinaryOperator::Sub => ("sub", *size),
+                    BinaryOperator::Mul => ("imul", *size),
+                    BinaryOperator::Ampersand => ("and", *size),
+                    BinaryOperator::Pipe => ("or", *size),
+                    BinaryOperator::Caret => ("xor", *size),
+                    // The shift count is always %cl or an immediate
+                    BinaryOperator::ShiftLeft => ("sal", Size::Byte),
+                    BinaryOperator::ShiftRight => ("sar", Size::Byte),
+                };
+                write!(f, "{}{} {}, {}", mnemonic, size.suffix(), src.sized(src_size), dst.sized(*size))
+            }
+            Instruction::Cmp(size, src, dst) => {
+                write!(f, "cmp{} {}, {}", size.suffix(), src.sized(*size), dst.sized(*size))
+            }
+            Instruction::Idiv(size, op) => write!(f, "idiv{} {}", size.suffix(), op.sized(*size)),
+            Instruction::Div(size, op) => write!(f, "div{} {}", size.suffix(), op.sized(*size)),
+            Instruction::Cdq(size) => f.write_str(match size {
+                Size::Byte => "cbw",
+                Size::Word => "cwd",
+                Size::Longword => "cdq",
+                Size::Quadword => "cqo",
+            }),
+            Instruction::Jmp(label) => write!(f, "jmp {}{}", label_prefix, label),
+            Instruction::JmpCC(code, label) => write!(f, "j{} {}{}", code, label_prefix, label),
+            // Lowering zeroes the destination first, so setting the low byte
+            // leaves the whole operand holding 0 or 1
+            Instruction::SetCC(code, dst) => write!(f, "set{} {}", code, dst.sized(Size::Byte)),
+            // A size suffix would be ambiguous (cmovl is cmov if less), and
+            // the destination register gives the size anyway
+            Instruction::Cmov(code, size, src, dst) => write!(f, "cmov{} {}, {}", code, src.sized(*size), dst.sized(*size)),
+            Instruction::Label(label) => write!(f, "{}{}:", label_prefix, label),
+            Instruction::AllocateStack(size) => write!(f, "subq ${}, %rsp", size),
+            Instruction::Comment(text) => write!(f, "# {}", text),
+            Instruction::InlineAsm(template) => f.write_str(template),
+            // The epilogue depends on the frame layout, so the function
+            // writes it
+            Instruction::Ret => f.write_str("ret"),
+            Instruction::Abort(message) => write!(f, "call abort # {}", message),
+            Instruction::Ud2 => f.write_str("ud2"),
+            Instruction::Call(name, _) => write!(f, "call {}", name),
         }
     }
 }
 
-impl Function {
-    pub fn replace_pseudo(&mut self) -> i32 {
-        let mut pseudo_map = HashMap::new();
-        let mut new_instructions = Vec::new();
-        let mut counter = -4;
+// Streams assembly text to any io::Write. Labels start at column zero,
+// directives and instructions are indented, and comments use the GNU
+// assembler's '#'.
+pub struct AsmWriter<W: Write> {
+    out: W,
+    options: CodegenOptions,
+}
 
-        for instr in self.instructions.iter() {
-            match instr {
-                Instruction::Mov(src, dst) => {
-                    let new_src = Self::replace_operand(src, &mut pseudo_map, &mut counter);
-                    let new_dst = Self::replace_operand(dst, &mut pseudo_map, &mut counter);
-                    new_instructions.push(Instruction::Mov(new_src, new_dst));
+impl<W: Write> AsmWriter<W> {
+    pub fn new(out: W, options: CodegenOptions) -> AsmWriter<W> {
+        AsmWriter { out, options }
+    }
+
+    pub fn options(&self) -> &CodegenOptions {
+        &self.options
+    }
+
+    pub fn directive(&mut self, text: &str) -> io::Result<()> {
+        writeln!(self.out, "    {}", text)
+    }
+
+    pub fn label(&mut self, name: &str) -> io::Result<()> {
+        writeln!(self.out, "{}:", name)
+    }
+
+    pub fn comment(&mut self, text: &str) -> io::Result<()> {
+        writeln!(self.out, "    # {}", text)
+    }
+
+    pub fn instruction(&mut self, instruction: &Instruction) -> io::Result<()> {
+        match instruction {
+            Instruction::Comment(text) if self.options.asm_comments => return self.comment(text),
+            Instruction::Comment(_) => return Ok(()),
+            _ => {}
+        }
+        let text = instruction.display(self.options.target).to_string();
+        if let Instruction::Label(_) = instruction {
+            return writeln!(self.out, "{}", text);
+        }
+        // An asm template separates its instructions with "\n\t"
+        for line in text.lines() {
+            writeln!(self.out, "    {}", line.trim_start())?;
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+// One arm of a branch that only copies a value into dst: a mov, or the mov
+// through %r10 that fix_mov makes of a memory to memory copy. Returns the
+// value, the destination and the index after the arm and any comments
+// around it.
+fn copy_arm(instructions: &[Instruction], index: usize) -> Option<(Operand, Operand, usize)> {
+    let index = skip_comments(instructions, index);
+    let arm = match (instructions.get(index), instructions.get(index + 1)) {
+        (Some(Instruction::Mov(Size::Longword, src, Operand::Register(Reg::R10))), Some(Instruction::Mov(Size::Longword, Operand::Register(Reg::R10), dst))) => {
+            (src.clone(), dst.clone(), index + 2)
+        }
+        (Some(Instruction::Mov(Size::Longword, src, dst)), _) if !matches!(dst, Operand::Register(_)) => (src.clone(), dst.clone(), index + 1),
+        _ => return None,
+    };
+    Some((arm.0, arm.1, skip_comments(instructions, arm.2)))
+}
+
+fn skip_comments(instructions: &[Instruction], mut index: usize) -> usize {
+    while let Some(Instruction::Comment(_)) = instructions.get(index) {
+        index += 1;
+    }
+    index
+}
+
+// Turns a branch that picks one of two values for the same destination, as
+// a ternary like c ? a : b compiles to, into a cmov, which can't be
+// mispredicted:
+//
+//     j<cc> else; mov a, dst; jmp end; else: mov b, dst; end:
+//
+// becomes
+//
+//     mov a, %r11d; cmov<cc> b, %r11d; mov %r11d, dst
+//
+// movs leave the flags alone, so the comparison before the jump still
+// decides. Both values are read either way, so they must be immediates or
+// stack slots: an object in another translation unit might not be safe to
+// read, if its address is the one the other arm avoids. The labels must not
+// be jumped to from anywhere else.
+fn select_conditional_moves(instructions: &[Instruction]) -> Vec<Instruction> {
+    let mut jumps: HashMap<Symbol, usize> = HashMap::new();
+    for instruction in instructions {
+        if let Instruction::Jmp(label) | Instruction::JmpCC(_, label) = instruction {
+            *jumps.entry(*label).or_default() += 1;
+        }
+    }
+    let selectable = |operand: &Operand| matches!(operand, Operand::Imm(_) | Operand::Stack(_));
+    let mut selected = Vec::new();
+    let mut index = 0;
+    while index < instructions.len() {
+        if let Instruction::JmpCC(code, else_label) = &instructions[index] {
+            let branch = copy_arm(instructions, index + 1).and_then(|(then, dst, next)| {
+                let Some(Instruction::Jmp(end_label)) = instructions.get(next) else { return None };
+                let Some(Instruction::Label(label)) = instructions.get(next + 1) else { return None };
+                if label != else_label {
+                    return None;
+                }
+                let (otherwise, other_dst, next) = copy_arm(instructions, next + 2)?;
+                match instructions.get(next) {
+                    Some(Instruction::Label(label)) if label == end_label && other_dst == dst => Some((then, otherwise, dst, *end_label, next + 1)),
+                    _ => None,
+                }
+            });
+            if let Some((then, otherwise, dst, end_label, next)) = branch {
+                if jumps[else_label] == 1 && jumps[&end_label] == 1 && selectable(&then) && selectable(&otherwise) {
+                    selected.push(Instruction::Mov(Size::Longword, then, Operand::Register(Reg::R11)));
+                    let otherwise = match otherwise {
+                        Operand::Imm(_) => {
+                            selected.push(Instruction::Mov(Size::Longword, otherwise, Operand::Register(Reg::R10)));
+                            Operand::Register(Reg::R10)
+                        }
+                        _ => otherwise,
+                    };
+                    selected.push(Instruction::Cmov(code.clone(), Size::Longword, otherwise, Operand::Register(Reg::R11)));
+                    selected.push(Instruction::Mov(Size::Longword, Operand::Register(Reg::R11), dst));
+                    index = next;
+                    continue;
                 }
-                Instruction::Unary(op, dst) => {
-                    let new_dst = Self::replace_operand(dst, &mut pseudo_map, &mut counter);
-                    new_instructions.push(Instruction::Unary(op.clone(), new_dst));
-                },
-                Instruction::Binary(op, src, dst) => {
-                    let new_src = Self::replace_operand(src, &mut pseudo_map, &mut counter);
-                    let new_dst = Self::replace_operand(dst, &mut pseudo_map, &mut counter);
-                    new_instructions.push(Instruction::Binary(op.clone(), new_src, new_dst));
-                },
-                Instruction::Idiv(op) => {
-                    let new_op = Self::replace_operand(op, &mut pseudo_map, &mut counter);
-                    new_instructions.push(Instruction::Idiv(new_op));
-                },
-                Instruction::SetCC(code, dst) => {
-                    let new_dst = Self::replace_operand(dst, &mut pseudo_map, &mut counter);
-                    new_instructions.push(Instruction::SetCC(code.clone(), new_dst));
-                },
-                Instruction::Cmp(src, dst) => {
-                    let new_src = Self::replace_operand(src, &mut pseudo_map, &mut counter);
-                    let new_dst = Self::replace_operand(dst, &mut pseudo_map, &mut counter);
-                    new_instructions.push(Instruction::Cmp(new_src, new_dst));
-                },
-                _ => new_instructions.push(instr.clone()),
             }
         }
+        selected.push(instructions[index].clone());
+        index += 1;
+    }
+    selected
+}
 
-        self.instructions = new_instructions;
-        -counter 
+impl Function {
+    pub fn new(name: Symbol, instructions: Vec<Instruction>) -> Function {
+        Function { name, instructions }
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
     }
 
-    fn replace_operand(
-        operand: &Operand,
-        pseudo_map: &mut HashMap<String, Operand>,
-        counter: &mut i32
-    ) -> Operand {
+    // Replace every pseudo-register with its stack slot and return the frame
+    // size. Pseudos that are never live at the same time share a slot.
+    pub fn replace_pseudo(&mut self) -> i32 {
+        let (slots, frame_size) = assign_stack_slots(&self.instructions);
+        self.instructions = self.instructions.iter()
+            .map(|instr| instr.map_operands(|_, operand| Self::replace_operand(operand, &slots)))
+            .collect();
+        frame_size
+    }
+
+    fn replace_operand(operand: &Operand, slots: &HashMap<Symbol, i32>) -> Operand {
         match operand {
-            Operand::Pseudo(id) => {
-                pseudo_map.entry(id.clone()).or_insert_with(|| {
-                    let new_op = Operand::Stack(*counter);
-                    *counter -= 4;
-                    new_op
-                }).clone()
+            Operand::Pseudo(id) => Operand::Stack(slots[id]),
+            _ => operand.clone(),
+        }
+    }
+
+    // Only a mov into a register can encode a 64-bit immediate; everywhere
+    // else the immediate is 32 bits, sign-extended to the operand size. A
+    // quadword constant outside that range is loaded into R10 first.
+    fn hoist_large_immediate(instr: &Instruction, new_instructions: &mut Vec<Instruction>) -> Instruction {
+        let mut hoist = |size: Size, operand: &Operand| match operand {
+            Operand::Imm(value) if size == Size::Quadword && i32::try_from(*value).is_err() => {
+                new_instructions.push(Instruction::Mov(Size::Quadword, operand.clone(), Operand::Register(Reg::R10)));
+                Operand::Register(Reg::R10)
             }
             _ => operand.clone(),
+        };
+        match instr {
+            Instruction::Mov(size, src, dst) if dst.is_memory() => Instruction::Mov(*size, hoist(*size, src), dst.clone()),
+            Instruction::Binary(op, size, src, dst) if !matches!(op, BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight) => {
+                Instruction::Binary(op.clone(), *size, hoist(*size, src), dst.clone())
+            }
+            Instruction::Cmp(size, src, dst) => Instruction::Cmp(*size, hoist(*size, src), dst.clone()),
+            _ => instr.clone(),
+        }
+    }
+
+    // An instruction can have at most one memory operand, so when both are
+    // in memory the source is loaded into R10 first
+    fn fix_memory_operands(instr: &Instruction, new_instructions: &mut Vec<Instruction>) {
+        let operands = instr.operands();
+        let memory_operands = operands.iter().filter(|(operand, _, _)| operand.is_memory()).count();
+        let source = operands.iter().position(|(operand, _, access)| operand.is_memory() && *access == Access::Read);
+        match source {
+            Some(index) if memory_operands > 1 => {
+                let (operand, size, _) = operands[index];
+                new_instructions.push(Instruction::Mov(size, operand.clone(), Operand::Register(Reg::R10)));
+                new_instructions.push(instr.map_operands(|position, operand| {
+                    if position == index { Operand::Register(Reg::R10) } else { operand.clone() }
+                }));
+            }
+            _ => new_instructions.push(instr.clone()),
         }
     }
 
     pub fn fix_mov(&mut self, stack_size: i32) {
         let mut new_instructions = Vec::new();
         for instr in self.instructions.iter() {
+            let instr = &Self::hoist_large_immediate(instr, &mut new_instructions);
             match instr {
-                Instruction::Mov(src, dst) => {
-                    match (src, dst) {
-                        (Operand::Stack(_), Operand::Stack(_)) => {
-                            new_instructions.push(Instruction::Mov(src.clone(), Operand::Register(Reg::R10)));
-                            new_instructions.push(Instruction::Mov(Operand::Register(Reg::R10), dst.clone()));
-                        },
-                        _ => {
-                            new_instructions.push(instr.clone());
-                        }
-                    }
-                },
-                Instruction::Binary(op, src, dst) => {
+                Instruction::Binary(op, size, src, dst) => {
                     match (op, src, dst) {
-                        (BinaryOperator::Add, Operand::Stack(_), Operand::Stack(_)) |
-                        (BinaryOperator::Sub, Operand::Stack(_), Operand::Stack(_)) => {
-                            new_instructions.push(Instruction::Mov(src.clone(), Operand::Register(Reg::R10)));
-                            new_instructions.push(Instruction::Binary(op.clone(), Operand::Register(Reg::R10), dst.clone()));
+                        (BinaryOperator::Mul, Operand::Imm(_) | Operand::Register(_), dst) if dst.is_memory() => {
+                            new_instructions.push(Instruction::Mov(*size, dst.clone(), Operand::Register(Reg::R11)));
+                            new_instructions.push(Instruction::Binary(BinaryOperator::Mul, *size, src.clone(), Operand::Register(Reg::R11)));
+                            new_instructions.push(Instruction::Mov(*size, Operand::Register(Reg::R11), dst.clone()));
                         },
-                        (BinaryOperator::Mul, Operand::Imm(_), dst @ Operand::Stack(_)) => {
-                            new_instructions.push(Instruction::Mov(dst.clone(), Operand::Register(Reg::R11)));
-                            new_instructions.push(Instruction::Binary(BinaryOperator::Mul, src.clone(), Operand::Register(Reg::R11)));
-                            new_instructions.push(Instruction::Mov(Operand::Register(Reg::R11), dst.clone()));
+                        (BinaryOperator::Mul, src, dst) if src.is_memory() && dst.is_memory() => {
+                            new_instructions.push(Instruction::Mov(*size, src.clone(), Operand::Register(Reg::R10)));
+                            new_instructions.push(Instruction::Mov(*size, dst.clone(), Operand::Register(Reg::R11)));
+                            new_instructions.push(Instruction::Binary(BinaryOperator::Mul, *size, Operand::Register(Reg::R10), Operand::Register(Reg::R11)));
+                            new_instructions.push(Instruction::Mov(*size, Operand::Register(Reg::R11), dst.clone()));
                         },
-                        (BinaryOperator::Mul, src @ Operand::Stack(_), dst @ Operand::Stack(_)) => {
-                            new_instructions.push(Instruction::Mov(src.clone(), Operand::Register(Reg::R10)));
-                            new_instructions.push(Instruction::Mov(dst.clone(), Operand::Register(Reg::R11)));
-                            new_instructions.push(Instruction::Binary(BinaryOperator::Mul, Operand::Register(Reg::R10), Operand::Register(Reg::R11)));
-                            new_instructions.push(Instruction::Mov(Operand::Register(Reg::R11), dst.clone()));
+                        // The hardware masks shift counts to 6 bits for 64-bit operands and 5
+                        // bits otherwise, so do the same for constants
+                        (BinaryOperator::ShiftLeft, Operand::Imm(count), _) |
+                        (BinaryOperator::ShiftRight, Operand::Imm(count), _) => {
+                            let mask = if *size == Size::Quadword { 63 } else { 31 };
+                            new_instructions.push(Instruction::Binary(op.clone(), *size, Operand::Imm(count & mask), dst.clone()));
                         },
-                        (BinaryOperator::Ampersand, Operand::Stack(_), Operand::Stack(_)) |
-                        (BinaryOperator::Pipe, Operand::Stack(_), Operand::Stack(_)) |
-                        (BinaryOperator::Caret, Operand::Stack(_), Operand::Stack(_)) |
-                        (BinaryOperator::ShiftLeft, Operand::Stack(_), Operand::Stack(_)) |
-                        (BinaryOperator::ShiftRight, Operand::Stack(_), Operand::Stack(_)) => {
-                            new_instructions.push(Instruction::Mov(src.clone(), Operand::Register(Reg::R10)));
-                            new_instructions.push(Instruction::Binary(op.clone(), Operand::Register(Reg::R10), dst.clone()));
+                        // Variable shift counts must live in %cl
+                        (BinaryOperator::ShiftLeft, _, _) |
+                        (BinaryOperator::ShiftRight, _, _) => {
+                            new_instructions.push(Instruction::Mov(*size, src.clone(), Operand::Register(Reg::CX)));
+                            new_instructions.push(Instruction::Binary(op.clone(), *size, Operand::Register(Reg::CX), dst.clone()));
                         },
-                        _ => {
-                            new_instructions.push(instr.clone());
-                        }
+                        _ => Self::fix_memory_operands(instr, &mut new_instructions),
                     }
                 },
-                Instruction::Idiv(op) => {
-                    match op {
-                        Operand::Imm(_) => {
-                            new_instructions.push(Instruction::Mov(op.clone(), Operand::Register(Reg::R10)));
-                            new_instructions.push(Instruction::Idiv(Operand::Register(Reg::R10)));
-                        },
-                        _ => {
-                            new_instructions.push(instr.clone());
-                        }
-                    }
+                // div and idiv can't take an immediate divisor
+                Instruction::Idiv(size, Operand::Imm(value)) => {
+                    new_instructions.push(Instruction::Mov(*size, Operand::Imm(*value), Operand::Register(Reg::R10)));
+                    new_instructions.push(Instruction::Idiv(*size, Operand::Register(Reg::R10)));
                 },
-                Instruction::Cmp(src, dst) => {
-                    match (src, dst) {
-                        (Operand::Stack(_), Operand::Stack(_)) => {
-                            new_instructions.push(Instruction::Mov(src.clone(), Operand::Register(Reg::R10)));
-                            new_instructions.push(Instruction::Cmp(Operand::Register(Reg::R10), dst.clone()));
-                        },
-                        (_ , Operand::Imm(_)) => {
-                            new_instructions.push(Instruction::Mov(dst.clone(), Operand::Register(Reg::R11)));
-                            new_instructions.push(Instruction::Cmp(src.clone(), Operand::Register(Reg::R11)));
-                        },
-                        _ => {
-                            new_instructions.push(instr.clone());
-                        }
-                    }
+                Instruction::Div(size, Operand::Imm(value)) => {
+                    new_instructions.push(Instruction::Mov(*size, Operand::Imm(*value), Operand::Register(Reg::R10)));
+                    new_instructions.push(Instruction::Div(*size, Operand::Register(Reg::R10)));
                 },
-                _ => {
-                    new_instructions.push(instr.clone());
-                }
+                // cmp can't compare against an immediate on the right
+                Instruction::Cmp(size, src, dst @ Operand::Imm(_)) => {
+                    new_instructions.push(Instruction::Mov(*size, dst.clone(), Operand::Register(Reg::R11)));
+                    new_instructions.push(Instruction::Cmp(*size, src.clone(), Operand::Register(Reg::R11)));
+                },
+                // movs and movz only write to a register
+                Instruction::MovSignExtend(_, size, _, dst) | Instruction::MovZeroExtend(_, size, _, dst) if dst.is_memory() => {
+                    new_instructions.push(instr.map_operands(|position, operand| {
+                        if position == 1 { Operand::Register(Reg::R11) } else { operand.clone() }
+                    }));
+                    new_instructions.push(Instruction::Mov(*size, Operand::Register(Reg::R11), dst.clone()));
+                },
+                // Each argument is moved into place on its own
+                Instruction::Call(..) => new_instructions.push(instr.clone()),
+                _ => Self::fix_memory_operands(instr, &mut new_instructions),
             }
         }
         self.instructions = new_instructions;
         self.instructions.insert(0, Instruction::AllocateStack(stack_size));
     }
 
-    pub fn to_assembly_file(self, result: &mut String) {
-        result.push_str(&format!(".globl _{}\n", self.name));
-        result.push_str(&format!("_{}:\n", self.name));
-        result.push_str("pushq %rbp\n");
-        result.push_str("movq %rsp, %rbp\n");
-        for instr in self.instructions.iter() {
+    fn makes_calls(&self) -> bool {
+        self.instructions.iter().any(|instruction| matches!(instruction, Instruction::Call(..)))
+    }
+
+    // Bytes reserved below the return address for locals, once fixed up
+    pub fn frame_size(&self) -> i32 {
+        match self.instructions.first() {
+            Some(Instruction::AllocateStack(size)) => *size,
+            _ => 0,
+        }
+    }
+
+    // Writes the function, adding the strings its code refers to to strings
+    pub fn write_assembly<W: Write>(&self, writer: &mut AsmWriter<W>, strings: &mut StringPool) -> io::Result<()> {
+        let options = *writer.options();
+        let target = options.target;
+        let name = target.symbol_name(&self.name.text());
+        writer.directive(&options.text_section(&self.name.text()))?;
+        if target == Target::Windows {
+            // COFF symbol record: external storage class, function type
+            writer.directive(&format!(".def {}; .scl 2; .type 32; .endef", name))?;
+        }
+        writer.directive(&format!(".globl {}", name))?;
+        if target == Target::Linux {
+            writer.directive(&format!(".type {}, @function", name))?;
+        }
+        writer.label(&name)?;
+        // Without a frame pointer %rsp stays where the prologue's subq
+        // leaves it, so each slot is addressed from %rsp by adding the frame
+        // size to its %rbp offset, and returning only gives the frame back.
+        // Debuggers and profilers that walk the %rbp chain can't unwind
+        // through such a function.
+        // A call needs %rsp 16-byte aligned, which the frame, rounded to 16
+        // bytes, leaves it once %rbp is pushed. Without that push the
+        // return address leaves it 8 bytes off, which 8 more make up.
+        let frame_size = match options.omit_frame_pointer && self.makes_calls() {
+            true => self.frame_size() + 8,
+            false => self.frame_size(),
+        };
+        // Where an operand is once pushed bytes have moved %rsp
+        let place = |operand: &Operand, pushed: i32| match operand {
+            Operand::Stack(offset) if options.omit_frame_pointer => Operand::Memory(Reg::SP, frame_size + offset + pushed),
+            Operand::Data(name) => Operand::Data(Symbol::intern(&target.symbol_name(&name.text()))),
+            Operand::Static(label) => Operand::Static(Symbol::intern(&format!("{}{}", target.local_label_prefix(), label))),
+            _ => operand.clone(),
+        };
+        if !options.omit_frame_pointer {
+            writer.directive("pushq %rbp")?;
+            writer.directive("movq %rsp, %rbp")?;
+        }
+        let instructions = if options.opt_level > 0 {
+            Cow::Owned(select_conditional_moves(&self.instructions))
+        } else {
+            Cow::Borrowed(&self.instructions[..])
+        };
+        // The message of each handler, in the order they are first jumped to
+        let mut handlers: Vec<&str> = Vec::new();
+        for instr in instructions.iter() {
             match instr {
-                Instruction::Mov(src, dst) => {
-                    result.push_str(&format!("movl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
+                Instruction::Abort(message) => {
+                    writer.instruction(&Instruction::Jmp(self.handler_label(&mut handlers, message)))?;
+                    continue;
                 }
-                Instruction::Unary(op, dst) => {
-                    match op {
-                        UnaryOperator::Neg => {
-                            result.push_str(&format!("negl {}\n", dst.to_assembly_file()))
-                        }
-                        UnaryOperator::Not => {
-                            result.push_str(&format!("notl {}\n", dst.to_assembly_file()))
-                        }
-                        UnaryOperator::LogicalNot => {
-                            result.push_str(&format!("cmpl $0, {}\n", dst.to_assembly_file()));
-                            result.push_str(&format!("movl $0, %eax\n"));
-                            result.push_str(&format!("sete %al\n"));
-                            result.push_str(&format!("movzbl %al, %eax\n"));
-                            result.push_str(&format!("movl %eax, {}\n", dst.to_assembly_file()));
-                    }
+                Instruction::Call(name, args) => {
+                    write_call(writer, *name, args, place)?;
+                    continue;
                 }
+                Instruction::AllocateStack(_) => {
+                    writer.instruction(&Instruction::AllocateStack(frame_size))?;
+                    continue;
+                }
+                _ => {}
             }
-                Instruction::AllocateStack(size) => {
-                    result.push_str(&format!("subq ${}, %rsp\n", size));
+            if let Instruction::Ret = instr {
+                if options.omit_frame_pointer {
+                    writer.directive(&format!("addq ${}, %rsp", frame_size))?;
+                } else {
+                    writer.directive("movq %rbp, %rsp")?;
+                    writer.directive("popq %rbp")?;
                 }
-                Instruction::Ret => {
-                    result.push_str("movq %rbp, %rsp\n");
-                    result.push_str("popq %rbp\n");
-                    result.push_str("ret\n");
-                },
-                Instruction::Binary(op, src, dst) => {
-                    match op {
-                        BinaryOperator::Add => {
-                            result.push_str(&format!("addl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                        }
-                        BinaryOperator::Sub => {
-                            result.push_str(&format!("subl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                        }
-                        BinaryOperator::Mul => {
-                            result.push_str(&format!("imull {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                        }
-                        BinaryOperator::Ampersand => {
-                            result.push_str(&format!("andl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                        }
-                        BinaryOperator::Pipe => {
-                            result.push_str(&format!("orl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                        }
-                        BinaryOperator::Caret => {
-                            result.push_str(&format!("xorl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                        }
-                        BinaryOperator::ShiftLeft => {
-                            match (src, dst) {
-                                (Operand::Register(_), Operand::Stack(_)) => {
-                                    result.push_str(&format!("movl {}, %ecx\n", src.to_assembly_file()));
-                                    result.push_str(&format!("sall %cl, {}\n", dst.to_assembly_file()));
-                                },
-                                (Operand::Stack(_), Operand::Stack(_)) => {
-                                    result.push_str(&format!("movl {}, %ecx\n", src.to_assembly_file()));
-                                    result.push_str(&format!("movl {}, %eax\n", dst.to_assembly_file()));
-                                    result.push_str("sall %cl, %eax\n");
-                                    result.push_str(&format!("movl %eax, {}\n", dst.to_assembly_file()));
-                                },
-                                _ => {
-                                    result.push_str(&format!("sall {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                                }
-                            }
-                        },
-                        BinaryOperator::ShiftRight => {
-                            match (src, dst) {
-                                (Operand::Register(_), Operand::Stack(_)) => {
-                                    result.push_str(&format!("movl {}, %ecx\n", src.to_assembly_file()));
-                                    result.push_str(&format!("sarl %cl, {}\n", dst.to_assembly_file()));
-                                },
-                                (Operand::Stack(_), Operand::Stack(_)) => {
-                                    result.push_str(&format!("movl {}, %ecx\n", src.to_assembly_file()));
-                                    result.push_str(&format!("movl {}, %eax\n", dst.to_assembly_file()));
-                                    result.push_str("sarl %cl, %eax\n");
-                                    result.push_str(&format!("movl %eax, {}\n", dst.to_assembly_file()));
-                                },
-                                _ => {
-                                    result.push_str(&format!("sarl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                                }
-                            }
-                        },
-                    }
-                },
-                Instruction::Idiv(op) => {
-                    result.push_str(&format!("idivl {}\n", op.to_assembly_file()));
-                },
-                Instruction::Cdq => {
-                    result.push_str("cdq\n");
-                },
-                Instruction::Jmp(label) => {
-                    result.push_str(&format!("jmp L{}\n", label));
-                },
-                Instruction::JmpCC(code, label) => {
-                    result.push_str(&format!("j{} L{}\n", match code {
-                        CodeGen::E => "e",
-                        CodeGen::NE => "ne",
-                        CodeGen::G => "g",
-                        CodeGen::GE => "ge",
-                        CodeGen::L => "l",
-                        CodeGen::LE => "le",
-                    }, label));
-                },
-                Instruction::SetCC(code, dst) => {
-                    // First initialize the destination to 0
-                    result.push_str(&format!("movl $0, {}\n", dst.to_assembly_file()));
-                    // Set the result bit based on the condition
-                    result.push_str(&format!("set{} {}\n", match code {
-                        CodeGen::E => "e",
-                        CodeGen::NE => "ne",
-                        CodeGen::G => "g",
-                        CodeGen::GE => "ge",
-                        CodeGen::L => "l",
-                        CodeGen::LE => "le",
-                    }, dst.to_assembly_file_byte()));
-                    // Zero extend the byte result to 32 bits
-                    match dst {
-                        Operand::Stack(_) => {
-                            result.push_str(&format!("movzbl {}, %eax\n", dst.to_assembly_file_byte()));
-                            result.push_str(&format!("movl %eax, {}\n", dst.to_assembly_file()));
-                        },
-                        _ => {
-                            result.push_str(&format!("movzbl {}, {}\n", 
-                                dst.to_assembly_file_byte(), 
-                                dst.to_assembly_file()));
-                        }
-                    }
-                },
-                Instruction::Label(label) => {
-                    result.push_str(&format!("L{}:\n", label));
-                },
-                Instruction::Cmp(src, dst) => {
-                    result.push_str(&format!("cmpl {}, {}\n", src.to_assembly_file(), dst.to_assembly_file()));
-                },
             }
+            writer.instruction(&instr.map_operands(|_, operand| place(operand, 0)))?;
+            if options.trapv && can_overflow(instr) {
+                writer.instruction(&Instruction::JmpCC(CodeGen::O, self.handler_label(&mut handlers, "signed integer overflow")))?;
+            }
+        }
+        for (index, message) in handlers.iter().enumerate() {
+            self.write_abort_handler(writer, index, message, strings)?;
+        }
+        if target == Target::Linux {
+            writer.directive(&format!(".size {}, .-{}", name, name))?;
+        }
+        Ok(())
+    }
+}
+
+// Whether instruction is signed arithmetic that sets the overflow flag
+// when its result doesn't fit, which -ftrapv checks after it
+fn can_overflow(instruction: &Instruction) -> bool {
+    matches!(instruction,
+        Instruction::Binary(BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul, ..)
+        | Instruction::Unary(UnaryOperator::Neg, ..))
+}
+
+// A call written for the target's convention: the first arguments go in
+// registers and the rest on the stack, pushed last to first, with %rsp kept
+// 16-byte aligned at the call and, for Microsoft x64, the shadow space
+// reserved above the return address. The caller pops it all afterwards.
+fn write_call<W: Write>(writer: &mut AsmWriter<W>, name: Symbol, args: &[Operand], place: impl Fn(&Operand, i32) -> Operand) -> io::Result<()> {
+    let options = *writer.options();
+    let registers = options.target.argument_registers();
+    let (in_registers, on_stack) = args.split_at(args.len().min(registers.len()));
+    let mut pushed = 0;
+    if on_stack.len() % 2 == 1 {
+        writer.directive("subq $8, %rsp")?;
+        pushed += 8;
+    }
+    for arg in on_stack.iter().rev() {
+        match place(arg, pushed) {
+            Operand::Imm(value) => writer.directive(&format!("pushq ${}", value))?,
+            // pushq would read 8 bytes of a 4-byte variable
+            operand => {
+                writer.instruction(&Instruction::Mov(Size::Longword, operand, Operand::Register(Reg::AX)))?;
+                writer.directive("pushq %rax")?;
+            }
+        }
+        pushed += 8;
+    }
+    for (arg, register) in in_registers.iter().zip(registers) {
+        writer.instruction(&Instruction::Mov(Size::Longword, place(arg, pushed), Operand::Register(*register)))?;
+    }
+    let shadow_space = options.target.shadow_space();
+    if shadow_space > 0 {
+        writer.directive(&format!("subq ${}, %rsp", shadow_space))?;
+        pushed += shadow_space;
+    }
+    writer.directive(&library_call(&options, &name.text()))?;
+    if pushed > 0 {
+        writer.directive(&format!("addq ${}, %rsp", pushed))?;
+    }
+    Ok(())
+}
+
+// How a call to name, which the linker resolves, is written for options:
+// Mach-O adds its underscore, and position-independent ELF code calls
+// through the PLT
+fn library_call(options: &CodegenOptions, name: &str) -> String {
+    match options.target {
+        Target::Linux if options.pic => format!("call {}@PLT", name),
+        target => format!("call {}", target.symbol_name(name)),
+    }
+}
+
+// Linux system call numbers and the signal abort raises, for -nostdlib
+const SYS_WRITE: i32 = 1;
+const SYS_GETPID: i32 = 39;
+const SYS_KILL: i32 = 62;
+const SYS_EXIT_GROUP: i32 = 231;
+const SIGABRT: i32 = 6;
+
+// The _start a -nostdlib program begins at in place of the C library's. The
+// kernel leaves %rsp 16-byte aligned, as main expects before the call, and
+// main's result becomes the exit status.
+fn write_entry_point<W: Write>(writer: &mut AsmWriter<W>, main: Symbol) -> io::Result<()> {
+    writer.directive(".text")?;
+    writer.directive(".globl _start")?;
+    writer.directive(".type _start, @function")?;
+    writer.label("_start")?;
+    // Marks the outermost frame for debuggers
+    writer.directive("xorl %ebp, %ebp")?;
+    writer.directive(&format!("call {}", main))?;
+    writer.directive("movl %eax, %edi")?;
+    writer.directive(&format!("movl ${}, %eax", SYS_EXIT_GROUP))?;
+    writer.directive("syscall")?;
+    writer.directive(".size _start, .-_start")
+}
+
+impl Function {
+    fn abort_label(&self, index: usize) -> Symbol {
+        Symbol::intern(&format!("{}_abort_{}", self.name, index))
+    }
+
+    // The label of the handler reporting message, adding one if no earlier
+    // check has needed it
+    fn handler_label<'a>(&self, handlers: &mut Vec<&'a str>, message: &'a str) -> Symbol {
+        let index = handlers.iter().position(|handler| *handler == message).unwrap_or_else(|| {
+            handlers.push(message);
+            handlers.len() - 1
+        });
+        self.abort_label(index)
+    }
+
+    // The runtime the checks need, written after the function's code: each
+    // failed check jumps to the handler for its message, which reports it on
+    // stderr and aborts. The stack is realigned first, as the jump may come
+    // from anywhere in the function.
+    fn write_abort_handler<W: Write>(&self, writer: &mut AsmWriter<W>, index: usize, message: &str, strings: &mut StringPool) -> io::Result<()> {
+        let options = *writer.options();
+        let target = options.target;
+        if options.freestanding {
+            writer.instruction(&Instruction::Label(self.abort_label(index)))?;
+            return writer.directive("ud2");
+        }
+        let text = format!("{}\n", message);
+        let text_label = strings.label(&text, target);
+        let arguments = target.argument_registers();
+        writer.instruction(&Instruction::Label(self.abort_label(index)))?;
+        writer.directive("andq $-16, %rsp")?;
+        if target.shadow_space() > 0 {
+            writer.directive(&format!("subq ${}, %rsp", target.shadow_space()))?;
+        }
+        // write(2, text, length), which the Microsoft C runtime spells _write
+        writer.directive(&format!("movl $2, {}", arguments[0].name(Size::Longword)))?;
+        writer.directive(&format!("leaq {}(%rip), {}", text_label, arguments[1].name(Size::Quadword)))?;
+        writer.directive(&format!("movl ${}, {}", text.len(), arguments[2].name(Size::Longword)))?;
+        if options.nostdlib {
+            // The same through Linux system calls, where abort is
+            // kill(getpid(), SIGABRT)
+            writer.directive(&format!("movl ${}, %eax", SYS_WRITE))?;
+            writer.directive("syscall")?;
+            writer.directive(&format!("movl ${}, %eax", SYS_GETPID))?;
+            writer.directive("syscall")?;
+            writer.directive("movl %eax, %edi")?;
+            writer.directive(&format!("movl ${}, %esi", SIGABRT))?;
+            writer.directive(&format!("movl ${}, %eax", SYS_KILL))?;
+            writer.directive("syscall")?;
+        } else {
+            writer.directive(&library_call(&options, if target == Target::Windows { "_write" } else { "write" }))?;
+            writer.directive(&library_call(&options, "abort"))?;
+        }
+        Ok(())
+    }
+}
+
+// The strings a translation unit's code refers to, in the order they are
+// first needed. Each is stored once, however many places use it.
+#[derive(Debug, Default)]
+pub struct StringPool {
+    strings: Vec<String>,
+}
+
+impl StringPool {
+    // The label of text, adding it to the pool if nothing has needed it yet
+    fn label(&mut self, text: &str, target: Target) -> String {
+        let index = self.strings.iter().position(|string| string == text).unwrap_or_else(|| {
+            self.strings.push(text.to_string());
+            self.strings.len() - 1
+        });
+        format!("{}str.{}", target.local_label_prefix(), index)
+    }
+
+    // The strings are read-only and end in a NUL, as C's do, which a section
+    // of mergeable strings needs to tell them apart
+    fn write_assembly<W: Write>(&self, writer: &mut AsmWriter<W>) -> io::Result<()> {
+        if self.strings.is_empty() {
+            return Ok(());
+        }
+        let options = *writer.options();
+        writer.directive(&options.string_section())?;
+        for (index, string) in self.strings.iter().enumerate() {
+            writer.label(&format!("{}str.{}", options.target.local_label_prefix(), index))?;
+            writer.directive(&format!(".asciz {:?}", string))?;
+        }
+        Ok(())
+    }
+}
+
+impl StaticVariable {
+    // A zero value goes in .bss, which takes no space in the object file.
+    // The label is a local one, so the object has no symbol for it.
+    fn write_assembly<W: Write>(&self, writer: &mut AsmWriter<W>) -> io::Result<()> {
+        let name = format!("{}{}", writer.options().target.local_label_prefix(), self.label);
+        writer.directive(if self.init == 0 { ".bss" } else { ".data" })?;
+        writer.directive(".balign 4")?;
+        writer.label(&name)?;
+        if self.init == 0 {
+            writer.directive(".zero 4")
+        } else {
+            writer.directive(&format!(".long {}", self.init))
         }
     }
 }
 
 impl Program {
     pub fn apply_fixes(&mut self) {
-        let stack_size = self.function.replace_pseudo();
+        let mut stack_size = self.function.replace_pseudo();
+        // See Function::write_assembly
+        if self.function.makes_calls() {
+            stack_size = (stack_size + 15) / 16 * 16;
+        }
         self.function.fix_mov(stack_size);
     }
 
-    pub fn to_assembly_file(&self) -> String {
-        let mut result = String::new();
-        self.function.clone().to_assembly_file(&mut result);
-        result
+    // Whether the fixed-up program is one the assembler will accept
+    pub fn verify(&self) -> Result<(), String> {
+        verify::verify_instructions(&self.function.instructions)
+    }
+
+    pub fn write_assembly<W: Write>(&self, out: W, options: &CodegenOptions) -> io::Result<W> {
+        self.interner.enter(|| self.write_assembly_in(out, options))
     }
+
+    fn write_assembly_in<W: Write>(&self, out: W, options: &CodegenOptions) -> io::Result<W> {
+        let mut writer = AsmWriter::new(out, *options);
+        let mut strings = StringPool::default();
+        self.function.write_assembly(&mut writer, &mut strings)?;
+        if options.nostdlib {
+            write_entry_point(&mut writer, self.function.name)?;
+        }
+        strings.write_assembly(&mut writer)?;
+        for variable in &self.statics {
+            variable.write_assembly(&mut writer)?;
+        }
+        if options.target == Target::Linux {
+            // Without this note GNU ld assumes the object needs an executable stack
+            writer.directive(".section .note.GNU-stack,\"\",@progbits")?;
+        }
+        Ok(writer.into_inner())
+    }
+
+    pub fn to_assembly_file(&self, options: &CodegenOptions) -> String {
+        let bytes = self.write_assembly(Vec::new(), options).expect("writing to a Vec can't fail");
+        String::from_utf8(bytes).expect("assembly text is UTF-8")
+    }
+
+    // Warnings about code generated with options, none of which stop it
+    // being written
+    pub fn diagnostics(&self, options: &CodegenOptions) -> Vec<Diagnostic> {
+        self.interner.enter(|| self.diagnostics_in(options))
+    }
+
+    fn diagnostics_in(&self, options: &CodegenOptions) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let has_inline_asm = self.function.instructions.iter().any(|instr| matches!(instr, Instruction::InlineAsm(_)));
+        if options.omit_frame_pointer && has_inline_asm {
+            diagnostics.push(Diagnostic {
+                message: format!("'{}' contains inline assembly, which can't rely on %rbp holding a frame pointer with -fomit-frame-pointer", self.function.name),
+                line: None,
+                column: None,
+                code: None,
+            });
+        }
+        diagnostics
+    }
+}
+
+// What codegen produced for a program, for tools that want to inspect it
+// without parsing the assembly text back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilationArtifacts {
+    pub assembly: String,
+    // The global symbols defined, spelled as the object file names them
+    pub symbols: Vec<String>,
+    // The frame size of each function, in bytes
+    pub stack_sizes: Vec<(String, i32)>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// Writes program out for the target in options. Codegen has already
+// accepted the program by now, so this can't fail.
+pub fn emit_assembly(program: &Program, options: &CodegenOptions) -> CompilationArtifacts {
+    let function = &program.function;
+    program.interner.enter(|| CompilationArtifacts {
+        assembly: program.to_assembly_file(options),
+        symbols: vec![options.target.symbol_name(&function.name.text())],
+        stack_sizes: vec![(function.name.to_string(), function.frame_size())],
+        diagnostics: program.diagnostics(options),
+    })
 }
 
 pub fn generate_assembly_ast(program: TacProgram) -> Program {
-    program.to_assembly_program()
+    program.into_assembly_program()
 }
 
@@ -0,0 +1,451 @@
+#[path = "../preprocess.rs"]
+mod preprocess;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{self, json_string, Diagnostic, Stage};
+use c_compiler_lib::intern::{Interner, Symbol};
+use c_compiler_lib::lex::{Lex, Span};
+use c_compiler_lib::parser::{self, BlockItem, Declaration, FunctionDeclaration, Program, Type};
+use c_compiler_lib::symbols::StorageClass;
+use c_compiler_lib::target::Target;
+use c_compiler_lib::timing::PassTimer;
+use preprocess::PreprocessOptions;
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process;
+
+// A language server over stdio, built with --features lsp. It publishes the
+// compiler's diagnostics whenever a document changes, and lists the
+// functions and variables it declares. Only what that needs of the protocol
+// is implemented, including a small JSON reader.
+//
+// Documents are preprocessed as the compiler does it, with the dialect and
+// preprocessor flags the server was started with:
+//
+//     c_compiler_lsp --std=c23 -Iinclude -DDEBUG
+
+struct Settings {
+    dialect: Dialect,
+    preprocess_options: PreprocessOptions,
+}
+
+#[derive(Debug, Clone)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    // Request ids are echoed back, so they must be written out again
+    fn to_json(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(value) => value.to_string(),
+            Json::Number(value) => value.to_string(),
+            Json::String(text) => json_string(text),
+            Json::Array(items) => format!("[{}]", items.iter().map(Json::to_json).collect::<Vec<_>>().join(",")),
+            Json::Object(members) => format!("{{{}}}", members.iter()
+                .map(|(name, value)| format!("{}:{}", json_string(name), value.to_json()))
+                .collect::<Vec<_>>()
+                .join(",")),
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser { chars: text.chars().peekable() };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected '{}' after JSON value", c)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected '{}', got '{}'", expected, c)),
+            None => Err(format!("Expected '{}', got end of input", expected)),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.chars.next();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&']').is_some() {
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(Json::Array(items)),
+                        _ => return Err("Expected ',' or ']' in array".to_string()),
+                    }
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&'}').is_some() {
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let name = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(':')?;
+                    members.push((name, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some('}') => return Ok(Json::Object(members)),
+                        _ => return Err("Expected ',' or '}' in object".to_string()),
+                    }
+                }
+            }
+            Some(c) if *c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+                    number.push(c);
+                }
+                number.parse().map(Json::Number).map_err(|_| format!("Invalid number '{}'", number))
+            }
+            Some(c) => Err(format!("Unexpected '{}' in JSON", c)),
+            None => Err("Unexpected end of JSON".to_string()),
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid \\u escape '{}'", digits))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.chars.next().ok_or("Unterminated string")? {
+                '"' => return Ok(text),
+                '\\' => match self.chars.next().ok_or("Unterminated string")? {
+                    'n' => text.push('\n'),
+                    'r' => text.push('\r'),
+                    't' => text.push('\t'),
+                    'b' => text.push('\u{8}'),
+                    'f' => text.push('\u{c}'),
+                    'u' => {
+                        let mut code = self.hex4()?;
+                        // Characters outside the BMP arrive as a surrogate pair
+                        if (0xd800..0xdc00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.hex4()?;
+                            code = match low {
+                                0xdc00..=0xdfff => 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00),
+                                _ => u32::MAX,
+                            };
+                        }
+                        text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    c => text.push(c),
+                },
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+// Reads one message, or None at end of input
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "message without Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn write_message(output: &mut impl Write, body: &str) -> io::Result<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// LSP positions are 0-based; the compiler's lines and columns are 1-based
+fn position(line: usize, column: usize) -> String {
+    format!("{{\"line\":{},\"character\":{}}}", line.saturating_sub(1), column.saturating_sub(1))
+}
+
+fn range(line: usize, column: usize, length: usize) -> String {
+    format!("{{\"start\":{},\"end\":{}}}", position(line, column), position(line, column + length))
+}
+
+// From the start of one name to the end of another
+fn range_between(start: &Span, end: &Span) -> String {
+    format!("{{\"start\":{},\"end\":{}}}", position(start.line, start.column), position(end.line, end.column + end.end - end.start))
+}
+
+// The file a document is, from its file: URI, with %XX escapes decoded.
+// Other documents, such as an editor's new ones, are taken to be in the
+// current directory.
+fn document_path(uri: &str) -> PathBuf {
+    let Some(mut rest) = uri.strip_prefix("file://").map(str::as_bytes) else {
+        return PathBuf::from("untitled.c");
+    };
+    let mut path = Vec::new();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail.get(..2).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                path.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                path.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&path).into_owned())
+}
+
+// Severities 1 and 2 are Error and Warning. Diagnostics without a position
+// are shown at the start of the file.
+fn diagnostic(severity: u32, diagnostic: &Diagnostic) -> String {
+    let (line, column) = (diagnostic.line.unwrap_or(1), diagnostic.column.unwrap_or(1));
+    let code = diagnostic.code.map_or(String::new(), |code| format!(",\"code\":{}", json_string(code)));
+    format!("{{\"range\":{},\"severity\":{},\"source\":\"c_compiler\"{},\"message\":{}}}",
+        range(line, column, 1), severity, code, json_string(&diagnostic.message))
+}
+
+// The document's text is preprocessed rather than its file, which may not
+// have been saved. Linemarkers keep the lines those of the document.
+fn preprocess(uri: &str, text: &str, settings: &Settings) -> Result<String, String> {
+    let preprocessed = preprocess::preprocess(&document_path(uri), Some(text), &settings.dialect, &settings.preprocess_options, Target::host());
+    // The preprocessor's own account of what went wrong says where
+    preprocessed.output.map_err(|err| match preprocessed.messages.trim_end() {
+        "" => err,
+        messages => format!("{}\n{}", err, messages),
+    })
+}
+
+fn diagnostics(uri: &str, text: &str, settings: &Settings) -> Vec<String> {
+    let source = match preprocess(uri, text, settings) {
+        Ok(source) => source,
+        Err(message) => return vec![diagnostic(1, &Diagnostic { message, line: None, column: None, code: None })],
+    };
+    let Err(err) = driver::compile_until(&source, &settings.dialect, Stage::Validate, &mut PassTimer::new(false)) else {
+        let warnings = driver::warnings(&source, &settings.dialect).expect("the document was checked once already");
+        return warnings.iter().map(|warning| diagnostic(2, warning)).collect();
+    };
+    vec![diagnostic(1, &Diagnostic::from(&err))]
+}
+
+fn document_symbol(name: &str, kind: u32, range: &str, name_range: &str, children: &[String]) -> String {
+    let children = match children {
+        [] => String::new(),
+        children => format!(",\"children\":[{}]", children.join(",")),
+    };
+    format!("{{\"name\":{},\"kind\":{},\"range\":{},\"selectionRange\":{}{}}}", json_string(name), kind, range, name_range, children)
+}
+
+fn name_range(span: &Span) -> String {
+    range(span.line, span.column, span.end - span.start)
+}
+
+// The symbols the document declares: the file-scope ones as --emit-symbols
+// lists them, at their first declaration, and main at its definition, with
+// the variables declared in its body as its children. Those declared extern
+// are file-scope objects and are listed as such. None if the document
+// doesn't get through resolution.
+fn document_symbols(uri: &str, text: &str, settings: &Settings) -> Option<Vec<String>> {
+    let source = preprocess(uri, text, settings).ok()?;
+    let mut tokens = Lex::with_dialect(&source, &settings.dialect).get_tokens().ok()?;
+    let program = parser::parse_program(&mut tokens, &settings.dialect).ok()?;
+    let Program::Program(_, FunctionDeclaration::Function(main, items, main_span), ..) = &program;
+    let (main, main_span) = (*main, *main_span);
+    let locals: Vec<(Symbol, Span)> = items.iter().filter_map(|item| match item.as_ref() {
+        BlockItem::D(Declaration::Declaration(name, _, _, storage_class, span)) if *storage_class != Some(StorageClass::Extern) => Some((*name, *span)),
+        _ => None,
+    }).collect();
+    let (_, symbols) = parser::resolve_program_with_symbols(program).ok()?;
+    // Symbol kinds 12 and 13 are Function and Variable
+    Some(symbols.entries().into_iter().filter_map(|(name, entry)| {
+        let span = entry.declarations.first()?;
+        Some(match entry.declared_type {
+            Type::Function(..) if name == main => {
+                let children: Vec<String> = locals.iter().map(|(name, span)| document_symbol(&name.text(), 13, &name_range(span), &name_range(span), &[])).collect();
+                let range = locals.last().map_or(name_range(&main_span), |(_, last)| range_between(&main_span, last));
+                document_symbol(&name.text(), 12, &range, &name_range(&main_span), &children)
+            }
+            Type::Function(..) => document_symbol(&name.text(), 12, &name_range(span), &name_range(span), &[]),
+            _ => document_symbol(&name.text(), 13, &name_range(span), &name_range(span), &[]),
+        })
+    }).collect())
+}
+
+fn uri_and_text(params: Option<&Json>) -> Option<(String, String)> {
+    let params = params?;
+    let uri = params.get("textDocument")?.get("uri")?.as_str()?.to_string();
+    // didOpen carries the text in the document; didChange, with full
+    // synchronization, as the last content change
+    let text = match params.get("textDocument")?.get("text") {
+        Some(text) => text.as_str()?.to_string(),
+        None => match params.get("contentChanges")? {
+            Json::Array(changes) => changes.last()?.get("text")?.as_str()?.to_string(),
+            _ => return None,
+        },
+    };
+    Some((uri, text))
+}
+
+// Each message is handled with an interner of its own, so the server
+// doesn't keep the names of every version of every document
+fn main() -> io::Result<()> {
+    let mut settings = Settings { dialect: Dialect::default(), preprocess_options: PreprocessOptions::new() };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let known = match settings.dialect.parse_flag(&arg) {
+            Ok(false) => settings.preprocess_options.parse_flag(&arg, || args.next()),
+            known => known,
+        };
+        match known {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("Unknown flag '{}'", arg);
+                process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    // The symbols of each open document, from the last version of it that
+    // got through resolution, so the outline stays while it is being edited
+    let mut outlines: HashMap<String, Vec<String>> = HashMap::new();
+    let mut shutting_down = false;
+
+    while let Some(body) = read_message(&mut input)? {
+        let message = match JsonParser::parse(&body) {
+            Ok(message) => message,
+            Err(err) => {
+                eprintln!("Ignoring malformed message: {}", err);
+                continue;
+            }
+        };
+        let method = message.get("method").and_then(Json::as_str).unwrap_or_default();
+        let params = message.get("params");
+        let id = message.get("id");
+
+        let result = match method {
+            "initialize" => Some("{\"capabilities\":{\"textDocumentSync\":1,\"documentSymbolProvider\":true},\
+                \"serverInfo\":{\"name\":\"c_compiler\"}}".to_string()),
+            "shutdown" => {
+                shutting_down = true;
+                Some("null".to_string())
+            }
+            "exit" => break,
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                if let Some((uri, text)) = uri_and_text(params) {
+                    let notification = format!(
+                        "{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{{\"uri\":{},\"diagnostics\":[{}]}}}}",
+                        json_string(&uri), Interner::new().enter(|| diagnostics(&uri, &text, &settings)).join(","));
+                    write_message(&mut output, &notification)?;
+                    if let Some(symbols) = Interner::new().enter(|| document_symbols(&uri, &text, &settings)) {
+                        outlines.insert(uri, symbols);
+                    }
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params.and_then(|params| params.get("textDocument")?.get("uri")?.as_str()) {
+                    outlines.remove(uri);
+                }
+                None
+            }
+            "textDocument/documentSymbol" => {
+                let uri = params.and_then(|params| params.get("textDocument")?.get("uri")?.as_str()).unwrap_or_default();
+                Some(format!("[{}]", outlines.get(uri).map_or(String::new(), |symbols| symbols.join(","))))
+            }
+            _ => None,
+        };
+
+        // Only requests carry an id and need an answer, even for methods we
+        // don't support
+        if let Some(id) = id {
+            let response = match result {
+                Some(result) => format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{}}}", id.to_json(), result),
+                None => format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"error\":{{\"code\":-32601,\"message\":{}}}}}",
+                    id.to_json(), json_string(&format!("Unsupported method '{}'", method))),
+            };
+            write_message(&mut output, &response)?;
+        }
+    }
+    // Exiting without a shutdown request first is an error by the protocol
+    if !shutting_down {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::intern::Symbol;
+use crate::tac::{BinaryOperator, Function, Instruction, Val};
+
+// Runtime checks that turn undefined behavior into a reported abort. They
+// are inserted into the optimized TAC, so no pass can reason them away, and
+// each one branches around an Abort that codegen sends to a handler after
+// the function's code.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RuntimeChecks {
+    // --check-div-zero: test the divisor of every / and % first
+    pub div_zero: bool,
+}
+
+pub fn insert_checks(function: &mut Function, checks: RuntimeChecks) {
+    if checks.div_zero {
+        check_division(function);
+    }
+}
+
+// Before each division by a value that isn't a constant other than zero:
+//     if divisor != 0 jump main_nonzero_N
+//     abort "division by zero"
+//     main_nonzero_N:
+fn check_division(function: &mut Function) {
+    let mut body = Vec::with_capacity(function.body.len());
+    let mut count = 0;
+    for instruction in function.body.drain(..) {
+        if let Instruction::Binary { operator: BinaryOperator::Divide | BinaryOperator::Modulo, src2, .. } = &instruction {
+            if !matches!(src2, Val::Constant(value) if *value != 0) {
+                let label = Symbol::intern(&format!("{}_nonzero_{}", function.identifier, count));
+                count += 1;
+                body.push(Instruction::JumpIfNotZero { src: src2.clone(), label, likely: Some(true) });
+                body.push(Instruction::Abort("division by zero".to_string()));
+                body.push(Instruction::Label { label });
+            }
+        }
+        body.push(instruction);
+    }
+    function.body = body;
+}
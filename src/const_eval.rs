@@ -0,0 +1,232 @@
+use crate::lex::Span;
+use crate::parser::{BinaryOp, Exp, ExpArena, ExpId, Type, UnaryOp};
+use std::collections::HashMap;
+
+// Evaluation of integer constant expressions. This is the one place that
+// decides what counts as a constant and what its value is, so every context
+// that needs one (array sizes, case labels, bit-field widths, #if, static
+// assertions) gets the same rules and diagnostics. Arithmetic is range-checked
+// in int: anything that would overflow or is undefined is reported instead of
+// silently wrapping.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstEvalError {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl std::fmt::Display for ConstEvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{}: {}", span, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn error(message: &str) -> ConstEvalError {
+    ConstEvalError { message: message.to_string(), span: None }
+}
+
+// The location of the first variable in exp, used to point at the part of an
+// expression that keeps it from being constant
+fn first_span(exps: &ExpArena, exp: ExpId) -> Option<Span> {
+    match &exps[exp] {
+        Exp::Constant(..) => None,
+        Exp::Var(_, span) => Some(*span),
+        Exp::Unary(_, operand) | Exp::Cast(_, operand) | Exp::Expect(operand, _) => first_span(exps, *operand),
+        Exp::Binary(left, _, right) => first_span(exps, *left).or_else(|| first_span(exps, *right)),
+        Exp::Assignment(left, right) | Exp::CompoundAssignment(_, left, right) => {
+            first_span(exps, *left).or_else(|| first_span(exps, *right))
+        }
+        Exp::Conditional(condition, then, otherwise) => {
+            first_span(exps, *condition).or_else(|| then.and_then(|then| first_span(exps, then))).or_else(|| first_span(exps, *otherwise))
+        }
+        Exp::Dot(inner, _) | Exp::Arrow(inner, _) => first_span(exps, *inner),
+        Exp::Call(_, _, span) => Some(*span),
+    }
+}
+
+fn not_constant(exps: &ExpArena, exp: ExpId, what: &str) -> ConstEvalError {
+    let what = match &exps[exp] {
+        Exp::Var(name, _) | Exp::Call(name, ..) => format!("{} '{}'", what, name),
+        _ => what.to_string(),
+    };
+    ConstEvalError {
+        message: format!("{} is not allowed in an integer constant expression", what),
+        span: first_span(exps, exp),
+    }
+}
+
+// Checks that an operand the evaluation skips (the right side of a
+// short-circuited && or ||, or the arm of a conditional not taken) would
+// still be a constant expression.
+fn ensure_constant(exps: &ExpArena, exp: ExpId) -> Result<(), ConstEvalError> {
+    match &exps[exp] {
+        Exp::Constant(..) => Ok(()),
+        Exp::Var(..) => Err(not_constant(exps, exp, "Variable")),
+        Exp::Unary(_, operand) | Exp::Cast(_, operand) | Exp::Expect(operand, _) => ensure_constant(exps, *operand),
+        Exp::Binary(left, _, right) => {
+            ensure_constant(exps, *left)?;
+            ensure_constant(exps, *right)
+        }
+        Exp::Conditional(condition, then, otherwise) => {
+            ensure_constant(exps, *condition)?;
+            if let Some(then) = then {
+                ensure_constant(exps, *then)?;
+            }
+            ensure_constant(exps, *otherwise)
+        }
+        Exp::Assignment(..) | Exp::CompoundAssignment(..) => Err(not_constant(exps, exp, "Assignment")),
+        Exp::Dot(..) | Exp::Arrow(..) => Err(not_constant(exps, exp, "Member access")),
+        Exp::Call(..) => Err(not_constant(exps, exp, "Call to")),
+    }
+}
+
+fn eval_unary(op: UnaryOp, value: i32) -> Result<i32, ConstEvalError> {
+    match op {
+        UnaryOp::Plus => Ok(value),
+        UnaryOp::Negation => value.checked_neg().ok_or_else(|| error("Integer overflow in constant expression")),
+        UnaryOp::Complement => Ok(!value),
+        UnaryOp::LogicalNot => Ok((value == 0) as i32),
+    }
+}
+
+// Converting to a narrower type keeps the low bits. Values are computed in
+// int, so the types at least as wide keep the value as it is.
+fn eval_cast(target: &Type, value: i32) -> Result<i32, ConstEvalError> {
+    match target {
+        Type::Char | Type::SChar => Ok(value as i8 as i32),
+        Type::UChar => Ok(value as u8 as i32),
+        Type::Short => Ok(value as i16 as i32),
+        Type::UShort => Ok(value as u16 as i32),
+        Type::Int | Type::UInt | Type::Long | Type::ULong | Type::LongLong | Type::ULongLong => Ok(value),
+        Type::Pointer(_) | Type::Array(..) | Type::Function(..) => {
+            Err(error(&format!("Cast to {} is not allowed in an integer constant expression", target)))
+        }
+        Type::Volatile(inner) => eval_cast(inner, value),
+    }
+}
+
+fn eval_binary(op: BinaryOp, left: i32, right: i32) -> Result<i32, ConstEvalError> {
+    let overflow = || error("Integer overflow in constant expression");
+    match op {
+        BinaryOp::Add => left.checked_add(right).ok_or_else(overflow),
+        BinaryOp::Subtract => left.checked_sub(right).ok_or_else(overflow),
+        BinaryOp::Multiply => left.checked_mul(right).ok_or_else(overflow),
+        BinaryOp::Divide | BinaryOp::Modulo if right == 0 => Err(error("Division by zero in constant expression")),
+        BinaryOp::Divide => left.checked_div(right).ok_or_else(overflow),
+        BinaryOp::Modulo => left.checked_rem(right).ok_or_else(overflow),
+        BinaryOp::LeftShift | BinaryOp::RightShift if !(0..32).contains(&right) => {
+            Err(error("Shift count is negative or not less than the width of int"))
+        }
+        BinaryOp::LeftShift if left < 0 => Err(error("Left shift of a negative value in constant expression")),
+        BinaryOp::LeftShift => i32::try_from((left as i64) << right).map_err(|_| overflow()),
+        BinaryOp::RightShift => Ok(left >> right),
+        BinaryOp::BitwiseAnd => Ok(left & right),
+        BinaryOp::BitwiseOr => Ok(left | right),
+        BinaryOp::BitwiseXor => Ok(left ^ right),
+        BinaryOp::Equal => Ok((left == right) as i32),
+        BinaryOp::NotEqual => Ok((left != right) as i32),
+        BinaryOp::GreaterThan => Ok((left > right) as i32),
+        BinaryOp::LessThan => Ok((left < right) as i32),
+        BinaryOp::GreaterThanOrEqual => Ok((left >= right) as i32),
+        BinaryOp::LessThanOrEqual => Ok((left <= right) as i32),
+        BinaryOp::LogicalAnd | BinaryOp::LogicalOr => unreachable!("short-circuit operators are evaluated by eval_constant"),
+        BinaryOp::Assignment | BinaryOp::LeftShiftAssignment | BinaryOp::RightShiftAssignment => {
+            unreachable!("assignments are parsed into their own expression kinds")
+        }
+    }
+}
+
+// Evaluates an integer constant expression, or explains why exp isn't one
+pub fn eval_constant(exps: &ExpArena, exp: ExpId) -> Result<i32, ConstEvalError> {
+    ConstEvaluator::new(exps).eval(exp)
+}
+
+// Evaluates the expressions of one arena, remembering the result for every
+// subexpression it has been through. A pass that asks about an expression
+// and then about the ones inside it, as the constant condition warnings do,
+// evaluates each only once. The arena is borrowed for as long as the cache
+// lives, so nothing can change under it.
+pub struct ConstEvaluator<'a> {
+    exps: &'a ExpArena,
+    values: HashMap<ExpId, Result<i32, ConstEvalError>>,
+    evaluations: usize,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(exps: &'a ExpArena) -> ConstEvaluator<'a> {
+        ConstEvaluator { exps, values: HashMap::new(), evaluations: 0 }
+    }
+
+    // How many expressions have been evaluated rather than found in the cache
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+
+    pub fn eval(&mut self, exp: ExpId) -> Result<i32, ConstEvalError> {
+        if let Some(value) = self.values.get(&exp) {
+            return value.clone();
+        }
+        self.evaluations += 1;
+        let value = self.evaluate(exp);
+        self.values.insert(exp, value.clone());
+        value
+    }
+
+    fn evaluate(&mut self, exp: ExpId) -> Result<i32, ConstEvalError> {
+        let exps = self.exps;
+        match &exps[exp] {
+            Exp::Constant(value, _) => Ok(*value),
+            Exp::Unary(op, operand) => eval_unary(*op, self.eval(*operand)?),
+            Exp::Cast(target, operand) => eval_cast(target, self.eval(*operand)?),
+            Exp::Expect(operand, _) => self.eval(*operand),
+            Exp::Binary(left, BinaryOp::LogicalAnd, right) => {
+                if self.eval(*left)? == 0 {
+                    ensure_constant(exps, *right)?;
+                    Ok(0)
+                } else {
+                    Ok((self.eval(*right)? != 0) as i32)
+                }
+            }
+            Exp::Binary(left, BinaryOp::LogicalOr, right) => {
+                if self.eval(*left)? != 0 {
+                    ensure_constant(exps, *right)?;
+                    Ok(1)
+                } else {
+                    Ok((self.eval(*right)? != 0) as i32)
+                }
+            }
+            Exp::Binary(left, op, right) => {
+                let left = self.eval(*left)?;
+                let right = self.eval(*right)?;
+                eval_binary(*op, left, right)
+            }
+            // Only the chosen arm is evaluated, but both must be constant
+            Exp::Conditional(condition, then, otherwise) => {
+                let value = self.eval(*condition)?;
+                match then {
+                    Some(then) if value != 0 => {
+                        ensure_constant(exps, *otherwise)?;
+                        self.eval(*then)
+                    }
+                    None if value != 0 => {
+                        ensure_constant(exps, *otherwise)?;
+                        Ok(value)
+                    }
+                    _ => {
+                        if let Some(then) = then {
+                            ensure_constant(exps, *then)?;
+                        }
+                        self.eval(*otherwise)
+                    }
+                }
+            }
+            Exp::Var(..) => Err(not_constant(exps, exp, "Variable")),
+            Exp::Assignment(..) | Exp::CompoundAssignment(..) => Err(not_constant(exps, exp, "Assignment")),
+            Exp::Dot(..) | Exp::Arrow(..) => Err(not_constant(exps, exp, "Member access")),
+            Exp::Call(..) => Err(not_constant(exps, exp, "Call to")),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::const_eval::ConstEvaluator;
+use crate::lex::Span;
+use crate::parser::{BinaryOp, BlockItem, Declaration, Exp, ExpArena, ExpId, FunctionDeclaration, Program, Statement};
+
+// Warnings about conditions that are constant where the rest of the
+// expression isn't: an operand of && or || that decides the result on its
+// own, or the condition of a ?:. Either some code is never evaluated or the
+// condition doesn't test anything. Expressions that are constant as a whole
+// are left alone, as folding them away is what they're written for.
+
+// The leftmost constant or variable in exp, which for a constant expression
+// is the constant it starts with
+fn start(exps: &ExpArena, exp: ExpId) -> Span {
+    match &exps[exp] {
+        Exp::Constant(_, span) | Exp::Var(_, span) | Exp::Call(_, _, span) => *span,
+        Exp::Unary(_, inner) | Exp::Cast(_, inner) | Exp::Dot(inner, _) | Exp::Arrow(inner, _) | Exp::Expect(inner, _) => start(exps, *inner),
+        Exp::Binary(left, ..) | Exp::Assignment(left, _) | Exp::CompoundAssignment(_, left, _) => start(exps, *left),
+        Exp::Conditional(condition, ..) => start(exps, *condition),
+    }
+}
+
+fn check_logical(exps: &ExpArena, constants: &mut ConstEvaluator, left: ExpId, op: BinaryOp, right: ExpId, warnings: &mut Vec<(Span, String)>) {
+    // The value of an operand that decides the result: 0 for &&, anything
+    // else for ||
+    let (name, decides, description) = match op {
+        BinaryOp::LogicalAnd => ("&&", false, "always 0"),
+        _ => ("||", true, "never 0"),
+    };
+    if let Ok(value) = constants.eval(left) {
+        if (value != 0) == decides {
+            warnings.push((start(exps, left), format!("Left operand of '{}' is {}, so the right one is never evaluated", name, description)));
+        }
+    } else if let Ok(value) = constants.eval(right) {
+        if (value != 0) == decides {
+            warnings.push((start(exps, right), format!("Right operand of '{}' is {}, so the condition is always {}", name, description, decides)));
+        }
+    }
+}
+
+fn check_expression(exps: &ExpArena, constants: &mut ConstEvaluator, exp: ExpId, warnings: &mut Vec<(Span, String)>) {
+    if constants.eval(exp).is_ok() {
+        return;
+    }
+    match &exps[exp] {
+        Exp::Constant(..) | Exp::Var(..) => {}
+        Exp::Unary(_, inner) | Exp::Cast(_, inner) | Exp::Dot(inner, _) | Exp::Arrow(inner, _) | Exp::Expect(inner, _) => {
+            check_expression(exps, constants, *inner, warnings)
+        }
+        Exp::Binary(left, op, right) => {
+            if matches!(op, BinaryOp::LogicalAnd | BinaryOp::LogicalOr) {
+                check_logical(exps, constants, *left, *op, *right, warnings);
+            }
+            check_expression(exps, constants, *left, warnings);
+            check_expression(exps, constants, *right, warnings);
+        }
+        Exp::Assignment(left, right) | Exp::CompoundAssignment(_, left, right) => {
+            check_expression(exps, constants, *left, warnings);
+            check_expression(exps, constants, *right, warnings);
+        }
+        Exp::Call(_, args, _) => {
+            for arg in args {
+                check_expression(exps, constants, *arg, warnings);
+            }
+        }
+        Exp::Conditional(condition, then, otherwise) => {
+            match (constants.eval(*condition), then) {
+                (Ok(0), Some(_)) => warnings.push((start(exps, *condition), "Condition of '?:' is always 0, so its second operand is never evaluated".to_string())),
+                (Ok(0), None) => warnings.push((start(exps, *condition), "Condition of '?:' is always 0, so it always yields its last operand".to_string())),
+                (Ok(_), _) => warnings.push((start(exps, *condition), "Condition of '?:' is never 0, so its last operand is never evaluated".to_string())),
+                (Err(_), _) => {}
+            }
+            check_expression(exps, constants, *condition, warnings);
+            if let Some(then) = then {
+                check_expression(exps, constants, *then, warnings);
+            }
+            check_expression(exps, constants, *otherwise, warnings);
+        }
+    }
+}
+
+fn check_declaration(exps: &ExpArena, constants: &mut ConstEvaluator, declaration: &Declaration, warnings: &mut Vec<(Span, String)>) {
+    // Static assertions are constant by definition
+    if let Declaration::Declaration(_, _, Some(init), ..) = declaration {
+        check_expression(exps, constants, *init, warnings);
+    }
+}
+
+// The warnings for program, in source order
+pub fn constant_conditions(program: &Program) -> Vec<(Span, String)> {
+    program.interner().enter(|| constant_conditions_in(program))
+}
+
+fn constant_conditions_in(program: &Program) -> Vec<(Span, String)> {
+    let Program::Program(declarations, FunctionDeclaration::Function(_, block_items, _), exps, _) = program;
+    // check_expression evaluates every subexpression of the ones that aren't
+    // constant, so one cache serves the whole program
+    let mut constants = ConstEvaluator::new(exps);
+    let mut warnings = Vec::new();
+    for declaration in declarations {
+        check_declaration(exps, &mut constants, declaration, &mut warnings);
+    }
+    for item in block_items {
+        match item.as_ref() {
+            BlockItem::D(declaration) => check_declaration(exps, &mut constants, declaration, &mut warnings),
+            BlockItem::S(Statement::Return(exp) | Statement::Expression(exp)) => check_expression(exps, &mut constants, *exp, &mut warnings),
+            BlockItem::S(Statement::Asm(_) | Statement::Trap | Statement::Unreachable | Statement::Null) => {}
+        }
+    }
+    warnings.sort_by_key(|(span, _)| span.start);
+    warnings
+}
@@ -0,0 +1,63 @@
+// The language being compiled: which revision of the C standard, and which
+// extensions to it the lexer and parser accept. Extensions are off by
+// default, so standard code is checked strictly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dialect {
+    pub std: Standard,
+    // GNU C extensions, such as a ?: b
+    pub gnu_extensions: bool,
+    // Legacy constructs the standard has since removed, such as implicit int
+    pub permissive: bool,
+}
+
+// Ordered by publication, so that a feature can require std >= Standard::C23
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Standard {
+    C11,
+    #[default]
+    C17,
+    C23,
+}
+
+impl Dialect {
+    // Applies a command-line flag that selects the dialect. Returns false for
+    // any other flag, and an error for a standard we don't know.
+    pub fn parse_flag(&mut self, flag: &str) -> Result<bool, String> {
+        if flag == "--gnu-extensions" {
+            self.gnu_extensions = true;
+            return Ok(true);
+        }
+        if flag == "-fpermissive" {
+            self.permissive = true;
+            return Ok(true);
+        }
+        // gcc spells it -std=, and the gnu variants enable the extensions too
+        let Some(name) = flag.strip_prefix("--std=").or_else(|| flag.strip_prefix("-std=")) else {
+            return Ok(false);
+        };
+        let (version, gnu_extensions) = match name.strip_prefix("gnu") {
+            Some(version) => (version, true),
+            None => (name.strip_prefix('c').unwrap_or_default(), false),
+        };
+        self.std = match version {
+            "11" | "1x" => Standard::C11,
+            "17" | "18" => Standard::C17,
+            "23" | "2x" => Standard::C23,
+            _ => return Err(format!("Unsupported language standard '{}'", name)),
+        };
+        self.gnu_extensions |= gnu_extensions;
+        Ok(true)
+    }
+
+    // The same dialect as a -std= flag for gcc and clang. c2x is the spelling
+    // both accept for C23.
+    pub fn std_flag(&self) -> String {
+        let family = if self.gnu_extensions { "gnu" } else { "c" };
+        let version = match self.std {
+            Standard::C11 => "11",
+            Standard::C17 => "17",
+            Standard::C23 => "2x",
+        };
+        format!("-std={}{}", family, version)
+    }
+}
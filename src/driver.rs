@@ -0,0 +1,451 @@
+use crate::assembly::{self, Program};
+use crate::checks::{self, RuntimeChecks};
+use crate::dialect::Dialect;
+use crate::intern::Interner;
+use crate::lex::{self, LexError};
+use crate::parser::Type;
+use crate::symbols::{Definition, Entry, Linkage, SymbolTable};
+use crate::target::CodegenOptions;
+use crate::timing::PassTimer;
+use crate::{dead_code, explain, format, ir_json, optimize, parser, tac, typecheck, verify};
+use std::fmt;
+use std::thread;
+
+// Why a compilation failed, by the stage that rejected the program
+#[derive(Debug)]
+pub enum CompileError {
+    Lex(LexError),
+    Parse(String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Lex(err) => write!(f, "{}", err),
+            CompileError::Parse(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+// A compile error in a form tools can consume: the message, where in the
+// source it points when that is known, and its code for --explain if it has
+// one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub code: Option<&'static str>,
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(err: &CompileError) -> Diagnostic {
+        match err {
+            CompileError::Lex(err) => Diagnostic {
+                message: err.message.clone(),
+                line: Some(err.span.line),
+                column: Some(err.span.column),
+                code: explain::code_for(&err.message),
+            },
+            // Parse errors that know their position start with "line:column: "
+            CompileError::Parse(message) => {
+                let position = message.split_once(": ").and_then(|(position, rest)| {
+                    let (line, column) = position.split_once(':')?;
+                    Some((line.parse().ok()?, column.parse().ok()?, rest))
+                });
+                match position {
+                    Some((line, column, rest)) => Diagnostic { message: rest.to_string(), line: Some(line), column: Some(column), code: explain::code_for(rest) },
+                    None => Diagnostic { message: message.clone(), line: None, column: None, code: explain::code_for(message) },
+                }
+            }
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn to_json(&self) -> String {
+        let position = |value: Option<usize>| value.map_or("null".to_string(), |value| value.to_string());
+        let code = self.code.map_or("null".to_string(), json_string);
+        format!("{{\"message\":{},\"line\":{},\"column\":{},\"code\":{}}}",
+            json_string(&self.message), position(self.line), position(self.column), code)
+    }
+
+    // "error", or "error[E0001]" when the error has a code
+    pub fn error_label(&self) -> String {
+        match self.code {
+            Some(code) => format!("error[{}]", code),
+            None => "error".to_string(),
+        }
+    }
+}
+
+// A string as a JSON string literal
+pub fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// The tokens of source as JSON Lines, one object per token with its
+// category, its text and where it starts, for checking the lexer on its own
+pub fn tokens_to_json(source: &str, dialect: &Dialect) -> Result<String, LexError> {
+    let mut json = String::new();
+    for token in lex::Lex::with_dialect(source, dialect).get_tokens()? {
+        let span = token.span;
+        json.push_str(&format!("{{\"kind\":\"{}\",\"lexeme\":{},\"line\":{},\"column\":{}}}\n",
+            token.kind.category(), json_string(&source[span.start..span.end]), span.line, span.column));
+    }
+    Ok(json)
+}
+
+// source parsed and printed back in the style of format::format_program
+pub fn format(source: &str, dialect: &Dialect) -> Result<String, CompileError> {
+    let mut tokens = lex::Lex::with_dialect(source, dialect).get_tokens().map_err(CompileError::Lex)?;
+    let program = parser::parse_program(&mut tokens, dialect).map_err(CompileError::Parse)?;
+    Ok(format::format_program(&program))
+}
+
+// The program as parsed, as JSON in the layout schemas/ast.v1.json describes
+pub fn ast_json(source: &str, dialect: &Dialect) -> Result<String, CompileError> {
+    let mut tokens = lex::Lex::with_dialect(source, dialect).get_tokens().map_err(CompileError::Lex)?;
+    let program = parser::parse_program(&mut tokens, dialect).map_err(CompileError::Parse)?;
+    Ok(ir_json::ast_to_json(&program))
+}
+
+// The optimized TAC, as JSON in the layout schemas/tac.v1.json describes
+pub fn tac_json(source: &str, dialect: &Dialect) -> Result<String, CompileError> {
+    compile_to_tac(source, dialect).map(|tac| ir_json::tac_to_json(&tac))
+}
+
+// The stages a compilation can be stopped after, in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Lex,
+    Parse,
+    Validate,
+    Tacky,
+    Codegen,
+}
+
+// Runs the front end up to and including last, which must be no later than
+// Stage::Tacky. The optimized TAC, with the runtime checks in it, is only
+// returned when that stage was reached.
+fn front_end(source: &str, dialect: &Dialect, checks: RuntimeChecks, last: Stage, timer: &mut PassTimer) -> Result<Option<tac::Program>, CompileError> {
+    let mut lexer = lex::Lex::with_dialect(source, dialect);
+    let mut tokens = timer.time("lex", || lexer.get_tokens()).map_err(CompileError::Lex)?;
+    if last == Stage::Lex {
+        return Ok(None);
+    }
+
+    let program = timer.time("parse", || parser::parse_program(&mut tokens, dialect)).map_err(CompileError::Parse)?;
+    if last == Stage::Parse {
+        return Ok(None);
+    }
+    let program = timer.time("resolve", || parser::resolve_program(program)).map_err(CompileError::Parse)?;
+    let program = timer.time("typecheck", || typecheck::typecheck_program(program)).map_err(CompileError::Parse)?;
+    if last == Stage::Validate {
+        return Ok(None);
+    }
+
+    let mut tac = timer.time("tac", || tac::generate_tac(program)).map_err(CompileError::Parse)?;
+    debug_verify(&tac, "TAC generation");
+    for (name, pass) in optimize::PASSES {
+        timer.time(name, || pass(&mut tac.function));
+        debug_verify(&tac, name);
+    }
+    if checks != RuntimeChecks::default() {
+        timer.time("runtime checks", || checks::insert_checks(&mut tac.function, checks));
+        debug_verify(&tac, "inserting runtime checks");
+    }
+    Ok(Some(tac))
+}
+
+// Debug builds check the TAC after it is generated and after every pass, so
+// a pass that breaks it fails where it does rather than somewhere in codegen
+fn debug_verify(tac: &tac::Program, after: &str) {
+    if cfg!(debug_assertions) {
+        if let Err(err) = verify::verify_tac(tac) {
+            panic!("Invalid TAC after {}: {}\n{}", after, err, tac);
+        }
+    }
+}
+
+// Likewise for the assembly once it is fixed up, before it is written out
+fn debug_verify_assembly(assembly: &Program) {
+    if cfg!(debug_assertions) {
+        if let Err(err) = assembly.verify() {
+            panic!("Invalid assembly after fixup: {}", err);
+        }
+    }
+}
+
+// Runs the stages up to and including last, timing each one with timer.
+// The assembly is only returned when codegen was reached.
+pub fn compile_until(source: &str, dialect: &Dialect, last: Stage, timer: &mut PassTimer) -> Result<Option<Program>, CompileError> {
+    compile_with_checks(source, dialect, RuntimeChecks::default(), last, timer)
+}
+
+// Likewise, with checks inserted into the code
+pub fn compile_with_checks(source: &str, dialect: &Dialect, checks: RuntimeChecks, last: Stage, timer: &mut PassTimer) -> Result<Option<Program>, CompileError> {
+    let tac = match front_end(source, dialect, checks, last.min(Stage::Tacky), timer)? {
+        Some(tac) if last > Stage::Tacky => tac,
+        _ => return Ok(None),
+    };
+    let mut assembly = timer.time("codegen", || assembly::generate_assembly_ast(tac));
+    timer.time("fixup", || assembly.apply_fixes());
+    debug_verify_assembly(&assembly);
+    Ok(Some(assembly))
+}
+
+// What a stage leaves a program as, for showing each representation in turn
+#[derive(Debug, Clone)]
+pub enum Artifact {
+    Tokens(Vec<lex::Token>),
+    Ast(parser::Program),
+    // After resolution and type checking
+    ResolvedAst(parser::Program),
+    // After the optimization passes
+    Tac(tac::Program),
+    Asm(Program),
+}
+
+// A compilation of one source that keeps what every stage it has run
+// produced, so asking for a later stage carries on from the furthest one
+// reached rather than starting again. Each stage works on a copy of the
+// previous artifact, leaving that one to be looked at too.
+pub struct Compilation {
+    source: String,
+    dialect: Dialect,
+    // One per stage run so far, in pipeline order
+    artifacts: Vec<Artifact>,
+    // The interner current when the compilation was made, which every stage
+    // runs in wherever the compilation has moved to since
+    interner: Interner,
+}
+
+impl Compilation {
+    pub fn new(source: &str, dialect: &Dialect) -> Compilation {
+        Compilation { source: source.to_string(), dialect: *dialect, artifacts: Vec::new(), interner: Interner::current() }
+    }
+
+    // The interner the artifacts' names are in
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    // Runs the stages up to and including last that haven't run yet and
+    // returns what last produced
+    pub fn run_until(&mut self, last: Stage) -> Result<&Artifact, CompileError> {
+        while self.artifacts.len() <= last as usize {
+            let next = self.interner.enter(|| self.run_next())?;
+            self.artifacts.push(next);
+        }
+        Ok(&self.artifacts[last as usize])
+    }
+
+    // What stage produced, if it has run
+    pub fn artifact(&self, stage: Stage) -> Option<&Artifact> {
+        self.artifacts.get(stage as usize)
+    }
+
+    fn run_next(&self) -> Result<Artifact, CompileError> {
+        Ok(match self.artifacts.last() {
+            None => Artifact::Tokens(lex::Lex::with_dialect(&self.source, &self.dialect).get_tokens().map_err(CompileError::Lex)?),
+            Some(Artifact::Tokens(tokens)) => {
+                Artifact::Ast(parser::parse_program(&mut tokens.clone(), &self.dialect).map_err(CompileError::Parse)?)
+            }
+            Some(Artifact::Ast(program)) => {
+                let program = parser::resolve_program(program.clone()).map_err(CompileError::Parse)?;
+                Artifact::ResolvedAst(typecheck::typecheck_program(program).map_err(CompileError::Parse)?)
+            }
+            Some(Artifact::ResolvedAst(program)) => {
+                let mut tac = tac::generate_tac(program.clone()).map_err(CompileError::Parse)?;
+                debug_verify(&tac, "TAC generation");
+                for (name, pass) in optimize::PASSES {
+                    pass(&mut tac.function);
+                    debug_verify(&tac, name);
+                }
+                Artifact::Tac(tac)
+            }
+            Some(Artifact::Tac(tac)) => {
+                let mut assembly = assembly::generate_assembly_ast(tac.clone());
+                assembly.apply_fixes();
+                debug_verify_assembly(&assembly);
+                Artifact::Asm(assembly)
+            }
+            Some(Artifact::Asm(_)) => unreachable!("codegen is the last stage"),
+        })
+    }
+}
+
+// Compiles source text as far as optimized TAC
+pub fn compile_to_tac(source: &str, dialect: &Dialect) -> Result<tac::Program, CompileError> {
+    front_end(source, dialect, RuntimeChecks::default(), Stage::Tacky, &mut PassTimer::new(false)).map(|tac| tac.expect("the front end always produces TAC"))
+}
+
+// What is wrong with the TAC or assembly for source, if anything: the TAC is
+// checked after generation and after each pass in turn, then the fixed-up
+// assembly, and the first step to produce something invalid is named. This
+// is what debug builds do on every compilation.
+pub fn verify_ir(source: &str, dialect: &Dialect) -> Result<Option<String>, CompileError> {
+    let Artifact::ResolvedAst(program) = Compilation::new(source, dialect).run_until(Stage::Validate)?.clone() else {
+        unreachable!("validation produces the resolved AST")
+    };
+    let mut tac = tac::generate_tac(program).map_err(CompileError::Parse)?;
+    if let Err(err) = verify::verify_tac(&tac) {
+        return Ok(Some(format!("TAC generation produced invalid TAC: {}", err)));
+    }
+    for (name, pass) in optimize::PASSES {
+        pass(&mut tac.function);
+        if let Err(err) = verify::verify_tac(&tac) {
+            return Ok(Some(format!("The {} pass left the TAC invalid: {}", name, err)));
+        }
+    }
+    let mut assembly = assembly::generate_assembly_ast(tac);
+    assembly.apply_fixes();
+    Ok(assembly.verify().err().map(|err| format!("Codegen produced invalid assembly: {}", err)))
+}
+
+// The identifiers with linkage that source declares, with where each is
+// declared and used, for tools that present a program's structure
+pub fn symbols(source: &str, dialect: &Dialect) -> Result<SymbolTable, CompileError> {
+    let mut tokens = lex::Lex::with_dialect(source, dialect).get_tokens().map_err(CompileError::Lex)?;
+    let program = parser::parse_program(&mut tokens, dialect).map_err(CompileError::Parse)?;
+    parser::resolve_program_with_symbols(program).map(|(_, symbols)| symbols).map_err(CompileError::Parse)
+}
+
+// Warnings about conditions that are constant where the rest of their
+// expression isn't, which mean some code is never evaluated. None of them
+// stop compilation.
+pub fn warnings(source: &str, dialect: &Dialect) -> Result<Vec<Diagnostic>, CompileError> {
+    let mut tokens = lex::Lex::with_dialect(source, dialect).get_tokens().map_err(CompileError::Lex)?;
+    let program = parser::parse_program(&mut tokens, dialect).map_err(CompileError::Parse)?;
+    let program = parser::resolve_program(program).map_err(CompileError::Parse)?;
+    Ok(dead_code::constant_conditions(&program).into_iter()
+        .map(|(span, message)| Diagnostic { message, line: Some(span.line), column: Some(span.column), code: None })
+        .collect())
+}
+
+// What --emit-symbols reports about a file-scope symbol: whether it is a
+// function or an object, its linkage, whether this translation unit defines
+// it, and its size in bytes, which only objects have before codegen
+fn symbol_fields(entry: &Entry) -> (&'static str, &'static str, &'static str, Option<u64>) {
+    let linkage = match entry.linkage {
+        Linkage::External => "external",
+        Linkage::Internal => "internal",
+    };
+    let definition = match entry.definition {
+        Definition::Defined => "defined",
+        Definition::Tentative => "tentative",
+        Definition::Declared => "undefined",
+    };
+    match entry.declared_type {
+        Type::Function(..) => ("function", linkage, definition, None),
+        ref object => ("object", linkage, definition, Some(typecheck::size(object))),
+    }
+}
+
+// The symbols a translation unit defines and references, one per line in the
+// order they are first declared, like nm but before anything is assembled
+pub fn symbols_to_text(symbols: &SymbolTable) -> String {
+    let mut text = String::new();
+    for (name, entry) in symbols.entries() {
+        let (kind, linkage, definition, size) = symbol_fields(entry);
+        let size = size.map_or("-".to_string(), |size| size.to_string());
+        text.push_str(&format!("{:<8} {:<8} {:<9} {:>4} {}\n", kind, linkage, definition, size, name));
+    }
+    text
+}
+
+// The same listing as JSON Lines, with the number of times each symbol is used
+pub fn symbols_to_json(symbols: &SymbolTable) -> String {
+    let mut json = String::new();
+    for (name, entry) in symbols.entries() {
+        let (kind, linkage, definition, size) = symbol_fields(entry);
+        let size = size.map_or("null".to_string(), |size| size.to_string());
+        json.push_str(&format!("{{\"name\":{},\"kind\":\"{}\",\"linkage\":\"{}\",\"definition\":\"{}\",\"size\":{},\"references\":{}}}\n",
+            json_string(&name.text()), kind, linkage, definition, size, entry.references.len()));
+    }
+    json
+}
+
+// Runs every stage from source text to assembly that is ready to be written out
+pub fn compile(source: &str, dialect: &Dialect, timer: &mut PassTimer) -> Result<Program, CompileError> {
+    compile_until(source, dialect, Stage::Codegen, timer).map(|program| program.expect("codegen always produces a program"))
+}
+
+// Runs the stages up to and including last over each of sources, with
+// checks inserted, in parallel, with the timer of the same index. Nothing is
+// shared between compilations, not even the interner, so each result is what
+// compiling its source alone gives, and they come back in the order of
+// sources. There must be a timer for every source.
+pub fn compile_all(sources: &[String], dialect: &Dialect, checks: RuntimeChecks, last: Stage, timers: &mut [PassTimer]) -> Result<Vec<Result<Option<Program>, CompileError>>, String> {
+    if timers.len() != sources.len() {
+        return Err(format!("{} source{} to compile, but {} timer{}", sources.len(), if sources.len() == 1 { "" } else { "s" },
+            timers.len(), if timers.len() == 1 { "" } else { "s" }));
+    }
+    if sources.is_empty() {
+        return Ok(Vec::new());
+    }
+    let workers = thread::available_parallelism().map_or(1, usize::from).min(sources.len());
+    let chunk = sources.len().div_ceil(workers);
+    Ok(thread::scope(|scope| {
+        let handles: Vec<_> = sources.chunks(chunk).zip(timers.chunks_mut(chunk))
+            .map(|(sources, timers)| scope.spawn(move || {
+                sources.iter().zip(timers).map(|(source, timer)| Interner::new().enter(|| compile_with_checks(source, dialect, checks, last, timer))).collect::<Vec<_>>()
+            }))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("a compilation panicked")).collect()
+    }))
+}
+
+// The settings to compile with, kept to compile any number of sources,
+// from any number of threads at once. Everything a compilation works on is
+// its own, including the interner its names go in, which is dropped with
+// the program it compiled.
+#[derive(Debug, Clone, Copy)]
+pub struct Compiler {
+    pub dialect: Dialect,
+    pub options: CodegenOptions,
+    pub checks: RuntimeChecks,
+}
+
+impl Compiler {
+    pub fn new(dialect: Dialect, options: CodegenOptions) -> Compiler {
+        Compiler { dialect, options, checks: RuntimeChecks::default() }
+    }
+
+    // Runs the stages up to and including last, timing each one with timer
+    pub fn compile_until(&self, source: &str, last: Stage, timer: &mut PassTimer) -> Result<Option<Program>, CompileError> {
+        Interner::new().enter(|| compile_with_checks(source, &self.dialect, self.checks, last, timer))
+    }
+
+    // Compiles source to the text of an assembly file
+    pub fn compile(&self, source: &str) -> Result<String, CompileError> {
+        let program = self.compile_until(source, Stage::Codegen, &mut PassTimer::new(false))?;
+        Ok(program.expect("codegen always produces a program").to_assembly_file(&self.options))
+    }
+
+    // compile_all with these settings
+    pub fn compile_all(&self, sources: &[String], last: Stage, timers: &mut [PassTimer]) -> Result<Vec<Result<Option<Program>, CompileError>>, String> {
+        compile_all(sources, &self.dialect, self.checks, last, timers)
+    }
+}
+
+// Compiles source text to assembly text for the target in options
+pub fn compile_to_assembly(source: &str, dialect: &Dialect, options: &CodegenOptions) -> Result<String, CompileError> {
+    let program = compile(source, dialect, &mut PassTimer::new(false))?;
+    Ok(program.to_assembly_file(options))
+}
@@ -0,0 +1,246 @@
+// Stable codes for the errors a learner is most likely to run into. Errors
+// are plain messages, so each code lists the messages it covers, with {}
+// standing for the parts that vary. --explain <code> prints the longer
+// description, with an example that triggers the error and how to fix it.
+
+pub struct ErrorCode {
+    pub code: &'static str,
+    messages: &'static [&'static str],
+    pub explanation: &'static str,
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        messages: &["Variable '{}' not declared"],
+        explanation: "\
+A variable was used before it was declared.
+
+C looks names up in the scopes that enclose the use, and a declaration only
+comes into scope at the point where it is written, so a variable must be
+declared above its first use:
+
+    int main(void) {
+        x = 1;          // error: 'x' isn't declared yet
+        int x;
+        return x;
+    }
+
+Declare the variable first, or check the name for a typo.",
+    },
+    ErrorCode {
+        code: "E0002",
+        messages: &["Variable '{}' already declared"],
+        explanation: "\
+A variable was declared twice in the same scope.
+
+Each name may be declared once per block. Another declaration of it in the
+same block is an error, even with the same type:
+
+    int main(void) {
+        int x = 1;
+        int x = 2;      // error: 'x' is already declared
+        return x;
+    }
+
+Assign to the existing variable instead (x = 2;), or give the second one a
+different name.",
+    },
+    ErrorCode {
+        code: "E0003",
+        messages: &["Left side of assignment must resolve to a variable"],
+        explanation: "\
+The left side of an assignment isn't something that can be assigned to.
+
+Only an lvalue, an expression that names an object, can be assigned. A
+constant or the result of an operator is just a value:
+
+    int main(void) {
+        int x = 1;
+        x + 1 = 2;      // error: x + 1 is a value, not a variable
+        return x;
+    }
+
+Assign to the variable itself, as in x = 2 - 1;.",
+    },
+    ErrorCode {
+        code: "E0004",
+        messages: &["Invalid character '{}'", "Invalid character '{}'; {}"],
+        explanation: "\
+The source contains a character that can't start any token.
+
+Outside of comments and literals, C only uses letters, digits, '_' and its
+punctuators. Characters such as '@', '$' and '`' mean nothing to it:
+
+    int main(void) {
+        return 1 @ 2;   // error: '@' isn't an operator
+    }
+
+Remove the character, or put it in a comment if it was meant as one. A
+character that looks like ASCII but isn't, like a curly quote pasted from
+a document, is reported the same way.",
+    },
+    ErrorCode {
+        code: "E0005",
+        messages: &["Unexpected token: '{}'", "Unexpected token while parsing factor: '{}'", "Expected '{}', got '{}'"],
+        explanation: "\
+The parser found a token where the grammar doesn't allow one.
+
+Usually a ';' or a closing parenthesis is missing just before the point
+reported, or an operator has no operand:
+
+    int main(void) {
+        return 0        // error: expected ';', got '}'
+    }
+
+Look at the end of the previous line as well as at the token reported.",
+    },
+    ErrorCode {
+        code: "E0006",
+        messages: &["Unexpected end of file{}"],
+        explanation: "\
+The file ended in the middle of a construct.
+
+Something that was opened was never closed, most often a '{' or a '(':
+
+    int main(void) {
+        return 0;
+                        // error: the function body never ends
+
+Add the missing closing token.",
+    },
+    ErrorCode {
+        code: "E0007",
+        messages: &["Integer constant '{}' is too large for type int", "Integer constant '-{}' is too small for type int"],
+        explanation: "\
+An integer constant doesn't fit in an int.
+
+Every value is an int so far, which holds -2147483648 to 2147483647:
+
+    int main(void) {
+        return 3000000000;  // error: too large for int
+    }
+
+Use a smaller constant.",
+    },
+    ErrorCode {
+        code: "E0008",
+        messages: &["Static assertion failed", "Static assertion failed: {}"],
+        explanation: "\
+A _Static_assert's condition is zero.
+
+A static assertion checks a condition while compiling, and stops the
+compilation when it doesn't hold:
+
+    _Static_assert(1 + 1 == 3, \"arithmetic\");   // error
+
+Either the condition is wrong or the assumption it checks no longer holds
+for this program.",
+    },
+    ErrorCode {
+        code: "E0009",
+        messages: &[
+            "Return type missing; C99 removed implicit int (-fpermissive accepts it)",
+            "Type specifier missing; C99 removed implicit int (-fpermissive accepts it)",
+        ],
+        explanation: "\
+A declaration has no type.
+
+Before C99 a missing type meant int. Newer standards require the type to be
+written out:
+
+    main(void) {        // error: no return type
+        return 0;
+    }
+
+Write the type (int main(void)), or pass -fpermissive to accept old code.",
+    },
+    ErrorCode {
+        code: "E0010",
+        messages: &["Only 'main' can be defined, got '{}'", "Only 'int main(void)' is supported, got '{}' as {}"],
+        explanation: "\
+The program defines a function other than int main(void).
+
+This compiler only handles programs made of a single main function that
+takes no parameters:
+
+    int helper(void) {  // error: only main can be defined
+        return 1;
+    }
+
+Put the code in main.",
+    },
+    ErrorCode {
+        code: "E0011",
+        messages: &["Conflicting types for '{}': {} and {}"],
+        explanation: "\
+Two declarations of the same name disagree about its type.
+
+Every declaration of something with linkage must give it the same type:
+
+    int main(void) {
+        extern int main;    // error: main is a function, not an int
+        return 0;
+    }
+
+Make the declarations agree.",
+    },
+    ErrorCode {
+        code: "E0012",
+        messages: &["Unterminated {}"],
+        explanation: "\
+A comment or literal runs to the end of the file.
+
+A block comment lasts until the first '*/', and a character constant or
+string literal until its closing quote on the same line:
+
+    int main(void) {
+        /* no end to this comment
+        return 0;
+    }
+
+Close it.",
+    },
+    ErrorCode {
+        code: "E0013",
+        messages: &["Omitting the middle operand of '?:' is a GNU extension; enable it with --gnu-extensions"],
+        explanation: "\
+a ?: b is a GNU extension, not standard C.
+
+It means a ? a : b, but only evaluates a once:
+
+    int main(void) {
+        return 0 ?: 1;  // error without --gnu-extensions
+    }
+
+Pass --gnu-extensions, or write the middle operand out.",
+    },
+];
+
+// Whether message fits pattern, where each {} in the pattern stands for any
+// text
+fn matches(pattern: &str, message: &str) -> bool {
+    let mut parts = pattern.split("{}");
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = message.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+// The code for an error message, without its "line:column: " prefix
+pub fn code_for(message: &str) -> Option<&'static str> {
+    ERROR_CODES.iter()
+        .find(|code| code.messages.iter().any(|pattern| matches(pattern, message)))
+        .map(|code| code.code)
+}
+
+pub fn explain(code: &str) -> Option<&'static str> {
+    ERROR_CODES.iter().find(|entry| entry.code.eq_ignore_ascii_case(code)).map(|entry| entry.explanation)
+}
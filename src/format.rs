@@ -0,0 +1,207 @@
+use crate::parser::{get_operator_precedence, BinaryOp, BlockItem, Declaration, Exp, ExpArena, ExpId, FunctionDeclaration, Program, Statement, Type, UnaryOp, CONDITIONAL_PRECEDENCE};
+use crate::symbols::StorageClass;
+
+// Prints a parsed program back as C source for --fmt: one declaration or
+// statement per line, four spaces of indentation, the opening brace on the
+// line it belongs to, and only the parentheses the expressions need.
+// Comments and preprocessor lines are gone by the time the program is
+// parsed, so they aren't kept.
+
+// The parser's precedence levels, so that the parentheses kept are exactly
+// the ones it needs to read the expression back the same way
+fn precedence(op: BinaryOp) -> u8 {
+    get_operator_precedence(&op)
+}
+
+const CONDITIONAL: u8 = CONDITIONAL_PRECEDENCE;
+const ASSIGNMENT: u8 = get_operator_precedence(&BinaryOp::Assignment);
+// Constants, variables, unary operators, casts and member accesses
+const PRIMARY: u8 = get_operator_precedence(&BinaryOp::Multiply) + 2;
+
+pub(crate) fn binary_spelling(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::LeftShift => "<<",
+        BinaryOp::RightShift => ">>",
+        BinaryOp::BitwiseAnd => "&",
+        BinaryOp::BitwiseOr => "|",
+        BinaryOp::BitwiseXor => "^",
+        BinaryOp::LogicalAnd => "&&",
+        BinaryOp::LogicalOr => "||",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "!=",
+        BinaryOp::GreaterThan => ">",
+        BinaryOp::LessThan => "<",
+        BinaryOp::GreaterThanOrEqual => ">=",
+        BinaryOp::LessThanOrEqual => "<=",
+        BinaryOp::Assignment => "=",
+        BinaryOp::LeftShiftAssignment => "<<=",
+        BinaryOp::RightShiftAssignment => ">>=",
+    }
+}
+
+fn exp_precedence(exp: &Exp) -> u8 {
+    match exp {
+        Exp::Binary(_, op, _) => precedence(*op),
+        Exp::Conditional(..) => CONDITIONAL,
+        Exp::Assignment(..) | Exp::CompoundAssignment(..) => ASSIGNMENT,
+        Exp::Constant(value, _) if *value < 0 => PRIMARY - 1,
+        _ => PRIMARY,
+    }
+}
+
+// exp as an operand that must bind at least as tightly as min
+fn operand(exps: &ExpArena, exp: ExpId, min: u8) -> String {
+    if exp_precedence(&exps[exp]) < min { format!("({})", expression(exps, exp)) } else { expression(exps, exp) }
+}
+
+// An operand of a binary operator. They are all left-associative, so an
+// operand of the same level on the right was grouped.
+fn binary_operand(exps: &ExpArena, exp: ExpId, op: BinaryOp, right: bool) -> String {
+    operand(exps, exp, if right { precedence(op) + 1 } else { precedence(op) })
+}
+
+pub fn expression(exps: &ExpArena, exp: ExpId) -> String {
+    match &exps[exp] {
+        Exp::Constant(value, _) => value.to_string(),
+        Exp::Var(name, _) => name.to_string(),
+        Exp::Unary(op, inner) => {
+            let spelling = match op {
+                UnaryOp::Plus => "+",
+                UnaryOp::Negation => "-",
+                UnaryOp::Complement => "~",
+                UnaryOp::LogicalNot => "!",
+            };
+            // - -x would otherwise be written --x, and -(1) as -1, which
+            // parses to the constant -1
+            let inner = match &exps[*inner] {
+                Exp::Unary(..) => format!("({})", expression(exps, *inner)),
+                Exp::Constant(..) if *op == UnaryOp::Negation => format!("({})", expression(exps, *inner)),
+                _ => operand(exps, *inner, PRIMARY),
+            };
+            format!("{}{}", spelling, inner)
+        }
+        Exp::Cast(target, inner) => format!("({}){}", target, operand(exps, *inner, PRIMARY)),
+        Exp::Binary(left, op, right) => {
+            format!("{} {} {}", binary_operand(exps, *left, *op, false), binary_spelling(*op), binary_operand(exps, *right, *op, true))
+        }
+        Exp::Assignment(left, right) => format!("{} = {}", operand(exps, *left, PRIMARY), operand(exps, *right, ASSIGNMENT)),
+        Exp::CompoundAssignment(op, left, right) => {
+            let spelling = match op {
+                BinaryOp::LeftShift => "<<=",
+                BinaryOp::RightShift => ">>=",
+                _ => unreachable!("only shifts have compound assignments"),
+            };
+            format!("{} {} {}", operand(exps, *left, PRIMARY), spelling, operand(exps, *right, ASSIGNMENT))
+        }
+        Exp::Conditional(condition, then, otherwise) => {
+            let condition = operand(exps, *condition, CONDITIONAL + 1);
+            let otherwise = operand(exps, *otherwise, CONDITIONAL);
+            match then {
+                Some(then) => format!("{} ? {} : {}", condition, expression(exps, *then), otherwise),
+                None => format!("{} ?: {}", condition, otherwise),
+            }
+        }
+        Exp::Dot(inner, member) => format!("{}.{}", operand(exps, *inner, PRIMARY), member),
+        Exp::Arrow(inner, member) => format!("{}->{}", operand(exps, *inner, PRIMARY), member),
+        Exp::Expect(inner, value) => format!("__builtin_expect({}, {})", operand(exps, *inner, ASSIGNMENT), value),
+        Exp::Call(name, args, _) => {
+            let args: Vec<String> = args.iter().map(|arg| operand(exps, *arg, ASSIGNMENT)).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+    }
+}
+
+// text as a C string literal
+fn string_literal(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            c if (c as u32) < 0x20 => literal.push_str(&format!("\\{:03o}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn declaration(exps: &ExpArena, declaration: &Declaration) -> String {
+    match declaration {
+        Declaration::Declaration(name, declared_type, init, storage_class, _) => {
+            let storage_class = match storage_class {
+                Some(StorageClass::Extern) => "extern ",
+                Some(StorageClass::Static) => "static ",
+                None => "",
+            };
+            // Functions only take and return int so far, and the parameter
+            // names aren't kept
+            if let Type::Function(params, ret) = declared_type {
+                let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+                let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+                return format!("{}{} {}({});", storage_class, ret, name, params);
+            }
+            // Declarations are only of int so far, so the type is its own
+            // declaration specifiers
+            match init {
+                Some(init) => format!("{}{} {} = {};", storage_class, declared_type, name, operand(exps, *init, ASSIGNMENT)),
+                None => format!("{}{} {};", storage_class, declared_type, name),
+            }
+        }
+        Declaration::StaticAssert(condition, Some(message), _) => {
+            format!("_Static_assert({}, {});", operand(exps, *condition, ASSIGNMENT), string_literal(message))
+        }
+        Declaration::StaticAssert(condition, None, _) => format!("_Static_assert({});", operand(exps, *condition, ASSIGNMENT)),
+    }
+}
+
+fn statement(exps: &ExpArena, statement: &Statement) -> String {
+    match statement {
+        Statement::Return(exp) => format!("return {};", expression(exps, *exp)),
+        Statement::Expression(exp) => format!("{};", expression(exps, *exp)),
+        Statement::Asm(template) => format!("__asm__({});", string_literal(template)),
+        Statement::Trap => "__builtin_trap();".to_string(),
+        Statement::Unreachable => "__builtin_unreachable();".to_string(),
+        Statement::Null => ";".to_string(),
+    }
+}
+
+pub fn format_program(program: &Program) -> String {
+    program.interner().enter(|| format_program_in(program))
+}
+
+fn format_program_in(program: &Program) -> String {
+    let Program::Program(declarations, FunctionDeclaration::Function(name, block_items, span), exps, _) = program;
+    // File-scope declarations go back on the side of the function they
+    // were written on
+    let (before, after): (Vec<&Declaration>, Vec<&Declaration>) = declarations.iter().partition(|declaration| match declaration {
+        Declaration::Declaration(.., declared_at) | Declaration::StaticAssert(.., declared_at) => declared_at.start < span.start,
+    });
+    let mut source = String::new();
+    for item in before {
+        source.push_str(&declaration(exps, item));
+        source.push_str("\n\n");
+    }
+    source.push_str(&format!("int {}(void) {{\n", name));
+    for item in block_items {
+        let line = match item.as_ref() {
+            BlockItem::D(item) => declaration(exps, item),
+            BlockItem::S(item) => statement(exps, item),
+        };
+        source.push_str(&format!("    {}\n", line));
+    }
+    source.push_str("}\n");
+    for item in after {
+        source.push('\n');
+        source.push_str(&declaration(exps, item));
+        source.push('\n');
+    }
+    source
+}
@@ -0,0 +1,239 @@
+use crate::dialect::Dialect;
+use crate::driver::CompileError;
+use crate::intern::Interner;
+use crate::lex::{self, LexError, Span, Token, TokenKind};
+use crate::parser::{self, ExpArena, ExternalDeclaration, Program, ProgramBuilder};
+use std::ops::Range;
+
+// A source file kept lexed and parsed through edits, for editors that
+// recompile on every keystroke. The file is split into its file-scope
+// declarations, and an edit lexes and parses again only the ones it touches:
+// lexing restarts at the first declaration the edit reaches and stops once
+// it is back in step with the old tokens past the edit. The declarations
+// after that keep their tokens and AST, moved along by the edit's length.
+
+// One file-scope declaration
+#[derive(Debug, Clone)]
+struct Item {
+    tokens: Vec<Token>,
+    // Whether the last token closes the declaration, with a ';' or the '}'
+    // of its body
+    closed: bool,
+    // The declaration and its expressions, when it parses on its own and
+    // uses all of its tokens. A declaration that doesn't is parsed again
+    // with the rest of the file after it, as parse_program would.
+    parsed: Option<(ExternalDeclaration, ExpArena)>,
+}
+
+impl Item {
+    fn new(tokens: Vec<Token>, closed: bool, dialect: &Dialect) -> Item {
+        let mut rest = tokens.clone();
+        let mut exps = ExpArena::new();
+        let parsed = match parser::parse_external_declaration(&mut rest, dialect, &mut exps) {
+            Ok(declaration) if rest.is_empty() => Some((declaration, exps)),
+            _ => None,
+        };
+        Item { tokens, closed, parsed }
+    }
+
+    fn start(&self) -> Span {
+        self.tokens[0].span
+    }
+
+    fn end(&self) -> usize {
+        self.tokens.last().expect("a declaration has tokens").span.end
+    }
+
+    // The same declaration once the source before it has grown by bytes and
+    // by lines, which leaves the columns as they were
+    fn moved(mut self, bytes: isize, lines: isize) -> Item {
+        let shift = |span: &mut Span| {
+            span.start = span.start.checked_add_signed(bytes).expect("spans stay in the file");
+            span.end = span.end.checked_add_signed(bytes).expect("spans stay in the file");
+            span.line = span.line.checked_add_signed(lines).expect("spans stay in the file");
+        };
+        self.tokens.iter_mut().for_each(|token| shift(&mut token.span));
+        if let Some((declaration, exps)) = &mut self.parsed {
+            declaration.spans_mut().into_iter().for_each(shift);
+            exps.spans_mut().for_each(shift);
+        }
+        self
+    }
+}
+
+// Groups tokens into declarations: each one ends with a ';' or a '}' outside
+// any braces
+struct Splitter {
+    tokens: Vec<Token>,
+    depth: usize,
+}
+
+impl Splitter {
+    // Adds token, and returns the declaration if it ends there
+    fn push(&mut self, token: Token, dialect: &Dialect) -> Option<Item> {
+        let kind = token.kind;
+        self.tokens.push(token);
+        match kind {
+            TokenKind::OpenBrace => self.depth += 1,
+            TokenKind::CloseBrace if self.depth > 1 => self.depth -= 1,
+            TokenKind::CloseBrace => return Some(self.finish(dialect)),
+            TokenKind::Semicolon if self.depth == 0 => return Some(self.finish(dialect)),
+            _ => {}
+        }
+        None
+    }
+
+    fn finish(&mut self, dialect: &Dialect) -> Item {
+        self.depth = 0;
+        Item::new(std::mem::take(&mut self.tokens), true, dialect)
+    }
+}
+
+pub struct IncrementalParse {
+    source: String,
+    dialect: Dialect,
+    items: Result<Vec<Item>, LexError>,
+    // How many declarations the last edit lexed and parsed again
+    reparsed: usize,
+    // The interner current when the parse was made, which the tokens' names
+    // are in, so an edit made on another thread interns into it too
+    interner: Interner,
+}
+
+impl IncrementalParse {
+    pub fn new(source: &str, dialect: &Dialect) -> IncrementalParse {
+        let mut parse = IncrementalParse { source: source.to_string(), dialect: *dialect, items: Ok(Vec::new()), reparsed: 0, interner: Interner::current() };
+        parse.items = parse.interner.clone().enter(|| parse.lex_from(0, 1, std::iter::empty(), 0, 0));
+        parse
+    }
+
+    // The interner the tokens' names are in
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn reparsed(&self) -> usize {
+        self.reparsed
+    }
+
+    // Replaces the bytes of the source in range with text. range must start
+    // and end on character boundaries.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) {
+        self.interner.clone().enter(|| self.edit_in(range, text))
+    }
+
+    fn edit_in(&mut self, range: Range<usize>, text: &str) {
+        self.source.replace_range(range.clone(), text);
+        let items = match std::mem::replace(&mut self.items, Ok(Vec::new())) {
+            Ok(items) => items,
+            // Nothing is known to be right after a lexing error
+            Err(_) => {
+                self.items = self.lex_from(0, 1, std::iter::empty(), 0, 0);
+                return;
+            }
+        };
+        // The first declaration the edit can change is the first to end at
+        // or after its start, since text added right after a token may
+        // become part of it. A declaration left open at the end of the file
+        // is carried on by whatever is added after it.
+        let mut first = items.iter().position(|item| item.end() >= range.start).unwrap_or(items.len());
+        if first == items.len() && items.last().is_some_and(|item| !item.closed) {
+            first -= 1;
+        }
+        // Lexing starts at that declaration, or after the one before it when
+        // the edit is in the space between them
+        let (start, line) = match (items.get(first), first.checked_sub(1).map(|before| &items[before])) {
+            (Some(item), _) if item.start().start <= range.start => (item.start().start, item.start().line),
+            (_, Some(before)) => (before.end(), before.tokens.last().unwrap().span.line),
+            (_, None) => (0, 1),
+        };
+        let mut kept = items;
+        let after = kept.split_off(first).into_iter().skip_while(|item| item.start().start < range.end);
+        let bytes = text.len() as isize - range.len() as isize;
+        self.items = self.lex_from(start, line, after, bytes, range.start + text.len()).map(|items| {
+            kept.extend(items);
+            kept
+        });
+    }
+
+    // Lexes and parses the source from start, which begins on line, up to
+    // the end of the file or the first of old that the new tokens are back
+    // in step with after edit_end. old are the declarations after the edit,
+    // from before it, which moved by bytes.
+    fn lex_from(&mut self, start: usize, line: usize, old: impl Iterator<Item = Item>, bytes: isize, edit_end: usize) -> Result<Vec<Item>, LexError> {
+        let mut old = old.peekable();
+        let mut lexer = lex::Lex::resume(&self.source, &self.dialect, start, line);
+        let mut splitter = Splitter { tokens: Vec::new(), depth: 0 };
+        let mut items = Vec::new();
+        self.reparsed = 0;
+        while let Some(token) = lexer.next_token()? {
+            if splitter.tokens.is_empty() && token.span.start >= edit_end {
+                while old.peek().is_some_and(|item| item.start().start.checked_add_signed(bytes) < Some(token.span.start)) {
+                    old.next();
+                }
+                if let Some(lines) = old.peek().and_then(|item| self.in_step(item.start(), &token, bytes)) {
+                    items.extend(old.map(|item| item.moved(bytes, lines)));
+                    return Ok(items);
+                }
+            }
+            if let Some(item) = splitter.push(token, &self.dialect) {
+                items.push(item);
+                self.reparsed += 1;
+            }
+        }
+        if !splitter.tokens.is_empty() {
+            items.push(Item::new(splitter.tokens, false, &self.dialect));
+            self.reparsed += 1;
+        }
+        Ok(items)
+    }
+
+    // How many lines further down token is than old was, if the text from
+    // token on is the text from old on and every token after it moves by
+    // that many lines. A line directive after token would number the lines
+    // after it the same as before, so then only no move at all is in step.
+    fn in_step(&self, old: Span, token: &Token, bytes: isize) -> Option<isize> {
+        if old.start.checked_add_signed(bytes) != Some(token.span.start) || old.column != token.span.column {
+            return None;
+        }
+        let lines = token.span.line as isize - old.line as isize;
+        let rest = &self.source[token.span.start..];
+        if lines != 0 && (rest.contains('#') || rest.contains("%:") || rest.contains("??=")) {
+            return None;
+        }
+        Some(lines)
+    }
+
+    // The tokens of the source, as Lex::get_tokens gives them
+    pub fn tokens(&self) -> Result<Vec<Token>, LexError> {
+        self.items.clone().map(|items| items.into_iter().flat_map(|item| item.tokens).collect())
+    }
+
+    // The program as parse_program gives it for the whole source
+    pub fn program(&self) -> Result<Program, CompileError> {
+        self.interner.enter(|| self.program_in())
+    }
+
+    fn program_in(&self) -> Result<Program, CompileError> {
+        let items = self.items.as_ref().map_err(|err| CompileError::Lex(err.clone()))?;
+        if items.is_empty() {
+            return parser::parse_program(&mut Vec::new(), &self.dialect).map_err(CompileError::Parse);
+        }
+        let mut program = ProgramBuilder::default();
+        for (index, item) in items.iter().enumerate() {
+            match &item.parsed {
+                Some((declaration, exps)) => program.append(declaration.clone(), exps.clone(), item.start()).map_err(CompileError::Parse)?,
+                None => {
+                    let mut rest: Vec<Token> = items[index..].iter().flat_map(|item| item.tokens.iter().cloned()).collect();
+                    program.parse(&mut rest, &self.dialect).map_err(CompileError::Parse)?;
+                    break;
+                }
+            }
+        }
+        program.finish().map_err(CompileError::Parse)
+    }
+}
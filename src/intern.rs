@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// An interned string. Comparing and hashing a Symbol is a single integer
+// operation, and copying one never allocates. A Symbol is an index into the
+// interner that made it, so it is interned and read through the interner
+// current on the thread, which must be that same one.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Table {
+    map: HashMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+// A table of interned strings, freed once the last handle to it is dropped.
+// Each compilation a Compiler runs has one of its own, so no two compilations
+// share one and a long session doesn't keep the names of everything it ever
+// compiled. Every program a stage produces keeps a handle to the interner
+// its names are in and enters it in its entry points, so it can be passed
+// to another thread between stages. Only the thread running a compilation
+// uses its interner while it runs, so the lock is never waited on.
+#[derive(Clone, Default)]
+pub struct Interner(Arc<Mutex<Table>>);
+
+thread_local! {
+    // A thread that hasn't entered an interner interns into one of its own,
+    // which lasts as long as the thread
+    static CURRENT: RefCell<Interner> = RefCell::new(Interner::new());
+}
+
+// Puts back the interner that was current before enter, even if the code
+// run in it panics
+struct Restore(Option<Interner>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    // The interner Symbols on this thread are interned into
+    pub fn current() -> Interner {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    // Runs f with this as the current interner
+    pub fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|current| current.replace(self.clone()));
+        let _restore = Restore(Some(previous));
+        f()
+    }
+
+    fn intern(&self, text: &str) -> Symbol {
+        let mut table = self.0.lock().unwrap();
+        if let Some(symbol) = table.map.get(text) {
+            return *symbol;
+        }
+        let text: Arc<str> = Arc::from(text);
+        let symbol = Symbol(table.strings.len() as u32);
+        table.strings.push(text.clone());
+        table.map.insert(text, symbol);
+        symbol
+    }
+
+    fn text(&self, symbol: Symbol) -> Arc<str> {
+        self.0.lock().unwrap().strings[symbol.0 as usize].clone()
+    }
+}
+
+impl Symbol {
+    pub fn intern(text: &str) -> Symbol {
+        CURRENT.with(|current| current.borrow().intern(text))
+    }
+
+    pub fn text(&self) -> Arc<str> {
+        CURRENT.with(|current| current.borrow().text(*self))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.text())
+    }
+}
@@ -0,0 +1,118 @@
+use crate::intern::Symbol;
+use crate::tac::{narrow, BinaryOperator, Function, Instruction, Program, UnaryOperator, Val};
+use std::collections::HashMap;
+
+// Runs a TAC function directly instead of compiling it, computing what the
+// generated code would: arithmetic wraps, shift counts are masked to 5 bits
+// and >> is arithmetic. Division by zero and INT_MIN / -1, which trap in the
+// compiled program, are errors here.
+
+fn value(val: &Val, variables: &HashMap<Symbol, i32>) -> Result<i32, String> {
+    match val {
+        Val::Constant(value) => Ok(*value),
+        Val::Identifier(name) => variables.get(name).copied()
+            .ok_or_else(|| format!("Variable '{}' is used before it is assigned", name)),
+    }
+}
+
+fn assign(variables: &mut HashMap<Symbol, i32>, dst: &Val, result: i32) {
+    if let Val::Identifier(name) = dst {
+        variables.insert(*name, result);
+    }
+}
+
+fn unary(operator: &UnaryOperator, value: i32) -> i32 {
+    match operator {
+        UnaryOperator::Negate => value.wrapping_neg(),
+        UnaryOperator::Complement => !value,
+        UnaryOperator::LogicalNot => (value == 0) as i32,
+    }
+}
+
+fn binary(operator: &BinaryOperator, left: i32, right: i32) -> Result<i32, String> {
+    let division = |result: Option<i32>| result.ok_or_else(|| if right == 0 {
+        "Division by zero".to_string()
+    } else {
+        "Division overflow".to_string()
+    });
+    Ok(match operator {
+        BinaryOperator::Add => left.wrapping_add(right),
+        BinaryOperator::Subtract => left.wrapping_sub(right),
+        BinaryOperator::Multiply => left.wrapping_mul(right),
+        BinaryOperator::Divide => division(left.checked_div(right))?,
+        BinaryOperator::Modulo => division(left.checked_rem(right))?,
+        BinaryOperator::Ampersand => left & right,
+        BinaryOperator::Pipe => left | right,
+        BinaryOperator::Caret => left ^ right,
+        BinaryOperator::ShiftLeft => left.wrapping_shl(right as u32),
+        BinaryOperator::ShiftRight => left.wrapping_shr(right as u32),
+        BinaryOperator::LogicalAnd => (left != 0 && right != 0) as i32,
+        BinaryOperator::LogicalOr => (left != 0 || right != 0) as i32,
+        BinaryOperator::Equal => (left == right) as i32,
+        BinaryOperator::NotEqual => (left != right) as i32,
+        BinaryOperator::GreaterThan => (left > right) as i32,
+        BinaryOperator::GreaterThanOrEqual => (left >= right) as i32,
+        BinaryOperator::LessThan => (left < right) as i32,
+        BinaryOperator::LessThanOrEqual => (left <= right) as i32,
+        BinaryOperator::Assign => right,
+    })
+}
+
+// The value the function returns
+pub fn run(function: &Function) -> Result<i32, String> {
+    run_with(function, HashMap::new())
+}
+
+// The value the program's function returns, with its static variables
+// holding their initial values
+pub fn run_program(program: &Program) -> Result<i32, String> {
+    program.interner.enter(|| run_with(&program.function, program.statics.iter().copied().collect()))
+}
+
+fn run_with(function: &Function, mut variables: HashMap<Symbol, i32>) -> Result<i32, String> {
+    let labels: HashMap<Symbol, usize> = function.body.iter().enumerate()
+        .filter_map(|(index, instruction)| match instruction {
+            Instruction::Label { label } => Some((*label, index)),
+            _ => None,
+        })
+        .collect();
+    let jump = |label: &Symbol| labels.get(label).copied().ok_or_else(|| format!("Jump to unknown label '{}'", label));
+
+    let mut pc = 0;
+    while let Some(instruction) = function.body.get(pc) {
+        pc += 1;
+        match instruction {
+            Instruction::Return(val) => return value(val, &variables),
+            Instruction::Unary { operator, src, dst } => {
+                let result = unary(operator, value(src, &variables)?);
+                assign(&mut variables, dst, result);
+            }
+            Instruction::Binary { operator, src1, src2, dst } => {
+                let result = binary(operator, value(src1, &variables)?, value(src2, &variables)?)?;
+                assign(&mut variables, dst, result);
+            }
+            Instruction::Copy { src, dst } => {
+                let result = value(src, &variables)?;
+                assign(&mut variables, dst, result);
+            }
+            Instruction::Narrow { target, src, dst } => {
+                let result = narrow(target, value(src, &variables)?);
+                assign(&mut variables, dst, result);
+            }
+            Instruction::Jump { label } => pc = jump(label)?,
+            Instruction::JumpIfZero { src, label, .. } => if value(src, &variables)? == 0 {
+                pc = jump(label)?;
+            },
+            Instruction::JumpIfNotZero { src, label, .. } => if value(src, &variables)? != 0 {
+                pc = jump(label)?;
+            },
+            Instruction::Label { .. } => {}
+            Instruction::FunCall { name, .. } => return Err(format!("Calls to '{}' can't be interpreted", name)),
+            Instruction::InlineAsm(_) => return Err("Inline assembly can't be interpreted".to_string()),
+            Instruction::Abort(message) => return Err(format!("Aborted: {}", message)),
+            Instruction::Trap => return Err("Trapped".to_string()),
+            Instruction::Unreachable => return Err("Reached '__builtin_unreachable'".to_string()),
+        }
+    }
+    Err(format!("Function '{}' ended without returning", function.identifier))
+}
@@ -0,0 +1,194 @@
+use crate::driver::json_string;
+use crate::format;
+use crate::lex::Span;
+use crate::parser::{BinaryOp, BlockItem, Declaration, Exp, ExpArena, ExpId, FunctionDeclaration, Program, Statement, Type, UnaryOp};
+use crate::symbols::StorageClass;
+use crate::tac::{self, Instruction, Val};
+
+// The AST and TAC as JSON for --ast-json and --tac-json, for tools outside
+// the compiler such as autograders. The layout is a contract: schemas/
+// describes each version, and a change that could break a reader of the
+// current layout needs a new version and a new schema rather than an edit
+// to the old one. Adding an instruction or expression kind counts, as a
+// reader may reject kinds it doesn't know.
+pub const AST_VERSION: u32 = 1;
+pub const TAC_VERSION: u32 = 1;
+
+fn location(span: Span) -> String {
+    format!("\"line\":{},\"column\":{}", span.line, span.column)
+}
+
+fn list<T>(items: impl IntoIterator<Item = T>, to_json: impl Fn(T) -> String) -> String {
+    let items: Vec<String> = items.into_iter().map(to_json).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn ty(ty: &Type) -> String {
+    let base = match ty {
+        Type::Char => "char",
+        Type::SChar => "signed char",
+        Type::UChar => "unsigned char",
+        Type::Short => "short",
+        Type::UShort => "unsigned short",
+        Type::Int => "int",
+        Type::UInt => "unsigned int",
+        Type::Long => "long",
+        Type::ULong => "unsigned long",
+        Type::LongLong => "long long",
+        Type::ULongLong => "unsigned long long",
+        Type::Pointer(referenced) => return format!("{{\"kind\":\"pointer\",\"referenced\":{}}}", self::ty(referenced)),
+        Type::Array(element, length) => {
+            return format!("{{\"kind\":\"array\",\"element\":{},\"length\":{}}}", self::ty(element), length)
+        }
+        Type::Function(parameters, returned) => {
+            return format!("{{\"kind\":\"function\",\"parameters\":{},\"return\":{}}}", list(parameters, self::ty), self::ty(returned))
+        }
+        Type::Volatile(qualified) => return format!("{{\"kind\":\"volatile\",\"qualified\":{}}}", self::ty(qualified)),
+    };
+    format!("{{\"kind\":\"{}\"}}", base)
+}
+
+fn unary_operator(op: UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Plus => "+",
+        UnaryOp::Negation => "-",
+        UnaryOp::Complement => "~",
+        UnaryOp::LogicalNot => "!",
+    }
+}
+
+fn exp(exps: &ExpArena, id: ExpId) -> String {
+    let operand = |id: &ExpId| exp(exps, *id);
+    match &exps[id] {
+        Exp::Constant(value, span) => format!("{{\"kind\":\"constant\",\"value\":{},{}}}", value, location(*span)),
+        Exp::Var(name, span) => format!("{{\"kind\":\"var\",\"name\":{},{}}}", json_string(&name.text()), location(*span)),
+        Exp::Unary(op, inner) => format!("{{\"kind\":\"unary\",\"operator\":\"{}\",\"operand\":{}}}", unary_operator(*op), operand(inner)),
+        Exp::Cast(target, inner) => format!("{{\"kind\":\"cast\",\"type\":{},\"operand\":{}}}", ty(target), operand(inner)),
+        Exp::Binary(left, op, right) => {
+            format!("{{\"kind\":\"binary\",\"operator\":\"{}\",\"left\":{},\"right\":{}}}", format::binary_spelling(*op), operand(left), operand(right))
+        }
+        Exp::Assignment(left, right) => format!("{{\"kind\":\"assignment\",\"left\":{},\"right\":{}}}", operand(left), operand(right)),
+        Exp::CompoundAssignment(op, left, right) => {
+            let spelling = match op {
+                BinaryOp::LeftShift => "<<=",
+                BinaryOp::RightShift => ">>=",
+                _ => unreachable!("only shifts have compound assignments"),
+            };
+            format!("{{\"kind\":\"compound_assignment\",\"operator\":\"{}\",\"left\":{},\"right\":{}}}", spelling, operand(left), operand(right))
+        }
+        Exp::Conditional(condition, then, otherwise) => {
+            let then = then.as_ref().map_or("null".to_string(), operand);
+            format!("{{\"kind\":\"conditional\",\"condition\":{},\"then\":{},\"else\":{}}}", operand(condition), then, operand(otherwise))
+        }
+        Exp::Dot(inner, member) => format!("{{\"kind\":\"dot\",\"operand\":{},\"member\":{}}}", operand(inner), json_string(&member.text())),
+        Exp::Arrow(inner, member) => format!("{{\"kind\":\"arrow\",\"operand\":{},\"member\":{}}}", operand(inner), json_string(&member.text())),
+        Exp::Expect(inner, value) => format!("{{\"kind\":\"expect\",\"operand\":{},\"expected\":{}}}", operand(inner), value),
+        Exp::Call(name, args, span) => {
+            format!("{{\"kind\":\"call\",\"name\":{},\"arguments\":{},{}}}", json_string(&name.text()), list(args, operand), location(*span))
+        }
+    }
+}
+
+fn declaration(exps: &ExpArena, declaration: &Declaration) -> String {
+    match declaration {
+        Declaration::Declaration(name, declared, init, storage_class, span) => {
+            let storage_class = match storage_class {
+                Some(StorageClass::Extern) => "\"extern\"",
+                Some(StorageClass::Static) => "\"static\"",
+                None => "null",
+            };
+            let init = init.map_or("null".to_string(), |init| exp(exps, init));
+            format!("{{\"kind\":\"declaration\",\"name\":{},\"type\":{},\"storage_class\":{},\"init\":{},{}}}",
+                json_string(&name.text()), ty(declared), storage_class, init, location(*span))
+        }
+        Declaration::StaticAssert(condition, message, span) => {
+            let message = message.as_deref().map_or("null".to_string(), json_string);
+            format!("{{\"kind\":\"static_assert\",\"condition\":{},\"message\":{},{}}}", exp(exps, *condition), message, location(*span))
+        }
+    }
+}
+
+fn statement(exps: &ExpArena, statement: &Statement) -> String {
+    match statement {
+        Statement::Return(value) => format!("{{\"kind\":\"return\",\"exp\":{}}}", exp(exps, *value)),
+        Statement::Expression(value) => format!("{{\"kind\":\"expression\",\"exp\":{}}}", exp(exps, *value)),
+        Statement::Asm(template) => format!("{{\"kind\":\"asm\",\"template\":{}}}", json_string(template)),
+        Statement::Trap => "{\"kind\":\"trap\"}".to_string(),
+        Statement::Unreachable => "{\"kind\":\"unreachable\"}".to_string(),
+        Statement::Null => "{\"kind\":\"null\"}".to_string(),
+    }
+}
+
+// The program as parsed, before names are resolved, as one JSON object
+pub fn ast_to_json(program: &Program) -> String {
+    program.interner().enter(|| ast_to_json_in(program))
+}
+
+fn ast_to_json_in(program: &Program) -> String {
+    let Program::Program(declarations, FunctionDeclaration::Function(name, body, span), exps, _) = program;
+    let body = list(body, |item| match item.as_ref() {
+        BlockItem::D(item) => declaration(exps, item),
+        BlockItem::S(item) => statement(exps, item),
+    });
+    format!("{{\"version\":{},\"declarations\":{},\"function\":{{\"name\":{},\"body\":{},{}}}}}\n",
+        AST_VERSION, list(declarations, |item| declaration(exps, item)), json_string(&name.text()), body, location(*span))
+}
+
+fn val(val: &Val) -> String {
+    match val {
+        Val::Constant(value) => format!("{{\"kind\":\"constant\",\"value\":{}}}", value),
+        Val::Identifier(name) => format!("{{\"kind\":\"var\",\"name\":{}}}", json_string(&name.text())),
+    }
+}
+
+fn likely(likely: Option<bool>) -> &'static str {
+    match likely {
+        Some(true) => "true",
+        Some(false) => "false",
+        None => "null",
+    }
+}
+
+fn instruction(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Return(src) => format!("{{\"op\":\"return\",\"src\":{}}}", val(src)),
+        Instruction::Unary { operator, src, dst } => {
+            format!("{{\"op\":\"unary\",\"operator\":\"{}\",\"src\":{},\"dst\":{}}}", operator, val(src), val(dst))
+        }
+        Instruction::Binary { operator, src1, src2, dst } => {
+            format!("{{\"op\":\"binary\",\"operator\":\"{}\",\"src1\":{},\"src2\":{},\"dst\":{}}}", operator, val(src1), val(src2), val(dst))
+        }
+        Instruction::Copy { src, dst } => format!("{{\"op\":\"copy\",\"src\":{},\"dst\":{}}}", val(src), val(dst)),
+        Instruction::Narrow { target, src, dst } => {
+            format!("{{\"op\":\"narrow\",\"type\":{},\"src\":{},\"dst\":{}}}", ty(target), val(src), val(dst))
+        }
+        Instruction::Jump { label } => format!("{{\"op\":\"jump\",\"label\":{}}}", json_string(&label.text())),
+        Instruction::JumpIfZero { src, label, likely: hint } => {
+            format!("{{\"op\":\"jump_if_zero\",\"src\":{},\"label\":{},\"likely\":{}}}", val(src), json_string(&label.text()), likely(*hint))
+        }
+        Instruction::JumpIfNotZero { src, label, likely: hint } => {
+            format!("{{\"op\":\"jump_if_not_zero\",\"src\":{},\"label\":{},\"likely\":{}}}", val(src), json_string(&label.text()), likely(*hint))
+        }
+        Instruction::Label { label } => format!("{{\"op\":\"label\",\"label\":{}}}", json_string(&label.text())),
+        Instruction::FunCall { name, args, dst } => {
+            format!("{{\"op\":\"call\",\"name\":{},\"args\":{},\"dst\":{}}}", json_string(&name.text()), list(args, val), val(dst))
+        }
+        Instruction::InlineAsm(template) => format!("{{\"op\":\"inline_asm\",\"template\":{}}}", json_string(template)),
+        Instruction::Abort(message) => format!("{{\"op\":\"abort\",\"message\":{}}}", json_string(message)),
+        Instruction::Trap => "{\"op\":\"trap\"}".to_string(),
+        Instruction::Unreachable => "{\"op\":\"unreachable\"}".to_string(),
+    }
+}
+
+// The program's TAC, as the optimization passes leave it, as one JSON object
+pub fn tac_to_json(program: &tac::Program) -> String {
+    program.interner.enter(|| tac_to_json_in(program))
+}
+
+fn tac_to_json_in(program: &tac::Program) -> String {
+    let function = &program.function;
+    let names = |names: &Vec<crate::Symbol>| list(names, |name| json_string(&name.text()));
+    let statics = list(&program.statics, |(name, init)| format!("{{\"name\":{},\"init\":{}}}", json_string(&name.text()), init));
+    format!("{{\"version\":{},\"externs\":{},\"statics\":{},\"function\":{{\"name\":{},\"volatiles\":{},\"body\":{}}}}}\n",
+        TAC_VERSION, names(&program.externs), statics, json_string(&function.identifier.text()), names(&function.volatiles), list(&function.body, instruction))
+}
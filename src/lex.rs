@@ -1,30 +1,183 @@
-use std::{collections::HashSet, process};
+use crate::dialect::{Dialect, Standard};
+use crate::intern::Symbol;
+use std::borrow::Cow;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Keyword {
+    Auto,
+    Break,
+    Case,
+    Char,
+    Const,
+    Continue,
+    Default,
+    Do,
+    Double,
+    Else,
+    Enum,
+    Extern,
+    Float,
+    For,
+    Goto,
+    If,
+    Inline,
+    Int,
+    Long,
+    Register,
+    Restrict,
+    Return,
+    Short,
+    Signed,
+    Sizeof,
+    Static,
+    Struct,
+    Switch,
+    Typedef,
+    Union,
+    Unsigned,
+    Void,
+    Volatile,
+    While,
+    Alignas,
+    Alignof,
+    Atomic,
+    Bool,
+    Complex,
+    Generic,
+    Imaginary,
+    Noreturn,
+    StaticAssert,
+    ThreadLocal,
+    True,
+    False,
+    Nullptr,
+    Asm,
+    BuiltinExpect,
+    BuiltinTrap,
+    BuiltinUnreachable,
+}
+
+// Every C11 keyword paired with its spelling
+const KEYWORDS: [(&str, Keyword); 44] = [
+    ("auto", Keyword::Auto),
+    ("break", Keyword::Break),
+    ("case", Keyword::Case),
+    ("char", Keyword::Char),
+    ("const", Keyword::Const),
+    ("continue", Keyword::Continue),
+    ("default", Keyword::Default),
+    ("do", Keyword::Do),
+    ("double", Keyword::Double),
+    ("else", Keyword::Else),
+    ("enum", Keyword::Enum),
+    ("extern", Keyword::Extern),
+    ("float", Keyword::Float),
+    ("for", Keyword::For),
+    ("goto", Keyword::Goto),
+    ("if", Keyword::If),
+    ("inline", Keyword::Inline),
+    ("int", Keyword::Int),
+    ("long", Keyword::Long),
+    ("register", Keyword::Register),
+    ("restrict", Keyword::Restrict),
+    ("return", Keyword::Return),
+    ("short", Keyword::Short),
+    ("signed", Keyword::Signed),
+    ("sizeof", Keyword::Sizeof),
+    ("static", Keyword::Static),
+    ("struct", Keyword::Struct),
+    ("switch", Keyword::Switch),
+    ("typedef", Keyword::Typedef),
+    ("union", Keyword::Union),
+    ("unsigned", Keyword::Unsigned),
+    ("void", Keyword::Void),
+    ("volatile", Keyword::Volatile),
+    ("while", Keyword::While),
+    ("_Alignas", Keyword::Alignas),
+    ("_Alignof", Keyword::Alignof),
+    ("_Atomic", Keyword::Atomic),
+    ("_Bool", Keyword::Bool),
+    ("_Complex", Keyword::Complex),
+    ("_Generic", Keyword::Generic),
+    ("_Imaginary", Keyword::Imaginary),
+    ("_Noreturn", Keyword::Noreturn),
+    ("_Static_assert", Keyword::StaticAssert),
+    ("_Thread_local", Keyword::ThreadLocal),
+];
+
+// Keywords C23 added, which are ordinary identifiers before it
+const C23_KEYWORDS: [(&str, Keyword); 4] = [
+    ("true", Keyword::True),
+    ("false", Keyword::False),
+    ("nullptr", Keyword::Nullptr),
+    ("static_assert", Keyword::StaticAssert),
+];
+
+// GNU keywords spelled as reserved identifiers, which no program can be
+// using as names, so they are keywords in every dialect
+const RESERVED_GNU_KEYWORDS: [(&str, Keyword); 5] = [
+    ("__asm__", Keyword::Asm),
+    ("__asm", Keyword::Asm),
+    ("__builtin_expect", Keyword::BuiltinExpect),
+    ("__builtin_trap", Keyword::BuiltinTrap),
+    ("__builtin_unreachable", Keyword::BuiltinUnreachable),
+];
+
+// GNU keywords that are ordinary identifiers without --gnu-extensions
+const GNU_KEYWORDS: [(&str, Keyword); 1] = [
+    ("asm", Keyword::Asm),
+];
+
+impl Keyword {
+    pub fn from_identifier(text: &str, dialect: &Dialect) -> Option<Keyword> {
+        let c23 = if dialect.std >= Standard::C23 { &C23_KEYWORDS[..] } else { &[] };
+        let gnu = if dialect.gnu_extensions { &GNU_KEYWORDS[..] } else { &[] };
+        KEYWORDS.iter().chain(c23).chain(&RESERVED_GNU_KEYWORDS).chain(gnu)
+            .find(|(spelling, _)| *spelling == text).map(|(_, keyword)| *keyword)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        KEYWORDS.iter().chain(&C23_KEYWORDS).chain(&RESERVED_GNU_KEYWORDS)
+            .find(|(_, keyword)| keyword == self).map(|(spelling, _)| *spelling).unwrap()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum TokenType {
-    IDENTIFIER, 
-    CONSTANT,
-    KEYWORD,
+pub enum TokenKind {
+    Identifier, 
+    Constant,
+    CharConstant,
+    StringLiteral,
+    Keyword(Keyword),
     OpenParen,
     CloseParen,
     OpenBrace,
     CloseBrace,
-    SEMICOLON,
-    SLASH,
-    COMMENT,
+    OpenBracket,
+    CloseBracket,
+    Semicolon,
+    Comma,
+    Question,
+    Colon,
+    Slash,
+    Whitespace,
+    Comment,
     LongComment,
-    STAR,
-    PLUS,
-    MODULUS,
+    Star,
+    Plus,
+    Modulus,
     TildeOp,
     NegationOp,
     DecrementOp,
-    AMPERSAND,
-    PIPE,
-    CARET,
+    Ampersand,
+    Pipe,
+    Caret,
     LessThan,
     GreaterThan,
     LeftShift,
     RightShift,
+    LeftShiftAssign,
+    RightShiftAssign,
     LogicalNot,
     LogicalAnd,
     LogicalOr,
@@ -34,240 +187,744 @@ pub enum TokenType {
     GreaterThanOrEqual,
     LessThanOrEqual,
     Tag,
+    Arrow,
+    Dot,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spelling = match self {
+            TokenKind::Identifier => "identifier",
+            TokenKind::Constant => "constant",
+            TokenKind::CharConstant => "character constant",
+            TokenKind::StringLiteral => "string literal",
+            TokenKind::Keyword(keyword) => keyword.as_str(),
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+            TokenKind::OpenBrace => "{",
+            TokenKind::CloseBrace => "}",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
+            TokenKind::Semicolon => ";",
+            TokenKind::Comma => ",",
+            TokenKind::Question => "?",
+            TokenKind::Colon => ":",
+            TokenKind::Slash => "/",
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Comment => "//",
+            TokenKind::LongComment => "/* */",
+            TokenKind::Star => "*",
+            TokenKind::Plus => "+",
+            TokenKind::Modulus => "%",
+            TokenKind::TildeOp => "~",
+            TokenKind::NegationOp => "-",
+            TokenKind::DecrementOp => "--",
+            TokenKind::Ampersand => "&",
+            TokenKind::Pipe => "|",
+            TokenKind::Caret => "^",
+            TokenKind::LessThan => "<",
+            TokenKind::GreaterThan => ">",
+            TokenKind::LeftShift => "<<",
+            TokenKind::RightShift => ">>",
+            TokenKind::LeftShiftAssign => "<<=",
+            TokenKind::RightShiftAssign => ">>=",
+            TokenKind::LogicalNot => "!",
+            TokenKind::LogicalAnd => "&&",
+            TokenKind::LogicalOr => "||",
+            TokenKind::Assignment => "=",
+            TokenKind::Equal => "==",
+            TokenKind::NotEqual => "!=",
+            TokenKind::GreaterThanOrEqual => ">=",
+            TokenKind::LessThanOrEqual => "<=",
+            TokenKind::Tag => "#",
+            TokenKind::Arrow => "->",
+            TokenKind::Dot => ".",
+        };
+        write!(f, "{}", spelling)
+    }
+}
+
+impl TokenKind {
+    // The token's category in the C grammar, a stable name for tools
+    pub fn category(&self) -> &'static str {
+        match self {
+            TokenKind::Identifier => "identifier",
+            TokenKind::Constant | TokenKind::CharConstant => "constant",
+            TokenKind::StringLiteral => "string-literal",
+            TokenKind::Keyword(_) => "keyword",
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Comment | TokenKind::LongComment => "comment",
+            TokenKind::Tag => "directive",
+            _ => "punctuator",
+        }
+    }
+
+    // Source text the parser never sees: whitespace, comments and # lines
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment | TokenKind::LongComment | TokenKind::Tag)
+    }
+}
+
+// A region of the source text. start and end are byte offsets; line and
+// column (both 1-based) locate start for diagnostics.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+// The line number set by a #line directive or by a linemarker, which is how
+// preprocessed output (# 12 "file.c") says where the next line came from
+fn line_marker(directive: &str) -> Option<usize> {
+    let rest = directive.strip_prefix('#').or_else(|| directive.strip_prefix("%:"))?.trim_start();
+    let rest = rest.strip_prefix("line").unwrap_or(rest);
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+// Punctuators C has that the compiler doesn't support yet, longest first so
+// that "..." isn't taken for something shorter, with what to write instead
+const UNSUPPORTED_PUNCTUATORS: [(&str, &str); 11] = [
+    ("...", "functions can't take variable arguments"),
+    ("++", "write 'x = x + 1' instead"),
+    ("+=", "write 'x = x + y' instead"),
+    ("-=", "write 'x = x - y' instead"),
+    ("*=", "write 'x = x * y' instead"),
+    ("/=", "write 'x = x / y' instead"),
+    ("%=", "write 'x = x % y' instead"),
+    ("&=", "write 'x = x & y' instead"),
+    ("|=", "write 'x = x | y' instead"),
+    ("^=", "write 'x = x ^ y' instead"),
+    ("##", "token pasting is only allowed in a macro definition"),
+];
+
+// Trigraphs were replaced before tokenizing up to C17 and removed in C23;
+// the preprocessor replaces them when the standard has them, so any left
+// are reported along with the character they stand for
+const TRIGRAPHS: [(char, char); 9] = [
+    ('=', '#'), ('(', '['), ('/', '\\'), (')', ']'), ('\'', '^'), ('<', '{'), ('!', '|'), ('>', '}'), ('-', '~'),
+];
+
+// Advice for characters that can't start a token, mostly ones that editors
+// and word processors put in place of ASCII punctuation
+fn invalid_character_hint(c: char) -> Option<&'static str> {
+    match c {
+        '\u{201c}' | '\u{201d}' => Some("did you mean '\"'?"),
+        '\u{2018}' | '\u{2019}' => Some("did you mean '\''?"),
+        '\u{2013}' | '\u{2014}' | '\u{2212}' => Some("did you mean '-'?"),
+        '\u{00a0}' => Some("this is a non-breaking space"),
+        '$' => Some("'$' in identifiers is not supported"),
+        '\\' => Some("a backslash is only allowed in a string or character constant"),
+        '`' => Some("string literals are written with '\"'"),
+        _ => None,
+    }
+}
+
+// The bytes a quoted literal stands for, given with its quotes, with escape
+// sequences replaced. An error carries the byte offset in literal that it
+// refers to.
+fn literal_bytes(literal: &str) -> Result<Vec<u8>, (usize, String)> {
+    let body = &literal[1..literal.len() - 1];
+    let mut bytes = Vec::new();
+    let mut chars = body.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        if c != '\\' {
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        let offset = offset + 1;
+        let byte = match chars.next().map(|(_, c)| c) {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('a') => 0x07,
+            Some('b') => 0x08,
+            Some('f') => 0x0c,
+            Some('v') => 0x0b,
+            Some(c @ ('\\' | '\'' | '"' | '?')) => c as u8,
+            // Up to three octal digits
+            Some(c @ '0'..='7') => {
+                let mut value = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    let Some(digit) = chars.peek().and_then(|(_, c)| c.to_digit(8)) else {
+                        break;
+                    };
+                    value = value * 8 + digit;
+                    chars.next();
+                }
+                u8::try_from(value).map_err(|_| (offset, "Octal escape sequence out of range".to_string()))?
+            }
+            // Any number of hex digits, but the value must fit in a char
+            Some('x') => {
+                let mut value: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = chars.peek().and_then(|(_, c)| c.to_digit(16)) {
+                    value = value.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                    chars.next();
+                }
+                if digits == 0 {
+                    return Err((offset, "\\x used with no following hex digits".to_string()));
+                }
+                u8::try_from(value).map_err(|_| (offset, "Hex escape sequence out of range".to_string()))?
+            }
+            Some(c) => return Err((offset, format!("Unknown escape sequence '\\{}'", c))),
+            None => return Err((offset, "Incomplete escape sequence".to_string())),
+        };
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+// The bytes of a string literal such as "a\n", without the terminating zero
+pub fn string_literal_value(literal: &str) -> Result<Vec<u8>, (usize, String)> {
+    literal_bytes(literal)
+}
+
+// The value of an integer constant's digits, which are octal after a
+// leading 0, or None if it doesn't fit in 64 bits
+pub fn integer_constant_value(digits: &str) -> Option<i64> {
+    match digits.strip_prefix('0') {
+        Some(octal) if !octal.is_empty() => i64::from_str_radix(octal, 8).ok(),
+        _ => digits.parse().ok(),
+    }
+}
+
+// The value of a character constant such as 'a' or '\x41'. char is signed,
+// so values above 127 become negative.
+pub fn char_constant_value(literal: &str) -> Result<i32, (usize, String)> {
+    // Such a character is several bytes in UTF-8, but it was written as one
+    if let Some((offset, c)) = literal.char_indices().find(|(_, c)| !c.is_ascii()) {
+        return Err((offset, format!("Non-ASCII character '{}' in a character constant is not supported", c)));
+    }
+    match literal_bytes(literal)?[..] {
+        [] => Err((0, "Empty character constant".to_string())),
+        [byte] => Ok(byte as i8 as i32),
+        _ => Err((0, "Multi-character character constants are not supported".to_string())),
+    }
+}
+
+// The source text of an identifier, constant, literal, comment or tag.
+// Only identifiers are interned: the rest are mostly seen once, and would
+// only fill the interner.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TokenValue {
+    Name(Symbol),
+    Text(String),
 }
 
+// Only identifiers, constants, comments and tags carry their source text;
+// everything else is fully described by its kind.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Token {
-    pub token_type: TokenType,
-    pub value: String,
+    pub kind: TokenKind,
+    pub value: Option<TokenValue>,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind) -> Token {
+        Token { kind, value: None, span: Span::default() }
+    }
+
+    pub fn with_value(kind: TokenKind, value: &str) -> Token {
+        let value = match kind {
+            TokenKind::Identifier => TokenValue::Name(Symbol::intern(value)),
+            _ => TokenValue::Text(value.to_string()),
+        };
+        Token { kind, value: Some(value), span: Span::default() }
+    }
+
+    // The interned name of an identifier
+    pub fn name(&self) -> Option<Symbol> {
+        match self.value {
+            Some(TokenValue::Name(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self) -> Cow<'_, str> {
+        match &self.value {
+            Some(TokenValue::Name(name)) => Cow::Owned(name.text().to_string()),
+            Some(TokenValue::Text(text)) => Cow::Borrowed(text),
+            None => Cow::Borrowed(""),
+        }
+    }
+}
+
+// A token with the trivia around it. Trivia on the same line as a token, up
+// to and including the line break, trails it; everything else leads the next
+// token. Trivia tokens carry spans, so their text is the slice of the source
+// they cover.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TriviaToken {
+    pub leading: Vec<Token>,
+    pub token: Token,
+    pub trailing: Vec<Token>,
+}
+
+// Every token of a source file, with the trivia after the last one kept in
+// end, so the spans of all pieces together cover the whole file
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct TokenStream {
+    pub tokens: Vec<TriviaToken>,
+    pub end: Vec<Token>,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(TokenValue::Name(name)) => write!(f, "{}", name),
+            Some(TokenValue::Text(text)) => write!(f, "{}", text),
+            None => write!(f, "{}", self.kind),
+        }
+    }
 }
 
 pub struct Lex<'a> {
     text: &'a str,
+    dialect: Dialect,
     pos: usize,
+    line: usize,
+    line_start: usize,
+    // The number a #line directive gave the line after it
+    next_line: Option<usize>,
 }
 
 impl<'a> Lex<'a> {
-    pub fn new(text: &str) -> Lex {
-        Lex { text, pos: 0 }
+    // A lexer for the default dialect
+    pub fn new(text: &str) -> Lex<'_> {
+        Lex::with_dialect(text, &Dialect::default())
+    }
+
+    pub fn with_dialect<'b>(text: &'b str, dialect: &Dialect) -> Lex<'b> {
+        Lex { text, dialect: *dialect, pos: 0, line: 1, line_start: 0, next_line: None }
     }
 
+    // A lexer that carries on from start, where a token lexed before on
+    // line begins or one ends
+    pub fn resume<'b>(text: &'b str, dialect: &Dialect, start: usize, line: usize) -> Lex<'b> {
+        let line_start = text[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        Lex { text, dialect: *dialect, pos: start, line, line_start, next_line: None }
+    }
+
+    // pos is a byte offset, so step over the whole UTF-8 sequence
     fn advance(&mut self) {
-        self.pos += 1;
+        if let Some(c) = self.peek() {
+            if c == '\n' {
+                self.line = self.next_line.take().unwrap_or(self.line + 1);
+                self.line_start = self.pos + 1;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.text[self.pos..].chars().nth(1)
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap().is_whitespace() {
+    fn span_from(&self, start: usize, line: usize, column: usize) -> Span {
+        Span { start, end: self.pos, line, column }
+    }
+
+    fn error_here(&self, message: String) -> LexError {
+        let column = self.text[self.line_start..self.pos].chars().count() + 1;
+        LexError { message, span: Span { start: self.pos, end: self.pos, line: self.line, column } }
+    }
+
+    // A directive runs to the end of the line
+    fn directive(&mut self) -> Token {
+        let mut tag = String::new();
+        while let Some(c) = self.peek().filter(|c| *c != '\n') {
+            tag.push(c);
             self.advance();
         }
-    
-        if self.pos >= self.text.len() {
-            return; 
+        self.next_line = line_marker(&tag).or(self.next_line);
+        Token::with_value(TokenKind::Tag, &tag)
+    }
+
+    // The trigraph starting here, if any, and the character it stands for
+    fn trigraph(&self) -> Option<(char, char)> {
+        let mut chars = self.text[self.pos..].chars().skip(1);
+        if chars.next() != Some('?') {
+            return None;
         }
+        let third = chars.next()?;
+        TRIGRAPHS.iter().find(|(trigraph, _)| *trigraph == third).copied()
     }
 
-    
+    // Stops after a line break, so that trivia can be split between lines
+    fn whitespace(&mut self) -> Token {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.advance();
+            if c == '\n' {
+                break;
+            }
+        }
+        Token::new(TokenKind::Whitespace)
+    }
 
+    // The value keeps only the digits; C23 digit separators (1'000'000) are
+    // dropped, but only when they sit between two digits. Before C23 the
+    // preprocessor reads a separator as the start of a character constant,
+    // so they are only accepted with --std=c23.
     fn number(&mut self) -> Token {
+        let separators = self.dialect.std >= Standard::C23;
         let mut result = String::new();
-        while self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap().is_digit(10) {
-            result.push(self.text.chars().nth(self.pos).unwrap());
-            self.advance();
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => {
+                    result.push(c);
+                    self.advance();
+                }
+                Some('\'') if separators && self.peek_second().is_some_and(|c| c.is_ascii_digit()) => self.advance(),
+                _ => break,
+            }
         }
-        Token { token_type: TokenType::CONSTANT, value: result }
+        Token::with_value(TokenKind::Constant, &result)
     }
 
+    fn identifier(&mut self) -> Token {
+        let mut result = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_alphanumeric() || *c == '_') {
+            result.push(c);
+            self.advance();
+        }
 
-fn identifier(&mut self) -> Token {
-    let mut result = String::new();
-
-    let first_char = self.text.chars().nth(self.pos).unwrap();
-    if !first_char.is_alphabetic() && first_char != '_' {
-        panic!("Invalid identifier at position {}: '{}'", self.pos, self.text);
+        match Keyword::from_identifier(&result, &self.dialect) {
+            Some(keyword) => Token::new(TokenKind::Keyword(keyword)),
+            None => Token::with_value(TokenKind::Identifier, &result),
+        }
     }
 
-    result.push(first_char);
-    self.advance();
-
-    while self.pos < self.text.len() {
-        let current_char = self.text.chars().nth(self.pos).unwrap();
-        if current_char.is_alphanumeric() || current_char == '_' {
-            result.push(current_char);
-            self.advance();
+    // Scans to the closing quote, so an unterminated literal is reported
+    // where it starts. The token keeps the source text, quotes included,
+    // once its escape sequences are known to be valid.
+    fn quoted_literal(&mut self, quote: char) -> Result<Token, LexError> {
+        let error = self.error_here(String::new());
+        let (what, kind) = if quote == '"' {
+            ("string literal", TokenKind::StringLiteral)
         } else {
-            break;
+            ("character constant", TokenKind::CharConstant)
+        };
+        self.advance();
+        loop {
+            match self.peek() {
+                None | Some('\n') => {
+                    return Err(LexError { message: format!("Unterminated {}", what), ..error });
+                }
+                Some('\\') => {
+                    self.advance();
+                    self.advance();
+                }
+                Some(c) if c == quote => {
+                    self.advance();
+                    let literal = &self.text[error.span.start..self.pos];
+                    let value = match kind {
+                        TokenKind::CharConstant => char_constant_value(literal).map(|_| ()),
+                        _ => string_literal_value(literal).map(|_| ()),
+                    };
+                    return match value {
+                        Ok(()) => Ok(Token::with_value(kind, literal)),
+                        Err((offset, message)) => {
+                            let mut span = error.span;
+                            span.start += offset;
+                            span.end = span.start;
+                            span.column += literal[..offset].chars().count();
+                            Err(LexError { message, span })
+                        }
+                    };
+                }
+                Some(_) => self.advance(),
+            }
         }
     }
 
-    let keywords: HashSet<&str> = ["if", "else", "while", "for", "return", "int"].iter().cloned().collect();
-
-    if keywords.contains(result.as_str()) {
-        Token { 
-            token_type: TokenType::KEYWORD, 
-            value: result 
-        }
-    } else {
-        Token { 
-            token_type: TokenType::IDENTIFIER, 
-            value: result 
+    fn block_comment(&mut self) -> Result<Token, LexError> {
+        // The error points at the opening '/*', which has already been consumed
+        let mut error = self.error_here("Unterminated block comment".to_string());
+        error.span.start -= 2;
+        error.span.column -= 2;
+        let mut long_comment = "/*".to_string();
+        while let Some(c) = self.peek() {
+            long_comment.push(c);
+            self.advance();
+            if c == '*' && self.peek() == Some('/') {
+                long_comment.push('/');
+                self.advance();
+                return Ok(Token::with_value(TokenKind::LongComment, &long_comment));
+            }
         }
+        Err(error)
     }
-}
-
 
-    fn next(&mut self) -> Result<Option<Token>, String> {
-        self.skip_whitespace();
-    
-        if self.pos >= self.text.len() {
-            return Ok(None); 
-        } 
-        match self.text.chars().nth(self.pos).unwrap() {
+    fn next(&mut self) -> Result<Option<Token>, LexError> {
+        let Some(current) = self.peek() else {
+            return Ok(None);
+        };
+        let start = self.pos;
+        let line = self.line;
+        let column = self.text[self.line_start..self.pos].chars().count() + 1;
+        let rest = &self.text[self.pos..];
+        if let Some((spelling, advice)) = UNSUPPORTED_PUNCTUATORS.iter().find(|(spelling, _)| rest.starts_with(spelling)) {
+            return Err(self.error_here(format!("'{}' is not supported yet; {}", spelling, advice)));
+        }
+        let mut token = match current {
+            // A no-break space is whitespace to Rust, but not to C
+            c if c.is_whitespace() && c != '\u{00a0}' => self.whitespace(),
             '0'..='9' => {
                 let num_token = self.number();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap().is_alphabetic() {
-                    return Err(format!("Invalid constant followed by identifier at position {}: '{}'", self.pos, self.text));
+                if self.peek() == Some('\'') && self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+                    return Err(self.error_here("Digit separators need --std=c23".to_string()));
+                }
+                if self.peek() == Some('.') {
+                    return Err(self.error_here("Floating-point constants are not supported".to_string()));
+                }
+                // A leading 0 makes the constant octal
+                let digits = &self.text[start..self.pos];
+                if let Some(offset) = digits.find(['8', '9']).filter(|_| digits.starts_with('0')) {
+                    let column = self.text[self.line_start..start].chars().count() + 1 + offset;
+                    return Err(LexError {
+                        message: format!("Invalid digit '{}' in octal constant", &digits[offset..offset + 1]),
+                        span: Span { start: start + offset, end: start + offset, line: self.line, column },
+                    });
+                }
+                if digits == "0" && matches!(self.peek(), Some('x' | 'X')) {
+                    return Err(self.error_here("Hexadecimal constants are not supported yet".to_string()));
+                }
+                if self.peek().is_some_and(|c| c.is_alphabetic() || c == '_') {
+                    return Err(self.error_here(format!("Invalid suffix '{}' on integer constant", self.peek().unwrap())));
                 }
-                Ok(Some(num_token))
+                num_token
             },
-            'a'..='z' | 'A'..='Z' => Ok(Some(self.identifier())),
-            '(' => { self.advance(); Ok(Some(Token { token_type: TokenType::OpenParen, value: "(".to_string() })) },
-            ')' => { self.advance(); Ok(Some(Token { token_type: TokenType::CloseParen, value: ")".to_string() })) },
-            '{' => { self.advance(); Ok(Some(Token { token_type: TokenType::OpenBrace, value: "{".to_string() })) },
-            '}' => { self.advance(); Ok(Some(Token { token_type: TokenType::CloseBrace, value: "}".to_string() })) },
-            ';' => { self.advance(); Ok(Some(Token { token_type: TokenType::SEMICOLON, value: ";".to_string() })) },
-            '/' => { 
+            'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
+            '(' => { self.advance(); Token::new(TokenKind::OpenParen) },
+            ')' => { self.advance(); Token::new(TokenKind::CloseParen) },
+            '{' => { self.advance(); Token::new(TokenKind::OpenBrace) },
+            '}' => { self.advance(); Token::new(TokenKind::CloseBrace) },
+            '[' => { self.advance(); Token::new(TokenKind::OpenBracket) },
+            ']' => { self.advance(); Token::new(TokenKind::CloseBracket) },
+            ';' => { self.advance(); Token::new(TokenKind::Semicolon) },
+            ',' => { self.advance(); Token::new(TokenKind::Comma) },
+            '?' => {
+                if let Some((trigraph, replacement)) = self.trigraph() {
+                    return Err(self.error_here(format!("Trigraph '??{}' is not supported; write '{}' instead", trigraph, replacement)));
+                }
+                self.advance();
+                Token::new(TokenKind::Question)
+            },
+            // The digraph :> is ]
+            ':' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '/' {
+                if self.peek() == Some('>') {
                     self.advance();
-                    while self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() != '\n' {
+                    Token::new(TokenKind::CloseBracket)
+                } else {
+                    Token::new(TokenKind::Colon)
+                }
+            },
+            '/' => {
+                self.advance();
+                if self.peek() == Some('/') {
+                    self.advance();
+                    while self.peek().is_some_and(|c| c != '\n') {
                         self.advance();
                     }
-                    Ok(Some(Token { token_type: TokenType::COMMENT, value: "//".to_string() }))
-                } else if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '*' {
+                    Token::new(TokenKind::Comment)
+                } else if self.peek() == Some('*') {
                     self.advance();
-                    let mut long_comment = "/*".to_string();
-                    while self.pos < self.text.len() {
-                        long_comment.push(self.text.chars().nth(self.pos).unwrap());
-                        if self.text.chars().nth(self.pos).unwrap() == '*' {
-                            self.advance();
-                            if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '/' {
-                                long_comment.push('/');
-                                self.advance();
-                                break;
-                            }
-                        } else {
-                            self.advance();
-                        }
-                    }
-                    Ok(Some(Token { token_type: TokenType::LongComment, value: long_comment }))
+                    self.block_comment()?
                 } else {
-                    Ok(Some(Token { token_type: TokenType::SLASH, value: "/".to_string() }))
+                    Token::new(TokenKind::Slash)
                 }
             },
-            '*' => { self.advance(); Ok(Some(Token { token_type: TokenType::STAR, value: "*".to_string() })) },
-            '~' => { self.advance(); Ok(Some(Token { token_type: TokenType::TildeOp, value: "~".to_string() })) },
+            '*' => { self.advance(); Token::new(TokenKind::Star) },
+            '~' => { self.advance(); Token::new(TokenKind::TildeOp) },
             '-' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '-' {
+                if self.peek() == Some('-') {
+                    self.advance();
+                    Token::new(TokenKind::DecrementOp)
+                } else if self.peek() == Some('>') {
+                    self.advance();
+                    Token::new(TokenKind::Arrow)
+                } else {
+                    Token::new(TokenKind::NegationOp)
+                }
+            },
+            '.' => {
+                // A '.' that starts a number is a floating constant such as .5, not member access
+                if self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+                    return Err(self.error_here("Floating-point constants are not supported".to_string()));
+                }
+                self.advance();
+                Token::new(TokenKind::Dot)
+            },
+            // The digraphs %> and %: are } and #
+            '%' if self.peek_second() == Some(':') => self.directive(),
+            '%' => {
+                self.advance();
+                if self.peek() == Some('>') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::DecrementOp, value: "--".to_string() }))
+                    Token::new(TokenKind::CloseBrace)
                 } else {
-                    Ok(Some(Token { token_type: TokenType::NegationOp, value: "-".to_string() }))
+                    Token::new(TokenKind::Modulus)
                 }
             },
-            '%' => { self.advance(); Ok(Some(Token { token_type: TokenType::MODULUS, value: "%".to_string() })) },
-            '+' => { self.advance(); Ok(Some(Token { token_type: TokenType::PLUS, value: "+".to_string() })) },
+            '+' => { self.advance(); Token::new(TokenKind::Plus) },
             '&' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '&' {
+                if self.peek() == Some('&') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::LogicalAnd, value: "&&".to_string() }))
+                    Token::new(TokenKind::LogicalAnd)
                 } else {
-                    Ok(Some(Token { token_type: TokenType::AMPERSAND, value: "&".to_string() }))
+                    Token::new(TokenKind::Ampersand)
                 }
             },
-            '|' => { 
+            '|' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '|' {
+                if self.peek() == Some('|') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::LogicalOr, value: "||".to_string() }))
+                    Token::new(TokenKind::LogicalOr)
                 } else {
-                    Ok(Some(Token { token_type: TokenType::PIPE, value: "|".to_string() }))
+                    Token::new(TokenKind::Pipe)
                 }
             },
-            '^' => { self.advance(); Ok(Some(Token { token_type: TokenType::CARET, value: "^".to_string() })) },
+            '^' => { self.advance(); Token::new(TokenKind::Caret) },
+            // The digraphs <: and <% are [ and {
             '<' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '<' {
+                if self.peek() == Some(':') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::LeftShift, value: "<<".to_string() }))
-                } else if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '=' {
+                    Token::new(TokenKind::OpenBracket)
+                } else if self.peek() == Some('%') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::LessThanOrEqual, value: "<=".to_string() }))
-                }
-                else {
-                    Ok(Some(Token { token_type: TokenType::LessThan, value: "<".to_string() }))
+                    Token::new(TokenKind::OpenBrace)
+                } else if self.peek() == Some('<') {
+                    self.advance();
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        Token::new(TokenKind::LeftShiftAssign)
+                    } else {
+                        Token::new(TokenKind::LeftShift)
+                    }
+                } else if self.peek() == Some('=') {
+                    self.advance();
+                    Token::new(TokenKind::LessThanOrEqual)
+                } else {
+                    Token::new(TokenKind::LessThan)
                 }
             },
             '>' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '>' {
+                if self.peek() == Some('>') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::RightShift, value: ">>".to_string() }))
-                }else if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '=' {
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        Token::new(TokenKind::RightShiftAssign)
+                    } else {
+                        Token::new(TokenKind::RightShift)
+                    }
+                } else if self.peek() == Some('=') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::GreaterThanOrEqual, value: ">=".to_string() }))}
-                 else {
-                    Ok(Some(Token { token_type: TokenType::GreaterThan, value: ">".to_string() }))
+                    Token::new(TokenKind::GreaterThanOrEqual)
+                } else {
+                    Token::new(TokenKind::GreaterThan)
                 }
             },
-            '!' => { 
+            '!' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '=' {
+                if self.peek() == Some('=') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::NotEqual, value: "!=".to_string() }))
+                    Token::new(TokenKind::NotEqual)
                 } else {
-                    Ok(Some(Token { token_type: TokenType::LogicalNot, value: "!".to_string() }))
+                    Token::new(TokenKind::LogicalNot)
                 }
-             },
+            },
             '=' => {
                 self.advance();
-                if self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() == '=' {
+                if self.peek() == Some('=') {
                     self.advance();
-                    Ok(Some(Token { token_type: TokenType::Equal, value: "==".to_string() }))
+                    Token::new(TokenKind::Equal)
                 } else {
-                    Ok(Some(Token { token_type: TokenType::Assignment, value: "=".to_string() }))
+                    Token::new(TokenKind::Assignment)
                 }
             },
-            '#' => {
-                self.advance();
-                let mut tag = "#".to_string();
-                while self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap() != '\n' {
-                    tag.push(self.text.chars().nth(self.pos).unwrap());
-                    self.advance();
-                }
-                Ok(Some(Token { token_type: TokenType::Tag, value: tag }))
+            '#' => self.directive(),
+            '"' | '\'' => self.quoted_literal(current)?,
+            _ => {
+                let message = match invalid_character_hint(current) {
+                    Some(hint) => format!("Invalid character '{}'; {}", current, hint),
+                    None => format!("Invalid character '{}'", current),
+                };
+                return Err(self.error_here(message));
             },
-            _ => Err(format!("Invalid character '{}' found at position {} in text '{}'", 
-                            self.text.chars().nth(self.pos).unwrap(), self.pos, self.text)),
+        };
+        token.span = self.span_from(start, line, column);
+        Ok(Some(token))
+    }
+
+    // The next token the parser needs, skipping trivia
+    pub fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        while let Some(token) = self.next()? {
+            if !token.kind.is_trivia() {
+                return Ok(Some(token));
+            }
+        }
+        Ok(None)
+    }
+
+    // The tokens the parser needs, with all trivia dropped
+    pub fn get_tokens(&mut self) -> Result<Vec<Token>, LexError> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
         }
-    }    
-
-    pub fn get_tokens(&mut self) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    while self.pos < self.text.len() {
-        match self.next() {
-            Ok(Some(token)) => tokens.push(token),
-            Ok(None) => break,
-             Err(err) => {
-                eprintln!("Lexing error: {}", err);
-                process::exit(1); 
+        Ok(tokens)
+    }
+
+    pub fn get_tokens_with_trivia(&mut self) -> Result<TokenStream, LexError> {
+        let mut stream = TokenStream::default();
+        // Trivia waiting for the next token
+        let mut pending = Vec::new();
+        while let Some(token) = self.next()? {
+            if !token.kind.is_trivia() {
+                stream.tokens.push(TriviaToken { leading: std::mem::take(&mut pending), token, trailing: Vec::new() });
+                continue;
+            }
+            match stream.tokens.last_mut() {
+                Some(last) if pending.is_empty() && !self.ends_line(&last.trailing) => last.trailing.push(token),
+                _ => pending.push(token),
             }
         }
+        stream.end = pending;
+        Ok(stream)
     }
-    tokens
-}
 
-    
+    fn ends_line(&self, trivia: &[Token]) -> bool {
+        trivia.last().is_some_and(|token| self.text[token.span.start..token.span.end].ends_with('\n'))
+    }
 }
-
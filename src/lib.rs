@@ -1,8 +1,30 @@
+pub mod intern;
+pub mod dialect;
 pub mod lex;
 pub mod parser;
 pub mod assembly;
 pub mod tac;
+pub mod optimize;
+pub mod checks;
+pub mod verify;
+pub mod const_eval;
+pub mod dead_code;
+pub mod target;
+pub mod scope;
+pub mod symbols;
+pub mod typecheck;
+pub mod stack_slots;
+pub mod timing;
+pub mod driver;
+pub mod explain;
+pub mod format;
+pub mod incremental;
+pub mod ir_json;
+pub mod playground;
+pub mod interpret;
+pub mod repl;
 
+pub use crate::intern::Symbol;
 pub use crate::lex::Lex;
 pub use crate::parser::parse_and_resolve_program;
 
@@ -1,113 +1,950 @@
+mod preprocess;
+mod temp;
+
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process;
-use c_compiler_lib::lex;
-use c_compiler_lib::parser;
-use c_compiler_lib::assembly;
-use c_compiler_lib::tac;
+use c_compiler_lib::assembly::Program;
+use c_compiler_lib::checks::{self, RuntimeChecks};
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{self, json_string, CompileError, Compiler, Diagnostic, Stage};
+use c_compiler_lib::explain;
+use c_compiler_lib::repl::Session;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::timing::PassTimer;
+use preprocess::PreprocessOptions;
+use temp::TempDir;
 
 
-fn main() {
-    // Get command line arguments
-    let args: Vec<String> = env::args().collect();
+// Writes "file:line:column: error[code]: message" to stderr
+fn print_diagnostic(input_file: &Path, diagnostic: &Diagnostic) {
+    match (diagnostic.line, diagnostic.column) {
+        (Some(line), Some(column)) => eprintln!("{}:{}:{}: {}: {}", input_file.display(), line, column, diagnostic.error_label(), diagnostic.message),
+        _ => eprintln!("{}: {}: {}", input_file.display(), diagnostic.error_label(), diagnostic.message),
+    }
+}
 
-    // Check if a file path is provided
-    // if args.len() > 10 {
-    //     // eprintln!("Usage: {} <input_file>", args[0]);
-    //     eprintln!("ARGUMENTS: {:?}", args);
-    //     process::exit(1);
-    // }
+// Writes "file:line:column: warning: message" to stderr
+fn print_warning(input_file: &Path, diagnostic: &Diagnostic) {
+    match (diagnostic.line, diagnostic.column) {
+        (Some(line), Some(column)) => eprintln!("{}:{}:{}: warning: {}", input_file.display(), line, column, diagnostic.message),
+        _ => eprintln!("{}: warning: {}", input_file.display(), diagnostic.message),
+    }
+}
+
+// Points at --explain for errors that have a code
+fn print_explain_hint(err: &CompileError) {
+    if let Some(code) = Diagnostic::from(err).code {
+        eprintln!("For more information about this error, try '--explain {}'.", code);
+    }
+}
 
+// --check: runs every stage up to semantic analysis and reports what it
+// found, as "file:line:column: error[code]: message" and "warning: " lines on
+// stderr or, with -fdiagnostics-format=json, as one JSON object on stdout
+fn run_check(input_file: &Path, source: &str, dialect: &Dialect, json: bool) {
+    let (diagnostics, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = match driver::compile_until(source, dialect, Stage::Validate, &mut PassTimer::new(false)) {
+        Ok(_) => (Vec::new(), driver::warnings(source, dialect).expect("the program was checked once already")),
+        Err(err) => (vec![Diagnostic::from(&err)], Vec::new()),
+    };
+    if json {
+        let entries: Vec<String> = diagnostics.iter().map(Diagnostic::to_json).collect();
+        let warnings: Vec<String> = warnings.iter().map(Diagnostic::to_json).collect();
+        println!("{{\"file\":{},\"diagnostics\":[{}],\"warnings\":[{}]}}",
+            json_string(&input_file.to_string_lossy()), entries.join(","), warnings.join(","));
+    } else {
+        for diagnostic in &diagnostics {
+            print_diagnostic(input_file, diagnostic);
+        }
+        for warning in &warnings {
+            print_warning(input_file, warning);
+        }
+    }
+    if !diagnostics.is_empty() {
+        process::exit(1);
+    }
+}
 
-    // Get the input file path
-    let input_file = Path::new(&args[1]);
-    println!("Input file: {}", input_file.display());
+// --emit-tokens: prints the tokens as JSON Lines on stdout
+fn run_emit_tokens(input_file: &Path, source: &str, dialect: &Dialect) {
+    match driver::tokens_to_json(source, dialect) {
+        Ok(json) => print!("{}", json),
+        Err(err) => {
+            print_diagnostic(input_file, &Diagnostic::from(&CompileError::Lex(err)));
+            process::exit(1);
+        }
+    }
+}
 
-    // Read the input file
-    let input = match fs::read_to_string(input_file) {
-        Ok(content) => content,
+// --fmt: prints the file parsed and written back in a consistent style
+fn run_fmt(input_file: &Path, source: &str, dialect: &Dialect) {
+    match driver::format(source, dialect) {
+        Ok(formatted) => print!("{}", formatted),
         Err(err) => {
-            eprintln!("Error reading file '{}': {}", input_file.display(), err);
+            print_diagnostic(input_file, &Diagnostic::from(&err));
             process::exit(1);
         }
+    }
+}
+
+// --emit-symbols: lists the file-scope symbols on stdout, as JSON Lines for
+// --emit-symbols=json
+fn run_emit_symbols(input_file: &Path, source: &str, dialect: &Dialect, json: bool) {
+    match driver::symbols(source, dialect) {
+        Ok(symbols) if json => print!("{}", driver::symbols_to_json(&symbols)),
+        Ok(symbols) => print!("{}", driver::symbols_to_text(&symbols)),
+        Err(err) => {
+            print_diagnostic(input_file, &Diagnostic::from(&err));
+            process::exit(1);
+        }
+    }
+}
+
+// --ast-json and --tac-json: prints the AST or the TAC as one JSON object
+// on stdout, in the layout the schema of its version describes
+fn run_ir_json(input_file: &Path, source: &str, dialect: &Dialect, stage: Stage) {
+    let json = match stage {
+        Stage::Parse => driver::ast_json(source, dialect),
+        _ => driver::tac_json(source, dialect),
     };
+    match json {
+        Ok(json) => print!("{}", json),
+        Err(err) => {
+            print_diagnostic(input_file, &Diagnostic::from(&err));
+            process::exit(1);
+        }
+    }
+}
+
+// --save-temps: keeps an intermediate file next to the output
+fn save_temp(path: &Path, contents: &str) {
+    if let Err(err) = fs::write(path, contents) {
+        eprintln!("Error writing '{}': {}", path.display(), err);
+        process::exit(1);
+    }
+}
 
-    
-
-    // Create a lexer instance and get tokens
-    let mut lexer = lex::Lex::new(&input);
-    let mut tokens = lexer.get_tokens();
-
-    //Remove comments from tokens
-    tokens.retain(|token| token.token_type != lex::TokenType::COMMENT
-         && token.token_type != lex::TokenType::LongComment
-         && token.token_type != lex::TokenType::Tag
-    );
-    // eprint!("Tokens: {:?}", tokens);
-
-    // Parse the program
-    match parser::parse_and_resolve_program(&mut tokens) {
-        Ok(program) => {
-        println!("Parsing successful");
-            let tac = tac::generate_tac(program);
-            let mut assembly = assembly::generate_assembly_ast(tac);
-            println!("{:?}", assembly);
-            assembly.apply_fixes();
-            println!("{:?}", assembly);
-            let assembly_code = assembly.to_assembly_file();
-            println!("{}", assembly_code);
-            
-            // Generate output file name (same as input but without extension)
-            let output_file = input_file.with_extension("");
-            
-            // Write assembly to a temporary file
-            let asm_file = output_file.with_extension("s");
-            if let Err(e) = fs::write(&asm_file, assembly_code) {
-                eprintln!("Error writing assembly file: {}", e);
+// Reads lines from stdin until end of input, printing each expression's value
+fn run_repl() {
+    let mut session = Session::new();
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("Failed to write to stdout");
+        line.clear();
+        match stdin.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Error reading input: {}", err);
                 process::exit(1);
             }
+        }
+        match session.eval(&line) {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => {}
+            Err(err) => eprintln!("error: {}", err),
+        }
+    }
+    println!();
+}
+
+fn read_input(input_file: &Path) -> Option<String> {
+    match fs::read_to_string(input_file) {
+        Ok(content) => Some(content),
+        Err(err) => {
+            eprintln!("Error reading file '{}': {}", input_file.display(), err);
+            None
+        }
+    }
+}
 
-            // Use GCC to assemble and link
-            let status = process::Command::new("clang")
-                .arg("-o")
-                .arg(&output_file)
-                .arg(&asm_file)
-                .status()
-                .expect("Failed to execute GCC");
+// The preprocessed text of input_file, or None once the error is reported.
+// A rule written by -MD is for product, the file this run produces.
+fn preprocess_file(input_file: &Path, compiler: &Compiler, preprocess_options: &PreprocessOptions, product: &Path, timer: &mut PassTimer) -> Option<String> {
+    let mut preprocess_options = preprocess_options.clone();
+    if preprocess_options.write_dependencies && preprocess_options.dependency_target.is_none() {
+        preprocess_options.dependency_target = Some(product.display().to_string());
+    }
+    let preprocessed = timer.time("preprocess", || preprocess::preprocess(input_file, None, &compiler.dialect, &preprocess_options, compiler.options.target));
+    // Pass the preprocessor's warnings and errors on as they are
+    eprint!("{}", preprocessed.messages);
+    match preprocessed.output {
+        Ok(preprocessed) => Some(preprocessed),
+        Err(err) => {
+            eprintln!("{}", err);
+            None
+        }
+    }
+}
 
-            if !status.success() {
-                eprintln!("GCC failed to assemble and link");
-                process::exit(1);
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+// How the run command runs the program it built
+#[derive(Default)]
+struct ProgramRun {
+    args: Vec<String>,
+    // --tee-stdout and --tee-stderr: files that get a copy of what the
+    // program writes, for test harnesses, while it still goes to the terminal
+    stdout_file: Option<PathBuf>,
+    stderr_file: Option<PathBuf>,
+}
+
+impl ProgramRun {
+    // Takes the run command's own flags
+    fn parse_flag(&mut self, arg: &str) -> bool {
+        if let Some(file) = arg.strip_prefix("--tee-stdout=") {
+            self.stdout_file = Some(PathBuf::from(file));
+        } else if let Some(file) = arg.strip_prefix("--tee-stderr=") {
+            self.stderr_file = Some(PathBuf::from(file));
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // Runs a program that was just linked
+    fn run(&self, path: &Path) -> process::ExitStatus {
+        let create = |file: &Option<PathBuf>| file.as_ref().map(|file| {
+            fs::File::create(file).unwrap_or_else(|err| exit_with_error(&format!("Can't write {}: {}", file.display(), err)))
+        });
+        let (stdout_file, stderr_file) = (create(&self.stdout_file), create(&self.stderr_file));
+        // A bare file name would be looked up on PATH, so run it relative to the current directory
+        let path = if path.is_relative() { Path::new(".").join(path) } else { path.to_path_buf() };
+        let mut command = process::Command::new(path);
+        command.args(&self.args);
+        if stdout_file.is_some() {
+            command.stdout(process::Stdio::piped());
+        }
+        if stderr_file.is_some() {
+            command.stderr(process::Stdio::piped());
+        }
+        let mut child = command.spawn().expect("Failed to execute the compiled program");
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        // Both streams are copied at once, so the program can't block
+        // writing to one while the other is read
+        std::thread::scope(|scope| {
+            if let (Some(stdout), Some(file)) = (stdout, stdout_file) {
+                scope.spawn(|| tee(stdout, io::stdout(), file));
+            }
+            if let (Some(stderr), Some(file)) = (stderr, stderr_file) {
+                scope.spawn(|| tee(stderr, io::stderr(), file));
             }
+        });
+        child.wait().expect("Failed to wait for the compiled program")
+    }
+}
 
-            // Remove the temporary assembly file
-            fs::remove_file(asm_file).expect("Failed to remove temporary assembly file");
+// Copies from to both to and file as it arrives
+fn tee(mut from: impl io::Read, mut to: impl Write, mut file: fs::File) {
+    let mut buffer = [0; 8192];
+    loop {
+        match from.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(length) => {
+                // Keep reading when one side fails, so the program can still finish
+                let _ = to.write_all(&buffer[..length]).and_then(|_| to.flush());
+                let _ = file.write_all(&buffer[..length]);
+            }
+        }
+    }
+}
 
-            println!("Compilation successful. Output: {}", output_file.display());
+// The driver's exit code for a program that exited with status: its own
+// code, or 128 and the signal that killed it, as a shell reports it
+fn exit_code(status: process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
+
+// The file a build ends with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Executable,
+    // -c: assembled but not linked
+    Object,
+    // -S: not even assembled
+    Assembly,
+}
 
-            // Now execute the compiled binary and capture its exit status
-            let run_status = process::Command::new(output_file.to_str().unwrap())
-                .status()
-                .expect("Failed to execute the compiled program");
+// What happens to a file once it has gone through the compiler
+struct Build {
+    compiler: Compiler,
+    // -T: a linker script laying out the executable, passed to the linker
+    linker_script: Option<PathBuf>,
+    // --raw-binary: also copy the linked program's loaded sections into a
+    // flat .bin image, as objcopy -O binary does, for loading at the address
+    // the linker script gives
+    raw_binary: bool,
+    stop_after: Option<Stage>,
+    produce: Output,
+    save_temps: bool,
+    // -o: where the output is written
+    output: Option<PathBuf>,
+    // Whether outputs are named as cc names them without -o: an executable
+    // a.out, and anything else after the input in the current directory.
+    // Otherwise each is named after its input and written next to it.
+    cc_names: bool,
+    // Whether to run the program once it is linked and say how it exited
+    run: bool,
+    // Whether to report each step on stdout, as invoking the compiler
+    // without a command does. The commands only report what went wrong.
+    verbose: bool,
+}
+
+impl Build {
+    // The file compiling input_file produces
+    fn product(&self, input_file: &Path) -> PathBuf {
+        let target = self.compiler.options.target;
+        if let Some(output) = &self.output {
+            return output.clone();
+        }
+        let extension = match self.produce {
+            Output::Executable if self.cc_names => return PathBuf::from(if target == Target::Windows { "a.exe" } else { "a.out" }),
+            Output::Executable => target.executable_extension(),
+            Output::Object => "o",
+            Output::Assembly => "s",
+        };
+        match input_file.file_stem() {
+            Some(stem) if self.cc_names => Path::new(stem).with_extension(extension),
+            _ => input_file.with_extension(extension),
+        }
+    }
 
-            // Print the exit status of the compiled program
-            if run_status.success() {
-                println!("Program executed successfully with exit status: 0");
-            } else if let Some(code) = run_status.code() {
-                println!("Program exited with status code: {}", code);
-            } else {
-                println!("Program terminated by signal");
+    // Reports how compiling input_file went and, for a program that
+    // compiled, writes, assembles, links and runs it. Returns whether every
+    // step succeeded.
+    fn finish(&self, input_file: &Path, input: &str, result: Result<Option<Program>, CompileError>, timer: &mut PassTimer) -> bool {
+        let (options, dialect, save_temps) = (&self.compiler.options, &self.compiler.dialect, self.save_temps);
+        match result {
+            Ok(None) => true,
+            Ok(Some(_)) if self.stop_after.is_some() => true,
+            Ok(Some(assembly)) => {
+                if self.verbose {
+                    println!("{:?}", assembly);
+                }
+                let warnings = driver::warnings(input, dialect).expect("the program compiled once already");
+                for diagnostic in warnings.iter().chain(&assembly.diagnostics(options)) {
+                    print_warning(input_file, diagnostic);
+                }
+
+                let output_file = self.product(input_file);
+                // Removed with whatever is in it when this returns
+                let temp_dir = match TempDir::new() {
+                    Ok(temp_dir) => temp_dir,
+                    Err(e) => {
+                        eprintln!("Error creating a temporary directory: {}", e);
+                        return false;
+                    }
+                };
+
+                // Stream the assembly into a file of its own, which is the
+                // output with -S and is kept next to the input with
+                // --save-temps
+                let emit = |path: &Path| fs::File::create(path)
+                    .and_then(|file| assembly.write_assembly(io::BufWriter::new(file), options))
+                    .and_then(|mut out| out.flush());
+                let asm_file = if save_temps { input_file.with_extension("s") } else { temp_dir.file("output.s") };
+                let written = timer.time("emit", || match self.produce == Output::Assembly {
+                    true => temp::write_atomically(&output_file, emit),
+                    false => emit(&asm_file),
+                });
+                if let Err(e) = written {
+                    eprintln!("Error writing assembly file: {}", e);
+                    return false;
+                }
+                if save_temps {
+                    let mut tac = driver::compile_to_tac(input, dialect).expect("the program compiled once already");
+                    checks::insert_checks(&mut tac.function, self.compiler.checks);
+                    save_temp(&input_file.with_extension("tac"), &tac.to_string());
+                }
+                if self.produce == Output::Assembly {
+                    return true;
+                }
+
+                // Assemble, and link unless -c, with the target's compiler
+                // driver. The output is only replaced once it is complete.
+                let linked = timer.time("assemble", || temp::write_atomically(&output_file, |partial| {
+                    let status = process::Command::new(options.target.assembler())
+                        .args(options.link_args())
+                        .args(self.linker_script.iter().flat_map(|script| [Path::new("-T"), script]))
+                        .args((self.produce == Output::Object).then_some("-c"))
+                        .arg("-o")
+                        .arg(partial)
+                        .arg(&asm_file)
+                        // Where GCC puts the object file it makes on the way
+                        .env("TMPDIR", temp_dir.path())
+                        .status()
+                        .map_err(|e| io::Error::new(e.kind(), format!("Failed to execute GCC: {}", e)))?;
+                    match status.success() {
+                        true => Ok(()),
+                        false => Err(io::Error::other("GCC failed to assemble and link")),
+                    }
+                }));
+                if let Err(e) = linked {
+                    eprintln!("{}", e);
+                    return false;
+                }
+
+                if self.verbose {
+                    println!("Compilation successful. Output: {}", output_file.display());
+                }
+                if self.raw_binary && self.produce == Output::Executable {
+                    let binary_file = input_file.with_extension("bin");
+                    let copied = timer.time("objcopy", || temp::write_atomically(&binary_file, |partial| {
+                        let status = process::Command::new("objcopy").args(["-O", "binary"]).arg(&output_file).arg(partial).status()?;
+                        match status.success() {
+                            true => Ok(()),
+                            false => Err(io::Error::other("objcopy failed")),
+                        }
+                    }));
+                    if copied.is_err() {
+                        eprintln!("objcopy failed to write the raw binary");
+                        return false;
+                    }
+                    if self.verbose {
+                        println!("Raw binary: {}", binary_file.display());
+                    }
+                }
+                if timer.enabled() {
+                    eprint!("{}", timer.report());
+                }
+
+                // A cross-compiled or bare-metal binary can't be run here
+                if !self.run || self.produce != Output::Executable || options.target != Target::host() || options.freestanding {
+                    return true;
+                }
+
+                // Now execute the compiled binary and capture its exit status
+                let run_status = ProgramRun::default().run(&output_file);
+
+                // Print the exit status of the compiled program
+                if run_status.success() {
+                    println!("Program executed successfully with exit status: 0");
+                } else if let Some(code) = run_status.code() {
+                    println!("Program exited with status code: {}", code);
+                } else {
+                    println!("Program terminated by signal");
+                }
+                true
+            }
+            Err(err) if !self.verbose => {
+                print_diagnostic(input_file, &Diagnostic::from(&err));
+                print_explain_hint(&err);
+                false
             }
+            Err(err @ CompileError::Lex(_)) => {
+                eprintln!("Lexing error: {}:{}", input_file.display(), err);
+                print_explain_hint(&err);
+                false
+            }
+            Err(err @ CompileError::Parse(_)) => {
+                // Parsing failed, print error and exit with non-zero code
+                eprintln!("Text input: {}", input);
+                eprintln!("Parsing error: {}", err);
+                print_explain_hint(&err);
+                false
+            }
+        }
+    }
+
+    // Preprocesses, compiles and finishes each of input_files, and returns
+    // whether all of them succeeded
+    fn build_all(&self, input_files: &[&Path], preprocess_options: &PreprocessOptions, time_passes: bool, verify_ir: bool) -> bool {
+        // Reading and preprocessing happen one file at a time, so what the
+        // preprocessor says about a file comes after the line naming it
+        let mut failed = false;
+        let (mut paths, mut sources, mut timers) = (Vec::new(), Vec::new(), Vec::new());
+        for &input_file in input_files {
+            if self.verbose {
+                println!("Input file: {}", input_file.display());
+            }
+            let mut timer = PassTimer::new(time_passes);
+            // The preprocessor reads the file itself, but can't say why it couldn't
+            if read_input(input_file).is_none() {
+                failed = true;
+                continue;
+            }
+            let Some(input) = preprocess_file(input_file, &self.compiler, preprocess_options, &self.product(input_file), &mut timer) else {
+                failed = true;
+                continue;
+            };
+            if self.save_temps {
+                save_temp(&input_file.with_extension("i"), &input);
+            }
+            // A program the front end rejects is reported by the compilation below
+            if verify_ir {
+                if let Ok(Some(err)) = driver::verify_ir(&input, &self.compiler.dialect) {
+                    eprintln!("{}: internal compiler error: {}", input_file.display(), err);
+                    failed = true;
+                    continue;
+                }
+            }
+            paths.push(input_file);
+            sources.push(input);
+            timers.push(timer);
+        }
+
+        // The files compile in parallel, and what each produced is then written
+        // out, assembled and reported in the order they were given
+        let results = self.compiler.compile_all(&sources, self.stop_after.unwrap_or(Stage::Codegen), &mut timers)
+            .expect("each source has a timer");
+        for (((input_file, input), result), timer) in paths.into_iter().zip(&sources).zip(results).zip(&mut timers) {
+            if !self.finish(input_file, input, result, timer) {
+                failed = true;
+            }
+        }
+        !failed
+    }
+}
+
+// The flags every way of invoking the compiler takes: the target's, code
+// generation's, the dialect's and the preprocessor's
+struct Settings {
+    target: Target,
+    pic: Option<bool>,
+    function_sections: bool,
+    asm_comments: bool,
+    omit_frame_pointer: bool,
+    trapv: bool,
+    nostdlib: bool,
+    freestanding: bool,
+    merge_strings: bool,
+    linker_script: Option<PathBuf>,
+    raw_binary: bool,
+    checks: RuntimeChecks,
+    opt_level: u8,
+    time_passes: bool,
+    save_temps: bool,
+    verify_ir: bool,
+    dialect: Dialect,
+    preprocess_options: PreprocessOptions,
+}
+
+impl Settings {
+    fn new() -> Settings {
+        Settings {
+            target: Target::host(),
+            pic: None,
+            function_sections: false,
+            asm_comments: false,
+            omit_frame_pointer: false,
+            trapv: false,
+            nostdlib: false,
+            freestanding: false,
+            merge_strings: false,
+            linker_script: None,
+            raw_binary: false,
+            checks: RuntimeChecks::default(),
+            opt_level: 0,
+            time_passes: false,
+            save_temps: false,
+            verify_ir: false,
+            dialect: Dialect::default(),
+            preprocess_options: PreprocessOptions::new(),
+        }
+    }
+
+    // Takes arg, with the value after it from rest if it needs one, when it
+    // is one of these flags
+    fn parse_flag(&mut self, arg: &str, rest: &mut std::slice::Iter<String>) -> Result<bool, String> {
+        let triple = match arg {
+            "--target" => Some(rest.next().map_or("", String::as_str)),
+            _ => arg.strip_prefix("--target="),
+        };
+        if let Some(triple) = triple {
+            self.target = Target::from_triple(triple).ok_or_else(|| format!("Unsupported target '{}'", triple))?;
+            return Ok(true);
+        }
+        if self.dialect.parse_flag(arg)? || self.preprocess_options.parse_flag(arg, || rest.next().cloned())? {
+            return Ok(true);
+        }
+        // -O alone means -O1; every level above 1 does what -O1 does
+        if let Some(level) = arg.strip_prefix("-O") {
+            self.opt_level = match level {
+                "" => 1,
+                _ => level.parse::<u8>().map_err(|_| format!("Unsupported optimization level '{}'", arg))?.min(1),
+            };
+            return Ok(true);
+        }
+        match arg {
+            "-fpic" | "-fPIC" | "-fpie" | "-fPIE" | "--pie" => self.pic = Some(true),
+            "-fno-pic" | "-fno-PIC" | "-fno-pie" | "-fno-PIE" | "--no-pie" => self.pic = Some(false),
+            "-ffunction-sections" => self.function_sections = true,
+            "--asm-comments" => self.asm_comments = true,
+            "-fomit-frame-pointer" => self.omit_frame_pointer = true,
+            "-fno-omit-frame-pointer" => self.omit_frame_pointer = false,
+            "-ftrapv" => self.trapv = true,
+            "-fwrapv" | "-fno-trapv" => self.trapv = false,
+            "--check-div-zero" => self.checks.div_zero = true,
+            "-nostdlib" => self.nostdlib = true,
+            "-ffreestanding" => self.freestanding = true,
+            "-fmerge-constants" => self.merge_strings = true,
+            "-fno-merge-constants" => self.merge_strings = false,
+            "--raw-binary" => self.raw_binary = true,
+            "-T" => match rest.next() {
+                Some(script) => self.linker_script = Some(PathBuf::from(script)),
+                None => return Err("Missing linker script after '-T'".to_string()),
+            },
+            _ if arg.starts_with("-T") => self.linker_script = Some(PathBuf::from(&arg[2..])),
+            "--time-passes" | "-ftime-report" => self.time_passes = true,
+            // Keep the preprocessed source, the TAC and the assembly
+            "--save-temps" | "-save-temps" => self.save_temps = true,
+            "--verify-ir" => self.verify_ir = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // The compiler the flags describe, once they are checked against each
+    // other
+    fn compiler(&mut self) -> Result<Compiler, String> {
+        let mut options = CodegenOptions::new(self.target);
+        if let Some(pic) = self.pic {
+            options.pic = pic;
+        }
+        options.function_sections = self.function_sections;
+        options.asm_comments = self.asm_comments;
+        options.omit_frame_pointer = self.omit_frame_pointer;
+        options.trapv = self.trapv;
+        if self.nostdlib && self.target != Target::Linux {
+            return Err("-nostdlib is only supported when targeting Linux".to_string());
+        }
+        options.nostdlib = self.nostdlib;
+        if (self.linker_script.is_some() || self.raw_binary) && self.target != Target::Linux {
+            return Err("-T and --raw-binary are only supported when targeting Linux".to_string());
+        }
+        options.freestanding = self.freestanding;
+        self.preprocess_options.freestanding = self.freestanding;
+        options.opt_level = self.opt_level;
+        options.merge_strings = self.merge_strings;
+        Ok(Compiler { dialect: self.dialect, options, checks: self.checks })
+    }
+
+    fn build(&mut self, stop_after: Option<Stage>, produce: Output) -> Build {
+        let compiler = self.compiler().unwrap_or_else(|err| exit_with_error(&err));
+        Build {
+            compiler,
+            linker_script: self.linker_script.clone(),
+            raw_binary: self.raw_binary,
+            stop_after,
+            produce,
+            save_temps: self.save_temps,
+            output: None,
+            cc_names: false,
+            run: false,
+            verbose: false,
+        }
+    }
+}
+
+const FLAGS: &str = "[--target <triple>] [-fpic|-fno-pic] [--asm-comments] [-fomit-frame-pointer] [-ftrapv] [--check-div-zero] [-nostdlib] [-ffreestanding] [-fmerge-constants] [-T <script>] [--raw-binary] [-O<level>] [--time-passes] [--std=<c11|c17|c23>] [--gnu-extensions] [-fpermissive] [-D <name>[=<value>]] [-U <name>] [-I <dir>] [-isystem <dir>] [-MD [-MF <file>] [-MT <target>]] [--save-temps] [--verify-ir]";
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} <command> [flags] <input_file>...", program);
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  compile [-o <file>] [-c|-S] <input_file>... Compile and link to a.out, or only to <input>.o");
+    eprintln!("                                            with -c or <input>.s with -S");
+    eprintln!("  run [--tee-stdout=<file>] [--tee-stderr=<file>] <input_file> [-- <arg>...]");
+    eprintln!("                                            Compile, link and run the program with the arguments,");
+    eprintln!("                                            exiting as it does; --run <input_file> is the same");
+    eprintln!("  check [--json] <input_file>               Report errors and warnings without compiling");
+    eprintln!("  dump --stage=<tokens|ast|tac|asm|symbols> [--json] <input_file>");
+    eprintln!("                                            Print the program as a stage leaves it");
+    eprintln!("  repl                                      Evaluate expressions read from stdin");
+    eprintln!();
+    eprintln!("Every command but repl takes: {}", FLAGS);
+    eprintln!();
+    eprintln!("Without a command, compiles, links and runs each file, reporting every step:");
+    eprintln!("  {} {} [--repl] [--explain <code>] [--check [-fdiagnostics-format=json]] [--emit-tokens] [--fmt] [--emit-symbols[=json]] [--ast-json|--tac-json] [--lex|--parse|--validate|--tacky|--codegen|-S] <input_file>...", program, FLAGS);
+}
+
+// The flags of a command: the ones every command takes go into settings,
+// and take_flag is offered the rest, along with what follows them, and
+// says whether it took each one. Whatever isn't a flag is an input file.
+fn parse_command<'a>(command: &str, args: &'a [String], settings: &mut Settings, mut take_flag: impl FnMut(&'a str, &mut std::slice::Iter<'a, String>) -> bool) -> Vec<&'a Path> {
+    let mut inputs = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match settings.parse_flag(arg, &mut rest) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(err) => exit_with_error(&err),
+        }
+        if take_flag(arg, &mut rest) {
+            continue;
+        }
+        if arg.starts_with('-') {
+            exit_with_error(&format!("Unknown flag '{}' for '{}'", arg, command));
         }
-        Err(e) => {
-            // Parsing failed, print error and exit with non-zero code
-            eprintln!("Text input: {}", input);
-            eprintln!("Tokens: {:?}", tokens);
-            eprintln!("Parsing error: {}", e);
+        inputs.push(Path::new(arg.as_str()));
+    }
+    inputs
+}
+
+// The one input file of a command that takes one
+fn single_input<'a>(command: &str, inputs: &[&'a Path]) -> &'a Path {
+    match inputs {
+        [input] => input,
+        [] => exit_with_error(&format!("'{}' needs an input file", command)),
+        _ => exit_with_error(&format!("'{}' takes a single input file", command)),
+    }
+}
+
+// compile: compiles, assembles and links a file, or with -c or -S stops
+// before linking or assembling, without running anything. Outputs are named
+// as cc names them.
+fn compile_command(args: &[String]) {
+    let mut settings = Settings::new();
+    let (mut output, mut produce) = (None, Output::Executable);
+    let inputs = parse_command("compile", args, &mut settings, |arg, rest| {
+        match arg {
+            "-o" => output = Some(PathBuf::from(rest.next().unwrap_or_else(|| exit_with_error("Missing file name after '-o'")))),
+            _ if arg.len() > 2 && arg.starts_with("-o") => output = Some(PathBuf::from(&arg[2..])),
+            // As with cc, -S wins over -c
+            "-S" => produce = Output::Assembly,
+            "-c" if produce == Output::Executable => produce = Output::Object,
+            "-c" => {}
+            _ => return false,
+        }
+        true
+    });
+    if inputs.is_empty() {
+        exit_with_error("'compile' needs an input file");
+    }
+    // Every file defines main, so no two can be linked together
+    if produce == Output::Executable && inputs.len() > 1 {
+        exit_with_error("'compile' links a single input file; use -c or -S to compile several");
+    }
+    if output.is_some() && inputs.len() > 1 {
+        exit_with_error("-o can't be used with -c or -S and more than one input file");
+    }
+    let mut build = settings.build(None, produce);
+    build.output = output;
+    build.cc_names = true;
+    if !build.build_all(&inputs, &settings.preprocess_options, settings.time_passes, settings.verify_ir) {
+        process::exit(1);
+    }
+}
+
+// run: compiles and links a file, then runs it with the arguments after
+// "--" and exits as it did
+fn run_command(args: &[String]) {
+    let (args, program_args) = match args.iter().position(|arg| arg == "--") {
+        Some(dash) => (&args[..dash], &args[dash + 1..]),
+        None => (args, &[][..]),
+    };
+    let mut settings = Settings::new();
+    let mut program = ProgramRun { args: program_args.to_vec(), ..ProgramRun::default() };
+    let inputs = parse_command("run", args, &mut settings, |arg, _| program.parse_flag(arg));
+    let input_file = single_input("run", &inputs);
+    let mut build = settings.build(None, Output::Executable);
+    let options = build.compiler.options;
+    if options.target != Target::host() || options.freestanding {
+        exit_with_error("'run' can only run programs built for this machine with a C library");
+    }
+    // The program is linked in a directory of its own, removed once it has
+    // run. process::exit doesn't drop it, so that is done first.
+    let temp_dir = TempDir::new().unwrap_or_else(|err| exit_with_error(&format!("Error creating a temporary directory: {}", err)));
+    let executable = temp_dir.file(build.product(input_file).file_name().expect("an input file has a name"));
+    build.output = Some(executable.clone());
+    if !build.build_all(&[input_file], &settings.preprocess_options, settings.time_passes, settings.verify_ir) {
+        drop(temp_dir);
+        process::exit(1);
+    }
+    let status = program.run(&executable);
+    drop(temp_dir);
+    process::exit(exit_code(status));
+}
+
+// check: reports a file's errors and warnings, as --check does
+fn check_command(args: &[String]) {
+    let mut settings = Settings::new();
+    let mut json = false;
+    let inputs = parse_command("check", args, &mut settings, |arg, _| {
+        let taken = matches!(arg, "--json" | "-fdiagnostics-format=json");
+        json |= taken;
+        taken
+    });
+    let input_file = single_input("check", &inputs);
+    let build = settings.build(Some(Stage::Validate), Output::Executable);
+    let Some(input) = preprocess_file(input_file, &build.compiler, &settings.preprocess_options, &build.product(input_file), &mut PassTimer::new(false)) else {
+        process::exit(1);
+    };
+    run_check(input_file, &input, &build.compiler.dialect, json);
+}
+
+// dump: prints a file as one stage of the compiler leaves it
+fn dump_command(args: &[String]) {
+    let mut settings = Settings::new();
+    let (mut stage, mut json) = (None, false);
+    let inputs = parse_command("dump", args, &mut settings, |arg, _| {
+        match arg.strip_prefix("--stage=") {
+            Some(name) => stage = Some(name.to_string()),
+            None if arg == "--json" => json = true,
+            None => return false,
+        }
+        true
+    });
+    let input_file = single_input("dump", &inputs);
+    let build = settings.build(None, Output::Executable);
+    let Some(source) = read_input(input_file) else { process::exit(1) };
+    let dialect = build.compiler.dialect;
+    // Tokens are of the file as it is written, the rest of it preprocessed
+    let stage = stage.unwrap_or_else(|| exit_with_error("'dump' needs --stage=<tokens|ast|tac|asm|symbols>"));
+    if stage == "tokens" {
+        run_emit_tokens(input_file, &source, &dialect);
+        return;
+    }
+    let Some(input) = preprocess_file(input_file, &build.compiler, &settings.preprocess_options, &build.product(input_file), &mut PassTimer::new(false)) else {
+        process::exit(1);
+    };
+    let text = match stage.as_str() {
+        "ast" => driver::ast_json(&input, &dialect),
+        "tac" if json => driver::tac_json(&input, &dialect),
+        "tac" => driver::compile_to_tac(&input, &dialect).map(|mut tac| {
+            checks::insert_checks(&mut tac.function, build.compiler.checks);
+            tac.to_string()
+        }),
+        "asm" => build.compiler.compile(&input),
+        "symbols" => {
+            run_emit_symbols(input_file, &input, &dialect, json);
+            return;
+        }
+        _ => exit_with_error(&format!("Unknown stage '{}'; expected tokens, ast, tac, asm or symbols", stage)),
+    };
+    match text {
+        Ok(text) => print!("{}", text),
+        Err(err) => {
+            print_diagnostic(input_file, &Diagnostic::from(&err));
             process::exit(1);
         }
+    }
 }
 
+fn main() {
+    // Get command line arguments
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("compile") => compile_command(&args[2..]),
+        Some("run") => run_command(&args[2..]),
+        Some("check") => check_command(&args[2..]),
+        Some("dump") => dump_command(&args[2..]),
+        Some("repl") if args.len() == 2 => run_repl(),
+        Some("repl") => exit_with_error("'repl' takes no arguments"),
+        Some("help" | "--help" | "-h") => print_usage(&args[0]),
+        // --run anywhere before "--" asks for the run command in the old form
+        _ if args[1..].iter().take_while(|arg| *arg != "--").any(|arg| arg == "--run") => {
+            let run = args[1..].iter().position(|arg| arg == "--run").expect("--run was found") + 1;
+            run_command(&[&args[1..run], &args[run + 1..]].concat())
+        }
+        // Without a command, everything is set by flags, as it always was
+        _ => legacy_main(&args),
+    }
+}
 
-}
\ No newline at end of file
+fn legacy_main(args: &[String]) {
+    let mut settings = Settings::new();
+    let mut stop_after = None;
+    let mut assembly_only = false;
+    let mut check = false;
+    let mut emit_tokens = false;
+    let mut emit_symbols = None;
+    let mut ir_json = None;
+    let mut fmt = false;
+    let mut json_diagnostics = false;
+    let mut input_paths = Vec::new();
+    let mut args_iter = args[1..].iter();
+    // Flags may appear anywhere; the remaining arguments are the input files
+    while let Some(arg) = args_iter.next() {
+        match settings.parse_flag(arg, &mut args_iter) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(err) => exit_with_error(&err),
+        }
+        match arg.as_str() {
+            // Describes an error code in more detail
+            "--explain" => {
+                let code = args_iter.next().map_or("", String::as_str);
+                match explain::explain(code) {
+                    Some(explanation) => println!("{}", explanation),
+                    None => exit_with_error(&format!("'{}' is not an error code", code)),
+                }
+                return;
+            }
+            "--repl" => {
+                run_repl();
+                return;
+            }
+            // Stop after a stage without writing anything, reporting only success or failure
+            "--lex" => stop_after = Some(Stage::Lex),
+            "--parse" => stop_after = Some(Stage::Parse),
+            "--validate" => stop_after = Some(Stage::Validate),
+            "--tacky" => stop_after = Some(Stage::Tacky),
+            "--codegen" => stop_after = Some(Stage::Codegen),
+            // Only report diagnostics, for editors checking on save
+            "--check" => check = true,
+            "-fdiagnostics-format=json" => json_diagnostics = true,
+            "--emit-tokens" => emit_tokens = true,
+            "--fmt" => fmt = true,
+            "--emit-symbols" => emit_symbols = Some(false),
+            "--emit-symbols=json" => emit_symbols = Some(true),
+            "--ast-json" => ir_json = Some(Stage::Parse),
+            "--tac-json" => ir_json = Some(Stage::Tacky),
+            // Write the assembly file but don't assemble or link it
+            "-S" => assembly_only = true,
+            _ => input_paths.push(arg),
+        }
+    }
+    let mut build = settings.build(stop_after, if assembly_only { Output::Assembly } else { Output::Executable });
+    build.run = true;
+    build.verbose = true;
+    let dialect = build.compiler.dialect;
+    let input_files: Vec<&Path> = input_paths.iter().map(|path| Path::new(path.as_str())).collect();
+    if input_files.is_empty() {
+        print_usage(&args[0]);
+        process::exit(1);
+    }
+
+    // The modes that report on a file instead of compiling it take one
+    if check || emit_tokens || fmt || emit_symbols.is_some() || ir_json.is_some() {
+        let [input_file] = input_files[..] else {
+            exit_with_error("--check, --emit-tokens, --fmt, --emit-symbols, --ast-json and --tac-json take a single input file");
+        };
+        let Some(input) = read_input(input_file) else { process::exit(1) };
+        // --emit-tokens and --fmt see the file as it is written, everything
+        // else sees it preprocessed
+        if emit_tokens {
+            run_emit_tokens(input_file, &input, &dialect);
+            return;
+        }
+        if fmt {
+            run_fmt(input_file, &input, &dialect);
+            return;
+        }
+        let mut timer = PassTimer::new(false);
+        let Some(input) = preprocess_file(input_file, &build.compiler, &settings.preprocess_options, &build.product(input_file), &mut timer) else {
+            process::exit(1);
+        };
+        match (emit_symbols, ir_json) {
+            (Some(json), _) => run_emit_symbols(input_file, &input, &dialect, json),
+            (None, Some(stage)) => run_ir_json(input_file, &input, &dialect, stage),
+            (None, None) => run_check(input_file, &input, &dialect, json_diagnostics),
+        }
+        return;
+    }
+
+    if !build.build_all(&input_files, &settings.preprocess_options, settings.time_passes, settings.verify_ir) {
+        process::exit(1);
+    }
+}
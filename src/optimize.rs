@@ -0,0 +1,354 @@
+use crate::intern::Symbol;
+use crate::tac::{BinaryOperator, Function, Instruction, Program, UnaryOperator, Val};
+use std::collections::{HashMap, HashSet};
+
+// The first non-label instruction at or after index
+fn first_instruction(body: &[Instruction], index: usize) -> Option<&Instruction> {
+    body[index..].iter().find(|instruction| !matches!(instruction, Instruction::Label { .. }))
+}
+
+// Follow a chain of labels that only lead to another unconditional jump and
+// return the label at the end of it. Cycles (an infinite loop made of jumps)
+// stop at the first label seen twice.
+fn final_target(body: &[Instruction], labels: &HashMap<Symbol, usize>, label: Symbol) -> Symbol {
+    let mut current = label;
+    let mut seen = HashSet::new();
+    while seen.insert(current) {
+        match labels.get(&current).and_then(|index| first_instruction(body, *index)) {
+            Some(Instruction::Jump { label: next }) => current = *next,
+            _ => break,
+        }
+    }
+    current
+}
+
+// Jump threading: retarget jumps that land on another jump straight to its
+// destination, and replace a jump that lands on a return with the return
+// itself. Labels nothing jumps to any more are removed afterwards.
+pub fn thread_jumps(function: &mut Function) {
+    let labels: HashMap<Symbol, usize> = function.body.iter().enumerate()
+        .filter_map(|(index, instruction)| match instruction {
+            Instruction::Label { label } => Some((*label, index)),
+            _ => None,
+        })
+        .collect();
+
+    let threaded: Vec<Instruction> = function.body.iter().map(|instruction| match instruction {
+        Instruction::Jump { label } => {
+            let target = final_target(&function.body, &labels, *label);
+            match labels.get(&target).and_then(|index| first_instruction(&function.body, *index)) {
+                Some(Instruction::Return(val)) => Instruction::Return(val.clone()),
+                _ => Instruction::Jump { label: target },
+            }
+        }
+        Instruction::JumpIfZero { src, label, likely } => Instruction::JumpIfZero {
+            src: src.clone(),
+            label: final_target(&function.body, &labels, *label),
+            likely: *likely,
+        },
+        Instruction::JumpIfNotZero { src, label, likely } => Instruction::JumpIfNotZero {
+            src: src.clone(),
+            label: final_target(&function.body, &labels, *label),
+            likely: *likely,
+        },
+        _ => instruction.clone(),
+    }).collect();
+
+    let used: HashSet<Symbol> = threaded.iter().filter_map(|instruction| match instruction {
+        Instruction::Jump { label }
+        | Instruction::JumpIfZero { label, .. }
+        | Instruction::JumpIfNotZero { label, .. } => Some(*label),
+        _ => None,
+    }).collect();
+
+    function.body = threaded.into_iter()
+        .filter(|instruction| match instruction {
+            Instruction::Label { label } => used.contains(label),
+            _ => true,
+        })
+        .collect();
+}
+
+// Unreachable code: __builtin_unreachable promises control never gets to
+// it, so a conditional jump to it is never taken, and one that would fall
+// into it always is. Code nothing can reach any more, up to the next label
+// that is still jumped to, is then removed.
+pub fn prune_unreachable(function: &mut Function) {
+    let labels: HashMap<Symbol, usize> = function.body.iter().enumerate()
+        .filter_map(|(index, instruction)| match instruction {
+            Instruction::Label { label } => Some((*label, index)),
+            _ => None,
+        })
+        .collect();
+    let body = &function.body;
+    let unreachable_at = |index: usize| matches!(first_instruction(body, index), Some(Instruction::Unreachable));
+    let unreachable_label = |label: &Symbol| labels.get(label).is_some_and(|index| unreachable_at(*index));
+
+    let pruned: Vec<Instruction> = body.iter().enumerate().filter_map(|(index, instruction)| match instruction {
+        Instruction::JumpIfZero { label, .. } | Instruction::JumpIfNotZero { label, .. } if unreachable_label(label) => None,
+        Instruction::JumpIfZero { label, .. } | Instruction::JumpIfNotZero { label, .. } if unreachable_at(index + 1) => {
+            Some(Instruction::Jump { label: *label })
+        }
+        _ => Some(instruction.clone()),
+    }).collect();
+
+    let used: HashSet<Symbol> = pruned.iter().filter_map(|instruction| match instruction {
+        Instruction::Jump { label }
+        | Instruction::JumpIfZero { label, .. }
+        | Instruction::JumpIfNotZero { label, .. } => Some(*label),
+        _ => None,
+    }).collect();
+
+    let mut reachable = true;
+    function.body = pruned.into_iter().filter(|instruction| {
+        if let Instruction::Label { label } = instruction {
+            reachable |= used.contains(label);
+        }
+        let keep = reachable;
+        if matches!(instruction, Instruction::Jump { .. } | Instruction::Return(_) | Instruction::Abort(_)
+            | Instruction::Trap | Instruction::Unreachable) {
+            reachable = false;
+        }
+        keep
+    }).collect();
+}
+
+// A pure computation, identified by its operator and operands
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Computation {
+    Unary(UnaryOperator, Val),
+    Binary(BinaryOperator, Val, Val),
+}
+
+impl Computation {
+    fn uses(&self, var: &Val) -> bool {
+        match self {
+            Computation::Unary(_, src) => src == var,
+            Computation::Binary(_, src1, src2) => src1 == var || src2 == var,
+        }
+    }
+}
+
+fn is_commutative(operator: &BinaryOperator) -> bool {
+    matches!(operator,
+        BinaryOperator::Add | BinaryOperator::Multiply | BinaryOperator::Ampersand | BinaryOperator::Pipe
+        | BinaryOperator::Caret | BinaryOperator::Equal | BinaryOperator::NotEqual)
+}
+
+// Order the operands of commutative operators so a+b and b+a share a key
+fn binary_computation(operator: &BinaryOperator, src1: &Val, src2: &Val) -> Computation {
+    if is_commutative(operator) && src1.to_string() > src2.to_string() {
+        Computation::Binary(operator.clone(), src2.clone(), src1.clone())
+    } else {
+        Computation::Binary(operator.clone(), src1.clone(), src2.clone())
+    }
+}
+
+// Local common subexpression elimination. Within each basic block, remember
+// which variable holds the result of every unary and binary computation; a
+// later identical computation becomes a copy from that variable. Writing to a
+// variable forgets every computation that read it or was held in it.
+//
+// Blocks start at labels and end after jumps and returns, so nothing is
+// assumed to be available across a control-flow edge. Nor across a call,
+// which may change any variable with static storage. Volatile variables
+// may change or be watched behind the program's back, so computations
+// reading them are never reused and they never hold one for reuse.
+//
+// Extending this to dominator-based global value numbering means seeding
+// each block's table from its immediate dominator instead of starting empty.
+pub fn eliminate_common_subexpressions(function: &mut Function) {
+    let mut available: HashMap<Computation, Val> = HashMap::new();
+    let mut body = Vec::with_capacity(function.body.len());
+    let volatiles: Vec<Val> = function.volatiles.iter().map(|name| Val::Identifier(*name)).collect();
+    let is_volatile = |val: &Val| volatiles.contains(val);
+
+    for instruction in function.body.drain(..) {
+        let (computation, dst) = match &instruction {
+            Instruction::Unary { operator, src, dst } => {
+                (Some(Computation::Unary(operator.clone(), src.clone())), Some(dst.clone()))
+            }
+            Instruction::Binary { operator, src1, src2, dst } => {
+                (Some(binary_computation(operator, src1, src2)), Some(dst.clone()))
+            }
+            Instruction::Copy { dst, .. } | Instruction::Narrow { dst, .. } | Instruction::FunCall { dst, .. } => (None, Some(dst.clone())),
+            _ => (None, None),
+        };
+        let computation = computation.filter(|computation| {
+            !volatiles.iter().any(|volatile| computation.uses(volatile)) && !dst.as_ref().is_some_and(is_volatile)
+        });
+
+        let rewritten = match computation.as_ref().and_then(|computation| available.get(computation)) {
+            Some(holder) if Some(holder) != dst.as_ref() => Instruction::Copy {
+                src: holder.clone(),
+                dst: dst.clone().unwrap(),
+            },
+            _ => instruction,
+        };
+
+        if let Some(dst) = &dst {
+            available.retain(|computation, holder| holder != dst && !computation.uses(dst));
+            if let Some(computation) = computation.filter(|computation| !computation.uses(dst)) {
+                available.entry(computation).or_insert_with(|| dst.clone());
+            }
+        }
+
+        if matches!(rewritten,
+            Instruction::Label { .. } | Instruction::Jump { .. } | Instruction::JumpIfZero { .. }
+            | Instruction::JumpIfNotZero { .. } | Instruction::Return(_) | Instruction::Abort(_)
+            | Instruction::Trap | Instruction::Unreachable | Instruction::FunCall { .. }) {
+            available.clear();
+        }
+        body.push(rewritten);
+    }
+
+    function.body = body;
+}
+
+// Whether control can run off the end of a block into the next one
+fn falls_through(block: &[Instruction]) -> bool {
+    !matches!(block.last(), Some(Instruction::Jump { .. } | Instruction::Return(_) | Instruction::Abort(_)
+        | Instruction::Trap | Instruction::Unreachable))
+}
+
+// Splits body into basic blocks: each starts at its labels, or after a jump,
+// and ends at a jump, a return or the next block's labels
+fn basic_blocks(body: Vec<Instruction>) -> Vec<Vec<Instruction>> {
+    let mut blocks = Vec::new();
+    let mut block = Vec::new();
+    for instruction in body {
+        if matches!(instruction, Instruction::Label { .. }) && block.iter().any(|instruction| !matches!(instruction, Instruction::Label { .. })) {
+            blocks.push(std::mem::take(&mut block));
+        }
+        let ends = matches!(instruction,
+            Instruction::Jump { .. } | Instruction::JumpIfZero { .. } | Instruction::JumpIfNotZero { .. }
+            | Instruction::Return(_) | Instruction::Abort(_) | Instruction::Trap | Instruction::Unreachable);
+        block.push(instruction);
+        if ends {
+            blocks.push(std::mem::take(&mut block));
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+// Block layout: blocks are laid out in chains from the entry, each
+// continuing into its preferred successor while that is still unplaced, so
+// as many as possible fall into the next one. A branch __builtin_expect says
+// is likely taken has its target placed right after it and its condition
+// inverted, so the likely path falls through and the unlikely one takes the
+// jump. A block ending in a jump is followed by the jump's target, unless
+// another block already falls into it. A block whose fallthrough no longer
+// follows it gets a jump to it, and jumps to the block that follows anyway
+// are removed.
+pub fn lay_out_blocks(function: &mut Function) {
+    let mut blocks = basic_blocks(std::mem::take(&mut function.body));
+    let mut block_of: HashMap<Symbol, usize> = blocks.iter().enumerate()
+        .flat_map(|(index, block)| block.iter().filter_map(move |instruction| match instruction {
+            Instruction::Label { label } => Some((*label, index)),
+            _ => None,
+        }))
+        .collect();
+    let fallthrough = |blocks: &[Vec<Instruction>], index: usize| (falls_through(&blocks[index]) && index + 1 < blocks.len()).then_some(index + 1);
+
+    let mut order = Vec::with_capacity(blocks.len());
+    let mut placed = vec![false; blocks.len()];
+    for start in 0..blocks.len() {
+        let mut next = Some(start);
+        while let Some(index) = next.filter(|index| !placed[*index]) {
+            placed[index] = true;
+            order.push(index);
+            next = match blocks[index].last() {
+                Some(Instruction::JumpIfZero { label, likely: Some(true), .. } | Instruction::JumpIfNotZero { label, likely: Some(true), .. })
+                    if !placed[block_of[label]] => Some(block_of[label]),
+                Some(Instruction::Jump { label }) => {
+                    let target = block_of[label];
+                    let fallen_into = target > 0 && fallthrough(&blocks, target - 1) == Some(target) && !placed[target - 1];
+                    (!fallen_into).then_some(target)
+                }
+                _ => fallthrough(&blocks, index),
+            };
+        }
+    }
+
+    // Every block may now be jumped to instead of fallen into
+    let labels: Vec<Symbol> = blocks.iter_mut().enumerate()
+        .map(|(index, block)| match block.first() {
+            Some(Instruction::Label { label }) => *label,
+            _ => {
+                let label = Symbol::intern(&format!("{}_block_{}", function.identifier, index));
+                block.insert(0, Instruction::Label { label });
+                label
+            }
+        })
+        .collect();
+    block_of.extend(labels.iter().enumerate().map(|(index, label)| (*label, index)));
+    let fallthroughs: Vec<Option<usize>> = (0..blocks.len()).map(|index| fallthrough(&blocks, index)).collect();
+
+    let mut blocks: Vec<Option<Vec<Instruction>>> = blocks.into_iter().map(Some).collect();
+    let mut body = Vec::new();
+    for (position, &index) in order.iter().enumerate() {
+        let next = order.get(position + 1).copied();
+        let mut fallthrough = fallthroughs[index];
+        let mut block = blocks[index].take().expect("each block is placed once");
+        if let (Some(target), Some(last)) = (fallthrough.filter(|target| Some(*target) != next), block.last_mut()) {
+            let inverted = match last {
+                Instruction::JumpIfZero { src, label, likely } if Some(block_of[label]) == next => {
+                    Some(Instruction::JumpIfNotZero { src: src.clone(), label: labels[target], likely: likely.map(|likely| !likely) })
+                }
+                Instruction::JumpIfNotZero { src, label, likely } if Some(block_of[label]) == next => {
+                    Some(Instruction::JumpIfZero { src: src.clone(), label: labels[target], likely: likely.map(|likely| !likely) })
+                }
+                _ => None,
+            };
+            if let Some(inverted) = inverted {
+                *last = inverted;
+                fallthrough = next;
+            }
+        }
+        // A jump to the next block only has to fall through
+        let redundant = match block.last() {
+            Some(Instruction::Jump { label }) => Some(block_of[label]) == next,
+            Some(Instruction::JumpIfZero { label, .. } | Instruction::JumpIfNotZero { label, .. }) => Some(block_of[label]) == next && fallthrough == next,
+            _ => false,
+        };
+        if redundant {
+            block.pop();
+            fallthrough = next;
+        }
+        body.extend(block);
+        if let Some(target) = fallthrough.filter(|target| Some(*target) != next) {
+            body.push(Instruction::Jump { label: labels[target] });
+        }
+    }
+
+    // Drop the labels nothing jumps to
+    let used: HashSet<Symbol> = body.iter().filter_map(|instruction| match instruction {
+        Instruction::Jump { label } | Instruction::JumpIfZero { label, .. } | Instruction::JumpIfNotZero { label, .. } => Some(*label),
+        _ => None,
+    }).collect();
+    body.retain(|instruction| !matches!(instruction, Instruction::Label { label } if !used.contains(label)));
+    function.body = body;
+}
+
+pub type Pass = fn(&mut Function);
+
+// Every TAC optimization pass, in the order they run
+pub const PASSES: &[(&str, Pass)] = &[
+    ("common subexpressions", eliminate_common_subexpressions),
+    ("unreachable code", prune_unreachable),
+    ("jump threading", thread_jumps),
+    ("block layout", lay_out_blocks),
+];
+
+// Run every TAC optimization pass over the program
+pub fn optimize(program: &mut Program) {
+    let interner = program.interner.clone();
+    interner.enter(|| {
+        for (_, pass) in PASSES {
+            pass(&mut program.function);
+        }
+    })
+}
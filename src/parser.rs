@@ -1,8 +1,13 @@
 use crate::lex::{self};
-use std::collections::HashMap;
+use crate::intern::{Interner, Symbol};
+use crate::const_eval::eval_constant;
+use crate::dialect::{Dialect, Standard};
+use crate::scope::{self, Scope};
+use crate::symbols::{StorageClass, SymbolTable};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
+    Plus, // +
     Negation, // -
     Complement, // ~
     LogicalNot, // !
@@ -31,33 +36,203 @@ pub enum BinaryOp {
     LessThanOrEqual,
     //Tag,
     Assignment,
+    LeftShiftAssignment,
+    RightShiftAssignment,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Factor {
-    Int(i32),
-    Unary(UnaryOp, Box<Factor>),
-    Exp(Box<Exp>),
+pub enum Exp {
+    Constant(i32, lex::Span), // Integer constant and where it appears
+    Var(Symbol, lex::Span), // Variable name (identifier) and where it appears
+    Unary(UnaryOp, ExpId), // Unary operation
+    Cast(Type, ExpId), // Explicit or inserted conversion, e.g. (int)x
+    Binary(ExpId, BinaryOp, ExpId), // Binary operation
+    Assignment(ExpId, ExpId), // Assignment
+    CompoundAssignment(BinaryOp, ExpId, ExpId), // Compound assignment, e.g. a <<= b
+    Conditional(ExpId, Option<ExpId>, ExpId), // cond ? then : else, or GNU cond ?: else
+    Dot(ExpId, Symbol), // Member access, e.g. s.field
+    Arrow(ExpId, Symbol), // Member access through a pointer, e.g. p->field
+    Expect(ExpId, i32), // __builtin_expect(exp, value), which is exp expected to equal value
+    Call(Symbol, Vec<ExpId>, lex::Span), // Function call: the function's name, the arguments and where the name appears
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Exp {
-    Var(String), // Variable name (identifier
-    Factor(Factor), // Constant or parenthesized expression
-    Binary(Box<Exp>, BinaryOp, Box<Exp>), // Binary operation
-    Assignment(Box<Exp>, Box<Exp>) // Assignment
+// An expression's place in the arena of the program it belongs to. Only
+// ExpArena::alloc makes one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExpId(u32);
+
+// The expressions of a program. They refer to their operands by ExpId, so a
+// program's expressions are one allocation instead of one per node, and
+// passes that rewrite an expression do it in place.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpArena {
+    exps: Vec<Exp>,
+}
+
+impl ExpArena {
+    pub fn new() -> ExpArena {
+        ExpArena::default()
+    }
+
+    pub fn alloc(&mut self, exp: Exp) -> ExpId {
+        let id = ExpId(u32::try_from(self.exps.len()).expect("fewer than 2^32 expressions"));
+        self.exps.push(exp);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.exps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exps.is_empty()
+    }
+
+    // Moves other's expressions to the end of this arena, and returns how
+    // much further along they now are
+    pub(crate) fn append(&mut self, other: ExpArena) -> u32 {
+        let offset = u32::try_from(self.exps.len()).expect("fewer than 2^32 expressions");
+        for mut exp in other.exps {
+            for operand in exp.operands_mut() {
+                operand.0 += offset;
+            }
+            self.exps.push(exp);
+        }
+        offset
+    }
+
+    // The locations of the constants, variables and calls in the arena
+    pub(crate) fn spans_mut(&mut self) -> impl Iterator<Item = &mut lex::Span> {
+        self.exps.iter_mut().filter_map(|exp| match exp {
+            Exp::Constant(_, span) | Exp::Var(_, span) | Exp::Call(_, _, span) => Some(span),
+            _ => None,
+        })
+    }
+}
+
+impl Exp {
+    fn operands_mut(&mut self) -> Vec<&mut ExpId> {
+        match self {
+            Exp::Constant(..) | Exp::Var(..) => Vec::new(),
+            Exp::Unary(_, inner) | Exp::Cast(_, inner) | Exp::Dot(inner, _) | Exp::Arrow(inner, _) | Exp::Expect(inner, _) => vec![inner],
+            Exp::Binary(left, _, right) | Exp::Assignment(left, right) | Exp::CompoundAssignment(_, left, right) => vec![left, right],
+            Exp::Conditional(condition, then, otherwise) => std::iter::once(condition).chain(then.as_mut()).chain(std::iter::once(otherwise)).collect(),
+            Exp::Call(_, args, _) => args.iter_mut().collect(),
+        }
+    }
+}
+
+impl std::ops::Index<ExpId> for ExpArena {
+    type Output = Exp;
+
+    fn index(&self, id: ExpId) -> &Exp {
+        &self.exps[id.0 as usize]
+    }
+}
+
+impl std::ops::IndexMut<ExpId> for ExpArena {
+    fn index_mut(&mut self, id: ExpId) -> &mut Exp {
+        &mut self.exps[id.0 as usize]
+    }
+}
+
+// The types a declaration can have. The only base type supported so far is
+// int; the specifier parser recognizes every other combination so it can
+// say which type was asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Char,
+    SChar,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    LongLong,
+    ULongLong,
+    Pointer(Box<Type>),
+    Array(Box<Type>, u64),
+    Function(Vec<Type>, Box<Type>), // Parameter types and return type
+    Volatile(Box<Type>),
+}
+
+impl Type {
+    // The type with its qualifiers removed, which is the type of the value
+    // read from an lvalue
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::Volatile(inner) => inner.unqualified(),
+            _ => self,
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Char => write!(f, "char"),
+            Type::SChar => write!(f, "signed char"),
+            Type::UChar => write!(f, "unsigned char"),
+            Type::Short => write!(f, "short"),
+            Type::UShort => write!(f, "unsigned short"),
+            Type::Int => write!(f, "int"),
+            Type::UInt => write!(f, "unsigned int"),
+            Type::Long => write!(f, "long"),
+            Type::ULong => write!(f, "unsigned long"),
+            Type::LongLong => write!(f, "long long"),
+            Type::ULongLong => write!(f, "unsigned long long"),
+            Type::Pointer(referenced) => write!(f, "pointer to {}", referenced),
+            Type::Array(element, size) => write!(f, "array of {} {}", size, element),
+            Type::Function(params, ret) if params.is_empty() => write!(f, "function returning {}", ret),
+            Type::Function(params, ret) => {
+                let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+                write!(f, "function taking ({}) returning {}", params.join(", "), ret)
+            }
+            Type::Volatile(inner) => write!(f, "volatile {}", inner),
+        }
+    }
+}
+
+// A declarator as written, which reads inside out: in `int *a[3]` the
+// array suffix binds tighter than the pointer, so a is an array of pointers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Declarator {
+    Ident(Symbol, lex::Span),
+    Pointer(Box<Declarator>, bool), // Whether the pointer itself is volatile
+    Array(Box<Declarator>, u64),
+    Function(Vec<Param>, Box<Declarator>),
+}
+
+// A declarator with no name, as in a cast or an unnamed parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbstractDeclarator {
+    Base,
+    Pointer(Box<AbstractDeclarator>, bool),
+    Array(Box<AbstractDeclarator>, u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Param {
+    Named(Type, Declarator),
+    Unnamed(Type, AbstractDeclarator),
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Return(Exp),
-    Expression(Exp),
+    Return(ExpId),
+    Expression(ExpId),
+    Asm(String), // Basic inline assembly, with its template decoded
+    Trap, // __builtin_trap();
+    Unreachable, // __builtin_unreachable();
     Null,
 }
 
 #[derive(Debug, Clone)]
 pub enum Declaration {
-    Declaration(String, Option<Exp>),
+    Declaration(Symbol, Type, Option<ExpId>, Option<StorageClass>, lex::Span), // Name, declared type, initializer, storage class and the name's location
+    StaticAssert(ExpId, Option<String>, lex::Span), // Condition, message and where it starts
 }
 
 #[derive(Debug, Clone)]
@@ -68,12 +243,26 @@ pub enum BlockItem {
 
 #[derive(Debug, Clone)]
 pub enum FunctionDeclaration {
-    Function(String, Vec<Box<BlockItem>>),
+    Function(Symbol, Vec<Box<BlockItem>>, lex::Span), // Name, body and the name's location
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum Program {
-    Program(FunctionDeclaration),
+    Program(Vec<Declaration>, FunctionDeclaration, ExpArena, Interner), // File-scope declarations, the function, the expressions in both and the interner its names are in
+}
+
+impl Program {
+    pub fn interner(&self) -> &Interner {
+        let Program::Program(.., interner) = self;
+        interner
+    }
+}
+
+impl std::fmt::Debug for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Program::Program(declarations, function, exps, interner) = self;
+        interner.enter(|| f.debug_tuple("Program").field(declarations).field(function).field(exps).finish())
+    }
 }
 
 pub enum Associativity{
@@ -82,58 +271,90 @@ pub enum Associativity{
 }
 
 pub trait PrettyPrint {
-    fn pretty_print(&self, indent: usize);
+    fn pretty_print(&self, exps: &ExpArena, indent: usize);
 }
 
-impl PrettyPrint for Factor {
-    fn pretty_print(&self, indent: usize) {
-        match self {
-            Factor::Int(value) => {
+impl PrettyPrint for ExpId {
+    fn pretty_print(&self, exps: &ExpArena, indent: usize) {
+        match &exps[*self] {
+            Exp::Constant(value, _) => {
                 println!("{}Int: {}", " ".repeat(indent), value);
-            }
-            Factor::Unary(op, factor) => {
+            },
+            Exp::Unary(op, exp) => {
                 println!("{}Unary Operation: {:?}", " ".repeat(indent), op);
-                factor.pretty_print(indent + 2);
-            }
-            Factor::Exp(exp) => {
-                println!("{}Parenthesized Expression:", " ".repeat(indent));
-                exp.pretty_print(indent + 2);
-            }
-        }
-    }
-}
-
-impl PrettyPrint for Exp {
-    fn pretty_print(&self, indent: usize) {
-        match self {
-            Exp::Factor(factor) => factor.pretty_print(indent),
+                exp.pretty_print(exps, indent + 2);
+            },
+            Exp::Cast(target, exp) => {
+                println!("{}Cast: {}", " ".repeat(indent), target);
+                exp.pretty_print(exps, indent + 2);
+            },
             Exp::Binary(left, op, right) => {
                 println!("{}Binary Operation: {:?}", " ".repeat(indent), op);
-                left.pretty_print(indent + 2);
-                right.pretty_print(indent + 2);
+                left.pretty_print(exps, indent + 2);
+                right.pretty_print(exps, indent + 2);
             },
-            Exp::Var(name) => {
+            Exp::Var(name, _) => {
                 println!("{}Variable: {}", " ".repeat(indent), name);
             },
             Exp::Assignment(left, right) => {
                 println!("{}Assignment:", " ".repeat(indent));
-                left.pretty_print(indent + 2);
-                right.pretty_print(indent + 2);
+                left.pretty_print(exps, indent + 2);
+                right.pretty_print(exps, indent + 2);
+            },
+            Exp::CompoundAssignment(op, left, right) => {
+                println!("{}Compound Assignment: {:?}", " ".repeat(indent), op);
+                left.pretty_print(exps, indent + 2);
+                right.pretty_print(exps, indent + 2);
+            },
+            Exp::Conditional(condition, then, otherwise) => {
+                println!("{}Conditional:", " ".repeat(indent));
+                condition.pretty_print(exps, indent + 2);
+                if let Some(then) = then {
+                    then.pretty_print(exps, indent + 2);
+                }
+                otherwise.pretty_print(exps, indent + 2);
+            },
+            Exp::Dot(exp, member) => {
+                println!("{}Member Access: .{}", " ".repeat(indent), member);
+                exp.pretty_print(exps, indent + 2);
+            },
+            Exp::Arrow(exp, member) => {
+                println!("{}Member Access: ->{}", " ".repeat(indent), member);
+                exp.pretty_print(exps, indent + 2);
+            },
+            Exp::Expect(exp, value) => {
+                println!("{}Expect: {}", " ".repeat(indent), value);
+                exp.pretty_print(exps, indent + 2);
+            },
+            Exp::Call(name, args, _) => {
+                println!("{}Call: {}", " ".repeat(indent), name);
+                for arg in args {
+                    arg.pretty_print(exps, indent + 2);
+                }
             }
         }
     }
 }
 
 impl PrettyPrint for Statement {
-    fn pretty_print(&self, indent: usize) {
+    fn pretty_print(&self, exps: &ExpArena, indent: usize) {
         match self {
             Statement::Return(exp) => {
                 println!("{}Return:", " ".repeat(indent));
-                exp.pretty_print(indent + 2);
+                exp.pretty_print(exps, indent + 2);
             },
             Statement::Expression(exp) => {
                 println!("{}Expression:", " ".repeat(indent));
-                exp.pretty_print(indent + 2);
+                exp.pretty_print(exps, indent + 2);
+            },
+            Statement::Asm(template) => {
+                println!("{}Asm: {:?}", " ".repeat(indent), template);
+            },
+            Statement::Trap => {
+                println!("{}Trap", " ".repeat(indent));
+            },
+            Statement::Unreachable => {
+                println!("{}Unreachable", " ".repeat(indent));
             },
             Statement::Null => {
                 println!("{}Null", " ".repeat(indent));
@@ -143,96 +364,418 @@ impl PrettyPrint for Statement {
 }
 
 impl PrettyPrint for Declaration {
-    fn pretty_print(&self, indent: usize) {
+    fn pretty_print(&self, exps: &ExpArena, indent: usize) {
         match self {
-            Declaration::Declaration(name, exp) => {
-                println!("{}Declaration: {}", " ".repeat(indent), name);
+            Declaration::Declaration(name, declared_type, exp, storage_class, _) => {
+                let storage_class = match storage_class {
+                    Some(StorageClass::Extern) => "extern ",
+                    Some(StorageClass::Static) => "static ",
+                    None => "",
+                };
+                println!("{}Declaration: {} ({}{})", " ".repeat(indent), name, storage_class, declared_type);
                 if let Some(exp) = exp {
-                    exp.pretty_print(indent + 2);
+                    exp.pretty_print(exps, indent + 2);
                 }
             }
+            Declaration::StaticAssert(exp, message, _) => {
+                println!("{}Static Assertion: {}", " ".repeat(indent), message.as_deref().unwrap_or_default());
+                exp.pretty_print(exps, indent + 2);
+            }
         }
     }
 }
 
 impl PrettyPrint for BlockItem {
-    fn pretty_print(&self, indent: usize) {
+    fn pretty_print(&self, exps: &ExpArena, indent: usize) {
         match self {
             BlockItem::D(declaration) => {
-                declaration.pretty_print(indent);
+                declaration.pretty_print(exps, indent);
             }
             BlockItem::S(statement) => {
-                statement.pretty_print(indent);
+                statement.pretty_print(exps, indent);
             }
         }
     }
 }
 
 impl PrettyPrint for FunctionDeclaration {
-    fn pretty_print(&self, indent: usize) {
+    fn pretty_print(&self, exps: &ExpArena, indent: usize) {
         match self {
-            FunctionDeclaration::Function(name, block_items) => {
+            FunctionDeclaration::Function(name, block_items, _) => {
                 println!("{}Function: {}", " ".repeat(indent), name);
                 for item in block_items {
-                    item.pretty_print(indent + 2);
+                    item.pretty_print(exps, indent + 2);
                 }
             }
         }
     }
 }
 
-impl PrettyPrint for Program {
-    fn pretty_print(&self, indent: usize) {
+impl Program {
+    pub fn pretty_print(&self, indent: usize) {
+        self.interner().enter(|| self.pretty_print_in(indent))
+    }
+
+    fn pretty_print_in(&self, indent: usize) {
         match self {
-            Program::Program(func_decl) => {
+            Program::Program(declarations, func_decl, exps, _) => {
                 println!("{}Program:", " ".repeat(indent));
-                func_decl.pretty_print(indent + 2);
+                for declaration in declarations {
+                    declaration.pretty_print(exps, indent + 2);
+                }
+                func_decl.pretty_print(exps, indent + 2);
+            }
+        }
+    }
+}
+
+// Whether a token can begin the specifiers of a declaration
+fn is_specifier(token: &lex::Token) -> bool {
+    use lex::Keyword::*;
+    matches!(token.kind, lex::TokenKind::Keyword(
+        Void | Char | Short | Int | Long | Signed | Unsigned | Bool | Float | Double
+        | Typedef | Extern | Static | ThreadLocal | Auto | Register
+        | Const | Volatile | Restrict | Atomic))
+}
+
+// The type named by a set of type specifier keywords, which C allows in any
+// order ("long unsigned int"), spelled the usual way
+fn type_name(keywords: &[lex::Keyword]) -> Option<&'static str> {
+    use lex::Keyword::*;
+    let count = |keyword: lex::Keyword| keywords.iter().filter(|k| **k == keyword).count();
+    let (signed, unsigned, int, long) = (count(Signed), count(Unsigned), count(Int), count(Long));
+    if signed + unsigned > 1 || int > 1 || long > 2 || keywords.iter().any(|k| *k != Long && count(*k) > 1) {
+        return None;
+    }
+    let sign = if unsigned == 1 { "unsigned " } else { "" };
+    let others = keywords.len() - signed - unsigned - int - long;
+    if others == 0 {
+        return Some(match (unsigned, long) {
+            (0, 0) => "int",
+            (0, 1) => "long",
+            (0, _) => "long long",
+            (_, 0) => "unsigned int",
+            (_, 1) => "unsigned long",
+            (_, _) => "unsigned long long",
+        });
+    }
+    // Every other type keyword allows at most a sign, and short also int
+    match keywords.iter().find(|k| !matches!(k, Signed | Unsigned | Int | Long))? {
+        Short if others == 1 && long == 0 => Some(if unsigned == 1 { "unsigned short" } else { "short" }),
+        Char if others == 1 && long == 0 && int == 0 => Some(if signed == 1 { "signed char" } else if unsigned == 1 { "unsigned char" } else { "char" }),
+        Double if others == 1 && long <= 1 && int == 0 && sign.is_empty() && signed == 0 => Some(if long == 1 { "long double" } else { "double" }),
+        Void if keywords.len() == 1 => Some("void"),
+        Bool if keywords.len() == 1 => Some("_Bool"),
+        Float if keywords.len() == 1 => Some("float"),
+        _ => None,
+    }
+}
+
+// The specifiers of a declaration that can't have a storage class, such as
+// a parameter's or a cast's
+fn parse_type_specifier(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<Type, String> {
+    let span = tokens.first().map(|token| token.span);
+    match parse_specifiers(tokens, dialect)? {
+        (base_type, None) => Ok(base_type),
+        (_, Some(storage_class)) => Err(format!("{}: Storage class '{}' is not allowed here",
+            span.expect("a storage class was parsed"), storage_class.as_str())),
+    }
+}
+
+fn qualified(base_type: Type, volatile: bool) -> Type {
+    if volatile { Type::Volatile(Box::new(base_type)) } else { base_type }
+}
+
+// The qualifiers after a declarator's '*', which apply to the pointer.
+// Returns whether it is volatile.
+fn parse_pointer_qualifiers(tokens: &mut Vec<lex::Token>) -> Result<bool, String> {
+    let mut volatile = false;
+    while let Some(token) = tokens.first() {
+        match token.kind {
+            lex::TokenKind::Keyword(lex::Keyword::Volatile) => volatile = true,
+            lex::TokenKind::Keyword(lex::Keyword::Restrict) => {}
+            lex::TokenKind::Keyword(qualifier @ (lex::Keyword::Const | lex::Keyword::Atomic)) => {
+                return Err(format!("{}: Type qualifier '{}' is not supported yet", token.span, qualifier.as_str()));
             }
+            _ => break,
         }
+        tokens.remove(0);
     }
+    Ok(volatile)
 }
 
-fn expect_int_keyword(token: &lex::Token) -> Result<(), String> {
-    if token.value != "int" {
-        return Err(format!("Expected int keyword, got '{}'", token.value));
+// Consumes the specifiers at the start of a declaration (type keywords,
+// storage classes and qualifiers, in any order) and returns the type they
+// name and the storage class, if any
+fn parse_specifiers(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<(Type, Option<lex::Keyword>), String> {
+    let Some(first) = tokens.first() else {
+        return Err("Unexpected end of file; expected a type specifier".to_string());
+    };
+    let span = first.span;
+    let mut type_keywords = Vec::new();
+    let mut storage_classes = Vec::new();
+    let mut qualifiers = Vec::new();
+    while tokens.first().is_some_and(is_specifier) {
+        let token = tokens.remove(0);
+        let lex::TokenKind::Keyword(keyword) = token.kind else { unreachable!() };
+        match keyword {
+            lex::Keyword::Typedef | lex::Keyword::Extern | lex::Keyword::Static | lex::Keyword::ThreadLocal
+            | lex::Keyword::Auto | lex::Keyword::Register => storage_classes.push(keyword),
+            lex::Keyword::Const | lex::Keyword::Volatile | lex::Keyword::Restrict | lex::Keyword::Atomic => qualifiers.push(keyword),
+            _ => type_keywords.push(keyword),
+        }
     }
-    Ok(())
+
+    let spelling = |keywords: &[lex::Keyword]| keywords.iter().map(|k| k.as_str()).collect::<Vec<_>>().join(" ");
+    // C23 made "auto x = 1;" infer the type from the initializer
+    if type_keywords.is_empty() && storage_classes.contains(&lex::Keyword::Auto) && dialect.std >= Standard::C23 {
+        return Err(format!("{}: Type inference with 'auto' is not supported yet", span));
+    }
+    // Specifiers with no type keyword among them, such as "static x", were
+    // an implicit int until C99
+    let implicit_int = type_keywords.is_empty() && !(storage_classes.is_empty() && qualifiers.is_empty());
+    if implicit_int && !dialect.permissive {
+        return Err(format!("{}: Type specifier missing; C99 removed implicit int (-fpermissive accepts it)", span));
+    }
+    if implicit_int {
+        type_keywords.push(lex::Keyword::Int);
+    }
+    if type_keywords.is_empty() {
+        return match tokens.first() {
+            Some(token) => Err(format!("{}: Expected a type specifier, got '{}'", token.span, token)),
+            None => Err("Unexpected end of file; expected a type specifier".to_string()),
+        };
+    }
+    let Some(name) = type_name(&type_keywords) else {
+        return Err(format!("{}: Invalid combination of type specifiers '{}'", span, spelling(&type_keywords)));
+    };
+    if storage_classes.len() > 1 {
+        return Err(format!("{}: Multiple storage classes in declaration: '{}'", span, spelling(&storage_classes)));
+    }
+    let storage_class = match storage_classes.first() {
+        Some(storage_class @ (lex::Keyword::Extern | lex::Keyword::Static | lex::Keyword::Auto | lex::Keyword::Register)) => Some(*storage_class),
+        Some(storage_class) => return Err(format!("{}: Storage class '{}' is not supported yet", span, storage_class.as_str())),
+        None => None,
+    };
+    let mut volatile = false;
+    for qualifier in &qualifiers {
+        match qualifier {
+            lex::Keyword::Volatile => volatile = true,
+            // The specifiers never name a pointer type, as there are no typedefs
+            lex::Keyword::Restrict => return Err(format!("{}: 'restrict' requires a pointer type", span)),
+            _ => return Err(format!("{}: Type qualifier '{}' is not supported yet", span, qualifier.as_str())),
+        }
+    }
+    let base_type = match name {
+        "char" => Type::Char,
+        "signed char" => Type::SChar,
+        "unsigned char" => Type::UChar,
+        "short" => Type::Short,
+        "unsigned short" => Type::UShort,
+        "int" => Type::Int,
+        "unsigned int" => Type::UInt,
+        "long" => Type::Long,
+        "unsigned long" => Type::ULong,
+        "long long" => Type::LongLong,
+        "unsigned long long" => Type::ULongLong,
+        _ => return Err(format!("{}: Type '{}' is not supported yet", span, name)),
+    };
+    Ok((qualified(base_type, volatile), storage_class))
 }
 
-fn expect_main_keyword(token: &lex::Token) -> Result<(), String> {
-    if token.value != "main" {
-        return Err(format!("Expected main keyword, got '{}'", token.value));
+fn expect_token(tokens: &mut Vec<lex::Token>, kind: lex::TokenKind) -> Result<(), String> {
+    if tokens.is_empty() {
+        return Err(format!("Unexpected end of file; expected '{}'", kind));
     }
-    Ok(())
+    expect_token_kind(&tokens.remove(0), kind)
+}
+
+fn next_is(tokens: &[lex::Token], kind: lex::TokenKind) -> bool {
+    tokens.first().is_some_and(|token| token.kind == kind)
 }
 
-fn expect_void_keyword(token: &lex::Token) -> Result<(), String> {
-    if token.value != "void" {
-        return Err(format!("Expected void keyword, got '{}'", token.value));
+// The size between an array declarator's brackets, which must be a
+// positive integer constant expression
+fn parse_array_size(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<u64, String> {
+    expect_token(tokens, lex::TokenKind::OpenBracket)?;
+    let Some(span) = tokens.first().map(|token| token.span) else {
+        return Err("Unexpected end of file; expected array size".to_string());
+    };
+    // The size is folded here and not kept, so it gets an arena of its own
+    let mut exps = ExpArena::new();
+    let size = parse_expression(tokens, dialect, &mut exps, 0)?;
+    let size = eval_constant(&exps, size).map_err(|err| match err.span {
+        Some(_) => err.to_string(),
+        None => format!("{}: {}", span, err.message),
+    })?;
+    if size <= 0 {
+        return Err(format!("{}: Array size must be positive, got {}", span, size));
     }
-    Ok(())
+    expect_token(tokens, lex::TokenKind::CloseBracket)?;
+    Ok(size as u64)
 }
 
-fn expect_identifier(token: &lex::Token, expected: Option<&str>) -> Result<(), String> {
-    match expected {
-        Some(n) if token.token_type != lex::TokenType::IDENTIFIER || token.value != n => {
-            Err(format!("Expected identifier '{}', got '{}'", n, token.value))
+// Whether the parameter starting at tokens[0] has a name, which is the case
+// when an identifier comes before the ',' or ')' that ends it
+fn param_is_named(tokens: &[lex::Token]) -> bool {
+    let mut depth = 0;
+    for token in tokens {
+        match token.kind {
+            lex::TokenKind::Identifier => return true,
+            lex::TokenKind::OpenParen | lex::TokenKind::OpenBracket => depth += 1,
+            lex::TokenKind::CloseParen | lex::TokenKind::CloseBracket if depth == 0 => return false,
+            lex::TokenKind::CloseParen | lex::TokenKind::CloseBracket => depth -= 1,
+            lex::TokenKind::Comma if depth == 0 => return false,
+            _ => {}
         }
-        None if token.token_type != lex::TokenType::IDENTIFIER => {
-            Err(format!("Expected identifier, got '{}'", token.value))
+    }
+    false
+}
+
+// ( void ) or ( param { , param } ). An empty list is taken to mean no
+// parameters, as it does in a definition.
+fn parse_param_list(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<Vec<Param>, String> {
+    expect_token(tokens, lex::TokenKind::OpenParen)?;
+    if next_is(tokens, lex::TokenKind::CloseParen)
+        || (next_is(tokens, lex::TokenKind::Keyword(lex::Keyword::Void)) && tokens.get(1).is_some_and(|token| token.kind == lex::TokenKind::CloseParen)) {
+        if !next_is(tokens, lex::TokenKind::CloseParen) {
+            tokens.remove(0);
         }
-        _ => Ok(()),
+        tokens.remove(0);
+        return Ok(Vec::new());
+    }
+    let mut params = Vec::new();
+    loop {
+        let param_type = parse_type_specifier(tokens, dialect)?;
+        params.push(if param_is_named(tokens) {
+            Param::Named(param_type, parse_declarator(tokens, dialect)?)
+        } else {
+            Param::Unnamed(param_type, parse_abstract_declarator(tokens, dialect)?)
+        });
+        if !next_is(tokens, lex::TokenKind::Comma) {
+            break;
+        }
+        tokens.remove(0);
+    }
+    expect_token(tokens, lex::TokenKind::CloseParen)?;
+    Ok(params)
+}
+
+// declarator := "*" declarator | simple-declarator [ param-list | { "[" size "]" } ]
+// simple-declarator := identifier | "(" declarator ")"
+pub fn parse_declarator(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<Declarator, String> {
+    if next_is(tokens, lex::TokenKind::Star) {
+        tokens.remove(0);
+        let volatile = parse_pointer_qualifiers(tokens)?;
+        return Ok(Declarator::Pointer(Box::new(parse_declarator(tokens, dialect)?), volatile));
+    }
+    let Some(token) = tokens.first().cloned() else {
+        return Err("Unexpected end of file; expected identifier".to_string());
+    };
+    let simple = if token.kind == lex::TokenKind::OpenParen {
+        tokens.remove(0);
+        let inner = parse_declarator(tokens, dialect)?;
+        expect_token(tokens, lex::TokenKind::CloseParen)?;
+        inner
+    } else {
+        let span = token.span;
+        Declarator::Ident(expect_identifier(tokens.remove(0))?, span)
+    };
+    if next_is(tokens, lex::TokenKind::OpenParen) {
+        return Ok(Declarator::Function(parse_param_list(tokens, dialect)?, Box::new(simple)));
+    }
+    let mut declarator = simple;
+    while next_is(tokens, lex::TokenKind::OpenBracket) {
+        declarator = Declarator::Array(Box::new(declarator), parse_array_size(tokens, dialect)?);
+    }
+    Ok(declarator)
+}
+
+// abstract-declarator := "*" [ abstract-declarator ] | direct-abstract-declarator
+// direct-abstract-declarator := "(" abstract-declarator ")" { "[" size "]" } | { "[" size "]" }+
+pub fn parse_abstract_declarator(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<AbstractDeclarator, String> {
+    if next_is(tokens, lex::TokenKind::Star) {
+        tokens.remove(0);
+        let volatile = parse_pointer_qualifiers(tokens)?;
+        return Ok(AbstractDeclarator::Pointer(Box::new(parse_abstract_declarator(tokens, dialect)?), volatile));
+    }
+    let mut declarator = if next_is(tokens, lex::TokenKind::OpenParen) {
+        tokens.remove(0);
+        let inner = parse_abstract_declarator(tokens, dialect)?;
+        expect_token(tokens, lex::TokenKind::CloseParen)?;
+        inner
+    } else {
+        AbstractDeclarator::Base
+    };
+    while next_is(tokens, lex::TokenKind::OpenBracket) {
+        declarator = AbstractDeclarator::Array(Box::new(declarator), parse_array_size(tokens, dialect)?);
     }
+    Ok(declarator)
 }
 
-fn expect_token_type(token: &lex::Token, token_type: lex::TokenType) -> Result<(), String> {
-    if token.token_type != token_type {
-        return Err(format!("Expected token type {:?}, got {:?}", token_type, token.token_type));
+// What a declarator declares once unrolled around its base type
+pub struct Declared {
+    pub name: Symbol,
+    pub span: lex::Span,
+    pub declared_type: Type,
+    // For a function, its parameter names (None for unnamed ones)
+    pub params: Vec<Option<Symbol>>,
+}
+
+pub fn process_declarator(declarator: Declarator, base_type: Type) -> Result<Declared, String> {
+    match declarator {
+        Declarator::Ident(name, span) => Ok(Declared { name, span, declared_type: base_type, params: Vec::new() }),
+        Declarator::Pointer(inner, volatile) => process_declarator(*inner, qualified(Type::Pointer(Box::new(base_type)), volatile)),
+        Declarator::Array(inner, size) => process_declarator(*inner, Type::Array(Box::new(base_type), size)),
+        Declarator::Function(params, inner) => {
+            let Declarator::Ident(name, span) = *inner else {
+                return Err("Function pointers are not supported yet".to_string());
+            };
+            let mut param_types = Vec::new();
+            let mut param_names = Vec::new();
+            for param in params {
+                let (param_name, param_type) = match param {
+                    Param::Named(param_type, declarator) => {
+                        let declared = process_declarator(declarator, param_type)?;
+                        (Some(declared.name), declared.declared_type)
+                    }
+                    Param::Unnamed(param_type, declarator) => (None, process_abstract_declarator(declarator, param_type)),
+                };
+                if matches!(param_type, Type::Function(..)) {
+                    return Err(format!("{}: Function parameters can't have function type", span));
+                }
+                param_types.push(param_type);
+                param_names.push(param_name);
+            }
+            Ok(Declared { name, span, declared_type: Type::Function(param_types, Box::new(base_type)), params: param_names })
+        }
+    }
+}
+
+pub fn process_abstract_declarator(declarator: AbstractDeclarator, base_type: Type) -> Type {
+    match declarator {
+        AbstractDeclarator::Base => base_type,
+        AbstractDeclarator::Pointer(inner, volatile) => process_abstract_declarator(*inner, qualified(Type::Pointer(Box::new(base_type)), volatile)),
+        AbstractDeclarator::Array(inner, size) => process_abstract_declarator(*inner, Type::Array(Box::new(base_type), size)),
+    }
+}
+
+// Consume an identifier token and return its name
+fn expect_identifier(token: lex::Token) -> Result<Symbol, String> {
+    match (token.kind, token.name()) {
+        (lex::TokenKind::Identifier, Some(name)) => Ok(name),
+        _ => Err(format!("{}: Expected identifier, got '{}'", token.span, token)),
+    }
+}
+
+fn expect_token_kind(token: &lex::Token, kind: lex::TokenKind) -> Result<(), String> {
+    if token.kind != kind {
+        return Err(format!("{}: Expected '{}', got '{}'", token.span, kind, token));
     }
     Ok(())
 }
 
-fn parse_factor(tokens: &mut Vec<lex::Token>) -> Result<Factor, String> {
+fn parse_factor(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<ExpId, String> {
     if tokens.is_empty() {
         return Err("Unexpected end of file while parsing factor".to_string());
     }
@@ -240,140 +783,315 @@ fn parse_factor(tokens: &mut Vec<lex::Token>) -> Result<Factor, String> {
     // Clone the token value we need, so we don't keep a reference to tokens
     let token = tokens[0].clone();
     
-    match token.token_type {
+    match token.kind {
         // Case 1: Integer constant
-        lex::TokenType::CONSTANT => {
+        lex::TokenKind::Constant => {
+            tokens.remove(0);
+            match lex::integer_constant_value(&token.text()).and_then(|value| i32::try_from(value).ok()) {
+                Some(value) => Ok(exps.alloc(Exp::Constant(value, token.span))),
+                None => Err(format!("{}: Integer constant '{}' is too large for type int", token.span, token)),
+            }
+        },
+        // C23's boolean constants, which are ints here as there is no bool yet
+        lex::TokenKind::Keyword(lex::Keyword::True | lex::Keyword::False) => {
             tokens.remove(0);
-            Ok(Factor::Int(token.value.parse().unwrap()))
+            Ok(exps.alloc(Exp::Constant((token.kind == lex::TokenKind::Keyword(lex::Keyword::True)) as i32, token.span)))
+        },
+        lex::TokenKind::StringLiteral => Err(format!("{}: String literals are not supported yet", token.span)),
+        lex::TokenKind::Keyword(lex::Keyword::Nullptr) => {
+            Err(format!("{}: 'nullptr' is not supported yet, as there are no pointers", token.span))
+        },
+        // A character constant is an int too, such as 'a' for 97
+        lex::TokenKind::CharConstant => {
+            tokens.remove(0);
+            Ok(exps.alloc(Exp::Constant(lex::char_constant_value(&token.text()).expect("the lexer checks character constants"), token.span)))
         },
         // Case 2: Identifier
-        lex::TokenType::IDENTIFIER => {
+        lex::TokenKind::Identifier => {
             tokens.remove(0);
-            Ok(Factor::Exp(Box::new(Exp::Var(token.value))))
+            let span = token.span;
+            let name = expect_identifier(token)?;
+            let primary = if next_is(tokens, lex::TokenKind::OpenParen) {
+                let args = parse_arguments(tokens, dialect, exps)?;
+                exps.alloc(Exp::Call(name, args, span))
+            } else {
+                exps.alloc(Exp::Var(name, span))
+            };
+            parse_postfix(tokens, exps, primary)
         },
         // Case 3: Unary operators
-        lex::TokenType::NegationOp => {
+        lex::TokenKind::NegationOp => {
+            tokens.remove(0);
+            // Fold the sign into a constant right after it, so that
+            // -2147483648 is INT_MIN even though 2147483648 is out of range
+            if let Some(constant) = tokens.first().filter(|token| token.kind == lex::TokenKind::Constant).cloned() {
+                if let Some(value) = lex::integer_constant_value(&constant.text()) {
+                    tokens.remove(0);
+                    return match i32::try_from(-value) {
+                        Ok(value) => Ok(exps.alloc(Exp::Constant(value, lex::Span { end: constant.span.end, ..token.span }))),
+                        Err(_) => Err(format!("{}: Integer constant '-{}' is too small for type int", constant.span, constant)),
+                    };
+                }
+            }
+            let factor = parse_factor(tokens, dialect, exps)?;
+            Ok(exps.alloc(Exp::Unary(UnaryOp::Negation, factor)))
+        },
+        lex::TokenKind::Plus => {
+            tokens.remove(0);
+            let factor = parse_factor(tokens, dialect, exps)?;
+            Ok(exps.alloc(Exp::Unary(UnaryOp::Plus, factor)))
+        },
+        lex::TokenKind::TildeOp => {
+            tokens.remove(0);
+            let factor = parse_factor(tokens, dialect, exps)?;
+            Ok(exps.alloc(Exp::Unary(UnaryOp::Complement, factor)))
+        },
+        lex::TokenKind::LogicalNot => {
             tokens.remove(0);
-            let factor = parse_factor(tokens)?;
-            Ok(Factor::Unary(UnaryOp::Negation, Box::new(factor)))
+            let factor = parse_factor(tokens, dialect, exps)?;
+            Ok(exps.alloc(Exp::Unary(UnaryOp::LogicalNot, factor)))
         },
-        lex::TokenType::TildeOp => {
+        // Case 4: Cast, where a type name follows the parenthesis
+        lex::TokenKind::OpenParen if tokens.get(1).is_some_and(is_specifier) => {
             tokens.remove(0);
-            let factor = parse_factor(tokens)?;
-            Ok(Factor::Unary(UnaryOp::Complement, Box::new(factor)))
+            let base_type = parse_type_specifier(tokens, dialect)?;
+            // A cast gives a value, which has no qualifiers
+            let target = process_abstract_declarator(parse_abstract_declarator(tokens, dialect)?, base_type).unqualified().clone();
+            expect_token(tokens, lex::TokenKind::CloseParen)?;
+            // Only int is implemented, and the other integer types are still rejected in declarations
+            if target != Type::Int {
+                return Err(format!("{}: Cast to {} is not supported yet", token.span, target));
+            }
+            let operand = parse_factor(tokens, dialect, exps)?;
+            Ok(exps.alloc(Exp::Cast(target, operand)))
         },
-        lex::TokenType::LogicalNot => {
+        // GNU's branch hint, whose expected value must be known when
+        // compiling: __builtin_expect(exp, value)
+        lex::TokenKind::Keyword(lex::Keyword::BuiltinExpect) => {
             tokens.remove(0);
-            let factor = parse_factor(tokens)?;
-            Ok(Factor::Unary(UnaryOp::LogicalNot, Box::new(factor)))
+            expect_token(tokens, lex::TokenKind::OpenParen)?;
+            let exp = parse_expression(tokens, dialect, exps, 0)?;
+            expect_token(tokens, lex::TokenKind::Comma)?;
+            let Some(span) = tokens.first().map(|token| token.span) else {
+                return Err("Unexpected end of file; expected the expected value of '__builtin_expect'".to_string());
+            };
+            // Only the value is kept, so it gets an arena of its own
+            let mut scratch = ExpArena::new();
+            let value = parse_expression(tokens, dialect, &mut scratch, 0)?;
+            let value = eval_constant(&scratch, value).map_err(|err| {
+                format!("{}: Expected value of '__builtin_expect' must be an integer constant: {}", err.span.unwrap_or(span), err.message)
+            })?;
+            expect_token(tokens, lex::TokenKind::CloseParen)?;
+            Ok(exps.alloc(Exp::Expect(exp, value)))
+        },
+        lex::TokenKind::Keyword(lex::Keyword::BuiltinTrap | lex::Keyword::BuiltinUnreachable) => {
+            Err(format!("{}: '{}' returns void, so it can't be used as a value", token.span, token))
         },
-        // Case 4: Parenthesized expression
-        lex::TokenType::OpenParen => {
+        // Case 5: Parenthesized expression
+        lex::TokenKind::OpenParen => {
             tokens.remove(0);
-            let exp = parse_expression(tokens, 0)?;
+            let exp = parse_expression(tokens, dialect, exps, 0)?;
             if tokens.is_empty() {
                 return Err("Unexpected end of file; expected closing parenthesis".to_string());
             }
-            expect_token_type(&tokens.remove(0), lex::TokenType::CloseParen)?;
-            Ok(Factor::Exp(Box::new(exp)))
+            expect_token_kind(&tokens.remove(0), lex::TokenKind::CloseParen)?;
+            parse_postfix(tokens, exps, exp)
         },
-        _ => Err(format!("Unexpected token while parsing factor: {:?}", token)),
+        _ => Err(format!("{}: Unexpected token while parsing factor: '{}'", token.span, token)),
     }
 }
 
+// The parenthesized, comma-separated arguments of a call
+fn parse_arguments(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<Vec<ExpId>, String> {
+    expect_token(tokens, lex::TokenKind::OpenParen)?;
+    let mut args = Vec::new();
+    if !next_is(tokens, lex::TokenKind::CloseParen) {
+        loop {
+            args.push(parse_expression(tokens, dialect, exps, 0)?);
+            if !next_is(tokens, lex::TokenKind::Comma) {
+                break;
+            }
+            tokens.remove(0);
+        }
+    }
+    expect_token(tokens, lex::TokenKind::CloseParen)?;
+    Ok(args)
+}
 
-fn get_operator_precedence(op: &BinaryOp) -> u8 {
+// Parse any `.member` / `->member` suffixes following a primary expression
+fn parse_postfix(tokens: &mut Vec<lex::Token>, exps: &mut ExpArena, primary: ExpId) -> Result<ExpId, String> {
+    let mut exp = primary;
+    while !tokens.is_empty()
+        && (tokens[0].kind == lex::TokenKind::Dot || tokens[0].kind == lex::TokenKind::Arrow) {
+        let op_token = tokens.remove(0);
+        if tokens.is_empty() {
+            return Err(format!("Unexpected end of file; expected member name after '{}'", op_token));
+        }
+        let member = expect_identifier(tokens.remove(0))?;
+        exp = exps.alloc(if op_token.kind == lex::TokenKind::Dot {
+            Exp::Dot(exp, member)
+        } else {
+            Exp::Arrow(exp, member)
+        });
+    }
+    Ok(exp)
+}
+
+
+// C's precedence levels, from the tightest binding down. The conditional
+// operator sits between || and assignment, at CONDITIONAL_PRECEDENCE.
+pub const fn get_operator_precedence(op: &BinaryOp) -> u8 {
     match op {
         BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 50,
         BinaryOp::Add | BinaryOp::Subtract => 45,
-        BinaryOp::LeftShift | BinaryOp::RightShift => 45,
-        BinaryOp::BitwiseAnd => 44,
-        BinaryOp::BitwiseXor => 43,
-        BinaryOp::BitwiseOr => 42,
+        BinaryOp::LeftShift | BinaryOp::RightShift => 40,
         BinaryOp::GreaterThan | BinaryOp::LessThan | BinaryOp::GreaterThanOrEqual | BinaryOp::LessThanOrEqual => 35,
         BinaryOp::Equal | BinaryOp::NotEqual => 30,
+        BinaryOp::BitwiseAnd => 25,
+        BinaryOp::BitwiseXor => 20,
+        BinaryOp::BitwiseOr => 15,
         BinaryOp::LogicalAnd => 10,
         BinaryOp::LogicalOr => 5,
-        BinaryOp::Assignment => 1,
+        BinaryOp::Assignment | BinaryOp::LeftShiftAssignment | BinaryOp::RightShiftAssignment => 1,
     }
 }
 
-// fn get_associativity(op: &BinaryOp) -> Associativity {
-//     match op {
-//         BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo |
-//         BinaryOp::LeftShift | BinaryOp::RightShift | BinaryOp::BitwiseAnd | BinaryOp::BitwiseXor | BinaryOp::BitwiseOr => Associativity::Left,
-//     }
-// }
+pub const CONDITIONAL_PRECEDENCE: u8 = 3;
+
+fn get_associativity(op: &BinaryOp) -> Associativity {
+    match op {
+        BinaryOp::Assignment | BinaryOp::LeftShiftAssignment | BinaryOp::RightShiftAssignment => Associativity::Right,
+        _ => Associativity::Left,
+    }
+}
+
+// An operator that can follow an operand: a binary operator, or the '?'
+// that starts a conditional, which parses like a right-associative binary
+// operator whose middle operand sits between '?' and ':'
+enum InfixOp {
+    Binary(BinaryOp),
+    Conditional,
+}
+
+fn parse_infix_op(token: &lex::Token) -> Option<InfixOp> {
+    if token.kind == lex::TokenKind::Question {
+        return Some(InfixOp::Conditional);
+    }
+    parse_op(token).ok().map(InfixOp::Binary)
+}
+
+fn infix_precedence(op: &InfixOp) -> (u8, Associativity) {
+    match op {
+        InfixOp::Binary(op) => (get_operator_precedence(op), get_associativity(op)),
+        InfixOp::Conditional => (CONDITIONAL_PRECEDENCE, Associativity::Right),
+    }
+}
 
 fn parse_op(token: &lex::Token) -> Result<BinaryOp, String> {
-    match token.token_type {
-        lex::TokenType::PLUS => Ok(BinaryOp::Add),
-        lex::TokenType::NegationOp => Ok(BinaryOp::Subtract),
-        lex::TokenType::STAR => Ok(BinaryOp::Multiply),
-        lex::TokenType::SLASH => Ok(BinaryOp::Divide),
-        lex::TokenType::MODULUS => Ok(BinaryOp::Modulo),
-        lex::TokenType::AMPERSAND => Ok(BinaryOp::BitwiseAnd),
-        lex::TokenType::PIPE => Ok(BinaryOp::BitwiseOr),
-        lex::TokenType::CARET => Ok(BinaryOp::BitwiseXor),
-        lex::TokenType::LeftShift => Ok(BinaryOp::LeftShift),
-        lex::TokenType::RightShift => Ok(BinaryOp::RightShift),
-        lex::TokenType::Equal => Ok(BinaryOp::Equal),
-        lex::TokenType::NotEqual => Ok(BinaryOp::NotEqual),
-        lex::TokenType::GreaterThan => Ok(BinaryOp::GreaterThan),
-        lex::TokenType::LessThan => Ok(BinaryOp::LessThan),
-        lex::TokenType::GreaterThanOrEqual => Ok(BinaryOp::GreaterThanOrEqual),
-        lex::TokenType::LessThanOrEqual => Ok(BinaryOp::LessThanOrEqual),
-        lex::TokenType::LogicalAnd => Ok(BinaryOp::LogicalAnd),
-        lex::TokenType::LogicalOr => Ok(BinaryOp::LogicalOr),
-        lex::TokenType::Assignment => Ok(BinaryOp::Assignment),
-        _ => Err(format!("Unexpected token: {:?}", token)),
-    }
-}
-
-fn parse_expression(tokens: &mut Vec<lex::Token>, min_precedence: u8) -> Result<Exp, String> {
-    let mut left = Exp::Factor(parse_factor(tokens)?);
-
-    while !tokens.is_empty() {
-        let op = match parse_op(&tokens[0]) {
-            Ok(op) => op,
-            Err(_) => break,
-        };
+    match token.kind {
+        lex::TokenKind::Plus => Ok(BinaryOp::Add),
+        lex::TokenKind::NegationOp => Ok(BinaryOp::Subtract),
+        lex::TokenKind::Star => Ok(BinaryOp::Multiply),
+        lex::TokenKind::Slash => Ok(BinaryOp::Divide),
+        lex::TokenKind::Modulus => Ok(BinaryOp::Modulo),
+        lex::TokenKind::Ampersand => Ok(BinaryOp::BitwiseAnd),
+        lex::TokenKind::Pipe => Ok(BinaryOp::BitwiseOr),
+        lex::TokenKind::Caret => Ok(BinaryOp::BitwiseXor),
+        lex::TokenKind::LeftShift => Ok(BinaryOp::LeftShift),
+        lex::TokenKind::RightShift => Ok(BinaryOp::RightShift),
+        lex::TokenKind::Equal => Ok(BinaryOp::Equal),
+        lex::TokenKind::NotEqual => Ok(BinaryOp::NotEqual),
+        lex::TokenKind::GreaterThan => Ok(BinaryOp::GreaterThan),
+        lex::TokenKind::LessThan => Ok(BinaryOp::LessThan),
+        lex::TokenKind::GreaterThanOrEqual => Ok(BinaryOp::GreaterThanOrEqual),
+        lex::TokenKind::LessThanOrEqual => Ok(BinaryOp::LessThanOrEqual),
+        lex::TokenKind::LogicalAnd => Ok(BinaryOp::LogicalAnd),
+        lex::TokenKind::LogicalOr => Ok(BinaryOp::LogicalOr),
+        lex::TokenKind::Assignment => Ok(BinaryOp::Assignment),
+        lex::TokenKind::LeftShiftAssign => Ok(BinaryOp::LeftShiftAssignment),
+        lex::TokenKind::RightShiftAssign => Ok(BinaryOp::RightShiftAssignment),
+        _ => Err(format!("{}: Unexpected token: '{}'", token.span, token)),
+    }
+}
 
-        let precedence = get_operator_precedence(&op);
+// Maps a compound assignment operator to the binary operation it applies
+fn compound_assignment_operator(op: &BinaryOp) -> Option<BinaryOp> {
+    match op {
+        BinaryOp::LeftShiftAssignment => Some(BinaryOp::LeftShift),
+        BinaryOp::RightShiftAssignment => Some(BinaryOp::RightShift),
+        _ => None,
+    }
+}
+
+// Precedence climbing: operators binding at least as tightly as
+// min_precedence extend the expression. The right operand of a
+// left-associative operator may only hold tighter operators, while a
+// right-associative one takes operators of its own level too, so that
+// a = b = c is a = (b = c) and a ? b : c ? d : e nests to the right.
+fn parse_expression(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena, min_precedence: u8) -> Result<ExpId, String> {
+    let mut left = parse_factor(tokens, dialect, exps)?;
+
+    while let Some(op) = tokens.first().and_then(parse_infix_op) {
+        let (precedence, associativity) = infix_precedence(&op);
         if precedence < min_precedence {
             break;
         }
-
-        if tokens[0].token_type == lex::TokenType::Assignment {
-            tokens.remove(0);
-            let right = parse_expression(tokens, precedence)?;
-            left = Exp::Assignment(Box::new(left), Box::new(right));
-            continue;
-        } else{
         tokens.remove(0);
-        let right = parse_expression(tokens, precedence + 1)?;
-        left = Exp::Binary(Box::new(left), op, Box::new(right));
-        }
+        let right_precedence = match associativity {
+            Associativity::Left => precedence + 1,
+            Associativity::Right => precedence,
+        };
+
+        let exp = match op {
+            // a ?: b is a ? a : b with a evaluated once
+            InfixOp::Conditional if next_is(tokens, lex::TokenKind::Colon) => {
+                if !dialect.gnu_extensions {
+                    return Err(format!("{}: Omitting the middle operand of '?:' is a GNU extension; enable it with --gnu-extensions", tokens[0].span));
+                }
+                tokens.remove(0);
+                let otherwise = parse_expression(tokens, dialect, exps, right_precedence)?;
+                Exp::Conditional(left, None, otherwise)
+            }
+            InfixOp::Conditional => {
+                let then = parse_expression(tokens, dialect, exps, 0)?;
+                expect_token(tokens, lex::TokenKind::Colon)?;
+                let otherwise = parse_expression(tokens, dialect, exps, right_precedence)?;
+                Exp::Conditional(left, Some(then), otherwise)
+            }
+            InfixOp::Binary(BinaryOp::Assignment) => {
+                Exp::Assignment(left, parse_expression(tokens, dialect, exps, right_precedence)?)
+            }
+            InfixOp::Binary(op) => {
+                let right = parse_expression(tokens, dialect, exps, right_precedence)?;
+                match compound_assignment_operator(&op) {
+                    Some(compound_op) => Exp::CompoundAssignment(compound_op, left, right),
+                    None => Exp::Binary(left, op, right),
+                }
+            }
+        };
+        left = exps.alloc(exp);
     }
     Ok(left)
 }
 
-fn parse_declaration(tokens: &mut Vec<lex::Token>) -> Result<Declaration, String> {
+fn parse_declaration(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<Declaration, String> {
     // Check if we have any tokens left
     if tokens.is_empty() {
         return Err("Unexpected end of file while parsing declaration".to_string());
     }
 
-    // Parse "int"
-    let int_token = tokens.remove(0);
-    expect_int_keyword(&int_token)?;
-
-    // Parse identifier
-    if tokens.is_empty() {
-        return Err("Unexpected end of file; expected identifier".to_string());
+    let (base_type, storage_class) = parse_specifiers(tokens, dialect)?;
+    // Every other local is automatic, and codegen decides what lives in a
+    // register
+    let storage_class = match storage_class {
+        Some(lex::Keyword::Extern) => Some(StorageClass::Extern),
+        Some(lex::Keyword::Static) => Some(StorageClass::Static),
+        _ => None,
+    };
+    let Declared { name, span, declared_type, .. } = process_declarator(parse_declarator(tokens, dialect)?, base_type)?;
+    if *declared_type.unqualified() != Type::Int {
+        return Err(format!("{}: Declaring '{}' as {} is not supported yet", span, name, declared_type));
     }
-    let name_token = tokens.remove(0);
-    expect_identifier(&name_token, None)?;
 
     // Check for optional assignment
     if tokens.is_empty() {
@@ -381,7 +1099,7 @@ fn parse_declaration(tokens: &mut Vec<lex::Token>) -> Result<Declaration, String
     }
 
     let next_token = &tokens[0];
-    let exp = if next_token.token_type == lex::TokenType::Assignment {
+    let exp = if next_token.kind == lex::TokenKind::Assignment {
         // Remove the '=' token
         tokens.remove(0);
         
@@ -389,22 +1107,26 @@ fn parse_declaration(tokens: &mut Vec<lex::Token>) -> Result<Declaration, String
         if tokens.is_empty() {
             return Err("Unexpected end of file; expected expression after '='".to_string());
         }
-        Some(parse_expression(tokens, 0)?)
+        Some(parse_expression(tokens, dialect, exps, 0)?)
     } else {
         None
     };
+    // The object is defined elsewhere, so this can't initialize it
+    if exp.is_some() && storage_class == Some(StorageClass::Extern) {
+        return Err(format!("{}: Block-scope extern declaration of '{}' can't have an initializer", span, name));
+    }
 
     // Parse semicolon
     if tokens.is_empty() {
         return Err("Unexpected end of file; expected ';'".to_string());
     }
     let semicolon_token = tokens.remove(0);
-    expect_token_type(&semicolon_token, lex::TokenType::SEMICOLON)?;
+    expect_token_kind(&semicolon_token, lex::TokenKind::Semicolon)?;
 
-    Ok(Declaration::Declaration(name_token.value, exp))
+    Ok(Declaration::Declaration(name, declared_type, exp, storage_class, span))
 }
 
-fn parse_statement(tokens: &mut Vec<lex::Token>) -> Result<Statement, String> {
+fn parse_statement(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<Statement, String> {
     // Check if we have any tokens
     if tokens.is_empty() {
         return Err("Unexpected end of file while parsing statement".to_string());
@@ -413,243 +1135,489 @@ fn parse_statement(tokens: &mut Vec<lex::Token>) -> Result<Statement, String> {
     // Get first token without removing it
     let token = &tokens[0];
 
-    match token.token_type {
+    match token.kind {
         // Case 3: Just a semicolon
-        lex::TokenType::SEMICOLON => {
+        lex::TokenKind::Semicolon => {
             tokens.remove(0); // Remove semicolon
             Ok(Statement::Null)
         },
         // Case 1: Return statement
-        lex::TokenType::KEYWORD if token.value == "return" => {
+        lex::TokenKind::Keyword(lex::Keyword::Return) => {
             tokens.remove(0); // Remove 'return'
             if tokens.is_empty() {
                 return Err("Unexpected end of file after 'return'".to_string());
             }
-            let exp = parse_expression(tokens, 0)?;
+            let exp = parse_expression(tokens, dialect, exps, 0)?;
             if tokens.is_empty() {
                 return Err("Unexpected end of file; expected semicolon".to_string());
             }
-            expect_token_type(&tokens.remove(0), lex::TokenType::SEMICOLON)?;
+            expect_token_kind(&tokens.remove(0), lex::TokenKind::Semicolon)?;
             Ok(Statement::Return(exp))
         },
+        lex::TokenKind::Keyword(lex::Keyword::Asm) => parse_asm(tokens),
+        lex::TokenKind::Keyword(lex::Keyword::BuiltinTrap | lex::Keyword::BuiltinUnreachable) => parse_void_builtin(tokens),
         // Case 2: Expression statement
         _ => {
-            let exp = parse_expression(tokens, 0)?;
+            let exp = parse_expression(tokens, dialect, exps, 0)?;
             if tokens.is_empty() {
                 return Err("Unexpected end of file; expected semicolon".to_string());
             }
-            expect_token_type(&tokens.remove(0), lex::TokenType::SEMICOLON)?;
+            expect_token_kind(&tokens.remove(0), lex::TokenKind::Semicolon)?;
             Ok(Statement::Expression(exp))
         }
     }
 }
 
-fn parse_block_items(tokens: &mut Vec<lex::Token>) -> Result<Box<BlockItem>, String> {
-    if expect_int_keyword(&tokens[0]).is_ok(){
-        let declaration = parse_declaration(tokens)?;
+// Adjacent string literals are one string; at least one must be next
+fn parse_string_literals(tokens: &mut Vec<lex::Token>) -> Result<Vec<u8>, String> {
+    if !next_is(tokens, lex::TokenKind::StringLiteral) {
+        return match tokens.first() {
+            Some(token) => Err(format!("{}: Expected a string literal, got '{}'", token.span, token)),
+            None => Err("Unexpected end of file; expected a string literal".to_string()),
+        };
+    }
+    let mut bytes = Vec::new();
+    while next_is(tokens, lex::TokenKind::StringLiteral) {
+        let literal = tokens.remove(0);
+        bytes.extend(lex::string_literal_value(&literal.text()).expect("the lexer checks string literals"));
+    }
+    Ok(bytes)
+}
+
+// Basic asm: __asm__ [volatile] ("template"); which is always volatile, so
+// the qualifier changes nothing. Extended asm, with operands after a ':', is
+// not supported.
+fn parse_asm(tokens: &mut Vec<lex::Token>) -> Result<Statement, String> {
+    tokens.remove(0);
+    if next_is(tokens, lex::TokenKind::Keyword(lex::Keyword::Volatile)) {
+        tokens.remove(0);
+    }
+    expect_token(tokens, lex::TokenKind::OpenParen)?;
+    let template = String::from_utf8_lossy(&parse_string_literals(tokens)?).into_owned();
+    if let Some(token) = tokens.first().filter(|token| token.kind == lex::TokenKind::Colon) {
+        return Err(format!("{}: Extended asm with operands is not supported", token.span));
+    }
+    expect_token(tokens, lex::TokenKind::CloseParen)?;
+    expect_token(tokens, lex::TokenKind::Semicolon)?;
+    Ok(Statement::Asm(template))
+}
+
+// __builtin_trap(); or __builtin_unreachable(); which return void, so
+// they are statements of their own here
+fn parse_void_builtin(tokens: &mut Vec<lex::Token>) -> Result<Statement, String> {
+    let token = tokens.remove(0);
+    expect_token(tokens, lex::TokenKind::OpenParen)?;
+    expect_token(tokens, lex::TokenKind::CloseParen)?;
+    expect_token(tokens, lex::TokenKind::Semicolon)?;
+    match token.kind {
+        lex::TokenKind::Keyword(lex::Keyword::BuiltinTrap) => Ok(Statement::Trap),
+        _ => Ok(Statement::Unreachable),
+    }
+}
+
+// _Static_assert(condition, "message"); where C23 makes the message optional
+fn parse_static_assert(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<Declaration, String> {
+    let span = tokens.remove(0).span;
+    expect_token(tokens, lex::TokenKind::OpenParen)?;
+    let condition = parse_expression(tokens, dialect, exps, 0)?;
+    let message = if next_is(tokens, lex::TokenKind::Comma) {
+        tokens.remove(0);
+        Some(String::from_utf8_lossy(&parse_string_literals(tokens)?).into_owned())
+    } else if dialect.std < Standard::C23 {
+        return Err(format!("{}: A static assertion without a message requires C23", span));
+    } else {
+        None
+    };
+    expect_token(tokens, lex::TokenKind::CloseParen)?;
+    expect_token(tokens, lex::TokenKind::Semicolon)?;
+    Ok(Declaration::StaticAssert(condition, message, span))
+}
+
+fn parse_block_items(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<Box<BlockItem>, String> {
+    if next_is(tokens, lex::TokenKind::Keyword(lex::Keyword::StaticAssert)) {
+        Ok(Box::new(BlockItem::D(parse_static_assert(tokens, dialect, exps)?)))
+    } else if is_specifier(&tokens[0]) {
+        let declaration = parse_declaration(tokens, dialect, exps)?;
         Ok(Box::new(BlockItem::D(declaration)))
     } else {
-        let statement = parse_statement(tokens)?;
+        let statement = parse_statement(tokens, dialect, exps)?;
         Ok(Box::new(BlockItem::S(statement)))
     }
 }
 
-fn parse_function_declaration(tokens: &mut Vec<lex::Token>) -> Result<FunctionDeclaration, String> {
-    if tokens.is_empty() {
-        return Err("Unexpected end of file while parsing function declaration".to_string());
+// A file-scope declaration: the definition of main, or a function defined
+// elsewhere that main can call, or a static assertion
+#[derive(Debug, Clone)]
+pub enum ExternalDeclaration {
+    Definition(FunctionDeclaration),
+    Declaration(Declaration),
+}
+
+impl Declaration {
+    fn exps_mut(&mut self) -> Vec<&mut ExpId> {
+        match self {
+            Declaration::Declaration(_, _, init, _, _) => init.iter_mut().collect(),
+            Declaration::StaticAssert(condition, _, _) => vec![condition],
+        }
     }
-    expect_int_keyword(&tokens.remove(0))?;
-    if tokens.is_empty() {
-        return Err("Unexpected end of file; expected function name".to_string());
+
+    fn span_mut(&mut self) -> &mut lex::Span {
+        match self {
+            Declaration::Declaration(_, _, _, _, span) | Declaration::StaticAssert(_, _, span) => span,
+        }
     }
-    let name_token = tokens.remove(0);
-    //expect_identifier(&name_token, Some("main"))?;
-    expect_main_keyword(&name_token)?;
-    if tokens.is_empty() {
-        return Err("Unexpected end of file; expected opening parenthesis".to_string());
+}
+
+impl ExternalDeclaration {
+    // The expressions the declaration refers to directly, leaving out their
+    // operands
+    pub(crate) fn exps_mut(&mut self) -> Vec<&mut ExpId> {
+        match self {
+            ExternalDeclaration::Declaration(declaration) => declaration.exps_mut(),
+            ExternalDeclaration::Definition(FunctionDeclaration::Function(_, body, _)) => body.iter_mut()
+                .flat_map(|item| match item.as_mut() {
+                    BlockItem::D(declaration) => declaration.exps_mut(),
+                    BlockItem::S(Statement::Return(exp) | Statement::Expression(exp)) => vec![exp],
+                    BlockItem::S(_) => Vec::new(),
+                })
+                .collect(),
+        }
     }
-    expect_token_type(&tokens.remove(0), lex::TokenType::OpenParen)?;
-    if tokens.is_empty() {
-        return Err("Unexpected end of file; expected 'void' or closing parenthesis".to_string());
+
+    // The locations in the declaration outside its expressions
+    pub(crate) fn spans_mut(&mut self) -> Vec<&mut lex::Span> {
+        match self {
+            ExternalDeclaration::Declaration(declaration) => vec![declaration.span_mut()],
+            ExternalDeclaration::Definition(FunctionDeclaration::Function(_, body, span)) => std::iter::once(span)
+                .chain(body.iter_mut().filter_map(|item| match item.as_mut() {
+                    BlockItem::D(declaration) => Some(declaration.span_mut()),
+                    BlockItem::S(_) => None,
+                }))
+                .collect(),
+        }
     }
-    // expect_identifier(&tokens.remove(0), Some("void"))?;
-    expect_void_keyword(&tokens.remove(0))?;
+}
+
+fn parse_function_declaration(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<ExternalDeclaration, String> {
     if tokens.is_empty() {
-        return Err("Unexpected end of file; expected closing parenthesis".to_string());
+        return Err("Unexpected end of file while parsing function declaration".to_string());
+    }
+    // A definition that starts with its name, as in "main() { ... }", has
+    // an implicit int return type
+    let (base_type, storage_class) = match tokens.first() {
+        Some(token) if token.kind == lex::TokenKind::Identifier && !dialect.permissive => {
+            return Err(format!("{}: Return type missing; C99 removed implicit int (-fpermissive accepts it)", token.span));
+        }
+        Some(token) if token.kind == lex::TokenKind::Identifier => (Type::Int, None),
+        // A function has external linkage whether or not it says extern
+        Some(token) => {
+            let span = token.span;
+            match parse_specifiers(tokens, dialect)? {
+                (base_type, None) => (base_type, None),
+                (base_type, Some(lex::Keyword::Extern)) => (base_type, Some(StorageClass::Extern)),
+                (_, Some(storage_class)) => return Err(format!("{}: Storage class '{}' is not allowed at file scope", span, storage_class.as_str())),
+            }
+        }
+        None => return Err("Unexpected end of file while parsing function declaration".to_string()),
+    };
+    let Declared { name, span, declared_type, .. } = process_declarator(parse_declarator(tokens, dialect)?, base_type)?;
+    // A declaration without a body, of a function the linker finds
+    if next_is(tokens, lex::TokenKind::Semicolon) {
+        match &declared_type {
+            Type::Function(params, ret) if params.iter().all(|param| *param == Type::Int) && **ret == Type::Int => {}
+            Type::Function(..) => return Err(format!("{}: Only functions taking and returning int can be declared, got '{}' as {}", span, name, declared_type)),
+            _ => return Err(format!("{}: Declaring '{}' as {} at file scope is not supported yet", span, name, declared_type)),
+        }
+        tokens.remove(0);
+        return Ok(ExternalDeclaration::Declaration(Declaration::Declaration(name, declared_type, None, storage_class, span)));
+    }
+    match declared_type {
+        Type::Function(params, ret) if params.is_empty() && *ret == Type::Int => {}
+        Type::Function(..) => return Err(format!("{}: Only 'int main(void)' is supported, got '{}' as {}", span, name, declared_type)),
+        _ => return Err(format!("{}: Expected a function definition, got '{}' declared as {}", span, name, declared_type)),
+    }
+    if &*name.text() != "main" {
+        return Err(format!("{}: Only 'main' can be defined, got '{}'", span, name));
     }
-    expect_token_type(&tokens.remove(0), lex::TokenType::CloseParen)?;
     if tokens.is_empty() {
         return Err("Unexpected end of file; expected opening brace".to_string());
     }
-    expect_token_type(&tokens.remove(0), lex::TokenType::OpenBrace)?;
+    expect_token_kind(&tokens.remove(0), lex::TokenKind::OpenBrace)?;
     let mut block_items = Vec::new();
-    while tokens[0].token_type != lex::TokenType::CloseBrace {
-        block_items.push(parse_block_items(tokens)?);
+    while tokens.first().is_some_and(|token| token.kind != lex::TokenKind::CloseBrace) {
+        block_items.push(parse_block_items(tokens, dialect, exps)?);
     }
     if tokens.is_empty() {
         return Err("Unexpected end of file; expected closing brace".to_string());
     }
-    expect_token_type(&tokens.remove(0), lex::TokenType::CloseBrace)?;
-    if !tokens.is_empty() {
-        return Err(format!("Unexpected token: {:?}", tokens[0]));
+    expect_token_kind(&tokens.remove(0), lex::TokenKind::CloseBrace)?;
+    Ok(ExternalDeclaration::Definition(FunctionDeclaration::Function(name, block_items, span)))
+}
+
+// One file-scope declaration, with static assertions and function
+// declarations allowed before and after the function
+pub fn parse_external_declaration(tokens: &mut Vec<lex::Token>, dialect: &Dialect, exps: &mut ExpArena) -> Result<ExternalDeclaration, String> {
+    if next_is(tokens, lex::TokenKind::Keyword(lex::Keyword::StaticAssert)) {
+        return parse_static_assert(tokens, dialect, exps).map(ExternalDeclaration::Declaration);
+    }
+    parse_function_declaration(tokens, dialect, exps)
+}
+
+// A program put together one file-scope declaration at a time
+#[derive(Default)]
+pub struct ProgramBuilder {
+    declarations: Vec<Declaration>,
+    definition: Option<FunctionDeclaration>,
+    exps: ExpArena,
+}
+
+impl ProgramBuilder {
+    // Adds a declaration that starts at span
+    fn push(&mut self, declaration: ExternalDeclaration, span: lex::Span) -> Result<(), String> {
+        match declaration {
+            ExternalDeclaration::Declaration(declaration) => self.declarations.push(declaration),
+            ExternalDeclaration::Definition(_) if self.definition.is_some() => return Err(format!("{}: Redefinition of 'main'", span)),
+            ExternalDeclaration::Definition(definition) => self.definition = Some(definition),
+        }
+        Ok(())
+    }
+
+    // Parses the declarations in tokens, up to the end of the file
+    pub fn parse(&mut self, tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<(), String> {
+        while let Some(token) = tokens.first() {
+            let span = token.span;
+            let declaration = parse_external_declaration(tokens, dialect, &mut self.exps)?;
+            self.push(declaration, span)?;
+        }
+        Ok(())
+    }
+
+    // Adds a declaration that was parsed on its own, into exps
+    pub fn append(&mut self, mut declaration: ExternalDeclaration, exps: ExpArena, span: lex::Span) -> Result<(), String> {
+        let offset = self.exps.append(exps);
+        for exp in declaration.exps_mut() {
+            exp.0 += offset;
+        }
+        self.push(declaration, span)
+    }
+
+    pub fn finish(self) -> Result<Program, String> {
+        match self.definition {
+            Some(definition) => Ok(Program::Program(self.declarations, definition, self.exps, Interner::current())),
+            None => Err("Expected a function definition".to_string()),
+        }
     }
-    Ok(FunctionDeclaration::Function(name_token.value, block_items))
 }
 
-fn parse_program(tokens: &mut Vec<lex::Token>) -> Result<Program, String> {
+pub fn parse_program(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<Program, String> {
     if tokens.is_empty() {
         return Err("Empty program".to_string());
     }
-    let func_decl = parse_function_declaration(tokens)?;
-    Ok(Program::Program(func_decl))
-}
-// Helper function to generate unique variable names
-fn make_temporary(name: String, symbol_table: &HashMap<String, String>) -> String {
-    let mut counter = 0;
-    let mut temp_name = name.clone();
-    while symbol_table.contains_key(&temp_name) {
-        counter += 1;
-        temp_name = format!("{}.{}", name, counter);
-    }
-    temp_name
-}
-
-// Expression resolution with improved error handling
-fn resolve_expression(exp: Exp, symbol_table: &HashMap<String, String>) -> Result<Exp, String> {
-    match exp {
-        Exp::Assignment(left, right) => {
-            let resolved_left = resolve_expression(*left, symbol_table)?;
-            let resolved_right = resolve_expression(*right, symbol_table)?;
-            
-            // Extract the variable name from the resolved left expression
-            let var_name = match &resolved_left {
-                Exp::Var(_) => Ok(resolved_left),
-                Exp::Factor(Factor::Exp(box_exp)) => {
-                    match **box_exp {
-                        Exp::Var(_) => Ok(resolved_left),
-                        _ => Err("Left side of assignment must resolve to a variable".to_string())
-                    }
-                }
-                _ => Err("Left side of assignment must resolve to a variable".to_string())
-            }?;
+    let mut program = ProgramBuilder::default();
+    program.parse(tokens, dialect)?;
+    program.finish()
+}
+// The left side of an assignment must have resolved to a variable
+fn expect_lvalue(exps: &ExpArena, left: ExpId) -> Result<(), String> {
+    match &exps[left] {
+        Exp::Var(..) => Ok(()),
+        _ => Err("Left side of assignment must resolve to a variable".to_string())
+    }
+}
 
-            Ok(Exp::Assignment(Box::new(var_name), Box::new(resolved_right)))
+// Expression resolution, which renames the variables in exp in place
+fn resolve_expression(exp: ExpId, exps: &mut ExpArena, scope: &Scope, symbols: &mut SymbolTable) -> Result<(), String> {
+    match exps[exp].clone() {
+        Exp::Assignment(left, right) | Exp::CompoundAssignment(_, left, right) => {
+            resolve_expression(left, exps, scope, symbols)?;
+            resolve_expression(right, exps, scope, symbols)?;
+            expect_lvalue(exps, left)
+        },
+        Exp::Var(name, span) => match scope.lookup(name) {
+            Some(entry) if is_function(&entry, symbols) => {
+                Err(format!("{}: Function '{}' can only be called, as there are no function pointers yet", span, name))
+            },
+            Some(entry) => {
+                symbols.reference(entry.unique_name, span);
+                exps[exp] = Exp::Var(entry.unique_name, span);
+                Ok(())
+            },
+            None => Err(format!("{}: Variable '{}' not declared", span, name)),
         },
-        Exp::Var(name) => {
-            if !symbol_table.contains_key(&name) {
-                return Err(format!("Variable '{}' not declared", name));
+        // C99 removed implicit declarations, so the function must be declared
+        Exp::Call(name, args, span) => {
+            match scope.lookup(name) {
+                Some(entry) if is_function(&entry, symbols) => symbols.reference(entry.unique_name, span),
+                Some(_) => return Err(format!("{}: Called object '{}' is not a function", span, name)),
+                None => return Err(format!("{}: Function '{}' not declared", span, name)),
             }
-            Ok(Exp::Var(symbol_table[&name].clone()))
+            for arg in args {
+                resolve_expression(arg, exps, scope, symbols)?;
+            }
+            Ok(())
         },
-        Exp::Binary(left, op, right) => {
-            let resolved_left = resolve_expression(*left, symbol_table)?;
-            let resolved_right = resolve_expression(*right, symbol_table)?;
-            Ok(Exp::Binary(Box::new(resolved_left), op, Box::new(resolved_right)))
+        Exp::Binary(left, _, right) => {
+            resolve_expression(left, exps, scope, symbols)?;
+            resolve_expression(right, exps, scope, symbols)
         },
-        Exp::Factor(factor) => {
-            match factor {
-                Factor::Int(value) => Ok(Exp::Factor(Factor::Int(value))),
-                Factor::Unary(op, factor) => {
-                    let resolved = resolve_expression(Exp::Factor(*factor), symbol_table)?;
-                    match resolved {
-                        Exp::Factor(f) => Ok(Exp::Factor(Factor::Unary(op, Box::new(f)))),
-                        _ => Err("Expected a Factor after resolving unary expression".to_string())
-                    }
-                },
-                Factor::Exp(exp) => {
-                    let resolved = resolve_expression(*exp, symbol_table)?;
-                    Ok(Exp::Factor(Factor::Exp(Box::new(resolved))))
-                }
+        Exp::Conditional(condition, then, otherwise) => {
+            resolve_expression(condition, exps, scope, symbols)?;
+            if let Some(then) = then {
+                resolve_expression(then, exps, scope, symbols)?;
             }
-        }
+            resolve_expression(otherwise, exps, scope, symbols)
+        },
+        // Every value is an int for now, so there is never a struct to take a member of
+        Exp::Dot(inner, member) => {
+            resolve_expression(inner, exps, scope, symbols)?;
+            Err(format!("Request for member '{}' in something not a structure or union", member))
+        },
+        Exp::Arrow(inner, member) => {
+            resolve_expression(inner, exps, scope, symbols)?;
+            Err(format!("Invalid type argument of '->' for member '{}'", member))
+        },
+        Exp::Constant(..) => Ok(()),
+        Exp::Unary(_, inner) | Exp::Cast(_, inner) | Exp::Expect(inner, _) => resolve_expression(inner, exps, scope, symbols),
     }
 }
 
 
+// Whether a name in scope is a function, which only names with linkage can be
+fn is_function(entry: &scope::Entry, symbols: &SymbolTable) -> bool {
+    entry.has_linkage && symbols.get(entry.unique_name).is_some_and(|symbol| matches!(symbol.declared_type, Type::Function(..)))
+}
+
 // Declaration resolution with improved error handling
-fn resolve_declaration(
-    name: String, 
-    init: Option<Exp>, 
-    symbol_table: &mut HashMap<String, String>
-) -> Result<Declaration, String> {
-    // Check for redeclaration
-    if symbol_table.contains_key(&name) {
-        return Err(format!("Variable '{}' already declared", name));
-    }
-
-    // Generate unique identifier
-    let unique_id = make_temporary(name.clone(), symbol_table);
-    
-    // Add to symbol table before resolving initialization
-    symbol_table.insert(name, unique_id.clone());
-    
-    // Resolve initialization if present
-    let resolved_init = match init {
-        Some(init_exp) => Some(resolve_expression(init_exp, symbol_table)?),
-        None => None
+fn resolve_declaration(declaration: Declaration, exps: &mut ExpArena, scope: &mut Scope, symbols: &mut SymbolTable) -> Result<Declaration, String> {
+    let Declaration::Declaration(name, declared_type, init, storage_class, span) = declaration else {
+        check_static_assert(&declaration, exps)?;
+        return Ok(declaration);
     };
+    // An extern declaration names the file-scope object, and gets no storage
+    // of its own
+    if storage_class == Some(StorageClass::Extern) {
+        let unique_id = scope.declare_extern(name)?;
+        symbols.declare(unique_id, declared_type.clone(), storage_class, false, span).map_err(|err| format!("{}: {}", span, err))?;
+        return Ok(Declaration::Declaration(unique_id, declared_type, None, storage_class, span));
+    }
+    // Declared before resolving the initializer, which may refer to it
+    let unique_id = scope.declare(name)?;
 
-    Ok(Declaration::Declaration(unique_id, resolved_init))
+    // Resolve initialization if present
+    if let Some(init_exp) = init {
+        resolve_expression(init_exp, exps, scope, symbols)?;
+        // A static variable is initialized before the program starts
+        if storage_class == Some(StorageClass::Static) {
+            eval_constant(exps, init_exp).map_err(|err| {
+                format!("{}: Initializer of static variable '{}' is not constant: {}", err.span.unwrap_or(span), name, err.message)
+            })?;
+        }
+    }
+
+    Ok(Declaration::Declaration(unique_id, declared_type, init, storage_class, span))
 }
 
 // Statement resolution with improved error handling
-fn resolve_statement(statement: Statement, symbol_table: &HashMap<String, String>) -> Result<Statement, String> {
+fn resolve_statement(statement: &Statement, exps: &mut ExpArena, scope: &Scope, symbols: &mut SymbolTable) -> Result<(), String> {
     match statement {
-        Statement::Return(exp) => {
-            let resolved_exp = resolve_expression(exp, symbol_table)?;
-            Ok(Statement::Return(resolved_exp))
-        },
-        Statement::Expression(exp) => {
-            let resolved_exp = resolve_expression(exp, symbol_table)?;
-            Ok(Statement::Expression(resolved_exp))
-        },
-        Statement::Null => Ok(Statement::Null)
+        Statement::Return(exp) | Statement::Expression(exp) => resolve_expression(*exp, exps, scope, symbols),
+        Statement::Asm(_) | Statement::Trap | Statement::Unreachable | Statement::Null => Ok(())
     }
 }
 
 // Block item resolution with proper error propagation
-fn resolve_block_item(item: BlockItem, symbol_table: &mut HashMap<String, String>) -> Result<BlockItem, String> {
+fn resolve_block_item(item: BlockItem, exps: &mut ExpArena, scope: &mut Scope, symbols: &mut SymbolTable) -> Result<BlockItem, String> {
     match item {
-        BlockItem::D(Declaration::Declaration(name, init)) => {
-            let resolved = resolve_declaration(name, init, symbol_table)?;
+        BlockItem::D(declaration) => {
+            let resolved = resolve_declaration(declaration, exps, scope, symbols)?;
             Ok(BlockItem::D(resolved))
         },
         BlockItem::S(statement) => {
-            let resolved = resolve_statement(statement, symbol_table)?;
-            Ok(BlockItem::S(resolved))
+            resolve_statement(&statement, exps, scope, symbols)?;
+            Ok(BlockItem::S(statement))
         }
     }
 }
 
+// A static assertion fails to compile when its condition is zero
+fn check_static_assert(declaration: &Declaration, exps: &ExpArena) -> Result<(), String> {
+    let Declaration::StaticAssert(condition, message, span) = declaration else {
+        return Ok(());
+    };
+    let value = eval_constant(exps, *condition).map_err(|err| match err.span {
+        Some(_) => err.to_string(),
+        None => format!("{}: {}", span, err.message),
+    })?;
+    match (value, message) {
+        (0, Some(message)) => Err(format!("{}: Static assertion failed: \"{}\"", span, message)),
+        (0, None) => Err(format!("{}: Static assertion failed", span)),
+        _ => Ok(()),
+    }
+}
+
 // Function declaration resolution with proper scope handling
-fn resolve_function_declaration(func_decl: FunctionDeclaration) -> Result<FunctionDeclaration, String> {
+fn resolve_function_declaration(func_decl: FunctionDeclaration, exps: &mut ExpArena, scope: &mut Scope, symbols: &mut SymbolTable) -> Result<FunctionDeclaration, String> {
     match func_decl {
-        FunctionDeclaration::Function(name, block_items) => {
-            let mut symbol_table = HashMap::new();
+        FunctionDeclaration::Function(name, block_items, span) => {
+            scope.enter_scope();
             let mut resolved_items = Vec::new();
 
             for item in block_items.into_iter() {
-                let resolved_item = resolve_block_item(*item, &mut symbol_table)?;
+                let resolved_item = resolve_block_item(*item, exps, scope, symbols)?;
                 resolved_items.push(Box::new(resolved_item));
             }
+            scope.exit_scope();
 
-            Ok(FunctionDeclaration::Function(name, resolved_items))
+            Ok(FunctionDeclaration::Function(name, resolved_items, span))
         }
     }
 }
 
 // Program resolution with proper error propagation
 pub fn resolve_program(program: Program) -> Result<Program, String> {
+    resolve_program_with_symbols(program).map(|(program, _)| program)
+}
+
+// Resolves program and also returns the identifiers it declares with
+// linkage, for tools that present its structure
+pub fn resolve_program_with_symbols(program: Program) -> Result<(Program, SymbolTable), String> {
+    program.interner().clone().enter(|| resolve_program_in(program))
+}
+
+fn resolve_program_in(program: Program) -> Result<(Program, SymbolTable), String> {
     match program {
-        Program::Program(func_decl) => {
-            let resolved_func = resolve_function_declaration(func_decl)?;
-            Ok(Program::Program(resolved_func))
+        Program::Program(declarations, func_decl, mut exps, interner) => {
+            for declaration in &declarations {
+                check_static_assert(declaration, &exps)?;
+            }
+            // Functions are declared in source order, and main's body only
+            // sees those declared before it, and main itself
+            let mut symbols = SymbolTable::new();
+            let mut scope = Scope::new();
+            let FunctionDeclaration::Function(name, _, main_span) = &func_decl;
+            let main_span = *main_span;
+            let mut functions: Vec<(Symbol, Type, Option<StorageClass>, bool, lex::Span)> = declarations.iter()
+                .filter_map(|declaration| match declaration {
+                    Declaration::Declaration(name, declared_type, _, storage_class, span) => Some((*name, declared_type.clone(), *storage_class, false, *span)),
+                    Declaration::StaticAssert(..) => None,
+                })
+                .collect();
+            functions.push((*name, Type::Function(Vec::new(), Box::new(Type::Int)), None, true, main_span));
+            functions.sort_by_key(|(.., span)| span.start);
+            for (name, declared_type, storage_class, defines, span) in functions {
+                symbols.declare(name, declared_type, storage_class, defines, span).map_err(|err| format!("{}: {}", span, err))?;
+                if span.start <= main_span.start {
+                    scope.declare_extern(name)?;
+                }
+            }
+            let resolved_func = resolve_function_declaration(func_decl, &mut exps, &mut scope, &mut symbols)?;
+            Ok((Program::Program(declarations, resolved_func, exps, interner), symbols))
         }
     }
 }
 
 // Main entry point for parsing and resolving
-pub fn parse_and_resolve_program(tokens: &mut Vec<lex::Token>) -> Result<Program, String> {
-    let parsed_program = parse_program(tokens)?;
+pub fn parse_and_resolve_program(tokens: &mut Vec<lex::Token>, dialect: &Dialect) -> Result<Program, String> {
+    let parsed_program = parse_program(tokens, dialect)?;
     resolve_program(parsed_program)
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+use crate::assembly::emit_assembly;
+use crate::dialect::Dialect;
+use crate::driver::{self, json_string, Diagnostic};
+use crate::target::{CodegenOptions, Target};
+use crate::timing::PassTimer;
+
+// The compiler as a pure function from source text to assembly text, for
+// hosts with no processes or filesystem such as a browser playground built
+// for wasm32-unknown-unknown. Nothing here prints, exits or touches files.
+
+pub struct Output {
+    // None when the program was rejected
+    pub assembly: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Output {
+    pub fn to_json(&self) -> String {
+        let assembly = self.assembly.as_deref().map_or("null".to_string(), json_string);
+        let diagnostics: Vec<String> = self.diagnostics.iter().map(Diagnostic::to_json).collect();
+        format!("{{\"assembly\":{},\"diagnostics\":[{}]}}", assembly, diagnostics.join(","))
+    }
+}
+
+// Compiles source for the target named by triple, or for Linux when the
+// triple isn't one we support
+pub fn compile(source: &str, triple: &str) -> Output {
+    let options = CodegenOptions::new(Target::from_triple(triple).unwrap_or(Target::Linux));
+    match driver::compile(source, &Dialect::default(), &mut PassTimer::new(false)) {
+        Ok(program) => {
+            let artifacts = emit_assembly(&program, &options);
+            Output { assembly: Some(artifacts.assembly), diagnostics: artifacts.diagnostics }
+        },
+        Err(err) => Output { assembly: None, diagnostics: vec![Diagnostic::from(&err)] },
+    }
+}
+
+// A minimal C ABI for the wasm module, so it can be driven without any
+// binding generator. The host copies UTF-8 source into a buffer from
+// playground_alloc, calls playground_compile, and reads back a buffer holding
+// a 4-byte little-endian length followed by Output::to_json(). Every buffer is
+// released with playground_free and its full length, which for a result
+// includes the 4 length bytes.
+#[cfg(target_arch = "wasm32")]
+mod exports {
+    use crate::target::Target;
+    use std::{ptr, slice};
+
+    fn leak(bytes: Vec<u8>) -> *mut u8 {
+        Box::into_raw(bytes.into_boxed_slice()) as *mut u8
+    }
+
+    #[no_mangle]
+    pub extern "C" fn playground_alloc(len: usize) -> *mut u8 {
+        leak(vec![0; len])
+    }
+
+    // Safety: ptr and len must come from playground_alloc or the result of
+    // playground_compile, and each buffer is freed once
+    #[no_mangle]
+    pub unsafe extern "C" fn playground_free(ptr: *mut u8, len: usize) {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+
+    // Safety: ptr must point to len initialized bytes
+    #[no_mangle]
+    pub unsafe extern "C" fn playground_compile(ptr: *const u8, len: usize) -> *mut u8 {
+        let source = String::from_utf8_lossy(slice::from_raw_parts(ptr, len));
+        let json = super::compile(&source, Target::Linux.triple()).to_json();
+        let mut result = Vec::with_capacity(4 + json.len());
+        result.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        result.extend_from_slice(json.as_bytes());
+        leak(result)
+    }
+}
@@ -0,0 +1,188 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::target::Target;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+// Preprocessing is handed to the target's C compiler driver, the same one
+// that assembles and links, run with -E. It defines the standard predefined
+// macros itself (__FILE__, __LINE__, __STDC__, __DATE__, ...). Its output
+// keeps linemarkers, which the lexer follows so that diagnostics point at
+// lines of the original file.
+//
+// Headers are searched for in the standard order: #include "..." looks in
+// the including file's directory first, then both forms look in the -I
+// directories, the -isystem directories and the system directories, each
+// in command-line order.
+
+// A -D or -U flag. They are applied in command-line order, so a later -U
+// removes an earlier -D of the same name and the other way round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroFlag {
+    Define(String, Option<String>),
+    Undefine(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreprocessOptions {
+    pub macros: Vec<MacroFlag>,
+    pub include_dirs: Vec<PathBuf>,
+    pub system_include_dirs: Vec<PathBuf>,
+    // -MD: also write a Makefile rule making dependency_target depend on the
+    // source file and every header it read, for Make and Ninja. It goes to
+    // dependency_file (-MF), by default the input with a .d extension.
+    pub write_dependencies: bool,
+    pub dependency_file: Option<PathBuf>,
+    // -MT, by default the output file
+    pub dependency_target: Option<String>,
+    // -ffreestanding: there is no hosted C library, so __STDC_HOSTED__ is 0
+    // and only the freestanding headers can be relied on
+    pub freestanding: bool,
+}
+
+impl PreprocessOptions {
+    pub fn new() -> PreprocessOptions {
+        PreprocessOptions::default()
+    }
+
+    // Applies -DNAME[=VALUE], -UNAME, -IDIR, -isystem DIR, -MD, -MF FILE or
+    // -MT TARGET, where the argument may also be the next one (-D NAME),
+    // which next supplies. Returns false for any other flag.
+    pub fn parse_flag(&mut self, flag: &str, next: impl FnOnce() -> Option<String>) -> Result<bool, String> {
+        if flag == "-MD" {
+            self.write_dependencies = true;
+            return Ok(true);
+        }
+        let found = ["-isystem", "-MF", "-MT", "-D", "-U", "-I"].into_iter()
+            .find_map(|option| Some((option, flag.strip_prefix(option)?)));
+        let Some((option, rest)) = found else {
+            return Ok(false);
+        };
+        let argument = if rest.is_empty() { next().unwrap_or_default() } else { rest.to_string() };
+        if argument.is_empty() {
+            return Err(match option {
+                "-D" | "-U" => format!("Missing macro name after '{}'", option),
+                "-MF" => format!("Missing file after '{}'", option),
+                "-MT" => format!("Missing target after '{}'", option),
+                _ => format!("Missing directory after '{}'", option),
+            });
+        }
+        match (option, argument.split_once('=')) {
+            ("-D", Some((name, value))) => self.macros.push(MacroFlag::Define(name.to_string(), Some(value.to_string()))),
+            ("-D", None) => self.macros.push(MacroFlag::Define(argument, None)),
+            ("-U", _) => self.macros.push(MacroFlag::Undefine(argument)),
+            ("-I", _) => self.include_dirs.push(PathBuf::from(argument)),
+            ("-MF", _) => self.dependency_file = Some(PathBuf::from(argument)),
+            ("-MT", _) => self.dependency_target = Some(argument),
+            _ => self.system_include_dirs.push(PathBuf::from(argument)),
+        }
+        Ok(true)
+    }
+
+    fn args(&self) -> Vec<String> {
+        let macros = self.macros.iter().map(|flag| match flag {
+            MacroFlag::Define(name, None) => format!("-D{}", name),
+            MacroFlag::Define(name, Some(value)) => format!("-D{}={}", name, value),
+            MacroFlag::Undefine(name) => format!("-U{}", name),
+        });
+        let include_dirs = self.include_dirs.iter().map(|dir| format!("-I{}", dir.display()));
+        let system_include_dirs = self.system_include_dirs.iter()
+            .flat_map(|dir| ["-isystem".to_string(), dir.display().to_string()]);
+        let freestanding = self.freestanding.then(|| "-ffreestanding".to_string());
+        macros.chain(include_dirs).chain(system_include_dirs).chain(freestanding).collect()
+    }
+
+    // The directories a header was looked for in, in search order
+    fn searched_dirs(&self) -> Vec<String> {
+        let mut dirs = vec!["the including file's directory (for #include \"...\" only)".to_string()];
+        dirs.extend(self.include_dirs.iter().map(|dir| dir.display().to_string()));
+        dirs.extend(self.system_include_dirs.iter().map(|dir| format!("{} (system)", dir.display())));
+        dirs.push("the default system directories".to_string());
+        dirs
+    }
+}
+
+// The header an error from the preprocessor says it couldn't find, in gcc's
+// wording or clang's. Only errors at an #include have a line and column.
+fn missing_header(stderr: &str) -> Option<&str> {
+    stderr.lines().find_map(|line| {
+        let (location, message) = line.split_once("fatal error: ")?;
+        let (location, column) = location.trim_end().strip_suffix(':')?.rsplit_once(':')?;
+        let (_, line) = location.rsplit_once(':')?;
+        line.parse::<usize>().ok()?;
+        column.parse::<usize>().ok()?;
+        message.strip_suffix(": No such file or directory")
+            .or_else(|| message.strip_prefix('\'')?.strip_suffix("' file not found"))
+    })
+}
+
+// What running the preprocessor on a file gave
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+    // The preprocessed text. The error only says that preprocessing failed,
+    // or for a missing header where it was looked for; messages has the
+    // preprocessor's own account.
+    pub output: Result<String, String>,
+    // The warnings and errors the preprocessor printed, as it printed them,
+    // for the caller to pass on
+    pub messages: String,
+}
+
+// The preprocessed text of input, or of text when it is given: an editor's
+// copy of input that may not be saved, for which input's directory is still
+// searched first for #include "..." headers. Nothing is printed, so any
+// number of files can be preprocessed at once.
+pub fn preprocess(input: &Path, text: Option<&str>, dialect: &Dialect, options: &PreprocessOptions, target: Target) -> Preprocessed {
+    let mut command = Command::new(target.assembler());
+    // clang predefines the macros of the platform it targets (__APPLE__,
+    // __linux__, _WIN32, ...), which are the host's unless it is told
+    if target != Target::host() {
+        command.arg(format!("--target={}", target.triple()));
+    }
+    if options.write_dependencies {
+        command.arg("-MD").arg("-MF").arg(options.dependency_file.clone().unwrap_or_else(|| input.with_extension("d")));
+        // -MQ quotes characters that are special to Make, such as spaces
+        if let Some(dependency_target) = &options.dependency_target {
+            command.arg("-MQ").arg(dependency_target);
+        }
+    }
+    command.arg("-E").arg(dialect.std_flag()).args(options.args());
+    match text {
+        Some(_) => {
+            let dir = input.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            command.arg("-iquote").arg(dir).args(["-x", "c", "-"]);
+        }
+        None => {
+            command.arg(input);
+        }
+    }
+    let run = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().and_then(|mut child| {
+        let stdin = child.stdin.take();
+        // Written from a thread of its own, so that a large output can't
+        // fill its pipe while the text is still being written
+        thread::scope(|scope| {
+            if let (Some(text), Some(mut stdin)) = (text, stdin) {
+                scope.spawn(move || stdin.write_all(text.as_bytes()));
+            }
+            child.wait_with_output()
+        })
+    });
+    let output = match run {
+        Ok(output) => output,
+        Err(err) => {
+            let output = Err(format!("Failed to run the preprocessor '{}': {}", target.assembler(), err));
+            return Preprocessed { output, messages: String::new() };
+        }
+    };
+    let messages = String::from_utf8_lossy(&output.stderr).into_owned();
+    let text = if !output.status.success() {
+        Err(match missing_header(&messages) {
+            Some(header) => format!("Header '{}' not found; searched:\n  {}", header, options.searched_dirs().join("\n  ")),
+            None => format!("Preprocessing '{}' failed", input.display()),
+        })
+    } else {
+        String::from_utf8(output.stdout).map_err(|_| format!("Preprocessed '{}' is not valid UTF-8", input.display()))
+    };
+    Preprocessed { output: text, messages }
+}
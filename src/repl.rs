@@ -0,0 +1,55 @@
+use crate::dialect::Dialect;
+use crate::intern::Interner;
+use crate::{driver, interpret};
+
+// State for --repl. Every accepted line is kept, and each new line is run as
+// the end of a main() holding all the earlier ones, so declarations and
+// assignments carry over. Programs are interpreted from TAC rather than
+// assembled, so values aren't cut down to an exit status.
+#[derive(Default)]
+pub struct Session {
+    lines: Vec<String>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    fn run(&self, tail: &str) -> Result<i32, String> {
+        let mut source = String::from("int main(void) {\n");
+        for line in self.lines.iter().map(String::as_str).chain([tail]) {
+            source.push_str(line);
+            source.push('\n');
+        }
+        source.push('}');
+        // The whole program is compiled again, so the names from earlier
+        // runs needn't be kept
+        Interner::new().enter(|| {
+            let tac = driver::compile_to_tac(&source, &Dialect::default()).map_err(|err| err.to_string())?;
+            interpret::run_program(&tac)
+        })
+    }
+
+    // Runs one line of input. A line ending in ';' is a declaration or
+    // statement and produces no value, unless it returns one; anything else
+    // is an expression whose value is returned. A line that fails is dropped.
+    pub fn eval(&mut self, line: &str) -> Result<Option<i32>, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        if line.starts_with("return") {
+            return self.run(line).map(Some);
+        }
+        if line.ends_with(';') {
+            self.run(line)?;
+            self.lines.push(line.to_string());
+            return Ok(None);
+        }
+        let value = self.run(&format!("return {};", line))?;
+        // Keep the expression for its side effects, such as x = 5
+        self.lines.push(format!("{};", line));
+        Ok(Some(value))
+    }
+}
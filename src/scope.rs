@@ -0,0 +1,89 @@
+use crate::intern::Symbol;
+use std::collections::{HashMap, HashSet};
+
+// The identifiers visible during resolution, as a stack of blocks. An inner
+// block may declare a name again, hiding the outer declaration until the
+// block ends; each declaration gets a unique name for the rest of the
+// compiler to use. A name declared extern refers to an object defined
+// elsewhere and keeps its own name, which the linker resolves.
+pub struct Scope {
+    blocks: Vec<HashMap<Symbol, (Symbol, bool)>>,
+    unique_names: HashSet<Symbol>,
+    // The names declared extern anywhere so far
+    extern_names: HashSet<Symbol>,
+}
+
+// What a name resolves to, whether it was declared in the innermost block
+// (where declaring it again is an error) or an enclosing one, and whether it
+// has linkage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub unique_name: Symbol,
+    pub from_current_block: bool,
+    pub has_linkage: bool,
+}
+
+impl Default for Scope {
+    fn default() -> Scope {
+        Scope::new()
+    }
+}
+
+impl Scope {
+    // A scope with one block open
+    pub fn new() -> Scope {
+        Scope { blocks: vec![HashMap::new()], unique_names: HashSet::new(), extern_names: HashSet::new() }
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.blocks.push(HashMap::new());
+    }
+
+    pub fn exit_scope(&mut self) {
+        self.blocks.pop();
+    }
+
+    // Declares name in the innermost block and returns its unique name: the
+    // name itself the first time, then name.1, name.2, ...
+    pub fn declare(&mut self, name: Symbol) -> Result<Symbol, String> {
+        if self.lookup(name).is_some_and(|entry| entry.from_current_block) {
+            return Err(format!("Variable '{}' already declared", name));
+        }
+        let mut unique_name = name;
+        let mut counter = 0;
+        while self.unique_names.contains(&unique_name) {
+            counter += 1;
+            unique_name = Symbol::intern(&format!("{}.{}", name, counter));
+        }
+        self.unique_names.insert(unique_name);
+        self.blocks.last_mut().expect("a scope always has a block open").insert(name, (unique_name, false));
+        Ok(unique_name)
+    }
+
+    // Declares name in the innermost block as referring to the object of
+    // that name with linkage. Declaring it extern again is fine, but not
+    // after a local of the same name in the same block.
+    pub fn declare_extern(&mut self, name: Symbol) -> Result<Symbol, String> {
+        match self.lookup(name) {
+            Some(entry) if entry.from_current_block && entry.has_linkage => return Ok(name),
+            Some(entry) if entry.from_current_block => return Err(format!("Variable '{}' already declared", name)),
+            _ => {}
+        }
+        // A local in an earlier block may already have been given the name
+        if self.unique_names.contains(&name) && !self.extern_names.contains(&name) {
+            return Err(format!("Extern declaration of '{}' clashes with a local variable of that name", name));
+        }
+        self.unique_names.insert(name);
+        self.extern_names.insert(name);
+        self.blocks.last_mut().expect("a scope always has a block open").insert(name, (name, true));
+        Ok(name)
+    }
+
+    // The innermost declaration of name
+    pub fn lookup(&self, name: Symbol) -> Option<Entry> {
+        let innermost = self.blocks.len() - 1;
+        self.blocks.iter().enumerate().rev().find_map(|(depth, block)| {
+            block.get(&name).map(|&(unique_name, has_linkage)| Entry { unique_name, from_current_block: depth == innermost, has_linkage })
+        })
+    }
+}
@@ -0,0 +1,177 @@
+use crate::assembly::{Instruction, Operand, Size};
+use crate::intern::Symbol;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+// Stack slot assignment for pseudo-registers. Rather than giving every
+// pseudo its own slot, compute which pseudos are live at the same time and
+// let the ones that never are share a slot, the same way a register
+// allocator colors an interference graph with registers.
+
+fn pseudo(operand: &Operand) -> Option<Symbol> {
+    match operand {
+        Operand::Pseudo(name) => Some(*name),
+        _ => None,
+    }
+}
+
+// The pseudos an instruction reads and the ones it writes
+fn uses_and_defs(instruction: &Instruction) -> (Vec<Symbol>, Vec<Symbol>) {
+    (
+        instruction.uses().iter().filter_map(pseudo).collect(),
+        instruction.defs().iter().filter_map(pseudo).collect(),
+    )
+}
+
+struct Block {
+    start: usize,
+    end: usize,
+    successors: Vec<usize>,
+}
+
+// Split the instructions into basic blocks: a block starts at a label or
+// after a jump, return or ud2, and ends at the next such point.
+fn basic_blocks(instructions: &[Instruction]) -> Vec<Block> {
+    let mut starts = vec![0];
+    for (index, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::Label(_) if index != 0 => starts.push(index),
+            Instruction::Jmp(_) | Instruction::JmpCC(..) | Instruction::Ret | Instruction::Ud2 => starts.push(index + 1),
+            _ => {}
+        }
+    }
+    starts.push(instructions.len());
+    starts.dedup();
+
+    let ranges: Vec<(usize, usize)> = starts.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    let block_of_label: HashMap<Symbol, usize> = ranges.iter().enumerate()
+        .filter_map(|(block, (start, _))| match instructions.get(*start) {
+            Some(Instruction::Label(label)) => Some((*label, block)),
+            _ => None,
+        })
+        .collect();
+
+    ranges.iter().enumerate().map(|(block, (start, end))| {
+        let fallthrough = (block + 1 < ranges.len()).then_some(block + 1);
+        let successors = match instructions[*end - 1] {
+            Instruction::Jmp(label) => block_of_label.get(&label).copied().into_iter().collect(),
+            Instruction::JmpCC(_, label) => block_of_label.get(&label).copied().into_iter().chain(fallthrough).collect(),
+            Instruction::Ret | Instruction::Ud2 => vec![],
+            _ => fallthrough.into_iter().collect(),
+        };
+        Block { start: *start, end: *end, successors }
+    }).collect()
+}
+
+// Pseudos live on exit from each block, by backward dataflow to a fixed point
+fn live_out(instructions: &[Instruction], blocks: &[Block]) -> Vec<HashSet<Symbol>> {
+    let mut live_in: Vec<HashSet<Symbol>> = vec![HashSet::new(); blocks.len()];
+    let mut live_out: Vec<HashSet<Symbol>> = vec![HashSet::new(); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, block) in blocks.iter().enumerate().rev() {
+            let out: HashSet<Symbol> = block.successors.iter()
+                .flat_map(|successor| live_in[*successor].iter().copied())
+                .collect();
+            let mut live = out.clone();
+            for instruction in instructions[block.start..block.end].iter().rev() {
+                let (uses, defs) = uses_and_defs(instruction);
+                for def in defs {
+                    live.remove(&def);
+                }
+                live.extend(uses);
+            }
+            if live != live_in[index] || out != live_out[index] {
+                live_in[index] = live;
+                live_out[index] = out;
+                changed = true;
+            }
+        }
+    }
+    live_out
+}
+
+fn width(size: Size) -> i32 {
+    match size {
+        Size::Quadword => 8,
+        _ => 4,
+    }
+}
+
+// The widest access to each pseudo decides how big its slot must be
+fn slot_widths(instructions: &[Instruction]) -> HashMap<Symbol, i32> {
+    let mut widths: HashMap<Symbol, i32> = HashMap::new();
+    let mut note = |operand: &Operand, size: Size| {
+        if let Some(name) = pseudo(operand) {
+            let entry = widths.entry(name).or_insert(4);
+            *entry = (*entry).max(width(size));
+        }
+    };
+    for instruction in instructions {
+        for (operand, size, _) in instruction.operands() {
+            note(operand, size);
+        }
+    }
+    widths
+}
+
+// Assigns each pseudo a frame offset (negative, relative to %rbp) so that
+// pseudos which are live at the same time never share one. Returns the
+// offsets and the number of bytes the frame needs.
+pub fn assign_stack_slots(instructions: &[Instruction]) -> (HashMap<Symbol, i32>, i32) {
+    let blocks = basic_blocks(instructions);
+    let live_out = live_out(instructions, &blocks);
+
+    // Two pseudos interfere when one is written while the other is live
+    let mut interference: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+    let mut order: Vec<Symbol> = Vec::new();
+    for instruction in instructions {
+        let (uses, defs) = uses_and_defs(instruction);
+        for name in uses.into_iter().chain(defs) {
+            if let Entry::Vacant(entry) = interference.entry(name) {
+                entry.insert(HashSet::new());
+                order.push(name);
+            }
+        }
+    }
+    for (block, out) in blocks.iter().zip(&live_out) {
+        let mut live = out.clone();
+        for instruction in instructions[block.start..block.end].iter().rev() {
+            let (uses, defs) = uses_and_defs(instruction);
+            for def in &defs {
+                for other in live.iter().filter(|other| *other != def) {
+                    interference.get_mut(def).unwrap().insert(*other);
+                    interference.get_mut(other).unwrap().insert(*def);
+                }
+            }
+            for def in defs {
+                live.remove(&def);
+            }
+            live.extend(uses);
+        }
+    }
+
+    // Greedily give each pseudo, in order of first appearance, the first
+    // slot of its width that no interfering pseudo already holds
+    let widths = slot_widths(instructions);
+    let mut slots: Vec<(i32, i32)> = Vec::new();
+    let mut frame_size = 0;
+    let mut offsets: HashMap<Symbol, i32> = HashMap::new();
+    for name in order {
+        let width = widths.get(&name).copied().unwrap_or(4);
+        let taken: HashSet<i32> = interference[&name].iter()
+            .filter_map(|other| offsets.get(other).copied())
+            .collect();
+        let offset = match slots.iter().find(|(offset, slot_width)| *slot_width == width && !taken.contains(offset)) {
+            Some((offset, _)) => *offset,
+            None => {
+                frame_size = (frame_size + width + width - 1) / width * width;
+                slots.push((-frame_size, width));
+                -frame_size
+            }
+        };
+        offsets.insert(name, offset);
+    }
+    (offsets, frame_size)
+}
@@ -0,0 +1,132 @@
+use crate::intern::Symbol;
+use crate::lex::Span;
+use crate::parser::Type;
+use std::collections::HashMap;
+
+// The identifiers declared at file scope, with C's rules for declaring one
+// more than once: every declaration must agree on the type, the storage
+// classes must agree on the linkage, and there may be only one definition.
+// An object declared without an initializer or extern is a tentative
+// definition, which any number of declarations may repeat and which becomes
+// a definition when the translation unit ends without a real one.
+//
+// The table is kept after resolution for tools: every entry records where
+// it was declared and referenced, so an editor can find the symbol under
+// the cursor.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    Extern,
+    Static,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    External,
+    Internal,
+}
+
+// Ordered so that combining two declarations keeps the greater
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Definition {
+    Declared,
+    Tentative,
+    Defined,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub declared_type: Type,
+    pub linkage: Linkage,
+    pub definition: Definition,
+    // The storage class the first declaration was written with
+    pub storage_class: Option<StorageClass>,
+    // Where the name is declared, in source order
+    pub declarations: Vec<Span>,
+    // Where the name is used in an expression, in source order
+    pub references: Vec<Span>,
+}
+
+#[derive(Default)]
+pub struct SymbolTable {
+    entries: HashMap<Symbol, Entry>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    // Records a file-scope declaration of name at span, where defines says
+    // whether it has an initializer or a body, and returns the linkage it
+    // ends up with
+    pub fn declare(&mut self, name: Symbol, declared_type: Type, storage_class: Option<StorageClass>, defines: bool, span: Span) -> Result<Linkage, String> {
+        let is_function = matches!(declared_type, Type::Function(..));
+        let definition = match (defines, storage_class) {
+            (true, _) => Definition::Defined,
+            (false, Some(StorageClass::Extern)) => Definition::Declared,
+            (false, _) if is_function => Definition::Declared,
+            (false, _) => Definition::Tentative,
+        };
+        let previous = self.entries.get(&name);
+        let linkage = match (storage_class, previous) {
+            (Some(StorageClass::Static), Some(previous)) if previous.linkage == Linkage::External => {
+                return Err(format!("Static declaration of '{}' follows non-static declaration", name));
+            }
+            (Some(StorageClass::Static), _) => Linkage::Internal,
+            // extern takes the linkage of an earlier declaration, and a
+            // function declared without a storage class is implicitly extern
+            (Some(StorageClass::Extern), Some(previous)) => previous.linkage,
+            (None, Some(previous)) if is_function => previous.linkage,
+            (None, Some(previous)) if previous.linkage == Linkage::Internal => {
+                return Err(format!("Non-static declaration of '{}' follows static declaration", name));
+            }
+            _ => Linkage::External,
+        };
+        if let Some(previous) = previous {
+            if previous.declared_type != declared_type {
+                return Err(format!("Conflicting types for '{}': {} and {}", name, previous.declared_type, declared_type));
+            }
+            if previous.definition == Definition::Defined && definition == Definition::Defined {
+                return Err(format!("Redefinition of '{}'", name));
+            }
+        }
+        let entry = self.entries.entry(name).or_insert_with(|| Entry {
+            declared_type,
+            linkage,
+            definition,
+            storage_class,
+            declarations: Vec::new(),
+            references: Vec::new(),
+        });
+        entry.definition = entry.definition.max(definition);
+        entry.declarations.push(span);
+        Ok(linkage)
+    }
+
+    // Records a use of name at span, if it is in the table
+    pub fn reference(&mut self, name: Symbol, span: Span) {
+        if let Some(entry) = self.entries.get_mut(&name) {
+            entry.references.push(span);
+        }
+    }
+
+    pub fn get(&self, name: Symbol) -> Option<&Entry> {
+        self.entries.get(&name)
+    }
+
+    // The symbol declared or referenced at a position, both counted from 1
+    pub fn at(&self, line: usize, column: usize) -> Option<(Symbol, &Entry)> {
+        let covers = |span: &Span| span.line == line && (span.column..span.column + (span.end - span.start)).contains(&column);
+        self.entries.iter()
+            .find(|(_, entry)| entry.declarations.iter().chain(&entry.references).any(covers))
+            .map(|(name, entry)| (*name, entry))
+    }
+
+    // Every symbol, in the order they are first declared
+    pub fn entries(&self) -> Vec<(Symbol, &Entry)> {
+        let mut entries: Vec<(Symbol, &Entry)> = self.entries.iter().map(|(name, entry)| (*name, entry)).collect();
+        entries.sort_by_key(|(_, entry)| entry.declarations.first().map(|span| span.start));
+        entries
+    }
+}
@@ -1,13 +1,17 @@
-use crate::parser::{Program as ParserProgram, FunctionDeclaration, Statement, Exp, UnaryOp, Factor, BinaryOp, BlockItem, Declaration};
+use crate::const_eval::ConstEvaluator;
+use crate::intern::{Interner, Symbol};
+use crate::symbols::StorageClass;
+use crate::typecheck;
+use crate::parser::{Program as ParserProgram, FunctionDeclaration, Statement, Exp, ExpArena, ExpId, UnaryOp, BinaryOp, Type, BlockItem, Declaration};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum UnaryOperator {
     Negate,
     Complement,
     LogicalNot
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BinaryOperator {
     Add,
     Subtract,
@@ -33,6 +37,7 @@ pub enum BinaryOperator {
 impl From<&UnaryOp> for UnaryOperator {
     fn from(op: &UnaryOp) -> Self {
         match op {
+            UnaryOp::Plus => unreachable!("unary plus generates no instruction"),
             UnaryOp::Negation => UnaryOperator::Negate,
             UnaryOp::Complement => UnaryOperator::Complement,
             UnaryOp::LogicalNot => UnaryOperator::LogicalNot,
@@ -62,13 +67,15 @@ impl From<&BinaryOp> for BinaryOperator {
             BinaryOp::LessThan => BinaryOperator::LessThan,
             BinaryOp::LessThanOrEqual => BinaryOperator::LessThanOrEqual,
             BinaryOp::Assignment => BinaryOperator::Assign,
+            BinaryOp::LeftShiftAssignment => BinaryOperator::ShiftLeft,
+            BinaryOp::RightShiftAssignment => BinaryOperator::ShiftRight,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Val {
-    Identifier(String),
+    Identifier(Symbol),
     Constant(i32),
 }
 
@@ -81,6 +88,41 @@ impl std::fmt::Display for Val {
     }
 }
 
+impl std::fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Complement => "~",
+            UnaryOperator::LogicalNot => "!",
+        })
+    }
+}
+
+impl std::fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Ampersand => "&",
+            BinaryOperator::Pipe => "|",
+            BinaryOperator::Caret => "^",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::LogicalAnd => "&&",
+            BinaryOperator::LogicalOr => "||",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::Assign => "=",
+        })
+    }
+}
 
 #[derive(Clone, Debug)]
 pub enum Instruction {
@@ -88,30 +130,179 @@ pub enum Instruction {
     Unary { operator: UnaryOperator, src: Val, dst: Val },
     Binary { operator: BinaryOperator, src1: Val, src2: Val, dst: Val },
     Copy { src: Val, dst: Val },
-    Jump { label: Val },
-    JumpIfZero { src: Val, label: Val },
-    JumpIfNotZero { src: Val, label: Val },
-    Label { label: Val },
+    // dst = (target)src, for a target narrower than int: src is truncated
+    // to it and extended back, so dst holds the narrow value as an int
+    Narrow { target: Type, src: Val, dst: Val },
+    Jump { label: Symbol },
+    // likely says whether the jump is expected to be taken, when
+    // __builtin_expect gave a hint
+    JumpIfZero { src: Val, label: Symbol, likely: Option<bool> },
+    JumpIfNotZero { src: Val, label: Symbol, likely: Option<bool> },
+    Label { label: Symbol },
+    // A call to a function the linker finds, which may read and write any
+    // variable with static storage
+    FunCall { name: Symbol, args: Vec<Val>, dst: Val },
+    // Basic inline assembly, copied into the output as it is. It can't name
+    // any variable, so the passes treat it as touching none.
+    InlineAsm(String),
+    // Reports the message on stderr and aborts the program. Only the runtime
+    // checks in checks.rs generate it.
+    Abort(String),
+    // Stops the program at once, from __builtin_trap
+    Trap,
+    // Control never gets here, as __builtin_unreachable promises, so
+    // nothing is generated for it
+    Unreachable,
+}
+
+// value converted to target, a type narrower than int, and back
+pub fn narrow(target: &Type, value: i32) -> i32 {
+    let shift = 32 - 8 * typecheck::size(target) as u32;
+    if typecheck::is_signed(target) {
+        value << shift >> shift
+    } else {
+        ((value as u32) << shift >> shift) as i32
+    }
+}
+
+fn hint(likely: Option<bool>) -> &'static str {
+    match likely {
+        Some(true) => " (likely)",
+        Some(false) => " (unlikely)",
+        None => "",
+    }
+}
+
+// One line of C-like pseudocode per instruction
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Return(val) => write!(f, "return {}", val),
+            Instruction::Unary { operator, src, dst } => write!(f, "{} = {}{}", dst, operator, src),
+            Instruction::Binary { operator, src1, src2, dst } => write!(f, "{} = {} {} {}", dst, src1, operator, src2),
+            Instruction::Copy { src, dst } => write!(f, "{} = {}", dst, src),
+            Instruction::Narrow { target, src, dst } => write!(f, "{} = ({}){}", dst, target, src),
+            Instruction::Jump { label } => write!(f, "jump {}", label),
+            Instruction::JumpIfZero { src, label, likely } => write!(f, "if {} == 0 jump {}{}", src, label, hint(*likely)),
+            Instruction::JumpIfNotZero { src, label, likely } => write!(f, "if {} != 0 jump {}{}", src, label, hint(*likely)),
+            Instruction::Label { label } => write!(f, "{}:", label),
+            Instruction::FunCall { name, args, dst } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{} = {}({})", dst, name, args.join(", "))
+            }
+            Instruction::InlineAsm(template) => write!(f, "asm {:?}", template),
+            Instruction::Abort(message) => write!(f, "abort {:?}", message),
+            Instruction::Trap => f.write_str("trap"),
+            Instruction::Unreachable => f.write_str("unreachable"),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Function {
-    pub identifier: String,
+    pub identifier: Symbol,
     pub body: Vec<Instruction>,
+    // Variables declared volatile, whose every read and write must happen
+    // as written
+    pub volatiles: Vec<Symbol>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Program {
     pub function: Function,
+    // Variables declared extern, which live in another translation unit
+    // rather than in the function's frame
+    pub externs: Vec<Symbol>,
+    // Variables declared static, with the value they start the program with.
+    // They live in the data section rather than in the function's frame.
+    pub statics: Vec<(Symbol, i32)>,
+    // The interner the program's names are in, which the passes that add
+    // temporaries intern them into too
+    pub interner: Interner,
 }
 
-impl Factor {
-    fn generate_tac(&self, body: &mut Vec<Instruction>) -> Val {
-        match self {
-            Factor::Int(value) => Val::Constant(*value),
-            Factor::Unary(op, exp) => {
-                let val = exp.generate_tac(body);
-                let dst = Val::Identifier(format!("tmp.{}", body.len()));
+impl std::fmt::Debug for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.interner.enter(|| f.debug_struct("Program")
+            .field("function", &self.function).field("externs", &self.externs).field("statics", &self.statics)
+            .finish())
+    }
+}
+
+// The externs and statics, then the function with its instructions
+// indented under it, as --save-temps writes it
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.interner.enter(|| self.write_to(f))
+    }
+}
+
+impl Program {
+    fn write_to(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for name in &self.externs {
+            writeln!(f, "extern {}", name)?;
+        }
+        for (name, value) in &self.statics {
+            writeln!(f, "static {} = {}", name, value)?;
+        }
+        writeln!(f, "function {}", self.function.identifier)?;
+        for instruction in &self.function.body {
+            match instruction {
+                Instruction::Label { .. } => writeln!(f, "{}", instruction)?,
+                _ => writeln!(f, "    {}", instruction)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Hands out the labels of one function, numbered in the order they are
+// made. Each starts with the function's name, so no two functions share
+// one, and codegen writes them with the target's local label prefix, which
+// keeps them out of the object file and apart from C identifiers.
+struct LabelAllocator {
+    function: Symbol,
+    count: usize,
+}
+
+impl LabelAllocator {
+    fn new(function: Symbol) -> LabelAllocator {
+        LabelAllocator { function, count: 0 }
+    }
+
+    fn next(&mut self) -> Symbol {
+        let label = Symbol::intern(&format!("{}_{}", self.function, self.count));
+        self.count += 1;
+        label
+    }
+}
+
+// The value __builtin_expect says exp probably has, if it says
+fn expected(exps: &ExpArena, exp: ExpId) -> Option<i32> {
+    match &exps[exp] {
+        Exp::Expect(_, value) => Some(*value),
+        _ => None,
+    }
+}
+
+impl ExpId {
+    fn generate_tac(&self, exps: &ExpArena, body: &mut Vec<Instruction>, labels: &mut LabelAllocator) -> Result<Val, String> {
+        Ok(match &exps[*self] {
+            Exp::Constant(value, _) => Val::Constant(*value),
+            // +x is just the value of x, but not an lvalue, which resolution has checked
+            Exp::Unary(UnaryOp::Plus, exp) => exp.generate_tac(exps, body, labels)?,
+            // Every value is an int so far, so a conversion leaves it as it is
+            Exp::Cast(Type::Int, exp) => exp.generate_tac(exps, body, labels)?,
+            Exp::Cast(target @ (Type::Char | Type::SChar | Type::UChar | Type::Short | Type::UShort), exp) => {
+                let src = exp.generate_tac(exps, body, labels)?;
+                let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
+                body.push(Instruction::Narrow { target: target.clone(), src, dst: dst.clone() });
+                dst
+            }
+            Exp::Cast(target, _) => return Err(format!("Conversion to {} is not supported yet", target)),
+            Exp::Unary(op, exp) => {
+                let val = exp.generate_tac(exps, body, labels)?;
+                let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
                 let instruction = Instruction::Unary {
                     operator: UnaryOperator::from(op),
                     src: val,
@@ -120,106 +311,43 @@ impl Factor {
                 body.push(instruction);
                 dst
             }
-            Factor::Exp(exp) => exp.generate_tac(body),
-        }
-    }
-}
-
-impl Exp {
-    fn generate_tac(&self, body: &mut Vec<Instruction>) -> Val {
-        match self {
-            Exp::Factor(factor) => factor.generate_tac(body),
+            // Arguments are evaluated left to right; C leaves the order
+            // unspecified
+            Exp::Call(name, args, _) => {
+                let args = args.iter().map(|arg| arg.generate_tac(exps, body, labels)).collect::<Result<_, _>>()?;
+                let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
+                body.push(Instruction::FunCall { name: *name, args, dst: dst.clone() });
+                dst
+            }
             Exp::Binary(left, op, right) => {
-                if op == &BinaryOp::LogicalAnd {
-                    let left_val = left.generate_tac(body);
-                    let dst = Val::Identifier(format!("tmp.{}", body.len()));
-                    let label = Val::Identifier(format!("label.{}", body.len()));
-                    
-                    // Convert left value to boolean (0 or 1)
-                    let bool_dst = Val::Identifier(format!("tmp.{}", body.len() + 1));
-                    body.push(Instruction::Binary {
-                        operator: BinaryOperator::NotEqual,
-                        src1: left_val.clone(),
-                        src2: Val::Constant(0),
-                        dst: bool_dst.clone(),
-                    });
-                    
-                    // Copy boolean result to dst
-                    body.push(Instruction::Copy {
-                        src: bool_dst.clone(),
-                        dst: dst.clone(),
-                    });
-    
-                    // Short circuit if false (0)
-                    body.push(Instruction::JumpIfZero {
-                        src: bool_dst,
-                        label: label.clone(),
-                    });
-                    
-                    // Evaluate right side if left was true
-                    let right_val = right.generate_tac(body);
-                    
-                    // Convert right value to boolean and store in dst
-                    body.push(Instruction::Binary {
-                        operator: BinaryOperator::NotEqual,
-                        src1: right_val,
-                        src2: Val::Constant(0),
-                        dst: dst.clone(),
-                    });
-    
-                    // Place the label for short-circuit
-                    body.push(Instruction::Label {
-                        label: label,
-                    });
-    
-                    dst
-                } else if op == &BinaryOp::LogicalOr {
-                    let left_val = left.generate_tac(body);
-                    let dst = Val::Identifier(format!("tmp.{}", body.len()));
-                    let label = Val::Identifier(format!("label.{}", body.len()));
-                    
-                    // Convert left value to boolean (0 or 1)
-                    let bool_dst = Val::Identifier(format!("tmp.{}", body.len() + 1));
-                    body.push(Instruction::Binary {
-                        operator: BinaryOperator::NotEqual,
-                        src1: left_val.clone(),
-                        src2: Val::Constant(0),
-                        dst: bool_dst.clone(),
-                    });
-                    
-                    // Copy boolean result to dst
-                    body.push(Instruction::Copy {
-                        src: bool_dst.clone(),
-                        dst: dst.clone(),
-                    });
-    
-                    // Short circuit if true (1)
-                    body.push(Instruction::JumpIfNotZero {
-                        src: bool_dst,
-                        label: label.clone(),
-                    });
-                    
-                    // Evaluate right side if left was false
-                    let right_val = right.generate_tac(body);
-                    
-                    // Convert right value to boolean and store in dst
-                    body.push(Instruction::Binary {
-                        operator: BinaryOperator::NotEqual,
-                        src1: right_val,
-                        src2: Val::Constant(0),
-                        dst: dst.clone(),
-                    });
-    
-                    // Place the label for short-circuit
-                    body.push(Instruction::Label {
-                        label: label,
-                    });
-    
+                if op == &BinaryOp::LogicalAnd || op == &BinaryOp::LogicalOr {
+                    // Either operand can decide the result on its own: zero
+                    // for && and nonzero for ||, which jumps straight to the
+                    // label that sets it. Otherwise both were evaluated and
+                    // the result is the other value.
+                    let (decided, undecided) = if op == &BinaryOp::LogicalAnd { (0, 1) } else { (1, 0) };
+                    let jump = |operand: &ExpId, src: Val, label: Symbol| if op == &BinaryOp::LogicalAnd {
+                        Instruction::JumpIfZero { src, label, likely: expected(exps, *operand).map(|value| value == 0) }
+                    } else {
+                        Instruction::JumpIfNotZero { src, label, likely: expected(exps, *operand).map(|value| value != 0) }
+                    };
+                    let decided_label = labels.next();
+                    let end_label = labels.next();
+                    let left_val = left.generate_tac(exps, body, labels)?;
+                    body.push(jump(left, left_val, decided_label));
+                    let right_val = right.generate_tac(exps, body, labels)?;
+                    body.push(jump(right, right_val, decided_label));
+                    let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
+                    body.push(Instruction::Copy { src: Val::Constant(undecided), dst: dst.clone() });
+                    body.push(Instruction::Jump { label: end_label });
+                    body.push(Instruction::Label { label: decided_label });
+                    body.push(Instruction::Copy { src: Val::Constant(decided), dst: dst.clone() });
+                    body.push(Instruction::Label { label: end_label });
                     dst
                 } else {
-                    let left_val = left.generate_tac(body);
-                    let right_val = right.generate_tac(body);
-                    let dst = Val::Identifier(format!("tmp.{}", body.len()));
+                    let left_val = left.generate_tac(exps, body, labels)?;
+                    let right_val = right.generate_tac(exps, body, labels)?;
+                    let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
                     body.push(Instruction::Binary {
                         operator: BinaryOperator::from(op),
                         src1: left_val,
@@ -229,13 +357,13 @@ impl Exp {
                     dst
                 }
             },
-            Exp::Var(identifier) => Val::Identifier(identifier.clone()),
+            Exp::Var(identifier, _) => Val::Identifier(*identifier),
             Exp::Assignment(left, right) => {
                 // Generate TAC for the right-hand side (rhs)
-                let rhs_val = right.generate_tac(body);
+                let rhs_val = right.generate_tac(exps, body, labels)?;
 
                 // Generate a copy instruction for the assignment
-                let left_val = left.generate_tac(body);
+                let left_val = left.generate_tac(exps, body, labels)?;
 
                 // Use a reference to left_val to avoid moving it
                 body.push(Instruction::Copy {
@@ -244,19 +372,61 @@ impl Exp {
                 });
 
                 left_val // Return the left-hand side variable
-            }
-        }
+            },
+            Exp::CompoundAssignment(op, left, right) => {
+                // a <<= b evaluates b, then updates a in place with a << b
+                let rhs_val = right.generate_tac(exps, body, labels)?;
+                let left_val = left.generate_tac(exps, body, labels)?;
+
+                body.push(Instruction::Binary {
+                    operator: BinaryOperator::from(op),
+                    src1: left_val.clone(),
+                    src2: rhs_val,
+                    dst: left_val.clone(),
+                });
+
+                left_val
+            },
+            Exp::Conditional(condition, then, otherwise) => {
+                // Evaluate only the chosen arm, copying its value into dst
+                let condition_val = condition.generate_tac(exps, body, labels)?;
+                let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
+                let else_label = labels.next();
+                let end_label = labels.next();
+                let likely = expected(exps, *condition).map(|value| value == 0);
+                body.push(Instruction::JumpIfZero { src: condition_val.clone(), label: else_label, likely });
+
+                // Without a middle operand the condition's value is the result
+                let then_val = match then {
+                    Some(then) => then.generate_tac(exps, body, labels)?,
+                    None => condition_val.clone(),
+                };
+                body.push(Instruction::Copy { src: then_val, dst: dst.clone() });
+                body.push(Instruction::Jump { label: end_label });
+
+                body.push(Instruction::Label { label: else_label });
+                let otherwise_val = otherwise.generate_tac(exps, body, labels)?;
+                body.push(Instruction::Copy { src: otherwise_val, dst: dst.clone() });
+                body.push(Instruction::Label { label: end_label });
+                dst
+            },
+            // Only the jumps on its value use the hint
+            Exp::Expect(exp, _) => exp.generate_tac(exps, body, labels)?,
+            Exp::Dot(..) | Exp::Arrow(..) => unreachable!("member access is rejected during resolution"),
+        })
         }
     }
     
     impl Declaration {
-        fn generate_tac(&self, body: &mut Vec<Instruction>) -> Option<Val> {
-            match self {
-                Declaration::Declaration(identifier, initializer) => {
+        fn generate_tac(&self, exps: &ExpArena, body: &mut Vec<Instruction>, labels: &mut LabelAllocator) -> Result<Option<Val>, String> {
+            Ok(match self {
+                // Initialized before the program starts, see ParserProgram::generate_tac
+                Declaration::Declaration(_, _, _, Some(StorageClass::Static), _) => None,
+                Declaration::Declaration(identifier, _, initializer, _, _) => {
                     // If there's an initializer, treat it like an assignment
                     if let Some(init_exp) = initializer {
-                        let val = init_exp.generate_tac(body);
-                        let dst = Val::Identifier(identifier.clone());
+                        let val = init_exp.generate_tac(exps, body, labels)?;
+                        let dst = Val::Identifier(*identifier);
                         body.push(Instruction::Copy {
                             src: val,
                             dst: dst.clone(),
@@ -267,77 +437,128 @@ impl Exp {
                         None
                     }
                 }
-            }
+                // Checked during resolution
+                Declaration::StaticAssert(..) => None,
+            })
         }
     }
     
     impl Statement {
-        fn generate_tac(&self, body: &mut Vec<Instruction>) {
+        fn generate_tac(&self, exps: &ExpArena, body: &mut Vec<Instruction>, labels: &mut LabelAllocator) -> Result<(), String> {
             match self {
                 Statement::Return(exp) => {
-                    let val = exp.generate_tac(body);
+                    let val = exp.generate_tac(exps, body, labels)?;
                     body.push(Instruction::Return(val));
                 },
                 Statement::Expression(exp) => {
                     // Generate TAC for the expression, but discard the result
-                    exp.generate_tac(body);
+                    exp.generate_tac(exps, body, labels)?;
                 },
+                Statement::Asm(template) => body.push(Instruction::InlineAsm(template.clone())),
+                Statement::Trap => body.push(Instruction::Trap),
+                Statement::Unreachable => body.push(Instruction::Unreachable),
                 Statement::Null => {
                     // Do nothing for null statements
                 },
             }
+            Ok(())
         }
     }
     
     impl BlockItem {
-        fn generate_tac(&self, body: &mut Vec<Instruction>) {
+        fn generate_tac(&self, exps: &ExpArena, body: &mut Vec<Instruction>, labels: &mut LabelAllocator) -> Result<(), String> {
             match self {
                 BlockItem::S(stmt) => {
-                    stmt.generate_tac(body);
+                    stmt.generate_tac(exps, body, labels)?;
                 },
                 BlockItem::D(decl) => {
                     // Handle declaration, ignore the result if no initializer
-                    decl.generate_tac(body);
+                    decl.generate_tac(exps, body, labels)?;
                 }
             }
+            Ok(())
         }
     }
     
     impl FunctionDeclaration {
-        pub fn generate_tac(&self) -> Function {
+        pub fn generate_tac(&self, exps: &ExpArena) -> Result<Function, String> {
             let mut body = Vec::new();
             match self {
-                FunctionDeclaration::Function(identifier, block_items) => {
+                FunctionDeclaration::Function(identifier, block_items, _) => {
+                    let mut labels = LabelAllocator::new(*identifier);
+                    let volatiles: Vec<Symbol> = block_items.iter()
+                        .filter_map(|item| match item.as_ref() {
+                            BlockItem::D(Declaration::Declaration(name, Type::Volatile(_), _, _, _)) => Some(*name),
+                            _ => None,
+                        })
+                        .collect();
                     // Process each block item in order
                     for block_item in block_items {
-                        block_item.generate_tac(&mut body);
+                        // Reading a volatile variable is a side effect even
+                        // when the value goes unused
+                        let volatile_read = match block_item.as_ref() {
+                            BlockItem::S(Statement::Expression(exp)) => match &exps[*exp] {
+                                Exp::Var(name, _) if volatiles.contains(name) => Some(*name),
+                                _ => None,
+                            },
+                            _ => None,
+                        };
+                        match volatile_read {
+                            Some(name) => {
+                                let dst = Val::Identifier(Symbol::intern(&format!("tmp.{}", body.len())));
+                                body.push(Instruction::Copy { src: Val::Identifier(name), dst });
+                            }
+                            None => block_item.generate_tac(exps, &mut body, &mut labels)?,
+                        }
                     }
     
                     // If the function is main and has no return, add an implicit return 0
-                    if identifier == "main" && !body.iter().any(|instruction| matches!(instruction, Instruction::Return(_))) {
+                    if &*identifier.text() == "main" && !body.iter().any(|instruction| matches!(instruction, Instruction::Return(_))) {
                         body.push(Instruction::Return(Val::Constant(0)));
                     }
     
-                    Function {
-                        identifier: identifier.clone(),
+                    Ok(Function {
+                        identifier: *identifier,
                         body,
-                    }
+                        volatiles,
+                    })
                 }
             }
         }
     }
     
     impl ParserProgram {
-        pub fn generate_tac(&self) -> Program {
+        pub fn generate_tac(&self) -> Result<Program, String> {
+            self.interner().enter(|| self.generate_tac_in())
+        }
+
+        fn generate_tac_in(&self) -> Result<Program, String> {
             match self {
-                ParserProgram::Program(func_decl) => {
-                    let function = func_decl.generate_tac();
-                    Program { function }
+                ParserProgram::Program(_, func_decl, exps, interner) => {
+                    let function = func_decl.generate_tac(exps)?;
+                    let FunctionDeclaration::Function(_, block_items, _) = func_decl;
+                    let mut externs = Vec::new();
+                    let mut statics = Vec::new();
+                    let mut constants = ConstEvaluator::new(exps);
+                    for item in block_items {
+                        match item.as_ref() {
+                            BlockItem::D(Declaration::Declaration(name, _, _, Some(StorageClass::Extern), _)) if !externs.contains(name) => {
+                                externs.push(*name);
+                            }
+                            // Resolution has checked that the initializer is constant
+                            BlockItem::D(Declaration::Declaration(name, _, init, Some(StorageClass::Static), _)) => {
+                                let value = init.map_or(0, |init| constants.eval(init).expect("static initializers are constant"));
+                                statics.push((*name, value));
+                            }
+                            _ => {}
+                        }
+                    }
+                    Ok(Program { function, externs, statics, interner: interner.clone() })
                 }
             }
         }
     }
     
-    pub fn generate_tac(program: ParserProgram) -> Program {
+    pub fn generate_tac(program: ParserProgram) -> Result<Program, String> {
         program.generate_tac()
     }
\ No newline at end of file
@@ -0,0 +1,178 @@
+use crate::assembly::Reg;
+
+// The platform the generated assembly is for. It decides how symbols and
+// local labels are spelled and which toolchain assembles and links the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    MacOs,
+    Linux,
+    Windows,
+}
+
+impl Target {
+    // The platform the compiler itself is running on
+    pub fn host() -> Target {
+        if cfg!(target_os = "macos") {
+            Target::MacOs
+        } else {
+            Target::Linux
+        }
+    }
+
+    // Parses a --target triple. Only the architecture and OS matter here.
+    pub fn from_triple(triple: &str) -> Option<Target> {
+        match triple {
+            "x86_64-apple-darwin" | "x86_64-apple-macosx" => Some(Target::MacOs),
+            "x86_64-unknown-linux-gnu" | "x86_64-linux-gnu" | "x86_64-pc-linux-gnu" => Some(Target::Linux),
+            "x86_64-pc-windows-msvc" => Some(Target::Windows),
+            _ => None,
+        }
+    }
+
+    pub fn triple(&self) -> &'static str {
+        match self {
+            Target::MacOs => "x86_64-apple-darwin",
+            Target::Linux => "x86_64-unknown-linux-gnu",
+            Target::Windows => "x86_64-pc-windows-msvc",
+        }
+    }
+
+    // Mach-O prefixes C symbols with an underscore; ELF and x64 COFF do not
+    pub fn symbol_name(&self, name: &str) -> String {
+        match self {
+            Target::MacOs => format!("_{}", name),
+            Target::Linux | Target::Windows => name.to_string(),
+        }
+    }
+
+    // Prefix that keeps a label out of the object file's symbol table
+    pub fn local_label_prefix(&self) -> &'static str {
+        match self {
+            Target::MacOs => "L",
+            Target::Linux | Target::Windows => ".L",
+        }
+    }
+
+    // The compiler driver used to assemble and link. On Windows clang hands
+    // the object to lld-link, so no MSVC toolchain is needed to build.
+    pub fn assembler(&self) -> &'static str {
+        match self {
+            Target::MacOs | Target::Windows => "clang",
+            Target::Linux => "cc",
+        }
+    }
+
+    // Registers holding the first integer arguments, in order: System V uses
+    // six, the Microsoft x64 convention four
+    pub fn argument_registers(&self) -> &'static [Reg] {
+        match self {
+            Target::MacOs | Target::Linux => &[Reg::DI, Reg::SI, Reg::DX, Reg::CX, Reg::R8, Reg::R9],
+            Target::Windows => &[Reg::CX, Reg::DX, Reg::R8, Reg::R9],
+        }
+    }
+
+    // Bytes the caller reserves above the return address for the callee to
+    // spill its register arguments into (the Microsoft x64 "home area")
+    pub fn shadow_space(&self) -> i32 {
+        match self {
+            Target::MacOs | Target::Linux => 0,
+            Target::Windows => 32,
+        }
+    }
+
+    pub fn executable_extension(&self) -> &'static str {
+        match self {
+            Target::MacOs | Target::Linux => "",
+            Target::Windows => "exe",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CodegenOptions {
+    pub target: Target,
+    // Generate position-independent code: globals are addressed relative to
+    // %rip and calls to other modules go through the PLT, so the output can be
+    // linked as a PIE. macOS only supports PIE executables, so it is always on
+    // there.
+    pub pic: bool,
+    // Put each function in its own section so the linker can drop unused
+    // ones with --gc-sections
+    pub function_sections: bool,
+    // Precede each block of instructions with the TAC instruction it
+    // implements, as a comment
+    pub asm_comments: bool,
+    // Address locals from %rsp and leave %rbp alone, saving the push, mov
+    // and pop every function otherwise spends on it
+    pub omit_frame_pointer: bool,
+    // 0 for -O0, the default, and 1 for -O1 and up, which selects cmov for
+    // branches that only pick between two values
+    pub opt_level: u8,
+    // Abort with a message when signed +, - or * overflows, instead of
+    // wrapping around
+    pub trapv: bool,
+    // Link without the C library (Linux only): the output brings its own
+    // _start, which calls main and exits with its result through a system
+    // call, and the checks' handlers make system calls instead of library
+    // calls
+    pub nostdlib: bool,
+    // Code for a bare-metal environment, which has neither a C library nor
+    // an operating system, so a failed check executes ud2 instead of
+    // reporting itself
+    pub freestanding: bool,
+    // Put strings in sections of mergeable strings, so the linker keeps one
+    // copy of each across all the objects it links
+    pub merge_strings: bool,
+}
+
+impl CodegenOptions {
+    pub fn new(target: Target) -> CodegenOptions {
+        CodegenOptions { target, pic: target == Target::MacOs, function_sections: false, asm_comments: false, omit_frame_pointer: false, opt_level: 0, trapv: false, nostdlib: false, freestanding: false, merge_strings: false }
+    }
+
+    // Linker flags matching the code model: PIC output is linked as a PIE and
+    // non-PIC output must not be, since it may contain absolute addresses.
+    // Windows images are relocatable either way.
+    pub fn link_args(&self) -> Vec<&'static str> {
+        let mut args = match (self.target, self.pic) {
+            (Target::MacOs, _) => vec![],
+            (Target::Windows, _) => vec!["--target=x86_64-pc-windows-msvc", "-fuse-ld=lld"],
+            // Nothing is loaded at run time without the C library, so a PIE
+            // must relocate itself
+            (Target::Linux, true) if self.nostdlib => vec!["-static-pie"],
+            (Target::Linux, true) => vec!["-pie"],
+            (Target::Linux, false) => vec!["-no-pie"],
+        };
+        if self.nostdlib {
+            args.push("-nostdlib");
+        }
+        args
+    }
+
+    // The section directive the strings the code refers to are emitted under.
+    // COFF has no mergeable sections, so Windows always uses .rdata.
+    pub fn string_section(&self) -> String {
+        match (self.target, self.merge_strings) {
+            (Target::Linux, true) => ".section .rodata.str1.1,\"aMS\",@progbits,1".to_string(),
+            (Target::Linux, false) => ".section .rodata".to_string(),
+            (Target::MacOs, true) => ".section __TEXT,__cstring,cstring_literals".to_string(),
+            (Target::MacOs, false) => ".section __TEXT,__const".to_string(),
+            (Target::Windows, _) => ".section .rdata,\"dr\"".to_string(),
+        }
+    }
+
+    // The section directive a function's code is emitted under
+    pub fn text_section(&self, function: &str) -> String {
+        match (self.target, self.function_sections) {
+            (Target::Linux, true) => format!(".section .text.{},\"ax\",@progbits", function),
+            (Target::Windows, true) => format!(".section .text${},\"xr\"", function),
+            _ => ".text".to_string(),
+        }
+    }
+}
+
+impl Default for CodegenOptions {
+    fn default() -> CodegenOptions {
+        CodegenOptions::new(Target::host())
+    }
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Files the driver writes on its way to the output. Intermediates go in a
+// directory of their own under the system's temporary directory (TMPDIR on
+// Unix), which is removed with everything in it when the TempDir is
+// dropped, whether the build got as far as the output or not.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+// Tells apart the directories and files one process makes
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn unique_name(prefix: &str) -> String {
+    format!("{}{}-{}", prefix, process::id(), COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+impl TempDir {
+    pub fn new() -> io::Result<TempDir> {
+        let temp = env::temp_dir();
+        loop {
+            let path = temp.join(unique_name("c_compiler-"));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(TempDir { path }),
+                // Left behind by an earlier process with the same id
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The path of a file called name in the directory
+    pub fn file(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// Makes path by having produce write a file next to it and renaming that
+// over path once produce succeeds, so path is never left half written: it
+// is either as it was or complete. The file is removed if produce fails.
+pub fn write_atomically<T>(path: &Path, produce: impl FnOnce(&Path) -> io::Result<T>) -> io::Result<T> {
+    // Next to path, as a rename can't cross file systems
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("'{}' is not a file name", path.display())))?;
+    let partial = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), unique_name("")));
+    let result = produce(&partial).and_then(|value| fs::rename(&partial, path).map(|_| value));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
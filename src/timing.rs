@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+// Wall-clock time spent in each compiler pass, collected for --time-passes.
+// When disabled, time() just runs the pass.
+pub struct PassTimer {
+    enabled: bool,
+    passes: Vec<(String, Duration)>,
+}
+
+impl PassTimer {
+    pub fn new(enabled: bool) -> PassTimer {
+        PassTimer { enabled, passes: Vec::new() }
+    }
+
+    pub fn time<T>(&mut self, name: &str, pass: impl FnOnce() -> T) -> T {
+        if !self.enabled {
+            return pass();
+        }
+        let start = Instant::now();
+        let result = pass();
+        self.passes.push((name.to_string(), start.elapsed()));
+        result
+    }
+
+    // A table of every timed pass in the order they ran, with its share of
+    // the total
+    pub fn report(&self) -> String {
+        let total: Duration = self.passes.iter().map(|(_, duration)| *duration).sum();
+        let width = self.passes.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("total".len());
+        let mut report = format!("{:<width$}  {:>10}  {:>6}\n", "pass", "time (ms)", "%", width = width);
+        for (name, duration) in self.passes.iter().chain([&("total".to_string(), total)]) {
+            let percent = if total.is_zero() { 0.0 } else { duration.as_secs_f64() / total.as_secs_f64() * 100.0 };
+            report.push_str(&format!("{:<width$}  {:>10.3}  {:>5.1}%\n",
+                name, duration.as_secs_f64() * 1000.0, percent, width = width));
+        }
+        report
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
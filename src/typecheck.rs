@@ -0,0 +1,244 @@
+use crate::intern::Symbol;
+use crate::parser::{BinaryOp, BlockItem, Declaration, Exp, ExpArena, ExpId, FunctionDeclaration, Program, Statement, Type, UnaryOp};
+use std::collections::HashMap;
+
+// Type checking, after resolution has given every variable a unique name.
+// It works out the type of each expression and makes every implicit
+// conversion an explicit Cast, so TAC generation and codegen see operands
+// that already have the type their operation is carried out in.
+
+// The integer conversion rank (C11 6.3.1.1), which orders types by width
+fn rank(t: &Type) -> u8 {
+    match t {
+        Type::Char | Type::SChar | Type::UChar => 1,
+        Type::Short | Type::UShort => 2,
+        Type::Int | Type::UInt => 3,
+        Type::Long | Type::ULong => 4,
+        Type::LongLong | Type::ULongLong => 5,
+        Type::Pointer(_) | Type::Array(..) | Type::Function(..) => unreachable!("{} is not an integer type", t),
+        Type::Volatile(inner) => rank(inner),
+    }
+}
+
+// Size in bytes on x86-64
+pub fn size(t: &Type) -> u64 {
+    match t {
+        Type::Char | Type::SChar | Type::UChar => 1,
+        Type::Short | Type::UShort => 2,
+        Type::Int | Type::UInt => 4,
+        Type::Long | Type::ULong | Type::LongLong | Type::ULongLong | Type::Pointer(_) => 8,
+        Type::Array(element, count) => size(element) * count,
+        Type::Function(..) => unreachable!("functions have no size"),
+        Type::Volatile(inner) => size(inner),
+    }
+}
+
+// Plain char is signed on x86-64
+pub fn is_signed(t: &Type) -> bool {
+    matches!(t, Type::Char | Type::SChar | Type::Short | Type::Int | Type::Long | Type::LongLong)
+}
+
+fn to_unsigned(t: &Type) -> Type {
+    match t {
+        Type::Char | Type::SChar => Type::UChar,
+        Type::Short => Type::UShort,
+        Type::Int => Type::UInt,
+        Type::Long => Type::ULong,
+        Type::LongLong => Type::ULongLong,
+        _ => t.clone(),
+    }
+}
+
+// The integer promotions: a type ranked below int becomes int, which can hold
+// every value of it
+pub fn promote(t: &Type) -> Type {
+    if rank(t) < rank(&Type::Int) { Type::Int } else { t.clone() }
+}
+
+// The usual arithmetic conversions: the type both operands of a binary
+// operator are converted to
+pub fn common_type(left: &Type, right: &Type) -> Type {
+    let (left, right) = (promote(left), promote(right));
+    if left == right {
+        return left;
+    }
+    if is_signed(&left) == is_signed(&right) {
+        return if rank(&left) >= rank(&right) { left } else { right };
+    }
+    let (signed, unsigned) = if is_signed(&left) { (left, right) } else { (right, left) };
+    if rank(&unsigned) >= rank(&signed) {
+        unsigned
+    } else if size(&signed) > size(&unsigned) {
+        // The signed type can hold every value of the unsigned one
+        signed
+    } else {
+        to_unsigned(&signed)
+    }
+}
+
+// exp converted to type to, which wraps it in a cast if it has another type
+fn convert(exps: &mut ExpArena, exp: ExpId, from: &Type, to: &Type) -> ExpId {
+    if from == to { exp } else { exps.alloc(Exp::Cast(to.clone(), exp)) }
+}
+
+// The type of exp, inserting the conversions its operands need
+fn check_expression(exp: ExpId, exps: &mut ExpArena, types: &HashMap<Symbol, Type>) -> Result<Type, String> {
+    match exps[exp].clone() {
+        Exp::Constant(..) => Ok(Type::Int),
+        Exp::Var(name, _) => {
+            let t = types.get(&name).expect("resolution declares every variable before it is used");
+            Ok(t.unqualified().clone())
+        },
+        Exp::Cast(target, inner) => {
+            check_expression(inner, exps, types)?;
+            Ok(target)
+        },
+        Exp::Unary(UnaryOp::LogicalNot, inner) => {
+            check_expression(inner, exps, types)?;
+            Ok(Type::Int)
+        },
+        // GNU declares it as taking and returning long, which isn't
+        // supported yet, so int stands in
+        Exp::Expect(inner, value) => {
+            let t = check_expression(inner, exps, types)?;
+            exps[exp] = Exp::Expect(convert(exps, inner, &t, &Type::Int), value);
+            Ok(Type::Int)
+        },
+        Exp::Unary(op, inner) => {
+            let t = check_expression(inner, exps, types)?;
+            let promoted = promote(&t);
+            exps[exp] = Exp::Unary(op, convert(exps, inner, &t, &promoted));
+            Ok(promoted)
+        },
+        Exp::Binary(left, op, right) => {
+            let left_type = check_expression(left, exps, types)?;
+            let right_type = check_expression(right, exps, types)?;
+            match op {
+                BinaryOp::LogicalAnd | BinaryOp::LogicalOr => Ok(Type::Int),
+                // Each operand of a shift is promoted on its own, and the result has the left one's type
+                BinaryOp::LeftShift | BinaryOp::RightShift => {
+                    let (left_promoted, right_promoted) = (promote(&left_type), promote(&right_type));
+                    let left = convert(exps, left, &left_type, &left_promoted);
+                    let right = convert(exps, right, &right_type, &right_promoted);
+                    exps[exp] = Exp::Binary(left, op, right);
+                    Ok(left_promoted)
+                },
+                _ => {
+                    let common = common_type(&left_type, &right_type);
+                    let left = convert(exps, left, &left_type, &common);
+                    let right = convert(exps, right, &right_type, &common);
+                    exps[exp] = Exp::Binary(left, op, right);
+                    match op {
+                        BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::LessThan | BinaryOp::LessThanOrEqual
+                        | BinaryOp::GreaterThan | BinaryOp::GreaterThanOrEqual => Ok(Type::Int),
+                        _ => Ok(common),
+                    }
+                },
+            }
+        },
+        Exp::Assignment(left, right) => {
+            let left_type = check_expression(left, exps, types)?;
+            let right_type = check_expression(right, exps, types)?;
+            let right = convert(exps, right, &right_type, &left_type);
+            exps[exp] = Exp::Assignment(left, right);
+            Ok(left_type)
+        },
+        // Only the shift assignments exist, whose right operand is promoted on
+        // its own. The left one would be promoted and converted back, which no
+        // type needs yet as every variable is an int.
+        Exp::CompoundAssignment(op, left, right) => {
+            let left_type = check_expression(left, exps, types)?;
+            let right_type = check_expression(right, exps, types)?;
+            let right = convert(exps, right, &right_type, &promote(&right_type));
+            exps[exp] = Exp::CompoundAssignment(op, left, right);
+            Ok(left_type)
+        },
+        Exp::Conditional(condition, Some(then), otherwise) => {
+            check_expression(condition, exps, types)?;
+            let then_type = check_expression(then, exps, types)?;
+            let otherwise_type = check_expression(otherwise, exps, types)?;
+            let common = common_type(&then_type, &otherwise_type);
+            let then = convert(exps, then, &then_type, &common);
+            let otherwise = convert(exps, otherwise, &otherwise_type, &common);
+            exps[exp] = Exp::Conditional(condition, Some(then), otherwise);
+            Ok(common)
+        },
+        // The condition doubles as the middle operand, so it is converted
+        // instead. Converting to the common type never turns a value into
+        // zero or zero into anything else, so the test is unchanged.
+        Exp::Conditional(condition, None, otherwise) => {
+            let condition_type = check_expression(condition, exps, types)?;
+            let otherwise_type = check_expression(otherwise, exps, types)?;
+            let common = common_type(&condition_type, &otherwise_type);
+            let condition = convert(exps, condition, &condition_type, &common);
+            let otherwise = convert(exps, otherwise, &otherwise_type, &common);
+            exps[exp] = Exp::Conditional(condition, None, otherwise);
+            Ok(common)
+        },
+        Exp::Call(name, args, span) => {
+            let Some(Type::Function(params, ret)) = types.get(&name).cloned() else {
+                unreachable!("resolution checks that only functions are called")
+            };
+            if args.len() != params.len() {
+                return Err(format!("{}: '{}' takes {} argument{}, but {} {} given", span, name, params.len(),
+                    if params.len() == 1 { "" } else { "s" }, args.len(), if args.len() == 1 { "was" } else { "were" }));
+            }
+            let mut converted = Vec::with_capacity(args.len());
+            for (arg, param) in args.into_iter().zip(&params) {
+                converted.push(check_converted(arg, exps, param, types)?);
+            }
+            exps[exp] = Exp::Call(name, converted, span);
+            Ok(*ret)
+        },
+        Exp::Dot(..) | Exp::Arrow(..) => unreachable!("member access is rejected during resolution"),
+    }
+}
+
+// Converts exp to the type it is stored or returned as
+fn check_converted(exp: ExpId, exps: &mut ExpArena, target: &Type, types: &HashMap<Symbol, Type>) -> Result<ExpId, String> {
+    let t = check_expression(exp, exps, types)?;
+    Ok(convert(exps, exp, &t, target))
+}
+
+fn check_block_item(item: BlockItem, exps: &mut ExpArena, types: &mut HashMap<Symbol, Type>) -> Result<BlockItem, String> {
+    match item {
+        BlockItem::D(Declaration::Declaration(name, declared_type, init, storage_class, span)) => {
+            types.insert(name, declared_type.clone());
+            let init = match init {
+                Some(init) => Some(check_converted(init, exps, declared_type.unqualified(), types)?),
+                None => None,
+            };
+            Ok(BlockItem::D(Declaration::Declaration(name, declared_type, init, storage_class, span)))
+        },
+        BlockItem::S(Statement::Return(exp)) => Ok(BlockItem::S(Statement::Return(check_converted(exp, exps, &Type::Int, types)?))),
+        BlockItem::S(Statement::Expression(exp)) => {
+            check_expression(exp, exps, types)?;
+            Ok(BlockItem::S(Statement::Expression(exp)))
+        },
+        BlockItem::D(assertion @ Declaration::StaticAssert(..)) => Ok(BlockItem::D(assertion)),
+        BlockItem::S(Statement::Asm(template)) => Ok(BlockItem::S(Statement::Asm(template))),
+        BlockItem::S(Statement::Trap) => Ok(BlockItem::S(Statement::Trap)),
+        BlockItem::S(Statement::Unreachable) => Ok(BlockItem::S(Statement::Unreachable)),
+        BlockItem::S(Statement::Null) => Ok(BlockItem::S(Statement::Null)),
+    }
+}
+
+pub fn typecheck_program(program: Program) -> Result<Program, String> {
+    program.interner().clone().enter(|| typecheck_program_in(program))
+}
+
+fn typecheck_program_in(program: Program) -> Result<Program, String> {
+    let Program::Program(declarations, FunctionDeclaration::Function(name, block_items, span), mut exps, interner) = program;
+    let mut types = HashMap::new();
+    types.insert(name, Type::Function(Vec::new(), Box::new(Type::Int)));
+    for declaration in &declarations {
+        if let Declaration::Declaration(name, declared_type, ..) = declaration {
+            types.insert(*name, declared_type.clone());
+        }
+    }
+    let mut checked = Vec::new();
+    for item in block_items {
+        checked.push(Box::new(check_block_item(*item, &mut exps, &mut types)?));
+    }
+    Ok(Program::Program(declarations, FunctionDeclaration::Function(name, checked, span), exps, interner))
+}
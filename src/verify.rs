@@ -0,0 +1,203 @@
+use crate::assembly::{self, Access, Operand};
+use crate::intern::Symbol;
+use crate::tac::{BinaryOperator, Function, Instruction, Program, Val};
+use crate::target::Target;
+use std::collections::{HashMap, HashSet};
+
+// Checks of the invariants TAC generation sets up and every optimization
+// pass must keep: each label is defined once and every jump goes to one,
+// results are written to variables, && || and = are already lowered, each
+// temporary is written on every path before it is read, and control can't
+// run off the end of the function without a return.
+//
+// Fixed-up assembly is checked for what x86-64 can encode, so a codegen
+// mistake is caught here instead of as an assembler error.
+
+// Temporaries are the names TAC generation makes up; variables come from
+// the program and may be read before they are written
+fn is_temporary(name: Symbol) -> bool {
+    name.text().starts_with("tmp.")
+}
+
+fn reads(instruction: &Instruction) -> Vec<&Val> {
+    match instruction {
+        Instruction::Return(src)
+        | Instruction::Unary { src, .. }
+        | Instruction::Copy { src, .. }
+        | Instruction::Narrow { src, .. }
+        | Instruction::JumpIfZero { src, .. }
+        | Instruction::JumpIfNotZero { src, .. } => vec![src],
+        Instruction::Binary { src1, src2, .. } => vec![src1, src2],
+        Instruction::FunCall { args, .. } => args.iter().collect(),
+        Instruction::Jump { .. } | Instruction::Label { .. } | Instruction::InlineAsm(_) | Instruction::Abort(_)
+        | Instruction::Trap | Instruction::Unreachable => Vec::new(),
+    }
+}
+
+fn writes(instruction: &Instruction) -> Option<&Val> {
+    match instruction {
+        Instruction::Unary { dst, .. } | Instruction::Binary { dst, .. } | Instruction::Copy { dst, .. }
+        | Instruction::Narrow { dst, .. } | Instruction::FunCall { dst, .. } => Some(dst),
+        _ => None,
+    }
+}
+
+fn check_operands(instruction: &Instruction) -> Result<(), String> {
+    if let Some(Val::Constant(_)) = writes(instruction) {
+        return Err(format!("'{}' writes to a constant", instruction));
+    }
+    match instruction {
+        Instruction::Binary { operator: BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr | BinaryOperator::Assign, .. } => {
+            Err(format!("'{}' uses an operator that should have been lowered", instruction))
+        }
+        _ => Ok(()),
+    }
+}
+
+// The instructions control can go to after the one at index, where
+// body.len() means falling off the end of the function
+fn successors(body: &[Instruction], labels: &HashMap<Symbol, usize>, index: usize) -> Vec<usize> {
+    match &body[index] {
+        Instruction::Return(_) | Instruction::Abort(_) | Instruction::Trap | Instruction::Unreachable => Vec::new(),
+        Instruction::Jump { label } => vec![labels[label]],
+        Instruction::JumpIfZero { label, .. } | Instruction::JumpIfNotZero { label, .. } => vec![index + 1, labels[label]],
+        _ => vec![index + 1],
+    }
+}
+
+pub fn verify_function(function: &Function) -> Result<(), String> {
+    let body = &function.body;
+    let mut labels = HashMap::new();
+    for (index, instruction) in body.iter().enumerate() {
+        check_operands(instruction)?;
+        if let Instruction::Label { label } = instruction {
+            if labels.insert(*label, index).is_some() {
+                return Err(format!("Label '{}' is defined more than once", label));
+            }
+        }
+    }
+    for instruction in body {
+        if let Instruction::Jump { label } | Instruction::JumpIfZero { label, .. } | Instruction::JumpIfNotZero { label, .. } = instruction {
+            if !labels.contains_key(label) {
+                return Err(format!("'{}' jumps to a label that isn't defined", instruction));
+            }
+        }
+    }
+
+    // The temporaries written on every path to each instruction, or None for
+    // an instruction no path reaches
+    let mut defined: Vec<Option<HashSet<Symbol>>> = vec![None; body.len()];
+    let mut worklist = Vec::new();
+    if !body.is_empty() {
+        defined[0] = Some(HashSet::new());
+        worklist.push(0);
+    }
+    while let Some(index) = worklist.pop() {
+        let mut out = defined[index].clone().expect("only reached instructions are queued");
+        if let Some(Val::Identifier(name)) = writes(&body[index]) {
+            if is_temporary(*name) {
+                out.insert(*name);
+            }
+        }
+        for successor in successors(body, &labels, index) {
+            if successor == body.len() {
+                return Err(format!("Control can reach the end of '{}' without a return", function.identifier));
+            }
+            let joined = match &defined[successor] {
+                Some(previous) => previous.intersection(&out).copied().collect(),
+                None => out.clone(),
+            };
+            if defined[successor].as_ref() != Some(&joined) {
+                defined[successor] = Some(joined);
+                worklist.push(successor);
+            }
+        }
+    }
+    if body.is_empty() {
+        return Err(format!("Control can reach the end of '{}' without a return", function.identifier));
+    }
+
+    for (instruction, defined) in body.iter().zip(&defined) {
+        let Some(defined) = defined else { continue };
+        for val in reads(instruction) {
+            if let Val::Identifier(name) = val {
+                if is_temporary(*name) && !defined.contains(name) {
+                    return Err(format!("'{}' reads '{}' before it is written", instruction, name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn verify_tac(program: &Program) -> Result<(), String> {
+    program.interner.enter(|| verify_function(&program.function))
+}
+
+// Why x86-64 can't encode instruction as it stands, if it can't
+fn check_encoding(instruction: &assembly::Instruction) -> Result<(), String> {
+    let operands = instruction.operands();
+    let fail = |problem: &str| Err(format!("'{}' {}", instruction.display(Target::Linux), problem));
+    if operands.iter().any(|(operand, _, _)| matches!(operand, Operand::Pseudo(_))) {
+        return fail("still has a pseudo-register");
+    }
+    // A call's arguments are each moved into place on their own
+    let moved_separately = matches!(instruction, assembly::Instruction::Call(..));
+    if !moved_separately && operands.iter().filter(|(operand, _, _)| operand.is_memory()).count() > 1 {
+        return fail("has more than one memory operand");
+    }
+    if operands.iter().any(|(operand, _, access)| *access != Access::Read && matches!(operand, Operand::Imm(_))) {
+        return fail("writes to an immediate");
+    }
+    match instruction {
+        assembly::Instruction::Binary(assembly::BinaryOperator::Mul, _, _, dst) if !matches!(dst, Operand::Register(_)) => {
+            fail("multiplies into memory, but imul's destination must be a register")
+        }
+        assembly::Instruction::Binary(assembly::BinaryOperator::ShiftLeft | assembly::BinaryOperator::ShiftRight, _, count, _)
+            if !matches!(count, Operand::Imm(_) | Operand::Register(assembly::Reg::CX)) => {
+            fail("shifts by a count that isn't an immediate or %cl")
+        }
+        assembly::Instruction::Cmp(_, _, Operand::Imm(_)) => fail("compares against an immediate on the right"),
+        assembly::Instruction::MovSignExtend(_, _, src, dst) | assembly::Instruction::MovZeroExtend(_, _, src, dst)
+            if matches!(src, Operand::Imm(_)) || !matches!(dst, Operand::Register(_)) => {
+            fail("extends an immediate or into memory, but movs and movz read memory or a register into a register")
+        }
+        assembly::Instruction::Idiv(_, Operand::Imm(_)) | assembly::Instruction::Div(_, Operand::Imm(_)) => fail("divides by an immediate"),
+        assembly::Instruction::Cmov(_, _, _, dst) if !matches!(dst, Operand::Register(_)) => {
+            fail("moves into memory, but cmov's destination must be a register")
+        }
+        // Only a mov into a register takes a 64-bit immediate
+        assembly::Instruction::Mov(_, _, Operand::Register(_)) => Ok(()),
+        _ if operands.iter().any(|(operand, _, _)| matches!(operand, Operand::Imm(value) if i32::try_from(*value).is_err())) => {
+            fail("has an immediate wider than 32 bits")
+        }
+        _ => Ok(()),
+    }
+}
+
+// Checks a function's fixed-up instructions: each can be encoded, the frame
+// is allocated once on entry, and every jump goes to a label
+pub fn verify_instructions(instructions: &[assembly::Instruction]) -> Result<(), String> {
+    let allocations: Vec<usize> = instructions.iter().enumerate()
+        .filter(|(_, instruction)| matches!(instruction, assembly::Instruction::AllocateStack(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if allocations != [0] {
+        return Err("The stack frame must be allocated exactly once, by the first instruction".to_string());
+    }
+    let labels: HashSet<Symbol> = instructions.iter()
+        .filter_map(|instruction| match instruction {
+            assembly::Instruction::Label(label) => Some(*label),
+            _ => None,
+        })
+        .collect();
+    for instruction in instructions {
+        check_encoding(instruction)?;
+        if let assembly::Instruction::Jmp(label) | assembly::Instruction::JmpCC(_, label) = instruction {
+            if !labels.contains(label) {
+                return Err(format!("'{}' jumps to a label that isn't defined", instruction.display(Target::Linux)));
+            }
+        }
+    }
+    Ok(())
+}
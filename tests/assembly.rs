@@ -0,0 +1,170 @@
+use c_compiler_lib::assembly::{emit_assembly, generate_assembly_ast, Access, BinaryOperator, CodeGen, Function, Instruction, Operand, Reg, Size};
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::intern::Interner;
+use c_compiler_lib::driver::compile;
+use c_compiler_lib::parser::Type;
+use c_compiler_lib::tac::{Function as TacFunction, Instruction as TacInstruction, Program as TacProgram, Val};
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::timing::PassTimer;
+use c_compiler_lib::Symbol;
+
+#[test]
+fn explicit_operands_have_a_width_and_an_access() {
+    let (x, y) = (Operand::Stack(-4), Operand::Stack(-8));
+    let add = Instruction::Binary(BinaryOperator::Add, Size::Longword, x.clone(), y.clone());
+    assert_eq!(add.operands(), vec![(&x, Size::Longword, Access::Read), (&y, Size::Longword, Access::ReadWrite)]);
+    let shift = Instruction::Binary(BinaryOperator::ShiftLeft, Size::Quadword, Operand::Register(Reg::CX), y.clone());
+    assert_eq!(shift.operands(), vec![(&Operand::Register(Reg::CX), Size::Byte, Access::Read), (&y, Size::Quadword, Access::ReadWrite)]);
+    let setcc = Instruction::SetCC(CodeGen::E, x.clone());
+    assert_eq!(setcc.operands(), vec![(&x, Size::Byte, Access::ReadWrite)]);
+    assert_eq!(Instruction::Jmp(Symbol::intern("end")).operands(), vec![]);
+}
+
+#[test]
+fn uses_and_defs_include_implicit_registers() {
+    let x = Operand::Stack(-4);
+    let mov = Instruction::Mov(Size::Longword, Operand::Imm(1), x.clone());
+    assert_eq!((mov.uses(), mov.defs()), (vec![Operand::Imm(1)], vec![x.clone()]));
+    let cmp = Instruction::Cmp(Size::Longword, Operand::Imm(0), x.clone());
+    assert_eq!((cmp.uses(), cmp.defs()), (vec![Operand::Imm(0), x.clone()], vec![]));
+    let idiv = Instruction::Idiv(Size::Longword, x.clone());
+    assert_eq!(idiv.uses(), vec![x, Operand::Register(Reg::AX), Operand::Register(Reg::DX)]);
+    assert_eq!(idiv.defs(), vec![Operand::Register(Reg::AX), Operand::Register(Reg::DX)]);
+    let cdq = Instruction::Cdq(Size::Longword);
+    assert_eq!((cdq.uses(), cdq.defs()), (vec![Operand::Register(Reg::AX)], vec![Operand::Register(Reg::DX)]));
+    assert_eq!(Instruction::Ret.uses(), vec![Operand::Register(Reg::AX)]);
+}
+
+#[test]
+fn operands_are_mapped_by_position() {
+    let x = Operand::Stack(-4);
+    // x = x + x, where only the source may move to a register
+    let add = Instruction::Binary(BinaryOperator::Add, Size::Longword, x.clone(), x.clone());
+    let mapped = add.map_operands(|index, operand| if index == 0 { Operand::Register(Reg::R10) } else { operand.clone() });
+    assert_eq!(mapped.operands(), vec![(&Operand::Register(Reg::R10), Size::Longword, Access::Read), (&x, Size::Longword, Access::ReadWrite)]);
+}
+
+#[test]
+fn artifacts_describe_the_generated_code() {
+    let program = compile("int main(void) { int a = 1; return a; }", &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    let options = CodegenOptions::new(Target::MacOs);
+    let artifacts = emit_assembly(&program, &options);
+    assert_eq!(artifacts.assembly, program.to_assembly_file(&options));
+    assert_eq!(artifacts.symbols, vec!["_main".to_string()]);
+    assert_eq!(artifacts.stack_sizes, vec![("main".to_string(), 4)]);
+    assert!(artifacts.diagnostics.is_empty());
+}
+
+#[test]
+fn inline_asm_without_a_frame_pointer_is_warned_about() {
+    let program = compile("int main(void) { __asm__(\"nop\"); return 0; }", &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    let options = CodegenOptions { omit_frame_pointer: true, ..CodegenOptions::new(Target::Linux) };
+    let diagnostics = emit_assembly(&program, &options).diagnostics;
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.contains("-fomit-frame-pointer"));
+}
+
+#[test]
+fn labels_are_local_and_named_after_their_function() {
+    let source = "int main(void) {\n  int a = 1;\n  int b = a ? 2 : 3;\n  return a && b;\n}\n";
+    let assembly = compile(source, &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    let text = assembly.to_assembly_file(&CodegenOptions::new(Target::Linux));
+    let labels: Vec<&str> = text.lines().filter(|line| line.starts_with(".L")).collect();
+    assert_eq!(labels, [".Lmain_0:", ".Lmain_1:", ".Lmain_2:"]);
+    // The same source always gets the same labels
+    let again = compile(source, &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    assert_eq!(again.to_assembly_file(&CodegenOptions::new(Target::Linux)), text);
+    let macos = assembly.to_assembly_file(&CodegenOptions::new(Target::MacOs));
+    assert!(macos.lines().any(|line| line == "Lmain_0:"));
+}
+
+#[test]
+fn ternaries_pick_with_cmov_at_o1() {
+    let source = "int main(void) {\n  int a = 7;\n  int b = 3;\n  int min = a < b ? a : b;\n  return min;\n}\n";
+    let assembly = compile(source, &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    let o0 = assembly.to_assembly_file(&CodegenOptions::new(Target::Linux));
+    assert!(!o0.contains("cmov"));
+    let o1 = assembly.to_assembly_file(&CodegenOptions { opt_level: 1, ..CodegenOptions::new(Target::Linux) });
+    assert!(o1.contains("    cmove -8(%rbp), %r11d\n"), "{}", o1);
+    assert!(!o1.contains(".Lmain_"));
+}
+
+#[test]
+fn cmov_never_reads_an_extern_the_branch_avoids() {
+    let source = "int main(void) {\n  extern int counter;\n  int a = 0;\n  int r = a ? counter : 1;\n  return r;\n}\n";
+    let assembly = compile(source, &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    let o1 = assembly.to_assembly_file(&CodegenOptions { opt_level: 1, ..CodegenOptions::new(Target::Linux) });
+    assert!(!o1.contains("cmov"), "{}", o1);
+}
+
+// The instructions fix_mov turns instruction into, after the frame
+fn fixed(instruction: Instruction) -> Vec<String> {
+    let mut function = Function::new(Symbol::intern("main"), vec![instruction]);
+    function.fix_mov(16);
+    function.instructions()[1..].iter().map(|instruction| instruction.display(Target::Linux).to_string()).collect()
+}
+
+#[test]
+fn only_immediates_wider_than_32_bits_go_through_a_register() {
+    let x = Operand::Stack(-8);
+    let add = |value: i64| Instruction::Binary(BinaryOperator::Add, Size::Quadword, Operand::Imm(value), x.clone());
+    let mov = |value: i64| Instruction::Mov(Size::Quadword, Operand::Imm(value), x.clone());
+    let cmp = |value: i64| Instruction::Cmp(Size::Quadword, Operand::Imm(value), x.clone());
+    // Sign-extended, i32::MIN and i32::MAX still fit
+    for value in [i32::MAX as i64, i32::MIN as i64] {
+        assert_eq!(fixed(add(value)), [format!("addq ${}, -8(%rbp)", value)]);
+        assert_eq!(fixed(mov(value)), [format!("movq ${}, -8(%rbp)", value)]);
+        assert_eq!(fixed(cmp(value)), [format!("cmpq ${}, -8(%rbp)", value)]);
+    }
+    for value in [i32::MAX as i64 + 1, i32::MIN as i64 - 1] {
+        let load = format!("movq ${}, %r10", value);
+        assert_eq!(fixed(add(value)), [load.clone(), "addq %r10, -8(%rbp)".to_string()]);
+        assert_eq!(fixed(mov(value)), [load.clone(), "movq %r10, -8(%rbp)".to_string()]);
+        assert_eq!(fixed(cmp(value)), [load, "cmpq %r10, -8(%rbp)".to_string()]);
+    }
+    // A mov into a register takes any 64-bit immediate
+    let wide = Instruction::Mov(Size::Quadword, Operand::Imm(i32::MAX as i64 + 1), Operand::Register(Reg::AX));
+    assert_eq!(fixed(wide), ["movq $2147483648, %rax"]);
+}
+
+#[test]
+fn extensions_into_memory_go_through_a_register() {
+    let (x, y) = (Operand::Stack(-4), Operand::Stack(-8));
+    let sign = Instruction::MovSignExtend(Size::Byte, Size::Longword, x.clone(), y.clone());
+    assert_eq!(fixed(sign), ["movsbl -4(%rbp), %r11d", "movl %r11d, -8(%rbp)"]);
+    let zero = Instruction::MovZeroExtend(Size::Word, Size::Longword, x, Operand::Register(Reg::AX));
+    assert_eq!(fixed(zero), ["movzwl -4(%rbp), %eax"]);
+}
+
+#[test]
+fn narrowing_conversions_extend_the_low_bytes_back() {
+    let (a, narrow) = (Val::Identifier(Symbol::intern("a")), Val::Identifier(Symbol::intern("tmp.1")));
+    let program = |target: Type, src: Val| TacProgram {
+        function: TacFunction {
+            identifier: Symbol::intern("main"),
+            body: vec![
+                TacInstruction::Copy { src: Val::Constant(200), dst: a.clone() },
+                TacInstruction::Narrow { target, src, dst: narrow.clone() },
+                TacInstruction::Return(narrow.clone()),
+            ],
+            volatiles: Vec::new(),
+        },
+        externs: Vec::new(),
+        statics: Vec::new(),
+        interner: Interner::current(),
+    };
+    let assembly = |program: TacProgram| {
+        let mut assembly = generate_assembly_ast(program);
+        assembly.apply_fixes();
+        assembly.to_assembly_file(&CodegenOptions::new(Target::Linux))
+    };
+    let signed = assembly(program(Type::Char, a.clone()));
+    // a is dead once it is read, so the result takes its slot
+    assert!(signed.contains("movsbl -4(%rbp), %r11d\n    movl %r11d, -4(%rbp)"), "{}", signed);
+    let unsigned = assembly(program(Type::UShort, a.clone()));
+    assert!(unsigned.contains("movzwl -4(%rbp), %r11d"), "{}", unsigned);
+    // A constant is converted before it is moved into place
+    let constant = assembly(program(Type::Char, Val::Constant(200)));
+    assert!(constant.contains("movl $-56, -"), "{}", constant);
+    assert!(!constant.contains("movs"), "{}", constant);
+}
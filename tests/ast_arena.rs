@@ -0,0 +1,37 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::lex::Lex;
+use c_compiler_lib::parser::{self, BinaryOp, BlockItem, Exp, FunctionDeclaration, Program, Statement};
+use c_compiler_lib::typecheck;
+
+fn parse(source: &str) -> Program {
+    let mut tokens = Lex::new(source).get_tokens().unwrap();
+    parser::parse_program(&mut tokens, &Dialect::default()).unwrap()
+}
+
+#[test]
+fn expressions_refer_to_their_operands_in_the_arena() {
+    let Program::Program(_, FunctionDeclaration::Function(_, items, _), exps, _) = parse("int main(void) { return 1 + 2 * 3; }");
+    // 1, 2, 3, 2 * 3 and the sum
+    assert_eq!(exps.len(), 5);
+    let BlockItem::S(Statement::Return(exp)) = items[0].as_ref() else { panic!("expected a return") };
+    let Exp::Binary(left, BinaryOp::Add, right) = &exps[*exp] else { panic!("expected a sum, got {:?}", exps[*exp]) };
+    assert!(matches!(exps[*left], Exp::Constant(1, _)));
+    assert!(matches!(exps[*right], Exp::Binary(_, BinaryOp::Multiply, _)));
+}
+
+#[test]
+fn resolution_and_typechecking_rewrite_expressions_in_place() {
+    let return_value = |program: &Program| {
+        let Program::Program(_, FunctionDeclaration::Function(_, items, _), ..) = program;
+        let BlockItem::S(Statement::Return(exp)) = items[1].as_ref() else { panic!("expected a return") };
+        *exp
+    };
+    let program = parse("int main(void) { int x = 1; return x; }");
+    let parsed = return_value(&program);
+    let program = typecheck::typecheck_program(parser::resolve_program(program).unwrap()).unwrap();
+    // Every operand is already an int, so no cast was added
+    assert_eq!(return_value(&program), parsed);
+    let Program::Program(_, _, exps, _) = &program;
+    assert_eq!(exps.len(), 2);
+    assert!(matches!(&exps[parsed], Exp::Var(name, _) if &*name.text() == "x"));
+}
@@ -0,0 +1,70 @@
+use c_compiler_lib::optimize::lay_out_blocks;
+use c_compiler_lib::tac::{Function, Instruction, Val};
+use c_compiler_lib::Symbol;
+
+fn lay_out(body: Vec<Instruction>) -> Vec<String> {
+    let mut function = Function { identifier: Symbol::intern("main"), body, volatiles: Vec::new() };
+    lay_out_blocks(&mut function);
+    function.body.iter().map(|instruction| instruction.to_string()).collect()
+}
+
+fn var(name: &str) -> Val {
+    Val::Identifier(Symbol::intern(name))
+}
+
+fn label(name: &str) -> Symbol {
+    Symbol::intern(name)
+}
+
+#[test]
+fn jump_to_next_block_is_removed() {
+    let lines = lay_out(vec![
+        Instruction::Copy { src: Val::Constant(1), dst: var("x") },
+        Instruction::Jump { label: label("main_0") },
+        Instruction::Label { label: label("main_0") },
+        Instruction::Return(var("x")),
+    ]);
+    assert_eq!(lines, ["x = 1", "return x"]);
+}
+
+#[test]
+fn jump_target_follows_the_jump() {
+    // main_1 is only reached by the jump, so it moves up to replace it
+    let lines = lay_out(vec![
+        Instruction::JumpIfZero { src: var("x"), label: label("main_0"), likely: None },
+        Instruction::Jump { label: label("main_1") },
+        Instruction::Label { label: label("main_0") },
+        Instruction::Return(Val::Constant(2)),
+        Instruction::Label { label: label("main_1") },
+        Instruction::Return(Val::Constant(3)),
+    ]);
+    assert_eq!(lines, [
+        "if x == 0 jump main_0",
+        "return 3",
+        "main_0:",
+        "return 2",
+    ]);
+}
+
+#[test]
+fn blocks_fallen_into_keep_their_place() {
+    // main_0 is already fallen into, so the jump to it stays
+    let lines = lay_out(vec![
+        Instruction::JumpIfZero { src: var("x"), label: label("main_1"), likely: None },
+        Instruction::Copy { src: Val::Constant(1), dst: var("y") },
+        Instruction::Label { label: label("main_0") },
+        Instruction::Return(var("y")),
+        Instruction::Label { label: label("main_1") },
+        Instruction::Copy { src: Val::Constant(2), dst: var("y") },
+        Instruction::Jump { label: label("main_0") },
+    ]);
+    assert_eq!(lines, [
+        "if x == 0 jump main_1",
+        "y = 1",
+        "main_0:",
+        "return y",
+        "main_1:",
+        "y = 2",
+        "jump main_0",
+    ]);
+}
@@ -0,0 +1,133 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Runner for the "Writing a C Compiler" test suite
+// (https://github.com/nlsandler/writing-a-c-compiler-tests). Set WACC_TESTS
+// to a checkout of it and WACC_CHAPTER to the last chapter to check, e.g.
+//
+//     WACC_TESTS=../writing-a-c-compiler-tests WACC_CHAPTER=4 cargo test --test book
+//
+// Without them the test does nothing. Programs under an invalid_* directory
+// must be rejected by the stage that directory names, using the matching
+// stage flag; valid programs are compiled with -S, linked and run, and their
+// exit code compared with the suite's expected_results.json. Extra-credit
+// programs are skipped.
+
+const COMPILER: &str = env!("CARGO_BIN_EXE_c_compiler");
+
+// The stage flag that must reject the programs in an invalid_* directory
+fn stage_flag(category: &str) -> Option<&'static str> {
+    match category {
+        "invalid_lex" => Some("--lex"),
+        "invalid_parse" => Some("--parse"),
+        "valid" => None,
+        // invalid_semantics, invalid_declarations, invalid_types, invalid_labels, ...
+        _ => Some("--validate"),
+    }
+}
+
+fn c_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for path in entries.map(|entry| entry.unwrap().path()) {
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != "extra_credit") {
+                files.extend(c_files(&path));
+            }
+        } else if path.extension().is_some_and(|ext| ext == "c") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+// Finds the return code recorded for a program in expected_results.json,
+// whose entries look like "chapter_1/valid/return_2.c": {"return_code": 2}
+fn expected_return_code(results: &str, relative: &str) -> Option<i32> {
+    let entry = &results[results.find(&format!("\"{}\"", relative))?..];
+    let value = &entry[entry.find("\"return_code\"")? + "\"return_code\"".len()..];
+    let value = value.trim_start().strip_prefix(':')?.trim_start();
+    let end = value.find(|c: char| !(c.is_ascii_digit() || c == '-')).unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+fn run_valid(source: &Path, expected: i32, out_dir: &Path) -> Result<(), String> {
+    let name = source.file_stem().unwrap().to_string_lossy().into_owned();
+    // Work on a copy so nothing is written into the suite checkout
+    let copy = out_dir.join(format!("{}.c", name));
+    fs::copy(source, &copy).map_err(|err| err.to_string())?;
+    let output = Command::new(COMPILER).arg("-S").arg(&copy).output().map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(format!("failed to compile: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let executable = out_dir.join(&name);
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(&executable)
+        .arg(copy.with_extension("s"))
+        .status()
+        .map_err(|err| err.to_string())?;
+    if !status.success() {
+        return Err("failed to assemble and link".to_string());
+    }
+    let code = Command::new(&executable).status().map_err(|err| err.to_string())?.code();
+    if code != Some(expected) {
+        return Err(format!("expected exit code {}, got {:?}", expected, code));
+    }
+    Ok(())
+}
+
+#[test]
+fn book_test_suite() {
+    let (Some(suite), Some(chapter)) = (env::var_os("WACC_TESTS"), env::var("WACC_CHAPTER").ok()) else {
+        return;
+    };
+    let suite = PathBuf::from(suite);
+    let last_chapter: u32 = chapter.parse().expect("WACC_CHAPTER must be a chapter number");
+    let results = fs::read_to_string(suite.join("expected_results.json")).expect("no expected_results.json in WACC_TESTS");
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("book");
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let mut failures = Vec::new();
+    for chapter in 1..=last_chapter {
+        let chapter_dir = suite.join(format!("tests/chapter_{}", chapter));
+        let mut categories: Vec<PathBuf> = fs::read_dir(&chapter_dir)
+            .unwrap_or_else(|err| panic!("can't read {}: {}", chapter_dir.display(), err))
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect();
+        categories.sort();
+
+        for category_dir in categories {
+            let category = category_dir.file_name().unwrap().to_string_lossy().into_owned();
+            let files = c_files(&category_dir);
+            let mut passed = 0;
+            for file in &files {
+                let relative = file.strip_prefix(suite.join("tests")).unwrap().to_string_lossy().replace('\\', "/");
+                let result = match stage_flag(&category) {
+                    Some(flag) => match Command::new(COMPILER).arg(flag).arg(file).output() {
+                        Ok(output) if output.status.success() => Err(format!("was accepted by {}", flag)),
+                        Ok(_) => Ok(()),
+                        Err(err) => Err(err.to_string()),
+                    },
+                    None => match expected_return_code(&results, &relative) {
+                        Some(expected) => run_valid(file, expected, &out_dir),
+                        None => Err("no expected result".to_string()),
+                    },
+                };
+                match result {
+                    Ok(()) => passed += 1,
+                    Err(message) => failures.push(format!("{}: {}", relative, message)),
+                }
+            }
+            println!("chapter {} {}: {}/{} passed", chapter, category, passed, files.len());
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,51 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::tac::Instruction;
+
+fn tac(body: &str) -> Vec<String> {
+    let source = format!("int main(void) {{\n{}\n}}\n", body);
+    let tac = compile_to_tac(&source, &Dialect::default()).unwrap();
+    tac.function.body.iter().map(|instruction| match instruction {
+        Instruction::Label { .. } => instruction.to_string(),
+        _ => format!("    {}", instruction),
+    }).collect()
+}
+
+#[test]
+fn hints_are_recorded_on_the_jumps() {
+    let lines = tac("int x = 3;\nreturn __builtin_expect(x, 0) || x > 1;");
+    assert!(lines.contains(&"    if x != 0 jump main_0 (unlikely)".to_string()), "{:#?}", lines);
+    // Only the operand under the hint gets one
+    assert!(lines.contains(&"    if tmp.2 != 0 jump main_0".to_string()), "{:#?}", lines);
+}
+
+#[test]
+fn likely_path_falls_through() {
+    // The else arm is likely, so it comes straight after the test, which
+    // jumps to the then arm instead
+    let lines = tac("int x = 3;\nreturn __builtin_expect(x > 5, 0) ? x * 2 : x - 1;");
+    assert_eq!(lines, [
+        "    x = 3",
+        "    tmp.1 = x > 5",
+        "    if tmp.1 != 0 jump main_block_1 (unlikely)",
+        "    tmp.7 = x - 1",
+        "    tmp.2 = tmp.7",
+        "    return tmp.2",
+        "main_block_1:",
+        "    tmp.3 = x * 2",
+        "    tmp.2 = tmp.3",
+        "    return tmp.2",
+    ]);
+    // Without the hint, the layout follows the source
+    let lines = tac("int x = 3;\nreturn x > 5 ? x * 2 : x - 1;");
+    assert_eq!(lines[2], "    if tmp.1 == 0 jump main_0");
+}
+
+#[test]
+fn expected_value_must_be_constant() {
+    let source = "int main(void) {\n  int x = 1;\n  return __builtin_expect(x, x);\n}\n";
+    assert_eq!(compile_to_tac(source, &Dialect::default()).unwrap_err().to_string(),
+        "3:30: Expected value of '__builtin_expect' must be an integer constant: Variable 'x' is not allowed in an integer constant expression");
+    // The hint doesn't stop the value being constant itself
+    assert!(compile_to_tac("int main(void) {\n  _Static_assert(__builtin_expect(2, 1) == 2, \"\");\n  return 0;\n}\n", &Dialect::default()).is_ok());
+}
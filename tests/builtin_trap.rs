@@ -0,0 +1,80 @@
+mod common;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::optimize::prune_unreachable;
+use c_compiler_lib::tac::{Function, Instruction, Val};
+use c_compiler_lib::Symbol;
+use common::run_compiler;
+use std::fs;
+use std::process::Command;
+
+fn tac(body: &str) -> Vec<String> {
+    let source = format!("int main(void) {{\n{}\n}}\n", body);
+    let tac = compile_to_tac(&source, &Dialect::default()).unwrap();
+    tac.function.body.iter().map(|instruction| instruction.to_string()).collect()
+}
+
+fn prune(body: Vec<Instruction>) -> Vec<String> {
+    let mut function = Function { identifier: Symbol::intern("main"), body, volatiles: Vec::new() };
+    prune_unreachable(&mut function);
+    function.body.iter().map(|instruction| instruction.to_string()).collect()
+}
+
+fn var(name: &str) -> Val {
+    Val::Identifier(Symbol::intern(name))
+}
+
+fn label(name: &str) -> Symbol {
+    Symbol::intern(name)
+}
+
+#[test]
+fn code_after_the_builtins_is_removed() {
+    assert_eq!(tac("int x = 3;\n__builtin_unreachable();\nreturn x;"), ["x = 3", "unreachable"]);
+    assert_eq!(tac("int x = 3;\n__builtin_trap();\nreturn x;"), ["x = 3", "trap"]);
+}
+
+#[test]
+fn builtins_have_no_value() {
+    let error = compile_to_tac("int main(void) {\n  return __builtin_trap();\n}\n", &Dialect::default()).unwrap_err();
+    assert_eq!(error.to_string(), "2:10: '__builtin_trap' returns void, so it can't be used as a value");
+}
+
+#[test]
+fn branches_to_unreachable_code_are_never_taken() {
+    let lines = prune(vec![
+        Instruction::JumpIfZero { src: var("x"), label: label("main_0"), likely: None },
+        Instruction::Return(Val::Constant(1)),
+        Instruction::Label { label: label("main_0") },
+        Instruction::Unreachable,
+    ]);
+    assert_eq!(lines, ["return 1"]);
+    // Falling into it can't happen either, so the jump always is taken
+    let lines = prune(vec![
+        Instruction::JumpIfNotZero { src: var("x"), label: label("main_0"), likely: None },
+        Instruction::Unreachable,
+        Instruction::Copy { src: Val::Constant(2), dst: var("y") },
+        Instruction::Label { label: label("main_0") },
+        Instruction::Return(var("x")),
+    ]);
+    assert_eq!(lines, ["jump main_0", "main_0:", "return x"]);
+}
+
+#[test]
+fn trap_stops_the_program() {
+    let dir = common::write_input("builtin_trap", "trap", "int main(void) {\n  int x = 3;\n  __builtin_trap();\n  return x;\n}\n");
+    let output = run_compiler(&dir, ["-S", "input.c"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let assembly = fs::read_to_string(dir.join("input.s")).unwrap();
+    assert!(assembly.contains("    ud2\n"), "{}", assembly);
+    assert!(!assembly.contains("ret"), "{}", assembly);
+
+    let output = run_compiler(&dir, ["input.c"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let status = Command::new(dir.join("input")).status().unwrap();
+    assert!(!status.success());
+    // SIGILL
+    #[cfg(unix)]
+    assert_eq!(std::os::unix::process::ExitStatusExt::signal(&status), Some(4));
+}
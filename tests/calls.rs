@@ -0,0 +1,87 @@
+mod common;
+
+use c_compiler_lib::assembly::emit_assembly;
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{compile, compile_to_tac};
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::timing::PassTimer;
+use std::fs;
+use std::process::Command;
+
+fn assembly(source: &str, options: &CodegenOptions) -> String {
+    let program = compile(source, &Dialect::default(), &mut PassTimer::new(false)).unwrap();
+    emit_assembly(&program, options).assembly
+}
+
+fn error(source: &str) -> String {
+    compile_to_tac(source, &Dialect::default()).unwrap_err().to_string()
+}
+
+const PUTCHAR: &str = "int putchar(int c);\n\nint main(void) {\n  putchar('h');\n  putchar('i');\n  return putchar(10) - 7;\n}\n";
+
+#[test]
+fn declared_functions_can_be_called() {
+    let dir = common::compiled("calls", "putchar", PUTCHAR, &[]);
+    let output = Command::new(dir.join("input")).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn calls_are_spelled_for_the_target() {
+    let mut options = CodegenOptions::new(Target::Linux);
+    assert!(assembly(PUTCHAR, &options).contains("    call putchar\n"));
+    options.pic = true;
+    assert!(assembly(PUTCHAR, &options).contains("    call putchar@PLT\n"));
+    assert!(assembly(PUTCHAR, &CodegenOptions::new(Target::MacOs)).contains("    call _putchar\n"));
+    // Microsoft x64 passes the first argument in %ecx, with 32 bytes of
+    // shadow space above the return address
+    let windows = assembly(PUTCHAR, &CodegenOptions::new(Target::Windows));
+    assert!(windows.contains("    movl $104, %ecx\n    subq $32, %rsp\n    call putchar\n    addq $32, %rsp\n"), "{}", windows);
+}
+
+#[test]
+fn frames_stay_aligned_for_calls() {
+    // One local would need only 4 bytes
+    let source = "int f(void);\nint main(void) {\n  int x = f();\n  return x;\n}\n";
+    assert!(assembly(source, &CodegenOptions::new(Target::Linux)).contains("    subq $16, %rsp\n"));
+    let mut options = CodegenOptions::new(Target::Linux);
+    options.omit_frame_pointer = true;
+    assert!(assembly(source, &options).contains("    subq $24, %rsp\n"));
+}
+
+// weigh tells its arguments apart, and fails if the stack wasn't aligned
+const WEIGH: &str = "int weigh(int a, int b, int c, int d, int e, int f, int g, int h) {\n  if ((unsigned long)__builtin_frame_address(0) % 16) return -1;\n  return a + 2*b + 3*c + 4*d + 5*e + 6*f + 7*g + 8*h;\n}\n";
+
+#[cfg(unix)]
+#[test]
+fn arguments_past_the_sixth_go_on_the_stack() {
+    let source = "int weigh(int a, int b, int c, int d, int e, int f, int g, int h);\nint main(void) {\n  int x = 4;\n  return weigh(1, 2, 3, x, 5, 6, x + 3, 8);\n}\n";
+    for flags in [&[][..], &["-fomit-frame-pointer"][..]] {
+        let dir = common::write_input("calls", &format!("stack{}", flags.len()), source);
+        fs::write(dir.join("weigh.c"), WEIGH).unwrap();
+        let output = common::run_compiler(&dir, [flags, &["-S", "input.c"]].concat());
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let status = Command::new("cc").args(["-fno-omit-frame-pointer", "-no-pie", "input.s", "weigh.c", "-o", "input"]).current_dir(&dir).status().unwrap();
+        assert!(status.success());
+        // 1 + 4 + 9 + 16 + 25 + 36 + 49 + 64
+        assert_eq!(Command::new(dir.join("input")).status().unwrap().code(), Some(204), "{:?}", flags);
+    }
+}
+
+#[test]
+fn calls_are_checked_against_the_declaration() {
+    assert_eq!(error("int main(void) {\n  return f(1);\n}\n"), "2:10: Function 'f' not declared");
+    assert_eq!(error("int f(int a, int b);\nint main(void) {\n  return f(1);\n}\n"), "3:10: 'f' takes 2 arguments, but 1 was given");
+    assert_eq!(error("int f(void);\nint main(void) {\n  return f + 1;\n}\n"), "3:10: Function 'f' can only be called, as there are no function pointers yet");
+    assert_eq!(error("int main(void) {\n  int f = 1;\n  return f();\n}\n"), "3:10: Called object 'f' is not a function");
+    assert_eq!(error("int f(int a);\nint f(void);\nint main(void) {\n  return 0;\n}\n"), "2:5: Conflicting types for 'f': function taking (int) returning int and function returning int");
+    // Only functions declared before main are in scope in it
+    assert_eq!(error("int main(void) {\n  return f();\n}\nint f(void);\n"), "2:10: Function 'f' not declared");
+}
+
+#[test]
+fn a_local_can_hide_a_function() {
+    let tac = compile_to_tac("int f(void);\nint main(void) {\n  int f = 2;\n  return f;\n}\n", &Dialect::default()).unwrap();
+    assert_eq!(tac.function.body.last().unwrap().to_string(), "return f.1");
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use std::process::Output;
+
+fn check(name: &str, source: &str, args: &[&str]) -> Output {
+    common::compile("check", name, source, &[&["--check"], args].concat()).1
+}
+
+#[test]
+fn check_reports_located_errors() {
+    let output = check("undeclared", "int main(void) {\n  return a;\n}\n", &[]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+        "input.c:2:10: error[E0001]: Variable 'a' not declared\n");
+}
+
+#[test]
+fn check_writes_json_diagnostics() {
+    let output = check("valid", "int main(void) { return 0; }\n", &["-fdiagnostics-format=json"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+        "{\"file\":\"input.c\",\"diagnostics\":[],\"warnings\":[]}\n");
+
+    let output = check("missing_semicolon", "int main(void) { return 0 }\n", &["-fdiagnostics-format=json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("[{\"message\":\"Expected ';', got '}'\",\"line\":1,\"column\":27,\"code\":\"E0005\"}]"));
+}
+
+#[test]
+fn gnu_extensions_need_a_flag() {
+    let source = "int main(void) {\n  return 0 ?: 1;\n}\n";
+    let output = check("gnu_conditional", source, &[]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+        "input.c:2:13: error[E0013]: Omitting the middle operand of '?:' is a GNU extension; enable it with --gnu-extensions\n");
+
+    let output = check("gnu_extensions", source, &["--gnu-extensions"]);
+    assert!(output.status.success());
+}
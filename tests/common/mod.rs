@@ -0,0 +1,78 @@
+// Each test file includes this module and uses only some of it
+#![allow(dead_code)]
+
+use c_compiler_lib::dialect::Dialect;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+pub const COMPILER: &str = env!("CARGO_BIN_EXE_c_compiler");
+
+// The corpus of C programs in tests/programs, in name order
+pub fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("can't read {}: {}", dir.display(), err))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// The dialect a program is written in, from a "// flags: ..." line
+pub fn dialect(source: &str) -> Dialect {
+    let mut dialect = Dialect::default();
+    let flags = source.lines().find_map(|line| line.strip_prefix("// flags:")).unwrap_or_default();
+    for flag in flags.split_whitespace() {
+        assert_eq!(dialect.parse_flag(flag), Ok(true), "unknown flag '{}'", flag);
+    }
+    dialect
+}
+
+pub fn name(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().into_owned()
+}
+
+// A fresh, empty directory for one test: group names the test file, and
+// name the test within it
+pub fn scratch_dir(group: &str, name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(group).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Writes source to input.c in a fresh directory, and returns the directory
+pub fn write_input(group: &str, name: &str, source: &str) -> PathBuf {
+    let dir = scratch_dir(group, name);
+    fs::write(dir.join("input.c"), source).unwrap();
+    dir
+}
+
+// The compiler, to be run in dir
+pub fn compiler(dir: &Path) -> Command {
+    let mut command = Command::new(COMPILER);
+    command.current_dir(dir);
+    command
+}
+
+pub fn run_compiler<S: AsRef<OsStr>>(dir: &Path, args: impl IntoIterator<Item = S>) -> Output {
+    compiler(dir).args(args).output().unwrap()
+}
+
+// Writes source to input.c in a fresh directory and compiles it there, with
+// args before the input file
+pub fn compile(group: &str, name: &str, source: &str, args: &[&str]) -> (PathBuf, Output) {
+    let dir = write_input(group, name, source);
+    let output = run_compiler(&dir, args.iter().chain(&["input.c"]));
+    (dir, output)
+}
+
+// compile, for a program that must compile
+pub fn compiled(group: &str, name: &str, source: &str, args: &[&str]) -> PathBuf {
+    let (dir, output) = compile(group, name, source, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    dir
+}
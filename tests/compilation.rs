@@ -0,0 +1,42 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{Artifact, Compilation, CompileError, Stage};
+use c_compiler_lib::parser::{FunctionDeclaration, Program};
+use c_compiler_lib::target::{CodegenOptions, Target};
+
+const SOURCE: &str = "int main(void) {\n  int x = 1;\n  return x + 2;\n}\n";
+
+#[test]
+fn each_stage_keeps_its_artifact() {
+    let mut compilation = Compilation::new(SOURCE, &Dialect::default());
+    assert!(compilation.artifact(Stage::Lex).is_none());
+    match compilation.run_until(Stage::Tacky).unwrap() {
+        Artifact::Tac(tac) => assert_eq!(&*tac.function.identifier.text(), "main"),
+        other => panic!("expected TAC, got {:?}", other),
+    }
+    // The earlier stages are still there, and codegen hasn't run
+    assert!(matches!(compilation.artifact(Stage::Lex), Some(Artifact::Tokens(tokens)) if tokens.len() == 17));
+    assert!(matches!(compilation.artifact(Stage::Parse), Some(Artifact::Ast(_))));
+    match compilation.artifact(Stage::Validate) {
+        Some(Artifact::ResolvedAst(Program::Program(_, FunctionDeclaration::Function(name, items, _), ..))) => {
+            assert_eq!(&*name.text(), "main");
+            assert_eq!(items.len(), 2);
+        }
+        other => panic!("expected a resolved AST, got {:?}", other),
+    }
+    assert!(compilation.artifact(Stage::Codegen).is_none());
+    match compilation.run_until(Stage::Codegen).unwrap() {
+        Artifact::Asm(assembly) => assert!(assembly.to_assembly_file(&CodegenOptions::new(Target::Linux)).contains("main:")),
+        other => panic!("expected assembly, got {:?}", other),
+    }
+}
+
+#[test]
+fn a_failed_stage_keeps_the_earlier_artifacts() {
+    let mut compilation = Compilation::new("int main(void) {\n  return y;\n}\n", &Dialect::default());
+    match compilation.run_until(Stage::Codegen) {
+        Err(CompileError::Parse(message)) => assert!(message.contains("'y'"), "{}", message),
+        other => panic!("expected a resolution error, got {:?}", other.map(|_| ())),
+    }
+    assert!(matches!(compilation.artifact(Stage::Parse), Some(Artifact::Ast(_))));
+    assert!(compilation.artifact(Stage::Validate).is_none());
+}
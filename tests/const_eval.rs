@@ -0,0 +1,43 @@
+use c_compiler_lib::const_eval::{eval_constant, ConstEvaluator};
+use c_compiler_lib::lex::Span;
+use c_compiler_lib::parser::{BinaryOp, Exp, ExpArena, ExpId, UnaryOp};
+use c_compiler_lib::Symbol;
+
+fn constant(exps: &mut ExpArena, value: i32) -> ExpId {
+    exps.alloc(Exp::Constant(value, Span::default()))
+}
+
+#[test]
+fn each_subexpression_is_evaluated_once() {
+    // -(1 + 2) * (1 + 2), where both sums are the same node
+    let mut exps = ExpArena::new();
+    let (one, two) = (constant(&mut exps, 1), constant(&mut exps, 2));
+    let sum = exps.alloc(Exp::Binary(one, BinaryOp::Add, two));
+    let negated = exps.alloc(Exp::Unary(UnaryOp::Negation, sum));
+    let product = exps.alloc(Exp::Binary(negated, BinaryOp::Multiply, sum));
+
+    let mut constants = ConstEvaluator::new(&exps);
+    assert_eq!(constants.eval(product), Ok(-9));
+    assert_eq!(constants.evaluations(), 5);
+    // Asking again, or about any part of it, finds the value already known
+    assert_eq!(constants.eval(product), Ok(-9));
+    assert_eq!(constants.eval(sum), Ok(3));
+    assert_eq!(constants.eval(one), Ok(1));
+    assert_eq!(constants.evaluations(), 5);
+}
+
+#[test]
+fn errors_are_remembered_too() {
+    let mut exps = ExpArena::new();
+    let x = exps.alloc(Exp::Var(Symbol::intern("x"), Span::default()));
+    let one = constant(&mut exps, 1);
+    let sum = exps.alloc(Exp::Binary(x, BinaryOp::Add, one));
+
+    let mut constants = ConstEvaluator::new(&exps);
+    let err = constants.eval(sum).unwrap_err();
+    assert_eq!(err.message, "Variable 'x' is not allowed in an integer constant expression");
+    assert_eq!(constants.eval(sum), Err(err.clone()));
+    assert_eq!(constants.eval(x), Err(err));
+    assert_eq!(constants.evaluations(), 2);
+    assert_eq!(eval_constant(&exps, sum), constants.eval(sum));
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver;
+
+// (line, column, message) for each warning about source
+fn warnings(source: &str) -> Vec<(usize, usize, String)> {
+    driver::warnings(source, &Dialect::default()).unwrap().into_iter()
+        .map(|warning| (warning.line.unwrap(), warning.column.unwrap(), warning.message))
+        .collect()
+}
+
+#[test]
+fn deciding_constants_in_logical_operators_are_reported() {
+    let source = "int main(void) {\n    int x = 3;\n    x = x && 0;\n    x = 0 && x;\n    x = x || 1 + 1;\n    return 1 || x;\n}\n";
+    assert_eq!(warnings(source), vec![
+        (3, 14, "Right operand of '&&' is always 0, so the condition is always false".to_string()),
+        (4, 9, "Left operand of '&&' is always 0, so the right one is never evaluated".to_string()),
+        (5, 14, "Right operand of '||' is never 0, so the condition is always true".to_string()),
+        (6, 12, "Left operand of '||' is never 0, so the right one is never evaluated".to_string()),
+    ]);
+}
+
+#[test]
+fn constants_that_do_not_decide_are_not_reported() {
+    assert!(warnings("int main(void) { int x = 3; return (x && 1) + (x || 0) + (1 && x); }").is_empty());
+}
+
+#[test]
+fn constant_conditions_of_conditionals_are_reported() {
+    let source = "int main(void) {\n    int x = 3;\n    x = (1 - 1) ? x : 2;\n    return -4 ? x : 2;\n}\n";
+    assert_eq!(warnings(source), vec![
+        (3, 10, "Condition of '?:' is always 0, so its second operand is never evaluated".to_string()),
+        (4, 12, "Condition of '?:' is never 0, so its last operand is never evaluated".to_string()),
+    ]);
+}
+
+#[test]
+fn constant_expressions_are_not_reported() {
+    let source = "_Static_assert(1 || 0, \"folded\");\nint main(void) { int x = 0 && 1; return x + (1 ? 2 : 3); }";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn warnings_do_not_fail_the_check() {
+    let (_, output) = common::compile("dead_code", "warning", "int main(void) {\n    int x = 1;\n    return x && 0;\n}\n", &["--check"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+        "input.c:3:17: warning: Right operand of '&&' is always 0, so the condition is always false\n");
+}
@@ -0,0 +1,15 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+
+fn declare(declaration: &str) -> String {
+    let source = format!("int main(void) {{\n  {}\n  return 0;\n}}\n", declaration);
+    compile_to_tac(&source, &Dialect::default()).map(|_| String::new()).unwrap_or_else(|err| err.to_string())
+}
+
+#[test]
+fn array_sizes_are_constant_expressions() {
+    assert_eq!(declare("int a[2 * 3 + 1];"), "2:7: Declaring 'a' as array of 7 int is not supported yet");
+    assert_eq!(declare("int *b[1 ? 2 : 0][4];"), "2:8: Declaring 'b' as array of 2 array of 4 pointer to int is not supported yet");
+    assert_eq!(declare("int c[4 - 4];"), "2:9: Array size must be positive, got 0");
+    assert_eq!(declare("int n = 2;\n  int d[n];"), "3:9: Variable 'n' is not allowed in an integer constant expression");
+}
@@ -0,0 +1,109 @@
+use c_compiler_lib::dialect::{Dialect, Standard};
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::interpret;
+
+fn dialect(flags: &[&str]) -> Dialect {
+    let mut dialect = Dialect::default();
+    for flag in flags {
+        assert_eq!(dialect.parse_flag(flag), Ok(true), "{}", flag);
+    }
+    dialect
+}
+
+fn run(source: &str, flags: &[&str]) -> Result<i32, String> {
+    let tac = compile_to_tac(source, &dialect(flags)).map_err(|err| err.to_string())?;
+    interpret::run(&tac.function)
+}
+
+#[test]
+fn std_flags_select_the_standard() {
+    assert_eq!(Dialect::default(), Dialect { std: Standard::C17, gnu_extensions: false, permissive: false });
+    assert_eq!(dialect(&["--std=c11"]), Dialect { std: Standard::C11, gnu_extensions: false, permissive: false });
+    assert_eq!(dialect(&["-std=c2x"]), Dialect { std: Standard::C23, gnu_extensions: false, permissive: false });
+    assert_eq!(dialect(&["--std=gnu17"]), Dialect { std: Standard::C17, gnu_extensions: true, permissive: false });
+    assert_eq!(dialect(&["--gnu-extensions", "--std=c23"]), Dialect { std: Standard::C23, gnu_extensions: true, permissive: false });
+    assert_eq!(Dialect::default().parse_flag("--std=c99"), Err("Unsupported language standard 'c99'".to_string()));
+    assert_eq!(Dialect::default().parse_flag("-S"), Ok(false));
+    assert_eq!(Dialect::default().std_flag(), "-std=c17");
+    assert_eq!(dialect(&["--std=gnu2x"]).std_flag(), "-std=gnu2x");
+    assert_eq!(dialect(&["--gnu-extensions", "--std=c11"]).std_flag(), "-std=gnu11");
+    assert!(dialect(&["-fpermissive"]).permissive);
+}
+
+#[test]
+fn implicit_int_needs_permissive() {
+    let source = "main(void) {\n  return 3;\n}\n";
+    assert_eq!(run(source, &[]), Err("1:1: Return type missing; C99 removed implicit int (-fpermissive accepts it)".to_string()));
+    assert_eq!(run(source, &["-fpermissive"]), Ok(3));
+    let source = "int main(void) {\n  const x = 2;\n  return x;\n}\n";
+    assert_eq!(run(source, &["--std=c23"]), Err("2:3: Type specifier missing; C99 removed implicit int (-fpermissive accepts it)".to_string()));
+    // A legacy declaration only gets as far as the qualifier, which isn't supported yet
+    assert_eq!(run(source, &["-fpermissive"]), Err("2:3: Type qualifier 'const' is not supported yet".to_string()));
+}
+
+#[test]
+fn storage_specifiers_and_qualifiers_are_accepted() {
+    let source = "int main(void) {\n  register int r = 1;\n  auto int a = 2;\n  volatile int v = r + a;\n  return v;\n}\n";
+    assert_eq!(run(source, &[]), Ok(3));
+    assert_eq!(run(source, &["--std=c23"]), Ok(3));
+    assert_eq!(run("int main(void) {\n  auto x = 1;\n  return x;\n}\n", &["--std=c23"]),
+        Err("2:3: Type inference with 'auto' is not supported yet".to_string()));
+    assert_eq!(run("int main(void) {\n  restrict int x = 1;\n  return x;\n}\n", &[]),
+        Err("2:3: 'restrict' requires a pointer type".to_string()));
+    // The qualifiers after '*' parse, though pointers can't be declared yet
+    assert_eq!(run("int main(void) {\n  int * restrict volatile p;\n  return 0;\n}\n", &[]),
+        Err("2:27: Declaring 'p' as volatile pointer to int is not supported yet".to_string()));
+    assert_eq!(run("register int main(void) {\n  return 0;\n}\n", &[]),
+        Err("1:1: Storage class 'register' is not allowed at file scope".to_string()));
+}
+
+#[test]
+fn c23_keywords_are_identifiers_before_c23() {
+    let source = "int main(void) {\n  int yes = true;\n  return yes * 10 + false + (true == 1);\n}\n";
+    assert_eq!(run(source, &["--std=c23"]), Ok(11));
+    assert_eq!(run(source, &["--std=c17"]), Err("2:13: Variable 'true' not declared".to_string()));
+    assert_eq!(run("int main(void) {\n  int nullptr = 4;\n  return nullptr;\n}\n", &["--std=c17"]), Ok(4));
+    assert!(run("int main(void) {\n  int nullptr = 4;\n  return nullptr;\n}\n", &["--std=c23"]).is_err());
+    assert_eq!(run("int main(void) {\n  return nullptr;\n}\n", &["--std=c23"]),
+        Err("2:10: 'nullptr' is not supported yet, as there are no pointers".to_string()));
+}
+
+#[test]
+fn static_assertions_are_checked() {
+    let source = "_Static_assert(sizeof_int - 4 == 0, \"no\");\nint main(void) { return 0; }\n";
+    assert_eq!(run(source, &[]), Err("1:16: Variable 'sizeof_int' is not allowed in an integer constant expression".to_string()));
+    let source = "_Static_assert(1 + 1 == 2, \"math\" \"s\");\nint main(void) {\n  _Static_assert(3 > 2, \"\");\n  return 7;\n}\n";
+    assert_eq!(run(source, &[]), Ok(7));
+    let source = "int main(void) {\n  int x = 1;\n  _Static_assert(2 < 1, \"two is \\154ess\");\n  return x;\n}\n";
+    assert_eq!(run(source, &[]), Err("3:3: Static assertion failed: \"two is less\"".to_string()));
+    let source = "int main(void) { return 0; }\n_Static_assert(0);\n";
+    assert_eq!(run(source, &[]), Err("2:1: A static assertion without a message requires C23".to_string()));
+    assert_eq!(run(source, &["--std=c23"]), Err("2:1: Static assertion failed".to_string()));
+    // static_assert is only a keyword from C23
+    let source = "int main(void) {\n  static_assert(1);\n  return 0;\n}\n";
+    assert_eq!(run(source, &["--std=c23"]), Ok(0));
+    assert!(run(source, &["--std=c17"]).is_err());
+}
+
+#[test]
+fn asm_is_a_keyword_with_gnu_extensions() {
+    let source = "int main(void) {\n  asm(\"nop\");\n  return 0;\n}\n";
+    assert_eq!(run(source, &["--gnu-extensions"]), Err("Inline assembly can't be interpreted".to_string()));
+    // Otherwise it is an ordinary call, whose argument can't be a string yet
+    assert_eq!(run(source, &["--std=c17"]), Err("2:7: String literals are not supported yet".to_string()));
+    let source = "int main(void) {\n  int asm = 2;\n  __asm__ volatile(\"nop\" : : );\n  return asm;\n}\n";
+    assert_eq!(run(source, &[]), Err("3:26: Extended asm with operands is not supported".to_string()));
+}
+
+#[test]
+fn digit_separators_need_c23() {
+    let source = "int main(void) {\n  return 1'000'000 / 1'000;\n}\n";
+    assert_eq!(run(source, &["--std=c23"]), Ok(1000));
+    assert_eq!(run(source, &["--std=gnu17"]), Err("2:11: Digit separators need --std=c23".to_string()));
+}
+
+#[test]
+fn octal_constants_may_have_digit_separators() {
+    assert_eq!(run("int main(void) {\n  return 0'17;\n}\n", &["--std=c23"]), Ok(15));
+    assert_eq!(run("int main(void) {\n  return 0'18;\n}\n", &["--std=c23"]), Err("2:13: Invalid digit '8' in octal constant".to_string()));
+}
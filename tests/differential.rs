@@ -0,0 +1,80 @@
+mod common;
+
+use c_compiler_lib::driver::compile_to_assembly;
+use c_compiler_lib::target::CodegenOptions;
+use common::{dialect, name, programs};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// Differential testing against a reference C compiler. Every program in
+// tests/programs is built both by us and by the reference compiler, and the
+// two executables must exit with the same code; a mismatch means we
+// miscompiled the program. Only built with the differential feature:
+//
+//     cargo test --features differential --test differential
+//
+// The reference compiler is cc unless REFERENCE_CC names another one. It
+// is run with -std=c2x since the corpus uses C23 digit separators.
+
+fn exit_code(executable: &Path) -> Result<Option<i32>, String> {
+    Command::new(executable).status().map(|status| status.code()).map_err(|err| err.to_string())
+}
+
+#[test]
+fn programs_match_reference_compiler() {
+    let reference = env::var_os("REFERENCE_CC").unwrap_or_else(|| OsString::from("cc"));
+    let options = CodegenOptions::default();
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("differential");
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let mut failures = Vec::new();
+    for path in programs() {
+        let name = name(&path);
+        let source = fs::read_to_string(&path).unwrap();
+
+        let reference_executable = out_dir.join(format!("{}.ref", name));
+        let status = Command::new(&reference)
+            .args(["-std=c2x", "-w"])
+            .arg("-o")
+            .arg(&reference_executable)
+            .arg(&path)
+            .status()
+            .unwrap_or_else(|err| panic!("failed to run {}: {}", reference.to_string_lossy(), err));
+        if !status.success() {
+            failures.push(format!("{}: rejected by {}", name, reference.to_string_lossy()));
+            continue;
+        }
+
+        let assembly = match compile_to_assembly(&source, &dialect(&source), &options) {
+            Ok(assembly) => assembly,
+            Err(err) => {
+                failures.push(format!("{}: failed to compile: {}", name, err));
+                continue;
+            }
+        };
+        let asm_file = out_dir.join(format!("{}.s", name));
+        let executable = out_dir.join(&name);
+        fs::write(&asm_file, assembly).unwrap();
+        let status = Command::new(options.target.assembler())
+            .args(options.link_args())
+            .arg("-o")
+            .arg(&executable)
+            .arg(&asm_file)
+            .status()
+            .expect("failed to run the assembler");
+        if !status.success() {
+            failures.push(format!("{}: failed to assemble and link", name));
+            continue;
+        }
+
+        match (exit_code(&executable), exit_code(&reference_executable)) {
+            (Ok(ours), Ok(theirs)) if ours == theirs => {}
+            (Ok(ours), Ok(theirs)) => failures.push(format!("{}: exited with {:?}, reference exited with {:?}", name, ours, theirs)),
+            (Err(err), _) | (_, Err(err)) => failures.push(format!("{}: failed to run: {}", name, err)),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
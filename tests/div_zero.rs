@@ -0,0 +1,43 @@
+mod common;
+
+use c_compiler_lib::checks::{self, RuntimeChecks};
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::interpret;
+use c_compiler_lib::tac::Instruction;
+use common::run_compiler;
+use std::fs;
+
+const DIVIDES: &str = "int main(void) {\n  int a = 0;\n  int b = 7 % a;\n  return 10 / a + b / 3;\n}\n";
+
+#[test]
+fn divisions_by_a_variable_are_checked_first() {
+    let mut tac = compile_to_tac(DIVIDES, &Dialect::default()).unwrap();
+    checks::insert_checks(&mut tac.function, RuntimeChecks { div_zero: true });
+    let lines: Vec<String> = tac.function.body.iter().map(|instruction| instruction.to_string()).collect();
+    // a is checked before both of its divisions, but dividing by 3 can't fail
+    assert_eq!(lines.iter().filter(|line| line.starts_with("if a != 0 jump main_nonzero_")).count(), 2, "{:#?}", lines);
+    assert_eq!(tac.function.body.iter().filter(|instruction| matches!(instruction, Instruction::Abort(_))).count(), 2);
+    assert_eq!(interpret::run_program(&tac), Err("Aborted: division by zero".to_string()));
+}
+
+#[test]
+fn checks_are_off_by_default() {
+    let mut tac = compile_to_tac(DIVIDES, &Dialect::default()).unwrap();
+    let before = tac.to_string();
+    checks::insert_checks(&mut tac.function, RuntimeChecks::default());
+    assert_eq!(tac.to_string(), before);
+}
+
+#[test]
+fn division_by_zero_aborts_with_a_message() {
+    let (dir, output) = common::compile("div_zero", "aborts", DIVIDES, &["--check-div-zero"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "division by zero\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Program terminated by signal"));
+    // Both checks share one handler
+    run_compiler(&dir, ["--check-div-zero", "-S", "input.c"]);
+    let assembly = fs::read_to_string(dir.join("input.s")).unwrap();
+    assert_eq!(assembly.matches("jmp .Lmain_abort_0").count(), 2, "{}", assembly);
+    assert_eq!(assembly.matches("call abort").count(), 1, "{}", assembly);
+}
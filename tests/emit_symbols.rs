@@ -0,0 +1,41 @@
+mod common;
+
+use std::process::Output;
+
+fn emit_symbols(name: &str, flag: &str, source: &str) -> Output {
+    common::compile("emit_symbols", name, source, &[flag]).1
+}
+
+const SOURCE: &str = "int main(void) {\n  extern int counter;\n  extern int unused;\n  int x = 1;\n  return counter + x;\n}\n";
+
+#[test]
+fn symbols_are_listed_like_nm() {
+    let output = emit_symbols("listing", "--emit-symbols", SOURCE);
+    assert!(output.status.success());
+    // Locals aren't file-scope symbols, and externs are undefined here
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+        "function external defined      - main\n\
+         object   external undefined    4 counter\n\
+         object   external undefined    4 unused\n");
+}
+
+#[test]
+fn symbols_are_written_as_json_lines() {
+    let output = emit_symbols("json", "--emit-symbols=json", SOURCE);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines, [
+        r#"{"name":"main","kind":"function","linkage":"external","definition":"defined","size":null,"references":0}"#,
+        r#"{"name":"counter","kind":"object","linkage":"external","definition":"undefined","size":4,"references":1}"#,
+        r#"{"name":"unused","kind":"object","linkage":"external","definition":"undefined","size":4,"references":0}"#,
+    ]);
+}
+
+#[test]
+fn resolution_errors_are_located() {
+    let output = emit_symbols("invalid", "--emit-symbols", "int main(void) {\n  return y;\n}\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).ends_with("input.c:2:10: error[E0001]: Variable 'y' not declared\n"));
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use std::process::Output;
+
+fn emit_tokens(name: &str, source: &str) -> Output {
+    common::compile("emit_tokens", name, source, &["--emit-tokens", "--std=c23"]).1
+}
+
+#[test]
+fn tokens_are_written_as_json_lines() {
+    let output = emit_tokens("tokens", "int main(void) {\n  // zero\n  return 1'000 >>= x;\n}\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 12);
+    assert_eq!(lines[0], r#"{"kind":"keyword","lexeme":"int","line":1,"column":1}"#);
+    assert_eq!(lines[1], r#"{"kind":"identifier","lexeme":"main","line":1,"column":5}"#);
+    assert_eq!(lines[2], r#"{"kind":"punctuator","lexeme":"(","line":1,"column":9}"#);
+    // The lexeme is the source text, digit separators included
+    assert_eq!(lines[7], r#"{"kind":"constant","lexeme":"1'000","line":3,"column":10}"#);
+    assert_eq!(lines[8], r#"{"kind":"punctuator","lexeme":">>=","line":3,"column":16}"#);
+}
+
+#[test]
+fn lexing_errors_are_located() {
+    let output = emit_tokens("invalid", "int main(void) {\n  return @;\n}\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).ends_with("input.c:2:10: error[E0004]: Invalid character '@'\n"));
+}
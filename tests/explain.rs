@@ -0,0 +1,46 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{compile_until, Diagnostic, Stage};
+use c_compiler_lib::explain::{code_for, explain, ERROR_CODES};
+use c_compiler_lib::timing::PassTimer;
+use std::process::Command;
+
+const COMPILER: &str = env!("CARGO_BIN_EXE_c_compiler");
+
+fn diagnostic(source: &str) -> Diagnostic {
+    let err = compile_until(source, &Dialect::default(), Stage::Validate, &mut PassTimer::new(false)).unwrap_err();
+    Diagnostic::from(&err)
+}
+
+#[test]
+fn errors_have_stable_codes() {
+    assert_eq!(diagnostic("int main(void) {\n  return y;\n}\n").code, Some("E0001"));
+    assert_eq!(diagnostic("int main(void) {\n  int x;\n  int x;\n  return 0;\n}\n").code, Some("E0002"));
+    assert_eq!(diagnostic("int main(void) {\n  return 0\n}\n").code, Some("E0005"));
+    assert_eq!(diagnostic("int main(void) {\n  return 0;\n").code, Some("E0006"));
+    assert_eq!(diagnostic("int main(void) {\n  extern int main;\n  return 0;\n}\n").code, Some("E0011"));
+    // The parts of a message that vary don't change its code
+    assert_eq!(code_for("Conflicting types for 'x': int and long"), Some("E0011"));
+    assert_eq!(code_for("Variable 'x' not declared here"), None);
+    assert_eq!(code_for("Cast to long is not supported yet"), None);
+}
+
+#[test]
+fn every_code_is_explained() {
+    for (index, entry) in ERROR_CODES.iter().enumerate() {
+        assert_eq!(entry.code, format!("E{:04}", index + 1));
+        assert!(entry.explanation.contains("\n    "), "{} has no example", entry.code);
+    }
+    assert_eq!(explain("e0001"), explain("E0001"));
+    assert!(explain("E9999").is_none());
+}
+
+#[test]
+fn explain_prints_the_description() {
+    let output = Command::new(COMPILER).args(["--explain", "E0001"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("A variable was used before it was declared.\n"));
+
+    let output = Command::new(COMPILER).args(["--explain", "E9999"]).output().unwrap();
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "'E9999' is not an error code\n");
+}
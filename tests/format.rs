@@ -0,0 +1,187 @@
+mod common;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver;
+use c_compiler_lib::lex::Lex;
+use c_compiler_lib::parser::{parse_program, BlockItem, Declaration, Exp, ExpArena, ExpId, FunctionDeclaration, Program, Statement};
+use std::fs;
+use std::process::Output;
+
+fn fmt(name: &str, source: &str, flags: &[&str]) -> Output {
+    common::compile("format", name, source, &[&["--fmt"], flags].concat()).1
+}
+
+fn formatted(name: &str, source: &str, flags: &[&str]) -> String {
+    let output = fmt(name, source, flags);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn layout_is_normalized() {
+    let source = "int main ( void ){int x=1;volatile int y ;x=x+2*y;;return x;}";
+    assert_eq!(formatted("layout", source, &[]), "\
+int main(void) {
+    int x = 1;
+    volatile int y;
+    x = x + 2 * y;
+    ;
+    return x;
+}
+");
+}
+
+#[test]
+fn only_needed_parentheses_are_kept() {
+    let source = "int main(void) { int a = 1; return ((a + 1)) * (2 - (a - 3)) + (a * 4) + -(-a) + (a < 2 == 1); }";
+    assert_eq!(formatted("parentheses", source, &[]), "\
+int main(void) {
+    int a = 1;
+    return (a + 1) * (2 - (a - 3)) + a * 4 + -(-a) + (a < 2 == 1);
+}
+");
+}
+
+#[test]
+fn shifts_mixed_with_additive_operators_are_parenthesized() {
+    let source = "int main(void) { int a = 1; a = (a << 2) + 1; a <<= a - (a >> 1); return a; }";
+    assert_eq!(formatted("shifts", source, &[]), "\
+int main(void) {
+    int a = 1;
+    a = (a << 2) + 1;
+    a <<= a - (a >> 1);
+    return a;
+}
+");
+}
+
+#[test]
+fn conditionals_and_assignments_nest_right() {
+    let source = "int main(void) { int a; int b; a = b = 3; return (a ? b : 0) ? a : b ? 1 : 2; }";
+    assert_eq!(formatted("conditionals", source, &[]), "\
+int main(void) {
+    int a;
+    int b;
+    a = b = 3;
+    return (a ? b : 0) ? a : b ? 1 : 2;
+}
+");
+}
+
+#[test]
+fn file_scope_declarations_stay_on_their_side_of_main() {
+    let source = "_Static_assert(1,\"before\\n\");\nint main(void) { extern int x; __asm__(\"nop\"); return x; }\n_Static_assert(2 > 1);\n";
+    assert_eq!(formatted("file_scope", source, &["--std=c23"]), "\
+_Static_assert(1, \"before\\n\");
+
+int main(void) {
+    extern int x;
+    __asm__(\"nop\");
+    return x;
+}
+
+_Static_assert(2 > 1);
+");
+}
+
+#[test]
+fn gnu_conditionals_are_kept() {
+    let source = "int main(void) { int a = 0; return a ?: 7; }";
+    assert!(formatted("gnu", source, &["--gnu-extensions"]).contains("return a ?: 7;"));
+}
+
+#[test]
+fn parse_errors_are_reported() {
+    let output = fmt("invalid", "int main(void) {\n  return 1\n}\n", &[]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("input.c:3:1: error[E0005]: Expected ';', got '}'"));
+}
+
+// Every program in the corpus formats to source that formats to itself
+#[test]
+fn formatting_is_idempotent() {
+    for path in common::programs() {
+        let source = fs::read_to_string(&path).unwrap();
+        let flags: Vec<&str> = source.lines()
+            .find_map(|line| line.strip_prefix("// flags:"))
+            .map(|flags| flags.split_whitespace().collect())
+            .unwrap_or_default();
+        let name = common::name(&path);
+        let once = formatted(&name, &source, &flags);
+        let twice = formatted(&name, &once, &flags);
+        assert_eq!(once, twice, "{} isn't stable under formatting", name);
+    }
+}
+
+// The tree an expression parsed to, with every operation parenthesized and
+// no locations, so that two parses of one expression compare equal
+fn shape(exps: &ExpArena, exp: ExpId) -> String {
+    match &exps[exp] {
+        Exp::Constant(value, _) => value.to_string(),
+        Exp::Var(name, _) => name.to_string(),
+        Exp::Unary(op, inner) => format!("({:?} {})", op, shape(exps, *inner)),
+        Exp::Cast(target, inner) => format!("(({}) {})", target, shape(exps, *inner)),
+        Exp::Binary(left, op, right) => format!("({} {:?} {})", shape(exps, *left), op, shape(exps, *right)),
+        Exp::Assignment(left, right) => format!("({} = {})", shape(exps, *left), shape(exps, *right)),
+        Exp::CompoundAssignment(op, left, right) => format!("({} {:?}= {})", shape(exps, *left), op, shape(exps, *right)),
+        Exp::Conditional(condition, then, otherwise) => format!("({} ? {} : {})",
+            shape(exps, *condition), then.map_or(String::new(), |then| shape(exps, then)), shape(exps, *otherwise)),
+        other => panic!("no shape for {:?}", other),
+    }
+}
+
+fn program_shape(source: &str) -> Vec<String> {
+    let mut tokens = Lex::new(source).get_tokens().unwrap();
+    let Program::Program(_, FunctionDeclaration::Function(_, items, _), exps, _) = parse_program(&mut tokens, &Dialect::default()).unwrap();
+    items.iter().map(|item| match item.as_ref() {
+        BlockItem::D(Declaration::Declaration(name, _, init, ..)) => format!("{} = {}", name, init.map_or(String::new(), |init| shape(&exps, init))),
+        BlockItem::S(Statement::Return(exp)) => format!("return {}", shape(&exps, *exp)),
+        BlockItem::S(Statement::Expression(exp)) => shape(&exps, *exp),
+        other => panic!("no shape for {:?}", other),
+    }).collect()
+}
+
+// A linear congruential generator, so that the programs are the same on
+// every run
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+}
+
+const BINARY_OPS: [&str; 18] = ["*", "/", "%", "+", "-", "<<", ">>", "<", "<=", ">", ">=", "==", "!=", "&", "^", "|", "&&", "||"];
+
+// An expression with every operand parenthesized, so it parses to the tree
+// it was generated as
+fn random_exp(random: &mut Random, depth: u32) -> String {
+    let var = |random: &mut Random| ["a", "b", "c"][random.below(3)];
+    if depth == 0 || random.below(5) == 0 {
+        return if random.below(2) == 0 { random.below(20).to_string() } else { var(random).to_string() };
+    }
+    let operand = |random: &mut Random| format!("({})", random_exp(random, depth - 1));
+    match random.below(6) {
+        0 => format!("{}{}", ["-", "+", "~", "!"][random.below(4)], operand(random)),
+        1 => format!("(int){}", operand(random)),
+        2 => format!("{} ? {} : {}", operand(random), operand(random), operand(random)),
+        3 => format!("{} {} {}", var(random), ["=", "<<=", ">>="][random.below(3)], operand(random)),
+        _ => format!("{} {} {}", operand(random), BINARY_OPS[random.below(BINARY_OPS.len())], operand(random)),
+    }
+}
+
+// Formatting keeps the tree each expression parsed to, and the formatted
+// source formats to itself
+#[test]
+fn formatting_round_trips_random_expressions() {
+    let mut random = Random(1);
+    for _ in 0..300 {
+        let source = format!("int main(void) {{\n    int a = {};\n    int b = 2;\n    int c = 3;\n    a = {};\n    return {};\n}}\n",
+            random_exp(&mut random, 2), random_exp(&mut random, 4), random_exp(&mut random, 4));
+        let once = driver::format(&source, &Dialect::default()).unwrap();
+        assert_eq!(program_shape(&once), program_shape(&source), "{}formatted as\n{}", source, once);
+        assert_eq!(driver::format(&once, &Dialect::default()).unwrap(), once, "{}", source);
+    }
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_assembly;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// Puts main at 0x100000, with _start and the data after it
+const LINKER_SCRIPT: &str = "ENTRY(_start)\nSECTIONS\n{\n  . = 0x100000;\n  .text : { *(.text*) }\n  .data : { *(.data*) }\n  .bss : { *(.bss*) }\n  /DISCARD/ : { *(.note*) }\n}\n";
+
+const SOURCE: &str = "int main(void) {\n  static int x = 40;\n  return x + 2 + __STDC_HOSTED__;\n}\n";
+
+fn compile(name: &str, flags: &[&str]) -> (PathBuf, Output) {
+    let dir = common::write_input("freestanding", name, SOURCE);
+    fs::write(dir.join("link.ld"), LINKER_SCRIPT).unwrap();
+    let output = common::run_compiler(&dir, flags.iter().chain(&["input.c"]));
+    (dir, output)
+}
+
+#[test]
+fn failed_checks_trap_without_a_library_to_report_through() {
+    let options = CodegenOptions { trapv: true, freestanding: true, ..CodegenOptions::new(Target::Linux) };
+    let assembly = compile_to_assembly("int main(void) { int x = 1; return x + 1; }", &Dialect::default(), &options).unwrap();
+    assert!(assembly.contains(".Lmain_abort_0:\n    ud2\n"), "{}", assembly);
+    assert!(!assembly.contains("call"), "{}", assembly);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linker_script_lays_out_the_program() {
+    let (dir, output) = compile("script", &["-nostdlib", "-fno-pic", "-T", "link.ld"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // Hosted, so __STDC_HOSTED__ is 1
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Program exited with status code: 43"));
+    let symbols = Command::new("nm").arg(dir.join("input")).output().unwrap();
+    assert!(String::from_utf8_lossy(&symbols.stdout).contains("0000000000100000 T main"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn raw_binary_is_the_loaded_image() {
+    let (dir, output) = compile("raw", &["-ffreestanding", "-nostdlib", "-fno-pic", "-Tlink.ld", "--raw-binary"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // A bare-metal program isn't run
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Raw binary: input.bin"));
+    assert!(!stdout.contains("Program exited"));
+    // main's prologue comes first, at the script's load address, and the
+    // static after the code: 40, as a little-endian long
+    let image = fs::read(dir.join("input.bin")).unwrap();
+    assert_eq!(image[..4], [0x55, 0x48, 0x89, 0xe5]);
+    assert_eq!(image[image.len() - 4..], [40, 0, 0, 0]);
+    // Freestanding, so __STDC_HOSTED__ is 0
+    assert_eq!(Command::new(dir.join("input")).status().unwrap().code(), Some(42));
+}
+
+#[test]
+fn layout_flags_are_only_for_linux() {
+    let (_, output) = compile("macos", &["--target", "x86_64-apple-darwin", "--raw-binary"]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "-T and --raw-binary are only supported when targeting Linux\n");
+}
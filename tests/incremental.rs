@@ -0,0 +1,111 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::incremental::IncrementalParse;
+use c_compiler_lib::lex::Lex;
+use c_compiler_lib::parser::parse_program;
+
+// What lexing and parsing the whole source from scratch gives, to compare
+// with what the incremental parse has after its edits
+fn assert_matches_full_parse(parse: &IncrementalParse) {
+    let source = parse.source();
+    let dialect = Dialect::default();
+    let tokens = Lex::with_dialect(source, &dialect).get_tokens();
+    assert_eq!(parse.tokens(), tokens, "tokens of {:?}", source);
+    let program = match tokens {
+        Ok(mut tokens) => format!("{:?}", parse_program(&mut tokens, &dialect).map_err(|err| err.to_string())),
+        Err(err) => format!("{:?}", Err::<(), String>(err.to_string())),
+    };
+    let incremental = format!("{:?}", parse.program().map_err(|err| err.to_string()));
+    assert_eq!(incremental, program, "program of {:?}", source);
+}
+
+fn declarations(count: usize) -> String {
+    let mut source = String::new();
+    for index in 0..count {
+        source.push_str(&format!("int f{}(int a, int b);\n_Static_assert({} + 1, \"ok\");\n", index, index));
+    }
+    source.push_str("int main(void) {\n    int x = 1;\n    return f0(x, 2) ? x : -x;\n}\n");
+    source
+}
+
+#[test]
+fn edit_inside_a_declaration_reparses_only_it() {
+    let source = declarations(50);
+    let mut parse = IncrementalParse::new(&source, &Dialect::default());
+    assert_eq!(parse.reparsed(), 101);
+    let at = source.find("f7(").unwrap() + 2;
+    parse.edit(at..at, "extra");
+    assert_eq!(parse.reparsed(), 1);
+    assert!(parse.source().contains("int f7extra(int a, int b);"));
+    assert_matches_full_parse(&parse);
+
+    // Lines added between declarations only move the ones after them down
+    let at = parse.source().find("_Static_assert(3").unwrap();
+    parse.edit(at..at, "\n\n");
+    assert_eq!(parse.reparsed(), 0);
+    assert_matches_full_parse(&parse);
+
+    let at = parse.source().find("return").unwrap();
+    parse.edit(at..at + "return".len(), "retur");
+    assert_eq!(parse.reparsed(), 1);
+    assert_matches_full_parse(&parse);
+    assert!(parse.program().is_err());
+}
+
+#[test]
+fn opening_a_comment_relexes_to_the_end() {
+    let source = declarations(5);
+    let mut parse = IncrementalParse::new(&source, &Dialect::default());
+    let at = source.find("int f2").unwrap();
+    parse.edit(at..at, "/*");
+    assert_matches_full_parse(&parse);
+    assert!(parse.program().is_err());
+    parse.edit(at..at + 2, "");
+    assert_matches_full_parse(&parse);
+    assert!(parse.program().is_ok());
+}
+
+#[test]
+fn lexing_errors_are_recovered_from() {
+    let mut parse = IncrementalParse::new("int main(void) { return 0; }", &Dialect::default());
+    parse.edit(24..24, "@");
+    assert!(parse.tokens().is_err());
+    assert_matches_full_parse(&parse);
+    parse.edit(24..25, "");
+    assert_matches_full_parse(&parse);
+    assert!(parse.program().is_ok());
+}
+
+#[test]
+fn line_directives_keep_their_numbering() {
+    let source = format!("int f(int);\n#line 100\n{}", declarations(2));
+    let mut parse = IncrementalParse::new(&source, &Dialect::default());
+    parse.edit(0..0, "\n\n");
+    assert_matches_full_parse(&parse);
+    parse.edit(0..2, "");
+    assert_matches_full_parse(&parse);
+}
+
+// Edits chosen by a fixed pseudo-random sequence, each checked against
+// parsing the result from scratch
+#[test]
+fn random_edits_match_a_full_parse() {
+    const PIECES: [&str; 16] = ["x", "1", " ", "\n", ";", "{", "}", "(", ")", "/*", "*/", "//", "int ", "\"", "#line 7\n", "f0(1, 2)"];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+    for _ in 0..20 {
+        let mut parse = IncrementalParse::new(&declarations(4), &Dialect::default());
+        for _ in 0..40 {
+            let length = parse.source().len();
+            let start = next(length + 1);
+            let end = (start + next(4)).min(length);
+            let text = if next(3) == 0 { "" } else { PIECES[next(PIECES.len())] };
+            parse.edit(start..end, text);
+            assert_matches_full_parse(&parse);
+        }
+    }
+}
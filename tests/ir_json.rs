@@ -0,0 +1,194 @@
+mod common;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{ast_json, tac_json};
+use c_compiler_lib::ir_json::{AST_VERSION, TAC_VERSION};
+use common::{dialect, name, programs};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+fn schema(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas").join(name);
+    serde_json::from_str(&fs::read_to_string(&path).unwrap_or_else(|err| panic!("can't read {}: {}", path.display(), err))).unwrap()
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => panic!("the validator doesn't know type '{}'", name),
+    }
+}
+
+// Checks value against schema, using only the parts of JSON Schema that
+// schemas/ does, and returns where the first mismatch is. It panics on any
+// other keyword rather than ignoring it.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let schema = schema.as_object().expect("a schema is an object");
+    for (keyword, expected) in schema {
+        match keyword.as_str() {
+            "$schema" | "$id" | "$defs" | "title" | "description" => {}
+            "$ref" => {
+                let name = expected.as_str().unwrap().strip_prefix("#/$defs/").expect("only local definitions are referred to");
+                validate(root, &root["$defs"][name], value, path)?;
+            }
+            "type" => {
+                let names: Vec<&str> = match expected {
+                    Value::Array(names) => names.iter().map(|name| name.as_str().unwrap()).collect(),
+                    name => vec![name.as_str().unwrap()],
+                };
+                if !names.iter().any(|name| type_matches(name, value)) {
+                    return Err(format!("{}: {} is not of type {:?}", path, value, names));
+                }
+            }
+            "const" if value != expected => return Err(format!("{}: {} is not {}", path, value, expected)),
+            "const" => {}
+            "enum" if !expected.as_array().unwrap().contains(value) => return Err(format!("{}: {} is not one of {}", path, value, expected)),
+            "enum" => {}
+            "minimum" if value.as_i64().is_some_and(|n| n < expected.as_i64().unwrap()) => {
+                return Err(format!("{}: {} is less than {}", path, value, expected))
+            }
+            "minimum" => {}
+            "oneOf" => {
+                let results: Vec<Result<(), String>> = expected.as_array().unwrap().iter().map(|branch| validate(root, branch, value, path)).collect();
+                match results.iter().filter(|result| result.is_ok()).count() {
+                    1 => {}
+                    0 => return Err(results.into_iter().map(|result| result.unwrap_err()).collect::<Vec<_>>().join(" / ")),
+                    n => return Err(format!("{}: {} matches {} alternatives", path, value, n)),
+                }
+            }
+            "properties" => {
+                let Some(object) = value.as_object() else { continue };
+                for (name, property) in expected.as_object().unwrap() {
+                    if let Some(field) = object.get(name) {
+                        validate(root, property, field, &format!("{}.{}", path, name))?;
+                    }
+                }
+            }
+            "required" => {
+                let Some(object) = value.as_object() else { continue };
+                if let Some(missing) = expected.as_array().unwrap().iter().find(|name| !object.contains_key(name.as_str().unwrap())) {
+                    return Err(format!("{}: {} is missing", path, missing));
+                }
+            }
+            "additionalProperties" => {
+                assert_eq!(expected, &Value::Bool(false), "only additionalProperties: false is used");
+                let Some(object) = value.as_object() else { continue };
+                if let Some(extra) = object.keys().find(|name| !schema["properties"].as_object().unwrap().contains_key(*name)) {
+                    return Err(format!("{}: '{}' is not allowed", path, extra));
+                }
+            }
+            "items" => {
+                let Some(items) = value.as_array() else { continue };
+                for (index, item) in items.iter().enumerate() {
+                    validate(root, expected, item, &format!("{}[{}]", path, index))?;
+                }
+            }
+            _ => panic!("the validator doesn't know keyword '{}'", keyword),
+        }
+    }
+    Ok(())
+}
+
+fn check(schema: &Value, json: &str) -> Result<(), String> {
+    assert!(json.ends_with('\n') && json.matches('\n').count() == 1, "not one line: {}", json);
+    let value: Value = serde_json::from_str(json).unwrap_or_else(|err| panic!("not JSON ({}): {}", err, json));
+    validate(schema, schema, &value, "$")
+}
+
+#[test]
+fn corpus_matches_the_schemas() {
+    let (ast_schema, tac_schema) = (schema("ast.v1.json"), schema("tac.v1.json"));
+    let mut failures = Vec::new();
+    for path in programs() {
+        let source = fs::read_to_string(&path).unwrap();
+        let dialect = dialect(&source);
+        if let Ok(json) = ast_json(&source, &dialect) {
+            if let Err(err) = check(&ast_schema, &json) {
+                failures.push(format!("{} (AST): {}", name(&path), err));
+            }
+        }
+        if let Ok(json) = tac_json(&source, &dialect) {
+            if let Err(err) = check(&tac_schema, &json) {
+                failures.push(format!("{} (TAC): {}", name(&path), err));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+// Every kind of node, so the schemas are checked against ones the corpus
+// may not have
+const EVERY_KIND: &str = "int f(int, int);\n\
+int main(void) {\n\
+    extern int e;\n\
+    static int s = 1;\n\
+    volatile int v = 2;\n\
+    int x = (int)-e;\n\
+    _Static_assert(1, \"ok\");\n\
+    x <<= +s;\n\
+    x = ~x ?: !v;\n\
+    asm(\"nop\");\n\
+    ;\n\
+    return __builtin_expect(f(x, 1), 0) ? x * 2 : s;\n\
+    __builtin_unreachable();\n\
+    __builtin_trap();\n\
+}\n";
+
+#[test]
+fn every_kind_matches_the_schemas() {
+    let source = EVERY_KIND;
+    let mut dialect = Dialect::default();
+    dialect.parse_flag("--gnu-extensions").unwrap();
+    let ast = ast_json(source, &dialect).unwrap();
+    check(&schema("ast.v1.json"), &ast).unwrap();
+    for kind in ["declaration", "static_assert", "cast", "unary", "compound_assignment", "conditional", "\"then\":null", "asm", "null", "expect", "call", "unreachable", "trap", "volatile", "function"] {
+        assert!(ast.contains(kind), "no {} in {}", kind, ast);
+    }
+    let tac = tac_json(source, &dialect).unwrap();
+    check(&schema("tac.v1.json"), &tac).unwrap();
+    for op in ["\"likely\":false", "\"op\":\"call\"", "inline_asm", "\"externs\":[\"e\"]", "\"volatiles\":[\"v"] {
+        assert!(tac.contains(op), "no {} in {}", op, tac);
+    }
+}
+
+#[test]
+fn schemas_are_the_current_versions() {
+    assert_eq!(schema(&format!("ast.v{}.json", AST_VERSION))["properties"]["version"]["const"], AST_VERSION);
+    assert_eq!(schema(&format!("tac.v{}.json", TAC_VERSION))["properties"]["version"]["const"], TAC_VERSION);
+    let ast = ast_json("int main(void) { return 0; }", &Dialect::default()).unwrap();
+    assert!(ast.starts_with(&format!("{{\"version\":{},", AST_VERSION)));
+}
+
+#[test]
+fn validator_rejects_what_the_schemas_exclude() {
+    let schema = schema("tac.v1.json");
+    let json = tac_json("int main(void) { return 0; }", &Dialect::default()).unwrap();
+    assert_eq!(check(&schema, &json), Ok(()));
+    assert!(check(&schema, &json.replace("\"op\":\"return\"", "\"op\":\"ret\"")).is_err());
+    assert!(check(&schema, &json.replace("\"version\":1", "\"version\":2")).is_err());
+    assert!(check(&schema, &json.replace("\"volatiles\":[],", "")).is_err());
+    assert!(check(&schema, &json.replace("\"externs\"", "\"extra\":0,\"externs\"")).is_err());
+}
+
+#[test]
+fn flags_print_the_json_on_stdout() {
+    let source = "int main(void) { return 42; }\n";
+    let dir = common::write_input("ir_json", "answer", source);
+    let dialect = Dialect::default();
+    for (flag, expected) in [("--ast-json", ast_json(source, &dialect)), ("--tac-json", tac_json(source, &dialect))] {
+        let output = common::run_compiler(&dir, [flag, "input.c"]);
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected.unwrap());
+    }
+
+    let (_, output) = common::compile("ir_json", "undeclared", "int main(void) { return y; }\n", &["--tac-json"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Variable 'y' not declared"));
+}
@@ -0,0 +1,184 @@
+use c_compiler_lib::lex::{char_constant_value, integer_constant_value, string_literal_value, Lex, Token, TokenKind, TokenValue};
+
+const SOURCE: &str = "#include <x.h>\n// main\nint main(void) { /* zero */\n    return 0; // done\n}\n";
+
+#[test]
+fn get_tokens_drops_trivia() {
+    let tokens = Lex::new(SOURCE).get_tokens().unwrap();
+    assert_eq!(tokens.len(), 10);
+    assert!(tokens.iter().all(|token| !token.kind.is_trivia()));
+}
+
+#[test]
+fn only_identifiers_are_interned() {
+    let stream = Lex::new(SOURCE).get_tokens_with_trivia().unwrap();
+    let tokens = stream.tokens.iter().flat_map(|token| token.leading.iter().chain([&token.token]).chain(&token.trailing));
+    for token in tokens {
+        match (&token.value, token.kind) {
+            (Some(TokenValue::Name(name)), TokenKind::Identifier) => assert_eq!(&*name.text(), "main"),
+            (Some(TokenValue::Name(_)), kind) => panic!("a {:?} token was interned", kind),
+            (Some(TokenValue::Text(text)), _) => assert_eq!(text, &SOURCE[token.span.start..token.span.end]),
+            (None, kind) => assert_ne!(kind, TokenKind::Identifier),
+        }
+    }
+}
+
+#[test]
+fn trivia_is_attached_to_tokens() {
+    let stream = Lex::new(SOURCE).get_tokens_with_trivia().unwrap();
+    let text = |tokens: &[Token]| -> String {
+        tokens.iter().map(|token| &SOURCE[token.span.start..token.span.end]).collect()
+    };
+
+    // The directive and comment lines lead the first token
+    assert_eq!(text(&stream.tokens[0].leading), "#include <x.h>\n// main\n");
+    // A comment on the same line trails the token before it, up to the line break
+    let brace = &stream.tokens[5];
+    assert_eq!(brace.token.kind, TokenKind::OpenBrace);
+    assert_eq!(text(&brace.trailing), " /* zero */\n");
+    let ret = &stream.tokens[6];
+    assert_eq!(text(&ret.leading), "    ");
+    let semicolon = &stream.tokens[8];
+    assert_eq!(text(&semicolon.trailing), " // done\n");
+    assert!(stream.end.is_empty());
+
+    // Together the pieces cover the whole source
+    let mut rebuilt = String::new();
+    for token in &stream.tokens {
+        rebuilt += &text(&token.leading);
+        rebuilt += &SOURCE[token.token.span.start..token.token.span.end];
+        rebuilt += &text(&token.trailing);
+    }
+    rebuilt += &text(&stream.end);
+    assert_eq!(rebuilt, SOURCE);
+}
+
+fn char_constant(source: &str) -> Result<i32, String> {
+    let tokens = Lex::new(source).get_tokens().map_err(|err| format!("{}: {}", err.span, err.message))?;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].kind, TokenKind::CharConstant);
+    assert_eq!(tokens[0].text(), source);
+    Ok(char_constant_value(&tokens[0].text()).unwrap())
+}
+
+#[test]
+fn character_constants_have_their_char_value() {
+    let cases = [
+        ("'a'", 97),
+        ("' '", 32),
+        ("'\"'", 34),
+        ("'\\n'", 10),
+        ("'\\t'", 9),
+        ("'\\r'", 13),
+        ("'\\a'", 7),
+        ("'\\b'", 8),
+        ("'\\f'", 12),
+        ("'\\v'", 11),
+        ("'\\\\'", 92),
+        ("'\\''", 39),
+        ("'\\\"'", 34),
+        ("'\\?'", 63),
+        ("'\\0'", 0),
+        ("'\\7'", 7),
+        ("'\\101'", 65),
+        ("'\\177'", 127),
+        // char is signed
+        ("'\\377'", -1),
+        ("'\\200'", -128),
+        ("'\\x41'", 65),
+        ("'\\x7f'", 127),
+        ("'\\xFF'", -1),
+        ("'\\x000041'", 65),
+    ];
+    for (source, value) in cases {
+        assert_eq!(char_constant(source), Ok(value), "{}", source);
+    }
+}
+
+#[test]
+fn invalid_character_constants_are_rejected() {
+    let cases = [
+        ("''", "1:1: Empty character constant"),
+        ("'ab'", "1:1: Multi-character character constants are not supported"),
+        ("'\\n\\n'", "1:1: Multi-character character constants are not supported"),
+        // Non-ASCII characters take more than one byte, but aren't written as several
+        ("'é'", "1:2: Non-ASCII character 'é' in a character constant is not supported"),
+        ("'aé'", "1:3: Non-ASCII character 'é' in a character constant is not supported"),
+        // The fourth octal digit is a second character
+        ("'\\1011'", "1:1: Multi-character character constants are not supported"),
+        ("'\\400'", "1:2: Octal escape sequence out of range"),
+        ("'\\x100'", "1:2: Hex escape sequence out of range"),
+        ("'\\xfffffffffff'", "1:2: Hex escape sequence out of range"),
+        ("'\\x'", "1:2: \\x used with no following hex digits"),
+        ("'a\\q'", "1:3: Unknown escape sequence '\\q'"),
+        ("'\\8'", "1:2: Unknown escape sequence '\\8'"),
+        ("'a", "1:1: Unterminated character constant"),
+        ("'\\'", "1:1: Unterminated character constant"),
+        ("'a\n'", "1:1: Unterminated character constant"),
+    ];
+    for (source, message) in cases {
+        assert_eq!(char_constant(source), Err(message.to_string()), "{}", source);
+    }
+}
+
+#[test]
+fn a_leading_zero_makes_a_constant_octal() {
+    let value = |source| {
+        let tokens = Lex::new(source).get_tokens().map_err(|err| format!("{}: {}", err.span, err.message))?;
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].kind, TokenKind::Constant);
+        Ok::<_, String>(integer_constant_value(&tokens[0].text()))
+    };
+    assert_eq!(value("0"), Ok(Some(0)));
+    assert_eq!(value("00"), Ok(Some(0)));
+    assert_eq!(value("017"), Ok(Some(15)));
+    assert_eq!(value("17"), Ok(Some(17)));
+    assert_eq!(value("0777"), Ok(Some(511)));
+    assert_eq!(value("08"), Err("1:2: Invalid digit '8' in octal constant".to_string()));
+    assert_eq!(value("0179"), Err("1:4: Invalid digit '9' in octal constant".to_string()));
+    assert_eq!(value("0x1F"), Err("1:2: Hexadecimal constants are not supported yet".to_string()));
+}
+
+#[test]
+fn string_literals_keep_their_escapes() {
+    let tokens = Lex::new("\"a\\tb\\x41\\0\" \"\"").get_tokens().unwrap();
+    assert_eq!(tokens.iter().map(|token| (token.kind, token.text().into_owned())).collect::<Vec<_>>(),
+        vec![(TokenKind::StringLiteral, "\"a\\tb\\x41\\0\"".to_string()), (TokenKind::StringLiteral, "\"\"".to_string())]);
+    assert_eq!(string_literal_value(&tokens[0].text()), Ok(b"a\tbA\0".to_vec()));
+    assert_eq!(string_literal_value(&tokens[1].text()), Ok(Vec::new()));
+    let err = Lex::new("\"abc").get_tokens().unwrap_err();
+    assert_eq!(format!("{}: {}", err.span, err.message), "1:1: Unterminated string literal");
+    let err = Lex::new("x = \"a\\q\";").get_tokens().unwrap_err();
+    assert_eq!(format!("{}: {}", err.span, err.message), "1:7: Unknown escape sequence '\\q'");
+}
+
+#[test]
+fn line_directives_renumber_the_next_line() {
+    let source = "# 0 \"<built-in>\"\n# 10 \"file.c\"\na\n#line 20\nb\n#include <x.h>\nc";
+    let lines: Vec<(String, usize)> = Lex::new(source).get_tokens().unwrap().iter().map(|token| (token.text().into_owned(), token.span.line)).collect();
+    assert_eq!(lines, vec![("a".to_string(), 10), ("b".to_string(), 20), ("c".to_string(), 22)]);
+}
+
+#[test]
+fn digraphs_are_punctuators() {
+    let kinds = |source| Lex::new(source).get_tokens().unwrap().iter().map(|token| token.kind).collect::<Vec<_>>();
+    assert_eq!(kinds("<: :> <% %> %:define X\n"), kinds("[ ] { } #define X\n"));
+    assert_eq!(kinds("a ? b :>"), kinds("a ? b ]"));
+    assert_eq!(kinds("a % b < c"), vec![TokenKind::Identifier, TokenKind::Modulus, TokenKind::Identifier, TokenKind::LessThan, TokenKind::Identifier]);
+}
+
+#[test]
+fn unsupported_punctuators_say_what_to_write() {
+    let error = |source| {
+        let err = Lex::new(source).get_tokens().unwrap_err();
+        format!("{}: {}", err.span, err.message)
+    };
+    assert_eq!(error("x += 1;"), "1:3: '+=' is not supported yet; write 'x = x + y' instead");
+    assert_eq!(error("x++;"), "1:2: '++' is not supported yet; write 'x = x + 1' instead");
+    assert_eq!(error("int f(int a, ...);"), "1:14: '...' is not supported yet; functions can't take variable arguments");
+    assert_eq!(error("x ??< y"), "1:3: Trigraph '??<' is not supported; write '{' instead");
+    assert_eq!(error("return \u{201c}a\u{201d};"), "1:8: Invalid character '\u{201c}'; did you mean '\"'?");
+    assert_eq!(error("int a$b;"), "1:6: Invalid character '$'; '$' in identifiers is not supported");
+    assert_eq!(error("x =\u{00a0}1;"), "1:4: Invalid character '\u{00a0}'; this is a non-breaking space");
+    assert_eq!(error("x @ y"), "1:3: Invalid character '@'");
+}
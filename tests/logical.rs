@@ -0,0 +1,55 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{compile_to_assembly, compile_to_tac};
+use c_compiler_lib::interpret;
+use c_compiler_lib::target::CodegenOptions;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const OPERANDS: [i32; 4] = [0, 1, 7, -1];
+
+// Returns the result of a op b times two, plus one if b was evaluated. b is
+// evaluated through an assignment, so skipping it leaves evaluated at zero.
+fn program(a: i32, op: &str, b: i32) -> String {
+    format!("int main(void) {{\n  int a = {};\n  int evaluated = 0;\n  int r = a {} (evaluated = 1) * {};\n  return r * 2 + evaluated;\n}}\n", a, op, b)
+}
+
+fn expected(a: i32, op: &str, b: i32) -> i32 {
+    let (result, evaluated) = match op {
+        "&&" => (a != 0 && b != 0, a != 0),
+        "||" => (a != 0 || b != 0, a == 0),
+        _ => unreachable!(),
+    };
+    result as i32 * 2 + evaluated as i32
+}
+
+fn cases() -> Vec<(i32, &'static str, i32)> {
+    ["&&", "||"].into_iter()
+        .flat_map(|op| OPERANDS.into_iter().flat_map(move |a| OPERANDS.into_iter().map(move |b| (a, op, b))))
+        .collect()
+}
+
+#[test]
+fn truth_tables_hold_when_interpreted() {
+    for (a, op, b) in cases() {
+        let tac = compile_to_tac(&program(a, op, b), &Dialect::default()).unwrap();
+        assert_eq!(interpret::run(&tac.function), Ok(expected(a, op, b)), "{} {} {}", a, op, b);
+    }
+}
+
+#[test]
+fn truth_tables_hold_when_executed() {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("logical");
+    fs::create_dir_all(&out_dir).unwrap();
+    let options = CodegenOptions::default();
+    for (index, (a, op, b)) in cases().into_iter().enumerate() {
+        let assembly = compile_to_assembly(&program(a, op, b), &Dialect::default(), &options).unwrap();
+        let asm_file = out_dir.join(format!("{}.s", index));
+        let executable = out_dir.join(index.to_string());
+        fs::write(&asm_file, assembly).unwrap();
+        let status = Command::new(options.target.assembler()).args(options.link_args()).arg("-o").arg(&executable).arg(&asm_file).status().unwrap();
+        assert!(status.success());
+        let code = Command::new(&executable).status().unwrap().code();
+        assert_eq!(code, Some(expected(a, op, b)), "{} {} {}", a, op, b);
+    }
+}
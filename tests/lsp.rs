@@ -0,0 +1,102 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Drives the language server through a short session over stdio. Only
+// built with the lsp feature:
+//
+//     cargo test --features lsp --test lsp
+
+const SERVER: &str = env!("CARGO_BIN_EXE_c_compiler_lsp");
+
+fn message(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+// The server's output for a session of messages, which ends with shutdown
+// and exit
+fn session(args: &[&str], bodies: &[&str]) -> String {
+    let mut server = Command::new(SERVER).args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let input: String = bodies.iter()
+        .chain(&[r#"{"jsonrpc":"2.0","id":99,"method":"shutdown"}"#, r#"{"jsonrpc":"2.0","method":"exit"}"#])
+        .map(|body| message(body))
+        .collect();
+    server.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = server.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn open(uri: &str, text: &str) -> String {
+    format!(r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":{:?},"text":{:?}}}}}}}"#, uri, text)
+}
+
+#[test]
+fn session_publishes_diagnostics_and_symbols() {
+    let stdout = session(&[], &[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.c","text":"int main(void) {\n  int a = b;\n}\n"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.c"},"contentChanges":[{"text":"int main(void) {\n  int a = 1;\n  extern int counter;\n  return a + counter;\n}\n"}]}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///a.c"}}}"#,
+    ]);
+    assert!(stdout.contains(r#""id":1,"result":{"capabilities""#));
+    assert!(stdout.contains(r#""diagnostics":[{"range":{"start":{"line":1,"character":10},"end":{"line":1,"character":11}},"severity":1,"source":"c_compiler","code":"E0001","message":"Variable 'b' not declared"}]"#));
+    assert!(stdout.contains(r#""diagnostics":[]"#));
+    // Locals are main's children, but an extern names a file-scope object
+    assert!(stdout.contains(r#""id":2,"result":[{"name":"main","kind":12,"range":{"start":{"line":0,"character":4},"end":{"line":1,"character":7}},"selectionRange":{"start":{"line":0,"character":4},"end":{"line":0,"character":8}},"children":[{"name":"a","kind":13,"range":{"start":{"line":1,"character":6},"end":{"line":1,"character":7}}"#), "{}", stdout);
+    assert!(stdout.contains(r#"{"name":"counter","kind":13,"range":{"start":{"line":2,"character":13},"end":{"line":2,"character":20}}"#), "{}", stdout);
+    assert_eq!(stdout.matches(r#""name":"counter""#).count(), 1, "{}", stdout);
+    assert!(stdout.contains(r#""id":99,"result":null"#));
+}
+
+#[test]
+fn symbols_outlast_an_edit_that_breaks_the_document() {
+    let symbols = r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///edit.c"}}}"#;
+    let stdout = session(&[], &[
+        &open("file:///edit.c", "int main(void) {\n  static int calls;\n  int total = 0;\n  return total;\n}\n"),
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///edit.c"},"contentChanges":[{"text":"int main(void) {\n  static int calls;\n  int total = \n}\n"}]}}"#,
+        symbols,
+    ]);
+    assert!(stdout.contains(r#""severity":1"#), "{}", stdout);
+    assert!(stdout.contains(r#""children":[{"name":"calls","kind":13"#), "{}", stdout);
+    assert!(stdout.contains(r#"{"name":"total","kind":13,"range":{"start":{"line":2,"character":6}"#), "{}", stdout);
+
+    // Nothing has got through yet
+    let stdout = session(&[], &[&open("file:///edit.c", "int main(void) {\n  int total = \n}\n"), symbols]);
+    assert!(stdout.contains(r#""id":2,"result":[]"#), "{}", stdout);
+}
+
+#[test]
+fn documents_are_preprocessed() {
+    let stdout = session(&[], &[&open("file:///macro.c", "#define X 3\nint main(void) {\n  return X;\n}\n")]);
+    assert!(stdout.contains(r#""diagnostics":[]"#), "{}", stdout);
+    // Errors are still at the document's lines
+    let stdout = session(&[], &[&open("file:///macro.c", "#define X 3\nint main(void) {\n  return X + y;\n}\n")]);
+    assert!(stdout.contains(r#""range":{"start":{"line":2,"character":13}"#), "{}", stdout);
+    assert!(stdout.contains("Variable 'y' not declared"), "{}", stdout);
+
+    // Symbols are those of the preprocessed document
+    let stdout = session(&[], &[
+        &open("file:///symbols.c", "#define NAME counter\nint main(void) {\n  extern int NAME;\n  return NAME;\n}\n"),
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///symbols.c"}}}"#,
+    ]);
+    assert!(stdout.contains(r#"{"name":"counter","kind":13,"range":{"start":{"line":2,"character":13}"#), "{}", stdout);
+}
+
+#[test]
+fn headers_are_found_next_to_the_document_and_through_flags() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("lsp/headers");
+    fs::create_dir_all(dir.join("include")).unwrap();
+    fs::write(dir.join("local.h"), "#define LOCAL 1\n").unwrap();
+    fs::write(dir.join("include/flags.h"), "#define FLAGS 2\n").unwrap();
+    // The document is never saved, so only its directory exists
+    let uri = format!("file://{}", dir.join("my%20file.c").display());
+    let text = "#include \"local.h\"\n#include <flags.h>\nint main(void) {\n  return LOCAL + FLAGS + LEVEL;\n}\n";
+    let include = format!("-I{}", dir.join("include").display());
+    let stdout = session(&[&include, "-DLEVEL=3"], &[&open(&uri, text)]);
+    assert!(stdout.contains(r#""diagnostics":[]"#), "{}", stdout);
+
+    let stdout = session(&[], &[&open(&uri, text)]);
+    assert!(stdout.contains("Header 'flags.h' not found"), "{}", stdout);
+}
@@ -0,0 +1,79 @@
+use c_compiler_lib::checks::RuntimeChecks;
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{self, Stage};
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::timing::PassTimer;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const COMPILER: &str = env!("CARGO_BIN_EXE_c_compiler");
+
+#[test]
+fn parallel_results_match_compiling_one_at_a_time() {
+    let sources: Vec<String> = (0..40)
+        .map(|n| match n % 3 {
+            0 => format!("int main(void) {{ int x = {}; return x * 2 && x; }}", n),
+            1 => format!("int main(void) {{ return {} ? y : 1; }}", n),
+            _ => format!("int main(void) {{ int a = {}; a = a << 2; return a ?: 9; }}", n),
+        })
+        .collect();
+    let dialect = Dialect { gnu_extensions: true, ..Dialect::default() };
+    let mut timers: Vec<PassTimer> = sources.iter().map(|_| PassTimer::new(false)).collect();
+    let results = driver::compile_all(&sources, &dialect, RuntimeChecks::default(), Stage::Codegen, &mut timers).unwrap();
+    assert_eq!(results.len(), sources.len());
+    let options = CodegenOptions::new(Target::Linux);
+    for (source, result) in sources.iter().zip(results) {
+        let alone = driver::compile(source, &dialect, &mut PassTimer::new(false));
+        match (result, alone) {
+            (Ok(Some(parallel)), Ok(alone)) => assert_eq!(parallel.to_assembly_file(&options), alone.to_assembly_file(&options)),
+            (Err(parallel), Err(alone)) => assert_eq!(parallel.to_string(), alone.to_string()),
+            (parallel, alone) => panic!("{} compiled to {:?} in parallel but {:?} alone", source, parallel.is_ok(), alone.is_ok()),
+        }
+    }
+}
+
+#[test]
+fn every_source_needs_a_timer() {
+    let sources = vec!["int main(void) { return 1; }".to_string(), "int main(void) { return 2; }".to_string()];
+    let compile = |timers: &mut [PassTimer]| driver::compile_all(&sources, &Dialect::default(), RuntimeChecks::default(), Stage::Codegen, timers);
+    assert_eq!(compile(&mut [PassTimer::new(false)]).err().unwrap(), "2 sources to compile, but 1 timer");
+    let mut timers: Vec<PassTimer> = (0..3).map(|_| PassTimer::new(false)).collect();
+    assert_eq!(compile(&mut timers).err().unwrap(), "2 sources to compile, but 3 timers");
+    assert_eq!(compile(&mut timers[..2]).unwrap().len(), 2);
+    let no_sources = driver::compile_all(&[], &Dialect::default(), RuntimeChecks::default(), Stage::Codegen, &mut timers);
+    assert_eq!(no_sources.err().unwrap(), "0 sources to compile, but 3 timers");
+}
+
+#[test]
+fn each_file_is_compiled_and_reported_in_order() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("multiple_files");
+    fs::create_dir_all(&dir).unwrap();
+    let files = [
+        ("first.c", "int main(void) { return 3; }\n"),
+        ("broken.c", "int main(void) { return x; }\n"),
+        ("last.c", "int main(void) { return 4; }\n"),
+    ];
+    for (name, source) in files {
+        fs::write(dir.join(name), source).unwrap();
+    }
+    let output = Command::new(COMPILER).arg("-S").args(files.map(|(name, _)| dir.join(name))).output().unwrap();
+    // One file failing fails the run, but doesn't stop the others
+    assert!(!output.status.success());
+    assert!(dir.join("first.s").exists());
+    assert!(dir.join("last.s").exists());
+    assert!(!dir.join("broken.s").exists());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let inputs: Vec<&str> = stdout.lines().filter(|line| line.starts_with("Input file: ")).collect();
+    assert_eq!(inputs.len(), 3);
+    assert!(inputs[0].ends_with("first.c") && inputs[2].ends_with("last.c"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Variable 'x' not declared"));
+}
+
+#[test]
+fn reporting_modes_take_one_file() {
+    let output = Command::new(COMPILER).args(["--check", "a.c", "b.c"]).output().unwrap();
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "--check, --emit-tokens, --fmt, --emit-symbols, --ast-json and --tac-json take a single input file\n");
+}
@@ -0,0 +1,39 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use c_compiler_lib::driver::compile_to_assembly;
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use std::process::{Command, Output};
+
+fn compile_and_run(name: &str, source: &str, flags: &[&str]) -> Output {
+    let dir = common::compiled("nostdlib", name, source, &[&["-nostdlib"], flags].concat());
+    Command::new(dir.join("input")).output().unwrap()
+}
+
+#[test]
+fn start_calls_main_and_exits_with_its_result() {
+    let options = CodegenOptions { nostdlib: true, ..CodegenOptions::new(Target::Linux) };
+    let assembly = compile_to_assembly("int main(void) { return 3; }", &Dialect::default(), &options).unwrap();
+    assert!(assembly.contains("_start:\n    xorl %ebp, %ebp\n    call main\n    movl %eax, %edi\n    movl $231, %eax\n    syscall\n"), "{}", assembly);
+    let with_libc = compile_to_assembly("int main(void) { return 3; }", &Dialect::default(), &CodegenOptions::new(Target::Linux)).unwrap();
+    assert!(!with_libc.contains("_start"));
+    for flags in [&["-fpic"][..], &["-fno-pic"]] {
+        assert_eq!(compile_and_run("exit", "int main(void) {\n  int a = 6;\n  return a * 7;\n}\n", flags).status.code(), Some(42));
+    }
+}
+
+#[test]
+fn checks_abort_through_system_calls() {
+    let output = compile_and_run("abort", "int main(void) {\n  int a = 0;\n  return 1 / a;\n}\n", &["--check-div-zero"]);
+    assert_eq!(std::os::unix::process::ExitStatusExt::signal(&output.status), Some(6));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "division by zero\n");
+}
+
+#[test]
+fn only_linux_is_supported() {
+    let (_, output) = common::compile("nostdlib", "darwin", "int main(void) { return 0; }\n", &["-nostdlib", "--target", "x86_64-apple-darwin"]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "-nostdlib is only supported when targeting Linux\n");
+}
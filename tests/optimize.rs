@@ -0,0 +1,29 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::tac::{Instruction, Val};
+use c_compiler_lib::Symbol;
+
+fn reads(body: &[Instruction], name: &str) -> usize {
+    let var = Val::Identifier(Symbol::intern(name));
+    body.iter().filter(|instruction| match instruction {
+        Instruction::Copy { src, .. } | Instruction::Unary { src, .. } => *src == var,
+        Instruction::Binary { src1, src2, .. } => *src1 == var || *src2 == var,
+        Instruction::Return(val) => *val == var,
+        _ => false,
+    }).count()
+}
+
+#[test]
+fn common_subexpressions_are_reused() {
+    let source = "int main(void) {\n  int x = 3;\n  int a = x * 2;\n  int b = x * 2;\n  return a + b;\n}\n";
+    let tac = compile_to_tac(source, &Dialect::default()).unwrap();
+    assert_eq!(reads(&tac.function.body, "x"), 1);
+}
+
+#[test]
+fn volatile_accesses_are_kept() {
+    let source = "int main(void) {\n  volatile int x = 3;\n  int a = x * 2;\n  int b = x * 2;\n  x;\n  return a + b;\n}\n";
+    let tac = compile_to_tac(source, &Dialect::default()).unwrap();
+    assert_eq!(tac.function.volatiles, vec![Symbol::intern("x")]);
+    assert_eq!(reads(&tac.function.body, "x"), 3);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// A fresh directory with src/prog.c and src/other.c in it
+fn setup(name: &str) -> PathBuf {
+    let dir = common::scratch_dir("output_names", name);
+    fs::create_dir(dir.join("src")).unwrap();
+    fs::write(dir.join("src/prog.c"), "int main(void) { return 12; }\n").unwrap();
+    fs::write(dir.join("src/other.c"), "int main(void) { return 13; }\n").unwrap();
+    dir
+}
+
+// Runs the compile command in a fresh directory, and returns the directory
+// and what the command did
+fn compile(name: &str, flags: &[&str]) -> (PathBuf, Output) {
+    let dir = setup(name);
+    let output = common::run_compiler(&dir, [&["compile"], flags].concat());
+    (dir, output)
+}
+
+#[test]
+fn linking_defaults_to_a_out_in_the_current_directory() {
+    let (dir, output) = compile("a_out", &["src/prog.c"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(Command::new(dir.join("a.out")).status().unwrap().code(), Some(12));
+    assert!(!dir.join("src/prog").exists());
+}
+
+#[test]
+fn c_and_s_default_to_the_input_stem_in_the_current_directory() {
+    let (dir, output) = compile("stems", &["-c", "src/prog.c", "src/other.c"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(dir.join("prog.o").exists() && dir.join("other.o").exists());
+    assert!(!dir.join("a.out").exists());
+
+    let (dir, output) = compile("assembly", &["-S", "src/prog.c"]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(dir.join("prog.s")).unwrap().contains("main"));
+}
+
+#[test]
+fn o_names_each_kind_of_output() {
+    let (dir, output) = compile("named", &["src/prog.c", "-o", "bin"]);
+    assert!(output.status.success());
+    assert_eq!(Command::new(dir.join("bin")).status().unwrap().code(), Some(12));
+
+    let (dir, output) = compile("named_object", &["-c", "src/prog.c", "-oout.obj"]);
+    assert!(output.status.success());
+    // The object links into the same program
+    let linked = Command::new("gcc").arg("-no-pie").arg(dir.join("out.obj")).arg("-o").arg(dir.join("linked")).status().unwrap();
+    assert!(linked.success());
+    assert_eq!(Command::new(dir.join("linked")).status().unwrap().code(), Some(12));
+
+    let (dir, output) = compile("named_assembly", &["-S", "-c", "src/prog.c", "-o", "out.asm"]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(dir.join("out.asm")).unwrap().contains("main"));
+    assert!(!dir.join("out.o").exists());
+}
+
+#[test]
+fn o_takes_one_input() {
+    let (_, output) = compile("one_input", &["-c", "src/prog.c", "src/other.c", "-o", "both.o"]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "-o can't be used with -c or -S and more than one input file\n");
+
+    let (_, output) = compile("one_link", &["src/prog.c", "src/other.c"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn invoking_without_a_command_keeps_naming_outputs_after_their_inputs() {
+    let dir = setup("legacy");
+    let output = common::run_compiler(&dir, ["src/prog.c"]);
+    assert!(output.status.success());
+    assert!(dir.join("src/prog").exists());
+    assert!(!dir.join("a.out").exists());
+}
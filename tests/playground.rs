@@ -0,0 +1,18 @@
+use c_compiler_lib::playground::compile;
+
+#[test]
+fn valid_program_produces_assembly() {
+    let output = compile("int main(void) { return 2; }", "x86_64-unknown-linux-gnu");
+    assert!(output.diagnostics.is_empty());
+    assert!(output.assembly.is_some_and(|assembly| assembly.contains("main:")));
+}
+
+#[test]
+fn rejected_program_produces_located_diagnostic() {
+    let output = compile("int main(void) {\n  return x;\n}", "x86_64-unknown-linux-gnu");
+    assert!(output.assembly.is_none());
+    assert_eq!(output.diagnostics.len(), 1);
+    assert_eq!(output.diagnostics[0].line, Some(2));
+    assert_eq!(output.to_json(),
+        "{\"assembly\":null,\"diagnostics\":[{\"message\":\"Variable 'x' not declared\",\"line\":2,\"column\":10,\"code\":\"E0001\"}]}");
+}
@@ -0,0 +1,51 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::interpret;
+
+fn run(body: &str) -> i32 {
+    let source = format!("int main(void) {{\n  int a = 0;\n  {}\n}}\n", body);
+    let tac = compile_to_tac(&source, &Dialect::default()).unwrap();
+    interpret::run(&tac.function).unwrap()
+}
+
+fn value(exp: &str) -> i32 {
+    run(&format!("return {};", exp))
+}
+
+// Each expression has a different value if the two operators in it were
+// grouped the other way round
+#[test]
+fn each_level_binds_tighter_than_the_next() {
+    let cases = [
+        ("1 + 2 * 3", 7),
+        ("1 << 2 + 1", 8),
+        ("1 < 1 << 1", 1),
+        ("3 == 3 < 4", 0),
+        ("2 == 2 & 1", 1),
+        ("3 ^ 1 & 2", 3),
+        ("1 | 1 ^ 1", 1),
+        ("0 && 1 | 1", 0),
+        ("1 || 0 && 0", 1),
+        ("0 || 1 ? 2 : 3", 2),
+    ];
+    for (exp, expected) in cases {
+        assert_eq!(value(exp), expected, "{}", exp);
+    }
+    // a = (1 ? 0 : 2), not (a = 1) ? 0 : 2
+    assert_eq!(run("a = 1 ? 0 : 2;\n  return a;"), 0);
+}
+
+#[test]
+fn bitwise_operators_bind_looser_than_comparisons() {
+    assert_eq!(value("1 | 2 == 2"), 1);
+    assert_eq!(value("2 & 3 != 0"), 0);
+    assert_eq!(value("4 ^ 4 > 3"), 5);
+}
+
+#[test]
+fn operators_on_one_level_group_by_their_associativity() {
+    assert_eq!(value("8 - 4 - 2"), 2);
+    assert_eq!(value("16 >> 2 >> 1"), 2);
+    assert_eq!(value("12 / 3 / 2"), 2);
+    assert_eq!(value("0 ? 1 : 0 ? 2 : 3"), 3);
+}
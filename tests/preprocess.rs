@@ -0,0 +1,131 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// Compiles and runs input.c in dir: the program's exit code, or what the
+// compiler printed when it couldn't compile it
+fn run_in(dir: &Path, flags: &[&str]) -> Result<i32, String> {
+    let output = common::run_compiler(dir, [&["run"], flags, &["input.c"]].concat());
+    match output.stderr.is_empty() {
+        true => Ok(output.status.code().unwrap()),
+        false => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+    }
+}
+
+fn run(name: &str, source: &str, flags: &[&str]) -> Result<i32, String> {
+    run_in(&common::write_input("preprocess", name, source), flags)
+}
+
+fn write(path: &Path, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn flags_need_their_argument() {
+    let source = "int main(void) { return 0; }\n";
+    let dir = common::write_input("preprocess", "arguments", source);
+    for (flag, message) in [("-D", "Missing macro name after '-D'"), ("-isystem", "Missing directory after '-isystem'"), ("-MF", "Missing file after '-MF'")] {
+        let output = common::run_compiler(&dir, ["run", "input.c", flag]);
+        assert!(!output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stderr), format!("{}\n", message));
+    }
+}
+
+#[test]
+fn dependency_files_list_every_header_read() {
+    let source = "#include <config.h>\nint main(void) { return LEVEL; }\n";
+    let dir = common::write_input("preprocess", "deps", source);
+    write(&dir.join("include/config.h"), "#define LEVEL 3\n");
+    let flags = ["-I", "include", "-MD", "-MF", "out/main.deps", "-MT", "build/main prog"];
+    // The rule can't be written until its directory exists
+    assert!(run_in(&dir, &flags).unwrap_err().ends_with("Preprocessing 'input.c' failed\n"));
+    fs::create_dir(dir.join("out")).unwrap();
+    assert_eq!(run_in(&dir, &flags), Ok(3));
+    let rule = fs::read_to_string(dir.join("out/main.deps")).unwrap().replace("\\\n", "");
+    assert!(rule.starts_with("build/main\\ prog: input.c "), "{}", rule);
+    assert!(rule.contains("include/config.h"), "{}", rule);
+
+    // By default the rule goes next to the input
+    assert_eq!(run_in(&dir, &["-Iinclude", "-MD"]), Ok(3));
+    assert!(fs::read_to_string(dir.join("input.d")).unwrap().contains("config.h"));
+}
+
+#[test]
+fn headers_are_searched_quote_then_bracket() {
+    let dir = common::scratch_dir("preprocess", "include");
+    write(&dir.join("src/value.h"), "#define VALUE 1\n");
+    write(&dir.join("first/value.h"), "#define VALUE 2\n");
+    write(&dir.join("second/value.h"), "#define VALUE 3\n");
+    write(&dir.join("system/value.h"), "#define VALUE 4\n");
+    write(&dir.join("system/other.h"), "#define OTHER 10\n");
+    let src = dir.join("src");
+    let flags = ["-I../first", "-I", "../second", "-isystem", "../system"];
+
+    // "..." finds the header next to the source file, <...> skips that directory
+    write(&src.join("input.c"), "#include \"value.h\"\n#include <other.h>\nint main(void) { return VALUE + OTHER; }\n");
+    assert_eq!(run_in(&src, &flags), Ok(11));
+    write(&src.join("input.c"), "#include <value.h>\n#include <other.h>\nint main(void) { return VALUE + OTHER; }\n");
+    assert_eq!(run_in(&src, &flags), Ok(12));
+    assert_eq!(run_in(&src, &flags[1..]), Ok(13));
+    assert_eq!(run_in(&src, &["-isystem../system"]), Ok(14));
+
+    write(&src.join("input.c"), "#include \"missing.h\"\nint main(void) { return 0; }\n");
+    assert!(run_in(&src, &flags[..1]).unwrap_err().ends_with(
+        "Header 'missing.h' not found; searched:\n  the including file's directory (for #include \"...\" only)\n  ../first\n  the default system directories\n"));
+}
+
+#[test]
+fn macros_are_defined_and_undefined() {
+    let source = "#define TWICE(x) ((x) * 2)\n#ifndef LATE\n#define LATE 0\n#endif\nint main(void) {\n  return TWICE(SIZE) + LATE + EMPTY;\n}\n";
+    assert_eq!(run("macros", source, &["-DSIZE=5", "-DEMPTY"]), Ok(11));
+    // Later flags win, whichever kind they are
+    assert_eq!(run("order", source, &["-DSIZE=5", "-DEMPTY", "-DLATE=7", "-ULATE", "-D", "LATE=3"]), Ok(14));
+    assert_eq!(run("undefined", source, &["-DSIZE=5", "-DEMPTY", "-DLATE=7", "-U", "LATE"]), Ok(11));
+    assert!(run("undeclared", source, &["-DEMPTY"]).unwrap_err().contains("'SIZE' not declared"));
+}
+
+#[test]
+fn standard_macros_are_predefined() {
+    let source = "int main(void) {\n\n\n\n\n\n\n\n\n\n\n  return __LINE__ + __STDC__ * 100;\n}\n";
+    assert_eq!(run("line", source, &[]), Ok(112));
+    let source = "int main(void) {\n#if __STDC_VERSION__ == 201710L\n  return 17;\n#elif __STDC_VERSION__ == 201112L\n  return 11;\n#endif\n}\n";
+    assert_eq!(run("version", source, &[]), Ok(17));
+    assert_eq!(run("c11", source, &["--std=c11"]), Ok(11));
+}
+
+#[test]
+fn errors_point_into_the_original_file() {
+    let source = "#define ZERO 0\n\n\n\n\n\n\n\n\n\n\nint main(void) {\n  return missing + ZERO;\n}\n";
+    assert!(run("missing", source, &[]).unwrap_err().starts_with("input.c:13:10: error[E0001]: Variable 'missing' not declared\n"));
+}
+
+#[test]
+fn digit_separators_survive_preprocessing() {
+    // Before C23 the preprocessor takes the separator for the start of a
+    // character constant and stops expanding X, so they need --std=c23
+    let source = "#define X 5\nint main(void) {\n  return 1'000 + X - 1000;\n}\n";
+    assert_eq!(run("c23", source, &["--std=c23"]), Ok(5));
+    assert!(run("c17", source, &[]).unwrap_err().contains("input.c:3:11: error"));
+    assert!(run("c17", source, &[]).unwrap_err().contains("Digit separators need --std=c23"));
+}
+
+#[test]
+fn the_target_platform_macros_are_predefined() {
+    let source = "int main(void) {\n#if defined(__APPLE__)\n  return 1;\n#elif defined(__linux__)\n  return 2;\n#else\n  return 3;\n#endif\n}\n";
+    let dir = common::write_input("preprocess", "platform", source);
+    let host = if cfg!(target_os = "macos") { 1 } else { 2 };
+    assert_eq!(run_in(&dir, &[]), Ok(host));
+    // Cross builds preprocess with clang, which may not be installed. What
+    // a cross-compiled program returns shows in its TAC.
+    if Command::new("clang").arg("--version").output().is_err() {
+        return;
+    }
+    for (triple, value) in [("x86_64-apple-darwin", 1), ("x86_64-unknown-linux-gnu", 2)] {
+        let output = common::run_compiler(&dir, ["dump", "--stage=tac", "--target", triple, "input.c"]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("return {}", value)), "{}", triple);
+    }
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use c_compiler_lib::driver::{compile_to_assembly, compile_to_tac};
+use c_compiler_lib::interpret;
+use c_compiler_lib::tac::Instruction;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use common::{dialect, name, programs};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// End-to-end tests over the programs in tests/programs. Each one starts with
+// a "// exit: N" line giving the exit code it must produce, and may have a
+// "// flags: ..." line selecting the dialect it is written in. Its assembly
+// for Linux is checked against tests/snapshots/<name>.s. Run with
+// UPDATE_SNAPSHOTS=1 to rewrite the snapshots after an intended codegen change.
+
+fn expected_exit_code(path: &Path, source: &str) -> i32 {
+    source.lines()
+        .find_map(|line| line.strip_prefix("// exit:"))
+        .and_then(|code| code.trim().parse().ok())
+        .unwrap_or_else(|| panic!("{} has no '// exit: N' line", path.display()))
+}
+
+// Compiles, links and runs every program that compiles to TAC satisfying
+// include, returning a line for each one that fails
+fn run_programs(options: &CodegenOptions, out_dir: &str, include: impl Fn(&[Instruction]) -> bool) -> Vec<String> {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(out_dir);
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let mut failures = Vec::new();
+    for path in programs() {
+        let name = name(&path);
+        let source = fs::read_to_string(&path).unwrap();
+        let expected = expected_exit_code(&path, &source);
+        if compile_to_tac(&source, &dialect(&source)).is_ok_and(|tac| !include(&tac.function.body)) {
+            continue;
+        }
+
+        let assembly = match compile_to_assembly(&source, &dialect(&source), options) {
+            Ok(assembly) => assembly,
+            Err(err) => {
+                failures.push(format!("{}: failed to compile: {}", name, err));
+                continue;
+            }
+        };
+        let asm_file = out_dir.join(format!("{}.s", name));
+        let executable = out_dir.join(&name);
+        fs::write(&asm_file, assembly).unwrap();
+
+        let status = Command::new(options.target.assembler())
+            .args(options.link_args())
+            .arg("-o")
+            .arg(&executable)
+            .arg(&asm_file)
+            .status()
+            .expect("failed to run the assembler");
+        if !status.success() {
+            failures.push(format!("{}: failed to assemble and link", name));
+            continue;
+        }
+
+        let code = Command::new(&executable).status().unwrap().code();
+        if code != Some(expected) {
+            failures.push(format!("{}: expected exit code {}, got {:?}", name, expected, code));
+        }
+    }
+    failures
+}
+
+#[test]
+fn programs_exit_with_expected_code() {
+    let failures = run_programs(&CodegenOptions::default(), "programs", |_| true);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// Inline assembly may rely on the %rbp frame, as inline_asm.c's early return
+// does, so those programs are left out
+#[test]
+fn programs_exit_with_expected_code_without_frame_pointer() {
+    let options = CodegenOptions { omit_frame_pointer: true, ..CodegenOptions::default() };
+    let failures = run_programs(&options, "programs-omit-frame-pointer", |body| {
+        !body.iter().any(|instr| matches!(instr, Instruction::InlineAsm(_)))
+    });
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn programs_exit_with_expected_code_at_o1() {
+    let options = CodegenOptions { opt_level: 1, ..CodegenOptions::default() };
+    let failures = run_programs(&options, "programs-o1", |_| true);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// The programs only need main to be called and its result to become the
+// exit status, which the _start that -nostdlib adds does without the C
+// library
+#[cfg(target_os = "linux")]
+#[test]
+fn programs_exit_with_expected_code_without_libc() {
+    let options = CodegenOptions { nostdlib: true, ..CodegenOptions::new(Target::Linux) };
+    let failures = run_programs(&options, "programs-nostdlib", |_| true);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// Checking for overflow mustn't change what a program that doesn't overflow
+// computes. These two go through INT_MAX + 10 and -INT_MAX - 2 on the way to
+// their result, so they abort instead.
+#[test]
+fn programs_exit_with_expected_code_with_trapv() {
+    let options = CodegenOptions { trapv: true, ..CodegenOptions::default() };
+    let failures = run_programs(&options, "programs-trapv", |_| true);
+    assert_eq!(failures, ["digit_separators: expected exit code 10, got None", "unary_plus: expected exit code 9, got None"]);
+}
+
+#[test]
+fn interpreter_agrees_with_expected_exit_code() {
+    let mut failures = Vec::new();
+    for path in programs() {
+        let name = name(&path);
+        let source = fs::read_to_string(&path).unwrap();
+        let expected = expected_exit_code(&path, &source);
+        let tac = match compile_to_tac(&source, &dialect(&source)) {
+            Ok(tac) => tac,
+            Err(err) => {
+                failures.push(format!("{}: {}", name, err));
+                continue;
+            }
+        };
+        // Inline assembly only means something to the processor
+        if tac.function.body.iter().any(|instruction| matches!(instruction, Instruction::InlineAsm(_))) {
+            continue;
+        }
+        // Only the low byte of the return value survives as an exit code
+        match interpret::run_program(&tac) {
+            Ok(value) if value & 0xff == expected => {}
+            Ok(value) => failures.push(format!("{}: expected exit code {}, interpreter returned {}", name, expected, value)),
+            Err(err) => failures.push(format!("{}: {}", name, err)),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn assembly_matches_snapshots() {
+    // Snapshots are always for Linux so they don't depend on the host
+    let options = CodegenOptions::new(Target::Linux);
+    let snapshot_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    let mut failures = Vec::new();
+    for path in programs() {
+        let name = name(&path);
+        let source = fs::read_to_string(&path).unwrap();
+        let assembly = match compile_to_assembly(&source, &dialect(&source), &options) {
+            Ok(assembly) => assembly,
+            Err(err) => {
+                failures.push(format!("{}: failed to compile: {}", name, err));
+                continue;
+            }
+        };
+
+        let snapshot = snapshot_dir.join(format!("{}.s", name));
+        if update {
+            fs::create_dir_all(&snapshot_dir).unwrap();
+            fs::write(&snapshot, assembly).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == assembly => {}
+            Ok(_) => failures.push(format!("{}: assembly differs from {}", name, snapshot.display())),
+            Err(_) => failures.push(format!("{}: missing snapshot {}", name, snapshot.display())),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}\n(rerun with UPDATE_SNAPSHOTS=1 to accept the new output)", failures.join("\n"));
+}
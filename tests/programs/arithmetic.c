@@ -0,0 +1,4 @@
+// exit: 13
+int main(void) {
+    return 2 + 3 * 4 - 10 / 3 % 2;
+}
@@ -0,0 +1,9 @@
+// exit: 14
+int main(void) {
+    int a;
+    int b;
+    a = b = 4;
+    int c = a + b;
+    c = c - 1;
+    return c * 2;
+}
@@ -0,0 +1,11 @@
+// exit: 13
+int main(void) {
+  int a;
+  int b;
+  int c;
+  a = b = (c) = 1;
+  int d = 64;
+  int e = 3;
+  d >>= e <<= 1;
+  return a + b + c + d + e + 10 - 4 - 3;
+}
@@ -0,0 +1,15 @@
+// exit: 42
+int main(void) {
+    // Define the object the extern declaration refers to, as another
+    // translation unit would
+    __asm__(".pushsection .data\n"
+            ".globl counter\n"
+            "counter: .long 40\n"
+            ".popsection");
+    extern int counter;
+    extern int counter;
+    counter = counter + 1;
+    int copy = counter;
+    counter = copy + 1;
+    return counter;
+}
@@ -0,0 +1,8 @@
+// exit: 7
+int main(void) {
+  int x = 3;
+  int y = __builtin_expect(x > 5, 0) ? x * 2 : x - 1;
+  int z = __builtin_expect(y, 1) && x;
+  int w = __builtin_expect(x == 3, 1) ? 4 : 0;
+  return y + z + w;
+}
@@ -0,0 +1,6 @@
+// exit: 17
+int main(void) {
+  int x = (int)5;
+  int y = (int)(x * 3) + (int)'\x01';
+  return (int)y + (signed int)(int)(x == 5);
+}
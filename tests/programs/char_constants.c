@@ -0,0 +1,8 @@
+// exit: 48
+int main(void) {
+  int newline = '\n';
+  int digit = '7' - '0';
+  int all_ones = '\377';
+  int hex = '\x41';
+  return newline + digit + all_ones + hex - 'A' + '\0' + ('\'' == 39) * 32 + -'\x01' + 1;
+}
@@ -0,0 +1,7 @@
+// exit: 7
+/* A block comment
+   spanning lines */
+int main(void) {
+    int a = 3; // trailing comment
+    return a /* inline */ + 4;
+}
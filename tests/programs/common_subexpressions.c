@@ -0,0 +1,10 @@
+// exit: 162
+int main(void) {
+    int a = 6; int b = 7;
+    int x = (a * b) + (b * a) - (a * b);
+    a = a * b;
+    int y = a * b;
+    int z = (a && b) + (a && b);
+    b <<= 1;
+    return x + y - z + (a << 1) + b * a - a * b;
+}
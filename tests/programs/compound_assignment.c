@@ -0,0 +1,9 @@
+// exit: 28
+int main(void) {
+    int a = 3;
+    a <<= 4;
+    a >>= 1;
+    int b = 1;
+    b <<= a - 22;
+    return a + b;
+}
@@ -0,0 +1,9 @@
+// exit: 42
+int main(void) {
+  int x = 0;
+  int a = 1 ? 2 : 3 ? 4 : 5;
+  int b = 0 ? 2 : 0 ? 4 : 5;
+  int c = x ? x = 10 : (x = 20);
+  int d = 1 || x ? 6 : 9;
+  return a + b + c + d + (x == 20 ? 9 : 0);
+}
@@ -0,0 +1,6 @@
+// exit: 9
+int main() {
+  int (a) = 4;
+  int ((b)) = 5;
+  return a + b;
+}
@@ -0,0 +1,6 @@
+// exit: 10
+// flags: --std=c23
+int main(void) {
+  int a = 1'000;
+  return a / 1'0'0 + 2'147'483'647 - 2147483647;
+}
\ No newline at end of file
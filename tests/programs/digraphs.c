@@ -0,0 +1,6 @@
+// exit: 9
+int main(void) <%
+    int x = 4;
+    int y = x ? x + 5 : 0;
+    return y;
+%>
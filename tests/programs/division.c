@@ -0,0 +1,9 @@
+// exit: 89
+int main(void) {
+    int a = -17;
+    int b = 5;
+    int q = a / b;
+    int r = a % b;
+    int s = 100 / 7 + 100 % -7;
+    return (q + 10) * 10 + (r + 5) + s;
+}
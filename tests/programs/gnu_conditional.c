@@ -0,0 +1,11 @@
+// exit: 30
+// flags: --gnu-extensions
+int main(void) {
+  int calls = 0;
+  int zero = 0;
+  int a = (calls = calls + 1) ?: 100;
+  int b = zero ?: 7;
+  int c = zero ?: zero ?: 9;
+  int d = 0 ? 1 ?: 2 : 3;
+  return calls * 10 + a + b + c + d;
+}
@@ -0,0 +1,5 @@
+// exit: 0
+int main(void) {
+    int a = 5;
+    a = a * 2;
+}
@@ -0,0 +1,11 @@
+// exit: 42
+int main(void) {
+    int a = 3;
+    __asm__("nop");
+    // Return from main without reaching the C return statement
+    __asm__ volatile("movl $42, %eax\n\t"
+                     "movq %rbp, %rsp\n\t"
+                     "popq %rbp\n\t"
+                     "ret");
+    return a;
+}
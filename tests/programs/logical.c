@@ -0,0 +1,7 @@
+// exit: 11
+int main(void) {
+    int a = 0; int b = 3;
+    int c = (a && b) || (b && 7) || (a || 0);
+    int d = (a || b) && (b || a) && !(a && b);
+    return c * 10 + d;
+}
@@ -0,0 +1,7 @@
+// exit: 101
+int main(void) {
+    int a = 5;
+    int b = -3;
+    int c = (a > b) + (a < b) * 2 + (a == 5) * 4 + (b != -3) * 8 + !a * 16 + !(b - b) * 32 + (a >= 5) * 64 + (b <= -4) * 128;
+    return c;
+}
@@ -0,0 +1,4 @@
+// exit: 2
+int main(void) {
+    return 2;
+}
@@ -0,0 +1,10 @@
+// exit: 62
+int main(void) {
+    int a = 3;
+    int b = 2;
+    a <<= b;
+    int c = 200;
+    c >>= a - 9;
+    c <<= 33;
+    return a + c;
+}
@@ -0,0 +1,12 @@
+// exit: 12
+_Static_assert(2 + 2 == 4, "addition works");
+
+int main(void) {
+    int a = 3;
+    _Static_assert('a' == 97, "ASCII" " " "is assumed");
+    int b = a * 4;
+    _Static_assert((1 << 4) - 1 == 15 ? 1 : 0, "");
+    return b;
+}
+
+_Static_assert(-1 < 0, "after main");
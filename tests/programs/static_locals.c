@@ -0,0 +1,11 @@
+// exit: 21
+int main(void) {
+    static int count = 3 * 4;
+    // Zero, in .bss
+    static int zero;
+    // Doesn't clash with the function's own symbol
+    static int main = -1;
+    count = count + 10;
+    zero <<= 2;
+    return count + zero + main;
+}
@@ -0,0 +1,9 @@
+// exit: 11
+int main(void) {
+    register int r = 2;
+    auto int a = 3;
+    volatile int v = r + a;
+    int volatile w = (volatile int)v * 2;
+    v = w + 1;
+    return v;
+}
@@ -0,0 +1,7 @@
+// exit: 12
+int main(void) {
+  signed int a = 3;
+  int signed b = 4;
+  signed c = 5;
+  return a + b + c;
+}
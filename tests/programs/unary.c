@@ -0,0 +1,4 @@
+// exit: 8
+int main(void) {
+    return -(-5) + ~(-3) + !0 + !7;
+}
@@ -0,0 +1,8 @@
+// exit: 9
+int main(void) {
+  int a = -2147483648;
+  int b = +5;
+  int c = - -2147483647;
+  int d = -(2147483647) - 1;
+  return (a == d) + b + +c - 2147483647 + -+-3;
+}
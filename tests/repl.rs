@@ -0,0 +1,20 @@
+use c_compiler_lib::repl::Session;
+
+#[test]
+fn declarations_and_assignments_carry_over() {
+    let mut session = Session::new();
+    assert_eq!(session.eval("int x = 6;"), Ok(None));
+    assert_eq!(session.eval("x * 7"), Ok(Some(42)));
+    assert_eq!(session.eval("x = x << 2"), Ok(Some(24)));
+    assert_eq!(session.eval("return x + 1;"), Ok(Some(25)));
+    assert_eq!(session.eval("x"), Ok(Some(24)));
+}
+
+#[test]
+fn failed_lines_are_dropped() {
+    let mut session = Session::new();
+    assert!(session.eval("int x = 1 / 0;").is_err());
+    assert!(session.eval("x").is_err());
+    assert_eq!(session.eval("int x = 3;"), Ok(None));
+    assert_eq!(session.eval("-2147483647 - 1 + x"), Ok(Some(-2147483645)));
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::run_compiler;
+use std::fs;
+use std::path::PathBuf;
+
+fn write(name: &str, source: &str) -> PathBuf {
+    common::write_input("run", name, source)
+}
+
+// Only int main(void) can be compiled, so the program can't see its
+// arguments; they must still reach it rather than the driver
+#[test]
+fn arguments_after_the_dashes_are_left_to_the_program() {
+    let dir = write("arguments", "int main(void) { return 0; }\n");
+    let output = run_compiler(&dir, ["run", "input.c", "--", "-O9", "missing.c"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn the_driver_exits_as_the_program_does() {
+    let dir = write("exit", "int main(void) { return 42; }\n");
+    for args in [&["run"][..], &["--run"], &["-O1", "--run"]] {
+        let output = run_compiler(&dir, [args, &["input.c"]].concat());
+        assert_eq!(output.status.code(), Some(42), "{:?}", args);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    }
+}
+
+#[test]
+fn a_signal_exits_with_128_and_its_number() {
+    let dir = write("signal", "int main(void) { __builtin_trap(); }\n");
+    let output = run_compiler(&dir, ["run", "--gnu-extensions", "input.c"]);
+    assert_eq!(output.status.code(), Some(128 + 4));
+}
+
+#[test]
+fn tee_copies_the_output_to_files() {
+    let dir = write("tee", "int putchar(int c);\nint main(void) { putchar(111); putchar(10); return 3; }\n");
+    let output = run_compiler(&dir, ["--run", "--tee-stdout=tee.stdout", "--tee-stderr=tee.stderr", "input.c"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "o\n");
+    assert_eq!(fs::read_to_string(dir.join("tee.stdout")).unwrap(), "o\n");
+    assert_eq!(fs::read_to_string(dir.join("tee.stderr")).unwrap(), "");
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::compiled;
+use std::fs;
+
+#[test]
+fn intermediate_files_are_kept_next_to_the_output() {
+    let dir = compiled("save_temps", "kept", "#define ANSWER 40\nint main(void) {\n  int x = ANSWER;\n  return x + 2;\n}\n", &["--save-temps"]);
+    let preprocessed = fs::read_to_string(dir.join("input.i")).unwrap();
+    assert!(preprocessed.contains("int x = 40;"));
+    let tac = fs::read_to_string(dir.join("input.tac")).unwrap();
+    assert!(tac.starts_with("function main\n"));
+    assert!(tac.contains("    return "));
+    assert!(fs::read_to_string(dir.join("input.s")).unwrap().contains("main:"));
+}
+
+#[test]
+fn intermediate_files_are_removed_by_default() {
+    let dir = compiled("save_temps", "removed", "int main(void) {\n  return 0;\n}\n", &[]);
+    assert!(!dir.join("input.i").exists());
+    assert!(!dir.join("input.tac").exists());
+    assert!(!dir.join("input.s").exists());
+}
@@ -0,0 +1,56 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_tac;
+use c_compiler_lib::scope::{Entry, Scope};
+use c_compiler_lib::Symbol;
+
+#[test]
+fn inner_declarations_shadow_outer_ones() {
+    let x = Symbol::intern("x");
+    let mut scope = Scope::new();
+    assert_eq!(scope.declare(x), Ok(x));
+
+    scope.enter_scope();
+    assert_eq!(scope.lookup(x), Some(Entry { unique_name: x, from_current_block: false, has_linkage: false }));
+    let inner = scope.declare(x).unwrap();
+    assert_eq!(inner, Symbol::intern("x.1"));
+    assert_eq!(scope.lookup(x), Some(Entry { unique_name: inner, from_current_block: true, has_linkage: false }));
+
+    scope.exit_scope();
+    assert_eq!(scope.lookup(x), Some(Entry { unique_name: x, from_current_block: true, has_linkage: false }));
+}
+
+#[test]
+fn redeclaration_in_the_same_block_is_an_error() {
+    let y = Symbol::intern("y");
+    let mut scope = Scope::new();
+    scope.declare(y).unwrap();
+    assert!(scope.declare(y).is_err());
+    assert_eq!(scope.lookup(Symbol::intern("z")), None);
+}
+
+#[test]
+fn extern_declarations_keep_their_name() {
+    let (x, y) = (Symbol::intern("x"), Symbol::intern("y"));
+    let mut scope = Scope::new();
+    assert_eq!(scope.declare_extern(x), Ok(x));
+    assert_eq!(scope.declare_extern(x), Ok(x));
+    assert_eq!(scope.lookup(x), Some(Entry { unique_name: x, from_current_block: true, has_linkage: true }));
+    assert!(scope.declare(x).is_err());
+    // A local declared later in an inner block is renamed around it
+    scope.enter_scope();
+    assert_eq!(scope.declare(x), Ok(Symbol::intern("x.1")));
+    scope.exit_scope();
+    scope.declare(y).unwrap();
+    assert!(scope.declare_extern(y).is_err());
+}
+
+#[test]
+fn block_scope_extern_declarations_are_checked() {
+    let compile = |body: &str| compile_to_tac(&format!("int main(void) {{\n{}\n}}\n", body), &Dialect::default())
+        .map(|tac| tac.externs)
+        .map_err(|err| err.to_string());
+    assert_eq!(compile("extern int x;\nextern int x;\nreturn x;"), Ok(vec![Symbol::intern("x")]));
+    assert_eq!(compile("extern int x = 1;\nreturn x;"), Err("2:12: Block-scope extern declaration of 'x' can't have an initializer".to_string()));
+    assert_eq!(compile("int x;\nextern int x;\nreturn 0;"), Err("Variable 'x' already declared".to_string()));
+    assert_eq!(compile("extern int main;\nreturn 0;"), Err("2:12: Conflicting types for 'main': function returning int and int".to_string()));
+}
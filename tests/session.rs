@@ -0,0 +1,130 @@
+mod common;
+
+use c_compiler_lib::assembly;
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{Artifact, Compilation, CompileError, Compiler, Stage};
+use c_compiler_lib::incremental::IncrementalParse;
+use c_compiler_lib::intern::Interner;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::timing::PassTimer;
+use c_compiler_lib::{parser, tac, Symbol};
+use common::{dialect, name, programs};
+use std::fs;
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn pipeline_types_are_send_and_sync() {
+    assert_send_sync::<Compiler>();
+    assert_send_sync::<Compilation>();
+    assert_send_sync::<IncrementalParse>();
+    assert_send_sync::<CompileError>();
+    assert_send_sync::<PassTimer>();
+    assert_send_sync::<Symbol>();
+    assert_send_sync::<parser::Program>();
+    assert_send_sync::<tac::Program>();
+    assert_send_sync::<assembly::Program>();
+}
+
+fn compiler() -> Compiler {
+    Compiler::new(Dialect::default(), CodegenOptions::new(Target::Linux))
+}
+
+// The corpus programs written in the default dialect, with what compiling
+// each one alone gives
+fn corpus(compiler: &Compiler) -> Vec<(String, String, Result<String, String>)> {
+    programs().iter()
+        .map(|path| (name(path), fs::read_to_string(path).unwrap()))
+        .filter(|(_, source)| dialect(source) == Dialect::default())
+        .map(|(name, source)| {
+            let assembly = compiler.compile(&source).map_err(|err| err.to_string());
+            (name, source, assembly)
+        })
+        .collect()
+}
+
+#[test]
+fn one_compiler_compiles_from_many_threads() {
+    let compiler = compiler();
+    let corpus = corpus(&compiler);
+    assert!(corpus.len() > 10);
+    thread::scope(|scope| {
+        for thread in 0..8 {
+            let (compiler, corpus) = (&compiler, &corpus);
+            scope.spawn(move || {
+                // Each thread goes through the corpus from a different place,
+                // so different programs are compiling at once
+                for index in 0..corpus.len() {
+                    let (name, source, expected) = &corpus[(index + thread * 5) % corpus.len()];
+                    assert_eq!(&compiler.compile(source).map_err(|err| err.to_string()), expected, "{}", name);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn a_compiler_is_unchanged_by_what_it_compiles() {
+    let compiler = compiler();
+    let valid = "int main(void) { int x = 3; return x * 2; }";
+    let first = compiler.compile(valid).unwrap();
+    assert!(compiler.compile("int main(void) { return y; }").is_err());
+    assert!(compiler.compile("int main(void) { return 1 +; }").is_err());
+    assert_eq!(compiler.compile(valid).unwrap(), first);
+
+    let mut timer = PassTimer::new(false);
+    assert!(compiler.compile_until(valid, Stage::Tacky, &mut timer).unwrap().is_none());
+    let sources = vec![valid.to_string(), "int main(void) { return z; }".to_string()];
+    let results = compiler.compile_all(&sources, Stage::Codegen, &mut [PassTimer::new(false), PassTimer::new(false)]).unwrap();
+    assert!(results[0].as_ref().is_ok_and(|program| program.is_some()));
+    assert!(results[1].is_err());
+}
+
+#[test]
+fn a_program_reads_its_names_through_its_own_interner() {
+    let compiler = compiler();
+    let source = "int answer(void);\nint main(void) { static int counter = 4; return counter + answer(); }";
+    let options = CodegenOptions::new(Target::Linux);
+    let expected = compiler.compile(source).unwrap();
+    // Compiled on another thread, whose names this thread has never seen
+    let program = thread::spawn(move || compiler.compile_until(source, Stage::Codegen, &mut PassTimer::new(false)))
+        .join().unwrap().unwrap().unwrap();
+    assert_eq!(program.to_assembly_file(&options), expected);
+    assert!(expected.contains("answer") && expected.contains("counter.static"));
+    assert!(format!("{:?}", program).contains("\"main\""));
+
+    // A symbol made in one interner isn't read through another
+    let (first, second) = (Interner::new(), Interner::new());
+    let name = first.enter(|| Symbol::intern("only_in_first"));
+    assert_eq!(&*first.enter(|| name.text()), "only_in_first");
+    assert_eq!(second.enter(|| Symbol::intern("other")), name);
+    assert_eq!(&*second.enter(|| name.text()), "other");
+}
+
+#[test]
+fn a_compilation_carries_on_after_moving_to_another_thread() {
+    // The optimizer makes new temporaries for the repeated products
+    let source = "int main(void) {\n  int x = 3;\n  int y = 4;\n  int a = x * y + 1;\n  int b = x * y + 2;\n  return a * b;\n}\n";
+    let options = CodegenOptions::new(Target::Linux);
+    let expected = compiler().compile(source).unwrap();
+    let mut compilation = thread::spawn(move || {
+        let mut compilation = Compilation::new(source, &Dialect::default());
+        compilation.run_until(Stage::Validate).unwrap();
+        compilation
+    }).join().unwrap();
+    // This thread's own interner has none of the names the compilation has
+    // made so far
+    let (tac, assembly) = thread::spawn(move || {
+        let tac = match compilation.run_until(Stage::Tacky).unwrap() {
+            Artifact::Tac(tac) => tac.to_string(),
+            other => panic!("expected TAC, got {:?}", other),
+        };
+        match compilation.run_until(Stage::Codegen).unwrap() {
+            Artifact::Asm(assembly) => (tac, assembly.to_assembly_file(&options)),
+            other => panic!("expected assembly, got {:?}", other),
+        }
+    }).join().unwrap();
+    assert_eq!(assembly, expected);
+    assert!(tac.starts_with("function main\n"), "{}", tac);
+}
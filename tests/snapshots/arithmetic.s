@@ -0,0 +1,34 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $8, %rsp
+    movl $3, -4(%rbp)
+    movl -4(%rbp), %r11d
+    imull $4, %r11d
+    movl %r11d, -4(%rbp)
+    movl $2, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl $10, %eax
+    cdq
+    movl $3, %r10d
+    idivl %r10d
+    movl %eax, -4(%rbp)
+    movl -4(%rbp), %eax
+    cdq
+    movl $2, %r10d
+    idivl %r10d
+    movl %edx, -4(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    subl %r10d, -8(%rbp)
+    movl -8(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
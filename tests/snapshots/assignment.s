@@ -0,0 +1,32 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $8, %rsp
+    movl $4, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    subl $1, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r11d
+    imull $2, %r11d
+    movl %r11d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
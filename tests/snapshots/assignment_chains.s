@@ -0,0 +1,52 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $20, %rsp
+    movl $1, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl $64, -16(%rbp)
+    movl $3, -20(%rbp)
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    sall $1, -20(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -20(%rbp), %ecx
+    sarl %cl, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -20(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $10, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    subl $4, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    subl $3, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
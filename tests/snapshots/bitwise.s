@@ -0,0 +1,134 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $36, %rsp
+    movl $45, -4(%rbp)
+    movl $22, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -8(%rbp), %r10d
+    andl %r10d, -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -8(%rbp), %r10d
+    orl %r10d, -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    movl -8(%rbp), %r10d
+    xorl %r10d, -20(%rbp)
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -24(%rbp)
+    sall $2, -24(%rbp)
+    movl -24(%rbp), %r10d
+    movl %r10d, -24(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -28(%rbp)
+    sarl $1, -28(%rbp)
+    movl -28(%rbp), %r10d
+    movl %r10d, -28(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -32(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -32(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -36(%rbp)
+    movl -16(%rbp), %r10d
+    subl %r10d, -36(%rbp)
+    movl -32(%rbp), %r10d
+    movl %r10d, -32(%rbp)
+    movl -36(%rbp), %r10d
+    movl -32(%rbp), %r11d
+    imull %r10d, %r11d
+    movl %r11d, -32(%rbp)
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    addl $1, -20(%rbp)
+    movl -32(%rbp), %eax
+    cdq
+    idivl -20(%rbp)
+    movl %eax, -20(%rbp)
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    movl -8(%rbp), %r10d
+    cmpl %r10d, -4(%rbp)
+    movl $0, -32(%rbp)
+    setg -32(%rbp)
+    movl $0, %r11d
+    cmpl -32(%rbp), %r11d
+    je .Lmain_0
+    movl -16(%rbp), %r10d
+    cmpl %r10d, -12(%rbp)
+    movl $0, -12(%rbp)
+    setl -12(%rbp)
+    movl $0, %r11d
+    cmpl -12(%rbp), %r11d
+    je .Lmain_0
+    movl $1, -12(%rbp)
+    jmp .Lmain_1
+.Lmain_0:
+    movl $0, -12(%rbp)
+.Lmain_1:
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    cmpl $45, -4(%rbp)
+    movl $0, -4(%rbp)
+    sete -4(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    jne .Lmain_2
+    cmpl $22, -8(%rbp)
+    movl $0, -4(%rbp)
+    setne -4(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    jne .Lmain_2
+    movl $0, -4(%rbp)
+    jmp .Lmain_3
+.Lmain_2:
+    movl $1, -4(%rbp)
+.Lmain_3:
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -20(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -24(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -28(%rbp), %r10d
+    subl %r10d, -8(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -12(%rbp), %r11d
+    imull $10, %r11d
+    movl %r11d, -12(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -12(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r11d
+    imull $5, %r11d
+    movl %r11d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,29 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $4, %rsp
+    .pushsection .data
+    .globl counter
+    counter: .long 40
+    .popsection
+    movl counter(%rip), %r10d
+    movl %r10d, -4(%rbp)
+    addl $1, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, counter(%rip)
+    movl counter(%rip), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $1, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, counter(%rip)
+    movl counter(%rip), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
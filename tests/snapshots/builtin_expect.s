@@ -0,0 +1,71 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $12, %rsp
+    movl $3, -4(%rbp)
+    cmpl $5, -4(%rbp)
+    movl $0, -8(%rbp)
+    setg -8(%rbp)
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    jne .Lmain_block_1
+    movl -4(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    subl $1, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+.Lmain_1:
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    je .Lmain_2
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_2
+    movl $1, -12(%rbp)
+    jmp .Lmain_3
+.Lmain_block_1:
+    movl -4(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -8(%rbp), %r11d
+    imull $2, %r11d
+    movl %r11d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    jmp .Lmain_1
+.Lmain_2:
+    movl $0, -12(%rbp)
+.Lmain_3:
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    cmpl $3, -4(%rbp)
+    movl $0, -4(%rbp)
+    sete -4(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_4
+    movl $4, -4(%rbp)
+    jmp .Lmain_5
+.Lmain_4:
+    movl $0, -4(%rbp)
+.Lmain_5:
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -12(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
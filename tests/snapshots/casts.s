@@ -0,0 +1,31 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $8, %rsp
+    movl $5, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -8(%rbp), %r11d
+    imull $3, %r11d
+    movl %r11d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    addl $1, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    cmpl $5, -4(%rbp)
+    movl $0, -4(%rbp)
+    sete -4(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
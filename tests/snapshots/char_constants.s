@@ -0,0 +1,60 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $16, %rsp
+    movl $10, -4(%rbp)
+    movl $55, -8(%rbp)
+    subl $48, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl $-1, -12(%rbp)
+    movl $65, -16(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -12(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    subl $65, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $0, -4(%rbp)
+    movl $39, %r11d
+    cmpl $39, %r11d
+    movl $0, -8(%rbp)
+    sete -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -8(%rbp), %r11d
+    imull $32, %r11d
+    movl %r11d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl $1, -8(%rbp)
+    negl -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $1, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,109 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $24, %rsp
+    movl $6, -4(%rbp)
+    movl $7, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    movl -4(%rbp), %r11d
+    imull %r10d, %r11d
+    movl %r11d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    addl %r10d, -16(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    subl %r10d, -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -8(%rbp), %r10d
+    movl -16(%rbp), %r11d
+    imull %r10d, %r11d
+    movl %r11d, -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_0
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    je .Lmain_0
+    movl $1, -20(%rbp)
+    jmp .Lmain_1
+.Lmain_0:
+    movl $0, -20(%rbp)
+.Lmain_1:
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_2
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    je .Lmain_2
+    movl $1, -24(%rbp)
+    jmp .Lmain_3
+.Lmain_2:
+    movl $0, -24(%rbp)
+.Lmain_3:
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    movl -24(%rbp), %r10d
+    addl %r10d, -20(%rbp)
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    sall $1, -8(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -20(%rbp), %r10d
+    subl %r10d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    sall $1, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl -8(%rbp), %r11d
+    imull %r10d, %r11d
+    movl %r11d, -8(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    subl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,32 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $12, %rsp
+    movl $3, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    sall $4, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    sarl $1, -4(%rbp)
+    movl $1, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    subl $22, -12(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -12(%rbp), %ecx
+    sall %cl, -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
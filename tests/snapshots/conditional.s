@@ -0,0 +1,114 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $20, %rsp
+    movl $0, -4(%rbp)
+    movl $0, %r11d
+    cmpl $1, %r11d
+    je .Lmain_0
+    movl $2, -8(%rbp)
+    jmp .Lmain_1
+.Lmain_0:
+    movl $0, %r11d
+    cmpl $3, %r11d
+    je .Lmain_2
+    movl $4, -8(%rbp)
+    jmp .Lmain_3
+.Lmain_2:
+    movl $5, -8(%rbp)
+.Lmain_3:
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+.Lmain_1:
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl $0, %r11d
+    cmpl $0, %r11d
+    je .Lmain_4
+    movl $2, -12(%rbp)
+    jmp .Lmain_5
+.Lmain_4:
+    movl $0, %r11d
+    cmpl $0, %r11d
+    je .Lmain_6
+    movl $4, -12(%rbp)
+    jmp .Lmain_7
+.Lmain_6:
+    movl $5, -12(%rbp)
+.Lmain_7:
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+.Lmain_5:
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_8
+    movl $10, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    jmp .Lmain_9
+.Lmain_8:
+    movl $20, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+.Lmain_9:
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl $0, %r11d
+    cmpl $1, %r11d
+    jne .Lmain_10
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    jne .Lmain_10
+    movl $0, -20(%rbp)
+    jmp .Lmain_11
+.Lmain_10:
+    movl $1, -20(%rbp)
+.Lmain_11:
+    movl $0, %r11d
+    cmpl -20(%rbp), %r11d
+    je .Lmain_12
+    movl $6, -20(%rbp)
+    jmp .Lmain_13
+.Lmain_12:
+    movl $9, -20(%rbp)
+.Lmain_13:
+    movl -20(%rbp), %r10d
+    movl %r10d, -20(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -12(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -20(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    cmpl $20, -4(%rbp)
+    movl $0, -4(%rbp)
+    sete -4(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_14
+    movl $9, -4(%rbp)
+    jmp .Lmain_15
+.Lmain_14:
+    movl $0, -4(%rbp)
+.Lmain_15:
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,25 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $4, %rsp
+    movl $1000, -4(%rbp)
+    movl -4(%rbp), %eax
+    cdq
+    movl $100, %r10d
+    idivl %r10d
+    movl %eax, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $2147483647, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    subl $2147483647, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,28 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $4, %rsp
+    movl $4, -4(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_0
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $5, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    jmp .Lmain_1
+.Lmain_0:
+    movl $0, -4(%rbp)
+.Lmain_1:
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
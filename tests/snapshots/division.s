@@ -0,0 +1,62 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $16, %rsp
+    movl $-17, -4(%rbp)
+    movl $5, -8(%rbp)
+    movl -4(%rbp), %eax
+    cdq
+    idivl -8(%rbp)
+    movl %eax, -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -4(%rbp), %eax
+    cdq
+    idivl -8(%rbp)
+    movl %edx, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl $100, %eax
+    cdq
+    movl $7, %r10d
+    idivl %r10d
+    movl %eax, -8(%rbp)
+    movl $100, %eax
+    cdq
+    movl $-7, %r10d
+    idivl %r10d
+    movl %edx, -16(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    addl $10, -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -12(%rbp), %r11d
+    imull $10, %r11d
+    movl %r11d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    addl $5, -4(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
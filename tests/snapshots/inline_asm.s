@@ -0,0 +1,19 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $4, %rsp
+    movl $3, -4(%rbp)
+    nop
+    movl $42, %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
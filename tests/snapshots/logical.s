@@ -0,0 +1,141 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $16, %rsp
+    movl $0, -4(%rbp)
+    movl $3, -8(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_4
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    je .Lmain_4
+    movl $1, -12(%rbp)
+    jmp .Lmain_5
+.Lmain_4:
+    movl $0, -12(%rbp)
+.Lmain_5:
+    movl $0, %r11d
+    cmpl -12(%rbp), %r11d
+    jne .Lmain_2
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    je .Lmain_6
+    movl $0, %r11d
+    cmpl $7, %r11d
+    je .Lmain_6
+    movl $1, -12(%rbp)
+    jmp .Lmain_7
+.Lmain_6:
+    movl $0, -12(%rbp)
+.Lmain_7:
+    movl $0, %r11d
+    cmpl -12(%rbp), %r11d
+    jne .Lmain_2
+    movl $0, -12(%rbp)
+    jmp .Lmain_3
+.Lmain_2:
+    movl $1, -12(%rbp)
+.Lmain_3:
+    movl $0, %r11d
+    cmpl -12(%rbp), %r11d
+    jne .Lmain_0
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    jne .Lmain_8
+    movl $0, %r11d
+    cmpl $0, %r11d
+    jne .Lmain_8
+    movl $0, -12(%rbp)
+    jmp .Lmain_9
+.Lmain_8:
+    movl $1, -12(%rbp)
+.Lmain_9:
+    movl $0, %r11d
+    cmpl -12(%rbp), %r11d
+    jne .Lmain_0
+    movl $0, -12(%rbp)
+    jmp .Lmain_1
+.Lmain_0:
+    movl $1, -12(%rbp)
+.Lmain_1:
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    jne .Lmain_14
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    jne .Lmain_14
+    movl $0, -16(%rbp)
+    jmp .Lmain_15
+.Lmain_14:
+    movl $1, -16(%rbp)
+.Lmain_15:
+    movl $0, %r11d
+    cmpl -16(%rbp), %r11d
+    je .Lmain_12
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    jne .Lmain_16
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    jne .Lmain_16
+    movl $0, -16(%rbp)
+    jmp .Lmain_17
+.Lmain_16:
+    movl $1, -16(%rbp)
+.Lmain_17:
+    movl $0, %r11d
+    cmpl -16(%rbp), %r11d
+    je .Lmain_12
+    movl $1, -16(%rbp)
+    jmp .Lmain_13
+.Lmain_12:
+    movl $0, -16(%rbp)
+.Lmain_13:
+    movl $0, %r11d
+    cmpl -16(%rbp), %r11d
+    je .Lmain_10
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_18
+    movl $0, %r11d
+    cmpl -8(%rbp), %r11d
+    je .Lmain_18
+    movl $1, -4(%rbp)
+    jmp .Lmain_19
+.Lmain_18:
+    movl $0, -4(%rbp)
+.Lmain_19:
+    cmpl $0, -4(%rbp)
+    movl $0, -4(%rbp)
+    sete -4(%rbp)
+    movl $0, %r11d
+    cmpl -4(%rbp), %r11d
+    je .Lmain_10
+    movl $1, -4(%rbp)
+    jmp .Lmain_11
+.Lmain_10:
+    movl $0, -4(%rbp)
+.Lmain_11:
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -8(%rbp), %r11d
+    imull $10, %r11d
+    movl %r11d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,110 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $16, %rsp
+    movl $5, -4(%rbp)
+    movl $-3, -8(%rbp)
+    movl -8(%rbp), %r10d
+    cmpl %r10d, -4(%rbp)
+    movl $0, -12(%rbp)
+    setg -12(%rbp)
+    movl -8(%rbp), %r10d
+    cmpl %r10d, -4(%rbp)
+    movl $0, -16(%rbp)
+    setl -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -16(%rbp), %r11d
+    imull $2, %r11d
+    movl %r11d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    cmpl $5, -4(%rbp)
+    movl $0, -16(%rbp)
+    sete -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -16(%rbp), %r11d
+    imull $4, %r11d
+    movl %r11d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    cmpl $-3, -8(%rbp)
+    movl $0, -16(%rbp)
+    setne -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -16(%rbp), %r11d
+    imull $8, %r11d
+    movl %r11d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    cmpl $0, -4(%rbp)
+    movl $0, -16(%rbp)
+    sete -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -16(%rbp), %r11d
+    imull $16, %r11d
+    movl %r11d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -8(%rbp), %r10d
+    subl %r10d, -16(%rbp)
+    cmpl $0, -16(%rbp)
+    movl $0, -16(%rbp)
+    sete -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -16(%rbp), %r11d
+    imull $32, %r11d
+    movl %r11d, -16(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -16(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    cmpl $5, -4(%rbp)
+    movl $0, -4(%rbp)
+    setge -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r11d
+    imull $64, %r11d
+    movl %r11d, -4(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -12(%rbp)
+    cmpl $-4, -8(%rbp)
+    movl $0, -4(%rbp)
+    setle -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %r11d
+    imull $128, %r11d
+    movl %r11d, -4(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -8(%rbp)
+    movl -4(%rbp), %r10d
+    addl %r10d, -8(%rbp)
+    movl -8(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,41 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $4, %rsp
+    movl .Lcount.static(%rip), %r10d
+    movl %r10d, -4(%rbp)
+    addl $10, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, .Lcount.static(%rip)
+    movl .Lzero.static(%rip), %r10d
+    movl %r10d, .Lzero.static(%rip)
+    sall $2, .Lzero.static(%rip)
+    movl .Lcount.static(%rip), %r10d
+    movl %r10d, -4(%rbp)
+    movl .Lzero.static(%rip), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl .Lmain.1.static(%rip), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .data
+    .balign 4
+.Lcount.static:
+    .long 12
+    .bss
+    .balign 4
+.Lzero.static:
+    .zero 4
+    .data
+    .balign 4
+.Lmain.1.static:
+    .long -1
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,47 @@
+    .text
+    .globl main
+    .type main, @function
+main:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $16, %rsp
+    movl $-2147483648, -4(%rbp)
+    movl $5, -8(%rbp)
+    movl $-2147483647, -12(%rbp)
+    negl -12(%rbp)
+    movl -12(%rbp), %r10d
+    movl %r10d, -12(%rbp)
+    movl $2147483647, -16(%rbp)
+    negl -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    subl $1, -16(%rbp)
+    movl -16(%rbp), %r10d
+    movl %r10d, -16(%rbp)
+    movl -16(%rbp), %r10d
+    cmpl %r10d, -4(%rbp)
+    movl $0, -4(%rbp)
+    sete -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -12(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    subl $2147483647, -4(%rbp)
+    movl $-3, -8(%rbp)
+    negl -8(%rbp)
+    movl -4(%rbp), %r10d
+    movl %r10d, -4(%rbp)
+    movl -8(%rbp), %r10d
+    addl %r10d, -4(%rbp)
+    movl -4(%rbp), %eax
+    movq %rbp, %rsp
+    popq %rbp
+    ret
+    .size main, .-main
+    .section .note.GNU-stack,"",@progbits
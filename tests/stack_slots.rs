@@ -0,0 +1,81 @@
+use c_compiler_lib::assembly::{BinaryOperator, CodeGen, Instruction, Operand, Reg, Size};
+use c_compiler_lib::stack_slots::assign_stack_slots;
+use c_compiler_lib::Symbol;
+
+fn pseudo(name: &str) -> Operand {
+    Operand::Pseudo(Symbol::intern(name))
+}
+
+fn mov(src: Operand, dst: Operand) -> Instruction {
+    Instruction::Mov(Size::Longword, src, dst)
+}
+
+fn offset(offsets: &std::collections::HashMap<Symbol, i32>, name: &str) -> i32 {
+    offsets[&Symbol::intern(name)]
+}
+
+#[test]
+fn pseudos_live_at_the_same_time_get_different_slots() {
+    // a = 1; b = 2; return a + b
+    let instructions = vec![
+        mov(Operand::Imm(1), pseudo("a")),
+        mov(Operand::Imm(2), pseudo("b")),
+        mov(pseudo("a"), Operand::Register(Reg::AX)),
+        Instruction::Binary(BinaryOperator::Add, Size::Longword, pseudo("b"), Operand::Register(Reg::AX)),
+        Instruction::Ret,
+    ];
+    let (offsets, frame_size) = assign_stack_slots(&instructions);
+    assert_ne!(offset(&offsets, "a"), offset(&offsets, "b"));
+    assert_eq!(frame_size, 8);
+}
+
+#[test]
+fn pseudos_never_live_together_share_a_slot() {
+    // a = 1; c = a; b = 2; return b + c. a is dead once it is copied to c,
+    // while c is still live when b is written.
+    let instructions = vec![
+        mov(Operand::Imm(1), pseudo("a")),
+        mov(pseudo("a"), pseudo("c")),
+        mov(Operand::Imm(2), pseudo("b")),
+        mov(pseudo("b"), Operand::Register(Reg::AX)),
+        Instruction::Binary(BinaryOperator::Add, Size::Longword, pseudo("c"), Operand::Register(Reg::AX)),
+        Instruction::Ret,
+    ];
+    let (offsets, frame_size) = assign_stack_slots(&instructions);
+    assert_eq!(offset(&offsets, "a"), offset(&offsets, "c"));
+    assert_ne!(offset(&offsets, "b"), offset(&offsets, "c"));
+    assert_eq!(frame_size, 8);
+}
+
+#[test]
+fn liveness_follows_jumps_back_to_a_loop() {
+    // a = 1; loop: b = 2; if (a == 0) goto loop; return b. a is read on
+    // every trip round the loop, so it is live while b is written.
+    let start = Symbol::intern("loop");
+    let instructions = vec![
+        mov(Operand::Imm(1), pseudo("a")),
+        Instruction::Label(start),
+        mov(Operand::Imm(2), pseudo("b")),
+        Instruction::Cmp(Size::Longword, Operand::Imm(0), pseudo("a")),
+        Instruction::JmpCC(CodeGen::E, start),
+        mov(pseudo("b"), Operand::Register(Reg::AX)),
+        Instruction::Ret,
+    ];
+    let (offsets, _) = assign_stack_slots(&instructions);
+    assert_ne!(offset(&offsets, "a"), offset(&offsets, "b"));
+}
+
+#[test]
+fn slots_are_shared_only_between_pseudos_of_the_same_width() {
+    let instructions = vec![
+        mov(Operand::Imm(1), pseudo("a")),
+        mov(pseudo("a"), Operand::Register(Reg::AX)),
+        Instruction::Mov(Size::Quadword, Operand::Imm(2), pseudo("b")),
+        Instruction::Mov(Size::Quadword, pseudo("b"), Operand::Register(Reg::AX)),
+        Instruction::Ret,
+    ];
+    let (offsets, frame_size) = assign_stack_slots(&instructions);
+    assert_eq!(offset(&offsets, "a"), -4);
+    assert_eq!(offset(&offsets, "b"), -16);
+    assert_eq!(frame_size, 16);
+}
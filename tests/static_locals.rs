@@ -0,0 +1,39 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::{compile_to_assembly, compile_to_tac};
+use c_compiler_lib::target::{CodegenOptions, Target};
+use c_compiler_lib::Symbol;
+
+fn compile(body: &str) -> Result<Vec<(Symbol, i32)>, String> {
+    compile_to_tac(&format!("int main(void) {{\n{}\n}}\n", body), &Dialect::default())
+        .map(|tac| tac.statics)
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn static_locals_start_with_their_constant_initializer() {
+    assert_eq!(compile("static int x = 2 * 3;\nstatic int y;\nreturn x + y;"),
+        Ok(vec![(Symbol::intern("x"), 6), (Symbol::intern("y"), 0)]));
+    // The initializer runs before main, so nothing is generated for it
+    let tac = compile_to_tac("int main(void) {\nstatic int x = 1;\nreturn x;\n}\n", &Dialect::default()).unwrap();
+    assert_eq!(tac.function.body.len(), 1);
+}
+
+#[test]
+fn static_initializers_must_be_constant() {
+    assert_eq!(compile("int y = 1;\nstatic int x = y;\nreturn x;"),
+        Err("3:16: Initializer of static variable 'x' is not constant: Variable 'y' is not allowed in an integer constant expression".to_string()));
+    assert_eq!(compile("static int x = 1 / 0;\nreturn x;"),
+        Err("2:12: Initializer of static variable 'x' is not constant: Division by zero in constant expression".to_string()));
+}
+
+#[test]
+fn statics_have_local_labels() {
+    let source = "int main(void) {\nstatic int count = 1;\nreturn count;\n}\n";
+    let assembly = |target| compile_to_assembly(source, &Dialect::default(), &CodegenOptions::new(target)).unwrap();
+    let linux = assembly(Target::Linux);
+    assert!(linux.contains("movl .Lcount.static(%rip), %eax") && linux.contains("\n.Lcount.static:\n"), "{}", linux);
+    assert!(!linux.contains(".type .Lcount.static"), "{}", linux);
+    // Mach-O's local labels start with L, and don't take the underscore
+    let macos = assembly(Target::MacOs);
+    assert!(macos.contains("movl Lcount.static(%rip), %eax") && macos.contains("\nLcount.static:\n"), "{}", macos);
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver::compile_to_assembly;
+use c_compiler_lib::target::{CodegenOptions, Target};
+use common::compiled;
+use std::fs;
+use std::process::Command;
+
+// Overflows with -ftrapv, and divides by zero with --check-div-zero
+const CHECKED: &str = "int main(void) {\n  int a = 0;\n  int x = 2147483647;\n  return (x + 1) / a + 7 % a;\n}\n";
+
+#[test]
+fn each_string_is_written_once_in_read_only_data() {
+    let dir = compiled("strings", "pooled", CHECKED, &["-ftrapv", "--check-div-zero", "-S"]);
+    let assembly = fs::read_to_string(dir.join("input.s")).unwrap();
+    assert!(assembly.contains("    .section .rodata\n.Lstr.0:\n    .asciz \"signed integer overflow\\n\"\n.Lstr.1:\n    .asciz \"division by zero\\n\"\n"), "{}", assembly);
+    // Two checks for overflow and two for division, but each message is
+    // written once
+    assert_eq!(assembly.matches(".asciz").count(), 2, "{}", assembly);
+}
+
+#[test]
+fn merged_strings_go_in_a_mergeable_section() {
+    let dir = compiled("strings", "merged", CHECKED, &["--check-div-zero", "-fmerge-constants"]);
+    let assembly_dir = compiled("strings", "merged-assembly", CHECKED, &["--check-div-zero", "-fmerge-constants", "-S"]);
+    let assembly = fs::read_to_string(assembly_dir.join("input.s")).unwrap();
+    assert!(assembly.contains("    .section .rodata.str1.1,\"aMS\",@progbits,1\n.Lstr.0:\n"), "{}", assembly);
+    let output = Command::new(dir.join("input")).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "division by zero\n");
+}
+
+#[test]
+fn string_sections_follow_the_target() {
+    let options = |target, merge_strings| CodegenOptions { trapv: true, merge_strings, ..CodegenOptions::new(target) };
+    let assembly = |options| compile_to_assembly(CHECKED, &Dialect::default(), &options).unwrap();
+    assert!(assembly(options(Target::MacOs, true)).contains(".section __TEXT,__cstring,cstring_literals\nLstr.0:\n"));
+    assert!(assembly(options(Target::MacOs, false)).contains(".section __TEXT,__const\nLstr.0:\n"));
+    // COFF can't merge them
+    assert!(assembly(options(Target::Windows, true)).contains(".section .rdata,\"dr\"\n.Lstr.0:\n"));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::run_compiler;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn write(name: &str, source: &str) -> PathBuf {
+    common::write_input("subcommands", name, source)
+}
+
+#[test]
+fn compile_links_to_the_output_without_running() {
+    let dir = write("compile", "int main(void) { return 7; }\n");
+    let output = run_compiler(&dir, ["compile", "input.c", "-O1", "-o", "compiled"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // Only the legacy invocation reports each step
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(Command::new(dir.join("compiled")).status().unwrap().code(), Some(7));
+}
+
+#[test]
+fn compile_with_s_writes_the_assembly_to_the_output() {
+    let dir = write("assembly", "int main(void) { return 1; }\n");
+    let output = run_compiler(&dir, ["compile", "-S", "input.c", "-o", "assembly.asm"]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(dir.join("assembly.asm")).unwrap().contains("main"));
+}
+
+#[test]
+fn run_exits_as_the_program_does() {
+    let dir = write("run", "int main(void) { return 3 + 4; }\n");
+    let output = run_compiler(&dir, ["run", "input.c"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn check_reports_errors_as_json() {
+    let dir = write("check", "int main(void) { return x; }\n");
+    let output = run_compiler(&dir, ["check", "--json", "input.c"]);
+    assert!(!output.status.success());
+    let legacy = run_compiler(&dir, ["--check", "-fdiagnostics-format=json", "input.c"]);
+    assert_eq!(output.stdout, legacy.stdout);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"code\":\"E0001\""));
+}
+
+#[test]
+fn dump_prints_each_stage() {
+    let dir = write("dump", "int main(void) { return 2 * 3; }\n");
+    let dump = |args: &[&str]| {
+        let output = run_compiler(&dir, [&["dump"], args, &["input.c"]].concat());
+        assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(dump(&["--stage=tokens"]).contains("\"kind\""));
+    assert!(dump(&["--stage=ast"]).starts_with("{\"version\":"));
+    assert!(dump(&["--stage=tac", "--json"]).starts_with("{\"version\":"));
+    assert!(!dump(&["--stage=tac"]).starts_with('{'));
+    assert!(dump(&["--stage=asm"]).contains("main"));
+    assert!(!dump(&["--stage=symbols"]).is_empty());
+}
+
+#[test]
+fn dump_needs_a_known_stage() {
+    let dir = write("stage", "int main(void) { return 0; }\n");
+    for stage in ["--stage=bytes", "-O1"] {
+        let output = run_compiler(&dir, ["dump", stage, "input.c"]);
+        assert!(!output.status.success());
+    }
+}
+
+#[test]
+fn commands_reject_flags_they_dont_take() {
+    let dir = write("flags", "int main(void) { return 0; }\n");
+    let output = run_compiler(&dir, ["check", "--tac-json", "input.c"]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Unknown flag '--tac-json' for 'check'\n");
+}
+
+#[test]
+fn invoking_without_a_command_still_compiles_and_runs() {
+    let dir = write("legacy", "int main(void) { return 5; }\n");
+    let output = run_compiler(&dir, ["input.c"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Input file: "));
+    assert!(stdout.contains("Program exited with status code: 5"));
+}
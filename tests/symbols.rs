@@ -0,0 +1,79 @@
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver;
+use c_compiler_lib::lex::Span;
+use c_compiler_lib::parser::Type;
+use c_compiler_lib::symbols::{Definition, Linkage, StorageClass, SymbolTable};
+use c_compiler_lib::Symbol;
+
+fn span() -> Span {
+    Span { start: 0, end: 1, line: 1, column: 1 }
+}
+
+fn function() -> Type {
+    Type::Function(Vec::new(), Box::new(Type::Int))
+}
+
+#[test]
+fn tentative_definitions_combine() {
+    let x = Symbol::intern("x");
+    let mut symbols = SymbolTable::new();
+    assert_eq!(symbols.declare(x, Type::Int, None, false, span()), Ok(Linkage::External));
+    assert_eq!(symbols.declare(x, Type::Int, Some(StorageClass::Extern), false, span()), Ok(Linkage::External));
+    assert_eq!(symbols.declare(x, Type::Int, None, false, span()), Ok(Linkage::External));
+    assert_eq!(symbols.get(x).unwrap().definition, Definition::Tentative);
+    assert_eq!(symbols.declare(x, Type::Int, None, true, span()), Ok(Linkage::External));
+    assert_eq!(symbols.get(x).unwrap().definition, Definition::Defined);
+    assert_eq!(symbols.declare(x, Type::Int, None, false, span()), Ok(Linkage::External));
+    assert_eq!(symbols.get(x).unwrap().definition, Definition::Defined);
+    assert_eq!(symbols.declare(x, Type::Int, None, true, span()), Err("Redefinition of 'x'".to_string()));
+}
+
+#[test]
+fn declarations_must_agree_on_type() {
+    let f = Symbol::intern("f");
+    let mut symbols = SymbolTable::new();
+    symbols.declare(f, function(), None, false, span()).unwrap();
+    assert_eq!(symbols.get(f).unwrap().definition, Definition::Declared);
+    assert_eq!(symbols.declare(f, Type::Int, None, false, span()),
+        Err("Conflicting types for 'f': function returning int and int".to_string()));
+    symbols.declare(f, function(), None, true, span()).unwrap();
+    assert_eq!(symbols.declare(f, function(), None, true, span()), Err("Redefinition of 'f'".to_string()));
+}
+
+#[test]
+fn storage_classes_must_agree_on_linkage() {
+    let (x, y, f) = (Symbol::intern("x"), Symbol::intern("y"), Symbol::intern("f"));
+    let mut symbols = SymbolTable::new();
+
+    symbols.declare(x, Type::Int, Some(StorageClass::Extern), false, span()).unwrap();
+    assert_eq!(symbols.declare(x, Type::Int, Some(StorageClass::Static), false, span()),
+        Err("Static declaration of 'x' follows non-static declaration".to_string()));
+
+    // extern keeps the internal linkage of an earlier static declaration
+    assert_eq!(symbols.declare(y, Type::Int, Some(StorageClass::Static), false, span()), Ok(Linkage::Internal));
+    assert_eq!(symbols.declare(y, Type::Int, Some(StorageClass::Extern), false, span()), Ok(Linkage::Internal));
+    assert_eq!(symbols.declare(y, Type::Int, None, true, span()),
+        Err("Non-static declaration of 'y' follows static declaration".to_string()));
+
+    // So does a function declared without a storage class
+    assert_eq!(symbols.declare(f, function(), Some(StorageClass::Static), false, span()), Ok(Linkage::Internal));
+    assert_eq!(symbols.declare(f, function(), None, true, span()), Ok(Linkage::Internal));
+}
+
+#[test]
+fn symbols_can_be_queried_by_position() {
+    let source = "int main(void) {\n  extern int count;\n  int local = 1;\n  return count + local;\n}\n";
+    let symbols = driver::symbols(source, &Dialect::default()).unwrap();
+    let names: Vec<String> = symbols.entries().iter().map(|(name, _)| name.to_string()).collect();
+    assert_eq!(names, vec!["main", "count"]);
+
+    let count = symbols.get(Symbol::intern("count")).unwrap();
+    assert_eq!((count.storage_class, count.linkage, count.definition), (Some(StorageClass::Extern), Linkage::External, Definition::Declared));
+    assert_eq!(count.declarations.iter().map(|span| (span.line, span.column)).collect::<Vec<_>>(), vec![(2, 14)]);
+    assert_eq!(count.references.iter().map(|span| (span.line, span.column)).collect::<Vec<_>>(), vec![(4, 10)]);
+
+    assert_eq!(symbols.at(4, 14).map(|(name, _)| name.to_string()), Some("count".to_string()));
+    assert_eq!(symbols.at(1, 5).map(|(name, entry)| (name.to_string(), entry.definition)), Some(("main".to_string(), Definition::Defined)));
+    // Locals have no linkage, so they aren't in the table
+    assert!(symbols.at(4, 18).is_none());
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::compiler;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A fresh directory holding input.c, and an empty one to use as TMPDIR
+fn setup(name: &str, source: &str) -> (PathBuf, PathBuf) {
+    let dir = common::write_input("temp_files", name, source);
+    fs::create_dir(dir.join("tmp")).unwrap();
+    (dir.clone(), dir.join("tmp"))
+}
+
+fn entries(dir: &Path) -> Vec<String> {
+    fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn intermediates_go_to_tmpdir_and_are_removed() {
+    let (dir, tmp) = setup("success", "int main(void) { return 0; }\n");
+    let output = compiler(&dir).args(["compile", "input.c", "-o", "program"]).env("TMPDIR", &tmp).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(entries(&tmp).is_empty());
+    let mut files = entries(&dir);
+    files.sort();
+    assert_eq!(files, ["input.c", "program", "tmp"]);
+}
+
+#[test]
+fn a_failed_link_leaves_nothing_behind_and_the_old_output_alone() {
+    let (dir, tmp) = setup("failure", "int main(void) { return 0; }\n");
+    fs::write(dir.join("program"), "old").unwrap();
+    // The linker script doesn't exist, so only linking fails
+    let output = compiler(&dir)
+        .args(["compile", "input.c", "-o", "program", "-T", "missing.ld"])
+        .env("TMPDIR", &tmp)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("GCC failed to assemble and link"));
+    assert_eq!(fs::read_to_string(dir.join("program")).unwrap(), "old");
+    assert!(entries(&tmp).is_empty());
+    let mut files = entries(&dir);
+    files.sort();
+    assert_eq!(files, ["input.c", "program", "tmp"]);
+}
+
+#[test]
+fn each_input_gets_a_directory_of_its_own() {
+    let (dir, tmp) = setup("inputs", "int main(void) { return 1; }\n");
+    fs::write(dir.join("other.c"), "int main(void) { return 2; }\n").unwrap();
+    let output = compiler(&dir).args(["compile", "-c", "input.c", "other.c"]).env("TMPDIR", &tmp).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(entries(&tmp).is_empty());
+    let mut files = entries(&dir);
+    files.sort();
+    assert_eq!(files, ["input.c", "input.o", "other.c", "other.o", "tmp"]);
+}
+
+#[test]
+fn run_links_the_program_in_tmpdir_and_removes_it() {
+    let (dir, tmp) = setup("run", "int main(void) { return 7; }\n");
+    let output = compiler(&dir).args(["run", "input.c"]).env("TMPDIR", &tmp).output().unwrap();
+    assert_eq!(output.status.code(), Some(7), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(entries(&tmp).is_empty());
+    let mut files = entries(&dir);
+    files.sort();
+    assert_eq!(files, ["input.c", "tmp"]);
+    // Nor is anything left when the program doesn't compile
+    fs::write(dir.join("input.c"), "int main(void) { return x; }\n").unwrap();
+    let output = compiler(&dir).args(["run", "input.c"]).env("TMPDIR", &tmp).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(entries(&tmp).is_empty());
+}
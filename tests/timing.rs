@@ -0,0 +1,43 @@
+use c_compiler_lib::timing::PassTimer;
+use std::thread::sleep;
+use std::time::Duration;
+
+// The pass name in each row of a report, after the header
+fn row_names(report: &str) -> Vec<&str> {
+    report.lines().skip(1).map(|line| line.split_whitespace().next().unwrap()).collect()
+}
+
+#[test]
+fn a_disabled_timer_runs_passes_and_records_nothing() {
+    let mut timer = PassTimer::new(false);
+    assert!(!timer.enabled());
+    assert_eq!(timer.time("lex", || 1 + 1), 2);
+    assert_eq!(timer.time("parse", || "parsed"), "parsed");
+    assert_eq!(row_names(&timer.report()), vec!["total"]);
+}
+
+#[test]
+fn the_report_lists_passes_in_the_order_they_ran() {
+    let mut timer = PassTimer::new(true);
+    timer.time("parse", || sleep(Duration::from_millis(2)));
+    timer.time("lex", || sleep(Duration::from_millis(2)));
+    timer.time("codegen", || sleep(Duration::from_millis(2)));
+    let report = timer.report();
+    assert_eq!(row_names(&report), vec!["parse", "lex", "codegen", "total"]);
+
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), vec!["pass", "time", "(ms)", "%"]);
+    // Every row is as wide as the longest pass name
+    assert!(lines.iter().skip(1).all(|line| line.len() == lines[1].len()));
+    assert!(lines.last().unwrap().ends_with("100.0%"));
+}
+
+#[test]
+fn a_report_with_no_time_recorded_has_no_shares() {
+    // Nothing to divide by, so every share is 0 rather than NaN
+    let timer = PassTimer::new(true);
+    assert_eq!(timer.report(), "pass    time (ms)       %\ntotal       0.000    0.0%\n");
+    let mut timer = PassTimer::new(false);
+    timer.time("lex", || {});
+    assert_eq!(timer.report(), "pass    time (ms)       %\ntotal       0.000    0.0%\n");
+}
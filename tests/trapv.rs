@@ -0,0 +1,35 @@
+mod common;
+
+use common::compiled;
+use std::fs;
+use std::process::Command;
+
+const OVERFLOWS: &str = "int main(void) {\n  int x = 2147483647;\n  return x + 1 == 0;\n}\n";
+
+#[test]
+fn overflow_aborts_with_a_message() {
+    let dir = compiled("trapv", "overflows", OVERFLOWS, &["-ftrapv"]);
+    let output = Command::new(dir.join("input")).output().unwrap();
+    assert!(!output.status.success());
+    #[cfg(unix)]
+    assert_eq!(std::os::unix::process::ExitStatusExt::signal(&output.status), Some(6));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "signed integer overflow\n");
+}
+
+#[test]
+fn overflow_wraps_around_by_default() {
+    let dir = compiled("trapv", "wraps", OVERFLOWS, &[]);
+    assert_eq!(Command::new(dir.join("input")).status().unwrap().code(), Some(0));
+    let dir = compiled("trapv", "fno-trapv", OVERFLOWS, &["-ftrapv", "-fno-trapv", "-S"]);
+    assert!(!fs::read_to_string(dir.join("input.s")).unwrap().contains("jo"));
+}
+
+#[test]
+fn only_signed_arithmetic_is_checked() {
+    let dir = compiled("trapv", "checked", "int main(void) {\n  int x = 3;\n  x = -x * 2 - 1 + x;\n  return x / 2 == x << 1;\n}\n", &["-ftrapv", "-S"]);
+    let assembly = fs::read_to_string(dir.join("input.s")).unwrap();
+    // negl, imull, subl and addl, but not the division or shift
+    assert_eq!(assembly.matches("jo .Lmain_abort_0").count(), 4, "{}", assembly);
+    // One handler, shared by every check
+    assert_eq!(assembly.matches("call abort").count(), 1, "{}", assembly);
+}
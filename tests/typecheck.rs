@@ -0,0 +1,184 @@
+use c_compiler_lib::intern::Interner;
+use c_compiler_lib::interpret;
+use c_compiler_lib::lex::Span;
+use c_compiler_lib::parser::{BinaryOp, BlockItem, Declaration, Exp, ExpArena, ExpId, FunctionDeclaration, Program, Statement, Type};
+use c_compiler_lib::tac::generate_tac;
+use c_compiler_lib::typecheck::{common_type, promote, size, typecheck_program};
+use c_compiler_lib::Symbol;
+
+#[test]
+fn types_ranked_below_int_are_promoted() {
+    for t in [Type::Char, Type::SChar, Type::UChar, Type::Short, Type::UShort] {
+        assert_eq!(promote(&t), Type::Int, "{}", t);
+    }
+    for t in [Type::Int, Type::UInt, Type::Long, Type::ULong, Type::LongLong, Type::ULongLong] {
+        assert_eq!(promote(&t), t, "{}", t);
+    }
+}
+
+#[test]
+fn usual_arithmetic_conversions() {
+    let cases = [
+        (Type::Char, Type::UChar, Type::Int),
+        (Type::Short, Type::Int, Type::Int),
+        (Type::Int, Type::UInt, Type::UInt),
+        (Type::UShort, Type::UInt, Type::UInt),
+        (Type::Int, Type::Long, Type::Long),
+        (Type::UInt, Type::Long, Type::Long),
+        (Type::Long, Type::ULong, Type::ULong),
+        (Type::ULong, Type::LongLong, Type::ULongLong),
+        (Type::UInt, Type::ULongLong, Type::ULongLong),
+        (Type::LongLong, Type::Long, Type::LongLong),
+        (Type::UChar, Type::LongLong, Type::LongLong),
+    ];
+    for (left, right, expected) in cases {
+        assert_eq!(common_type(&left, &right), expected, "{} and {}", left, right);
+        assert_eq!(common_type(&right, &left), expected, "{} and {}", right, left);
+    }
+}
+
+#[test]
+fn sizes_follow_the_lp64_model() {
+    assert_eq!(size(&Type::Char), 1);
+    assert_eq!(size(&Type::UShort), 2);
+    assert_eq!(size(&Type::Int), 4);
+    assert_eq!(size(&Type::ULong), 8);
+    assert_eq!(size(&Type::Pointer(Box::new(Type::Char))), 8);
+    assert_eq!(size(&Type::Array(Box::new(Type::Int), 3)), 12);
+}
+
+// The parser still rejects every type but int, so these programs are built
+// by hand. Each variable is named after its type.
+fn var(exps: &mut ExpArena, name: &str) -> ExpId {
+    exps.alloc(Exp::Var(Symbol::intern(name), Span::default()))
+}
+
+fn constant(exps: &mut ExpArena, value: i32) -> ExpId {
+    exps.alloc(Exp::Constant(value, Span::default()))
+}
+
+fn declare(name: &str, declared_type: Type, init: Option<ExpId>) -> BlockItem {
+    BlockItem::D(Declaration::Declaration(Symbol::intern(name), declared_type, init, None, Span::default()))
+}
+
+// main's body after type checking, and the expressions it refers to
+fn check(items: Vec<BlockItem>, exps: ExpArena) -> (Vec<BlockItem>, ExpArena) {
+    let main = FunctionDeclaration::Function(Symbol::intern("main"), items.into_iter().map(Box::new).collect(), Span::default());
+    let Program::Program(_, FunctionDeclaration::Function(_, items, _), exps, _) = typecheck_program(Program::Program(Vec::new(), main, exps, Interner::current())).unwrap();
+    (items.into_iter().map(|item| *item).collect(), exps)
+}
+
+fn initializer(item: &BlockItem) -> ExpId {
+    match item {
+        BlockItem::D(Declaration::Declaration(_, _, Some(init), _, _)) => *init,
+        _ => panic!("not an initialized declaration: {:?}", item),
+    }
+}
+
+fn expression(item: &BlockItem) -> ExpId {
+    match item {
+        BlockItem::S(Statement::Expression(exp) | Statement::Return(exp)) => *exp,
+        _ => panic!("not an expression: {:?}", item),
+    }
+}
+
+// The type exp is cast to and the expression cast, if it is a cast
+fn cast(exps: &ExpArena, exp: ExpId) -> Option<(Type, ExpId)> {
+    match &exps[exp] {
+        Exp::Cast(target, inner) => Some((target.clone(), *inner)),
+        _ => None,
+    }
+}
+
+#[test]
+fn initializers_are_converted_to_the_declared_type() {
+    let mut exps = ExpArena::new();
+    let (int, long) = (constant(&mut exps, 300), constant(&mut exps, 1));
+    let char_var = var(&mut exps, "c");
+    let same = constant(&mut exps, 2);
+    let items = vec![
+        declare("c", Type::Char, Some(int)),
+        declare("l", Type::Long, Some(long)),
+        declare("s", Type::Short, Some(char_var)),
+        declare("i", Type::Int, Some(same)),
+    ];
+    let (items, exps) = check(items, exps);
+    assert_eq!(cast(&exps, initializer(&items[0])), Some((Type::Char, int)));
+    assert_eq!(cast(&exps, initializer(&items[1])), Some((Type::Long, long)));
+    assert_eq!(cast(&exps, initializer(&items[2])), Some((Type::Short, char_var)));
+    assert_eq!(initializer(&items[3]), same);
+}
+
+#[test]
+fn assignments_convert_the_value_to_the_variable_type() {
+    let mut exps = ExpArena::new();
+    let (c, l) = (var(&mut exps, "c"), var(&mut exps, "l"));
+    let narrowing = exps.alloc(Exp::Assignment(c, l));
+    let (l2, c2) = (var(&mut exps, "l"), var(&mut exps, "c"));
+    let widening = exps.alloc(Exp::Assignment(l2, c2));
+    let (c3, d) = (var(&mut exps, "c"), var(&mut exps, "d"));
+    let same = exps.alloc(Exp::Assignment(c3, d));
+    let items = vec![
+        declare("c", Type::Char, None),
+        declare("d", Type::Char, None),
+        declare("l", Type::Long, None),
+        BlockItem::S(Statement::Expression(narrowing)),
+        BlockItem::S(Statement::Expression(widening)),
+        BlockItem::S(Statement::Expression(same)),
+    ];
+    let (_, exps) = check(items, exps);
+    let Exp::Assignment(_, value) = exps[narrowing] else { panic!("{:?}", exps[narrowing]) };
+    assert_eq!(cast(&exps, value), Some((Type::Char, l)));
+    let Exp::Assignment(_, value) = exps[widening] else { panic!("{:?}", exps[widening]) };
+    assert_eq!(cast(&exps, value), Some((Type::Long, c2)));
+    assert_eq!(exps[same], Exp::Assignment(c3, d));
+}
+
+#[test]
+fn binary_operands_are_converted_to_their_common_type() {
+    let mut exps = ExpArena::new();
+    let (c, one) = (var(&mut exps, "c"), constant(&mut exps, 1));
+    let promoted = exps.alloc(Exp::Binary(c, BinaryOp::Add, one));
+    let (l, d) = (var(&mut exps, "l"), var(&mut exps, "c"));
+    let widened = exps.alloc(Exp::Binary(l, BinaryOp::Multiply, d));
+    let items = vec![
+        declare("c", Type::Char, None),
+        declare("l", Type::Long, None),
+        BlockItem::S(Statement::Expression(promoted)),
+        BlockItem::S(Statement::Return(widened)),
+    ];
+    let (items, exps) = check(items, exps);
+    // char + int: the char is promoted and the int is left alone
+    let Exp::Binary(left, _, right) = exps[promoted] else { panic!("{:?}", exps[promoted]) };
+    assert_eq!(cast(&exps, left), Some((Type::Int, c)));
+    assert_eq!(right, one);
+    // long * char, returned as an int: the char goes straight to long
+    let Exp::Binary(left, _, right) = exps[widened] else { panic!("{:?}", exps[widened]) };
+    assert_eq!(left, l);
+    assert_eq!(cast(&exps, right), Some((Type::Long, d)));
+    assert_eq!(cast(&exps, expression(&items[3])), Some((Type::Int, widened)));
+}
+
+#[test]
+fn tac_generation_rejects_conversions_it_cannot_represent() {
+    let mut exps = ExpArena::new();
+    let one = constant(&mut exps, 1);
+    let main = FunctionDeclaration::Function(Symbol::intern("main"), vec![Box::new(declare("l", Type::Long, Some(one)))], Span::default());
+    let program = typecheck_program(Program::Program(Vec::new(), main, exps, Interner::current())).unwrap();
+    assert_eq!(generate_tac(program).unwrap_err(), "Conversion to long is not supported yet");
+}
+
+#[test]
+fn narrowing_conversions_wrap_to_the_narrow_type() {
+    let cases = [(Type::Char, 200, -56), (Type::SChar, -129, 127), (Type::UChar, -1, 255), (Type::Short, 70000, 4464), (Type::UShort, -1, 65535)];
+    for (declared_type, value, expected) in cases {
+        // t v = value; return v;
+        let mut exps = ExpArena::new();
+        let init = constant(&mut exps, value);
+        let v = var(&mut exps, "v");
+        let items = vec![declare("v", declared_type.clone(), Some(init)), BlockItem::S(Statement::Return(v))];
+        let main = FunctionDeclaration::Function(Symbol::intern("main"), items.into_iter().map(Box::new).collect(), Span::default());
+        let tac = generate_tac(typecheck_program(Program::Program(Vec::new(), main, exps, Interner::current())).unwrap()).unwrap();
+        assert_eq!(interpret::run(&tac.function), Ok(expected), "{} {}", declared_type, value);
+    }
+}
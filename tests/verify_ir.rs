@@ -0,0 +1,125 @@
+mod common;
+
+use c_compiler_lib::assembly::{self, CodeGen, Operand, Reg, Size};
+use c_compiler_lib::dialect::Dialect;
+use c_compiler_lib::driver;
+use c_compiler_lib::tac::{BinaryOperator, Function, Instruction, Val};
+use c_compiler_lib::verify::{verify_function, verify_instructions};
+use c_compiler_lib::Symbol;
+
+fn function(body: Vec<Instruction>) -> Function {
+    Function { identifier: Symbol::intern("main"), body, volatiles: Vec::new() }
+}
+
+fn var(name: &str) -> Val {
+    Val::Identifier(Symbol::intern(name))
+}
+
+fn label(name: &str) -> Symbol {
+    Symbol::intern(name)
+}
+
+#[test]
+fn generated_tac_is_valid() {
+    let source = "int main(void) {\n  int x = 3;\n  int y = x && (x || 2) ? x * 2 : -x;\n  return y;\n}\n";
+    assert_eq!(driver::verify_ir(source, &Dialect::default()).unwrap(), None);
+}
+
+#[test]
+fn temporaries_must_be_written_on_every_path() {
+    let body = vec![
+        Instruction::JumpIfZero { src: var("x"), label: label("main_0"), likely: None },
+        Instruction::Copy { src: Val::Constant(1), dst: var("tmp.1") },
+        Instruction::Label { label: label("main_0") },
+        Instruction::Return(var("tmp.1")),
+    ];
+    assert_eq!(verify_function(&function(body)), Err("'return tmp.1' reads 'tmp.1' before it is written".to_string()));
+
+    // Variables may be read before they are written
+    assert_eq!(verify_function(&function(vec![Instruction::Return(var("x"))])), Ok(()));
+}
+
+#[test]
+fn jumps_need_a_label() {
+    let body = vec![Instruction::Jump { label: label("main_9") }, Instruction::Return(Val::Constant(0))];
+    assert_eq!(verify_function(&function(body)), Err("'jump main_9' jumps to a label that isn't defined".to_string()));
+
+    let body = vec![
+        Instruction::Label { label: label("main_0") },
+        Instruction::Label { label: label("main_0") },
+        Instruction::Return(Val::Constant(0)),
+    ];
+    assert_eq!(verify_function(&function(body)), Err("Label 'main_0' is defined more than once".to_string()));
+}
+
+#[test]
+fn functions_end_with_a_return() {
+    let body = vec![Instruction::Copy { src: Val::Constant(1), dst: var("x") }];
+    assert_eq!(verify_function(&function(body)), Err("Control can reach the end of 'main' without a return".to_string()));
+
+    // Code after the return is never reached, so it can't fall off the end
+    let body = vec![Instruction::Return(Val::Constant(0)), Instruction::Copy { src: Val::Constant(1), dst: var("x") }];
+    assert_eq!(verify_function(&function(body)), Ok(()));
+}
+
+#[test]
+fn operands_are_well_formed() {
+    let body = vec![Instruction::Copy { src: var("x"), dst: Val::Constant(1) }, Instruction::Return(Val::Constant(0))];
+    assert_eq!(verify_function(&function(body)), Err("'1 = x' writes to a constant".to_string()));
+
+    let body = vec![
+        Instruction::Binary { operator: BinaryOperator::LogicalAnd, src1: var("x"), src2: var("y"), dst: var("tmp.0") },
+        Instruction::Return(var("tmp.0")),
+    ];
+    assert_eq!(verify_function(&function(body)), Err("'tmp.0 = x && y' uses an operator that should have been lowered".to_string()));
+}
+
+// A frame allocation followed by instruction and a return
+fn framed(instruction: assembly::Instruction) -> Vec<assembly::Instruction> {
+    vec![assembly::Instruction::AllocateStack(16), instruction, assembly::Instruction::Ret]
+}
+
+#[test]
+fn assembly_must_be_encodable() {
+    let (x, y) = (Operand::Stack(-4), Operand::Stack(-8));
+    let mov = assembly::Instruction::Mov(Size::Longword, x.clone(), y.clone());
+    assert_eq!(verify_instructions(&framed(mov)), Err("'movl -4(%rbp), -8(%rbp)' has more than one memory operand".to_string()));
+
+    let imul = assembly::Instruction::Binary(assembly::BinaryOperator::Mul, Size::Longword, Operand::Imm(3), x.clone());
+    assert_eq!(verify_instructions(&framed(imul)),
+        Err("'imull $3, -4(%rbp)' multiplies into memory, but imul's destination must be a register".to_string()));
+
+    let setcc = assembly::Instruction::SetCC(CodeGen::E, Operand::Imm(0));
+    assert_eq!(verify_instructions(&framed(setcc)), Err("'sete $0' writes to an immediate".to_string()));
+    // %spl is encodable with a REX prefix
+    let setcc = assembly::Instruction::SetCC(CodeGen::E, Operand::Register(Reg::SP));
+    assert_eq!(verify_instructions(&framed(setcc)), Ok(()));
+
+    let pseudo = assembly::Instruction::Mov(Size::Longword, Operand::Imm(1), Operand::Pseudo(Symbol::intern("x")));
+    assert_eq!(verify_instructions(&framed(pseudo)), Err("'movl $1, x' still has a pseudo-register".to_string()));
+
+    let add = assembly::Instruction::Binary(assembly::BinaryOperator::Add, Size::Quadword, Operand::Imm(1 << 40), Operand::Register(Reg::AX));
+    assert_eq!(verify_instructions(&framed(add)), Err("'addq $1099511627776, %rax' has an immediate wider than 32 bits".to_string()));
+    let mov = assembly::Instruction::Mov(Size::Quadword, Operand::Imm(1 << 40), Operand::Register(Reg::AX));
+    assert_eq!(verify_instructions(&framed(mov)), Ok(()));
+}
+
+#[test]
+fn the_frame_is_allocated_once_on_entry() {
+    let ret = assembly::Instruction::Ret;
+    let error = Err("The stack frame must be allocated exactly once, by the first instruction".to_string());
+    assert_eq!(verify_instructions(std::slice::from_ref(&ret)), error);
+    assert_eq!(verify_instructions(&[ret.clone(), assembly::Instruction::AllocateStack(16)]), error);
+    assert_eq!(verify_instructions(&[assembly::Instruction::AllocateStack(16), assembly::Instruction::AllocateStack(16), ret]), error);
+}
+
+#[test]
+fn assembly_jumps_need_a_label() {
+    let jump = assembly::Instruction::Jmp(Symbol::intern("main_3"));
+    assert_eq!(verify_instructions(&framed(jump)), Err("'jmp .Lmain_3' jumps to a label that isn't defined".to_string()));
+}
+
+#[test]
+fn verify_ir_flag_accepts_valid_programs() {
+    common::compiled("verify_ir", "valid", "int main(void) {\n  int x = 2;\n  return x ? x : 1;\n}\n", &["--verify-ir", "--tacky"]);
+}